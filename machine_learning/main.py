@@ -1,5 +1,5 @@
 from flask import Flask, request, jsonify
-from utils import load_trained_model_and_scaler, preprocess_single_house
+from utils import load_trained_model_and_scaler, preprocess_houses, preprocess_single_house
 import numpy as np
 
 app = Flask(__name__)
@@ -19,5 +19,17 @@ def predict():
     except Exception as e:
         return jsonify({"error": str(e)}), 400
 
+@app.route("/predict-batch", methods=["POST"])
+def predict_batch():
+    houses = request.json
+    # Predict every house in one pass through the model
+    try:
+        houses_scaled = preprocess_houses(houses, scaler)
+        predicted_log_prices = model.predict(houses_scaled)
+        predicted_prices = np.expm1(predicted_log_prices)  # Reverse log transformation
+        return jsonify({"prices": [round(float(price[0]), 2) for price in predicted_prices]})
+    except Exception as e:
+        return jsonify({"error": str(e)}), 400
+
 if __name__ == "__main__":
     app.run(port=5000)
@@ -8,7 +8,10 @@ def load_trained_model_and_scaler():
     return model, scaler
 
 def preprocess_single_house(house_data, scaler):
-    house_df = pd.DataFrame([house_data])
+    return preprocess_houses([house_data], scaler)
+
+def preprocess_houses(houses, scaler):
+    house_df = pd.DataFrame(houses)
     missing_features = set(scaler.feature_names_in_) - set(house_df.columns)
     for feature in missing_features:
         house_df[feature] = 0  # Add default value for missing features
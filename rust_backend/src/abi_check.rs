@@ -0,0 +1,127 @@
+use ethers::abi::Abi;
+use ethers::providers::Middleware;
+use ethers::types::{Address, Bytes, H256};
+
+use crate::config::optional_env;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+
+/// Functions whose ABI signature must match the deployed contract before minting starts.
+const CHECKED_FUNCTIONS: &[&str] = &["mintNFT", "tokenURI"];
+
+/// keccak256("eip1967.proxy.implementation") - 1, where EIP-1967 proxies keep their logic contract.
+const IMPLEMENTATION_SLOT: &str = "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+
+const PUSH1: u8 = 0x60;
+const PUSH32: u8 = 0x7f;
+
+/// Whether the bytecode pushes `selector` as a constant, which is how Solidity and Vyper
+/// dispatchers match calls. The optimizer drops leading zero bytes, so shorter pushes count too.
+fn exposes(code: &[u8], selector: [u8; 4]) -> bool {
+    let wanted = u32::from_be_bytes(selector);
+    let mut i = 0;
+    while i < code.len() {
+        let op = code[i];
+        i += 1;
+        if !(PUSH1..=PUSH32).contains(&op) {
+            continue;
+        }
+        let size = usize::from(op - PUSH1 + 1);
+        if size <= 4 && i + size <= code.len() {
+            let pushed = code[i..i + size].iter().fold(0u32, |value, byte| value << 8 | u32::from(*byte));
+            if pushed == wanted {
+                return true;
+            }
+        }
+        i += size;
+    }
+    false
+}
+
+/// The collection's code, or its implementation's when it is an EIP-1967 proxy.
+async fn dispatch_code(state: &AppState, address: Address) -> Result<Bytes, String> {
+    let slot: H256 = IMPLEMENTATION_SLOT.parse().unwrap();
+    let implementation = state
+        .client
+        .get_storage_at(address, slot, None)
+        .await
+        .map_err(|e| format!("Failed to read proxy implementation: {}", e))?;
+    let target = match Address::from(implementation) {
+        implementation if implementation.is_zero() => address,
+        implementation => implementation,
+    };
+    state
+        .client
+        .get_code(target, None)
+        .await
+        .map_err(|e| format!("Failed to read contract code: {}", e))
+}
+
+/// The `CHECKED_FUNCTIONS` signatures in `abi` that the deployed code doesn't dispatch on.
+/// `Ok(None)` when there is no code to probe.
+pub async fn missing_functions(
+    state: &AppState,
+    collection: &Collection,
+    abi: &Abi,
+) -> Result<Option<Vec<String>>, String> {
+    let code = dispatch_code(state, collection.address).await?;
+    if code.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        CHECKED_FUNCTIONS
+            .iter()
+            .flat_map(|name| abi.functions_by_name(name).into_iter().flatten())
+            .filter(|function| !exposes(&code, function.short_signature()))
+            .map(|function| {
+                let inputs: Vec<String> = function.inputs.iter().map(|input| input.kind.to_string()).collect();
+                format!("{}({}) ({})", function.name, inputs.join(","), hex(function.short_signature()))
+            })
+            .collect(),
+    ))
+}
+
+fn hex(selector: [u8; 4]) -> String {
+    format!("0x{:08x}", u32::from_be_bytes(selector))
+}
+
+/// Probes every registered collection's bytecode for the `mintNFT` and `tokenURI` selectors its
+/// ABI declares, so a stale or wrong ABI stops startup instead of failing the first mint.
+/// Collections whose code can't be read are left to the self-test. ABI_CHECK=false skips it, e.g.
+/// for diamond contracts that dispatch through facets.
+pub async fn check(state: &AppState) -> Result<(), String> {
+    if optional_env("ABI_CHECK").is_some_and(|check| check == "false") {
+        return Ok(());
+    }
+    let mut mismatched = Vec::new();
+    for collection in registry::list_all(&state.db)? {
+        let Ok(abi) = state.artifacts.abi(&collection.contract_name, collection.contract_version.as_deref()) else {
+            continue;
+        };
+        let Ok(chain) = state.on(&collection) else {
+            let network = collection.network.unwrap_or_default();
+            eprintln!("ABI check skipped for collection {}: network {} is not configured", collection.name, network);
+            continue;
+        };
+        match missing_functions(&chain, &collection, &abi).await {
+            Ok(Some(missing)) if !missing.is_empty() => mismatched.push(format!(
+                "collection {} at {:?} (ABI {}{}) doesn't expose {}",
+                collection.name,
+                collection.address,
+                collection.contract_name,
+                collection.contract_version.as_deref().map(|version| format!(" {}", version)).unwrap_or_default(),
+                missing.join(", ")
+            )),
+            Ok(_) => {}
+            Err(e) => eprintln!("ABI check skipped for collection {}: {}", collection.name, e),
+        }
+    }
+    if !mismatched.is_empty() {
+        return Err(format!(
+            "The loaded ABI doesn't match the deployed bytecode: {}. Register the collection with the \
+             contract version it was deployed from, or set ABI_CHECK=false",
+            mismatched.join("; ")
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, H256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::AdminControls;
+use crate::error::ApiError;
+use crate::registry;
+use crate::simulation;
+use crate::state::{AppState, EthClient};
+use crate::tx::{self, TransactionResponse};
+use crate::CollectionQuery;
+
+#[derive(Deserialize)]
+pub struct RoleRequest {
+    role: String,
+    account: Address,
+}
+
+#[derive(Serialize)]
+pub struct PausedResponse {
+    collection: String,
+    paused: bool,
+}
+
+#[derive(Serialize)]
+pub struct RoleResponse {
+    role: String,
+    account: Address,
+    has_role: bool,
+}
+
+/// The state for the collection's chain, the collection's name, and its controls on that chain.
+fn controls(state: &AppState, query: &CollectionQuery) -> Result<(AppState, String, AdminControls<EthClient>), ApiError> {
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let state = state.on(&collection)?;
+    let contract = AdminControls::new(collection.address, state.client.clone());
+    Ok((state, collection.name, contract))
+}
+
+/// Accepts OpenZeppelin role names (`MINTER_ROLE`), `DEFAULT_ADMIN_ROLE`, or a raw 32-byte hex role.
+fn role_id(role: &str) -> Result<H256, ApiError> {
+    if role == "DEFAULT_ADMIN_ROLE" {
+        return Ok(H256::zero());
+    }
+    if role.starts_with("0x") {
+        return role
+            .parse()
+            .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid role id: {}", role)));
+    }
+    Ok(H256::from(keccak256(role.as_bytes())))
+}
+
+pub async fn pause(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let (state, collection, contract) = controls(&state, &query)?;
+    println!("Pausing collection {}...", collection);
+    let receipt = tx::submit(&state, contract.pause()).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
+
+pub async fn unpause(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let (state, collection, contract) = controls(&state, &query)?;
+    println!("Unpausing collection {}...", collection);
+    let receipt = tx::submit(&state, contract.unpause()).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
+
+pub async fn paused(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<PausedResponse>, ApiError> {
+    let (_, collection, contract) = controls(&state, &query)?;
+    let paused = contract
+        .paused()
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read paused state", e))?;
+    Ok(Json(PausedResponse { collection, paused }))
+}
+
+pub async fn grant_role(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<RoleRequest>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let (state, collection, contract) = controls(&state, &query)?;
+    let role = role_id(&request.role)?;
+    println!("Granting {} to {:?} on {}...", request.role, request.account, collection);
+    let receipt = tx::submit(&state, contract.grant_role(role.0, request.account)).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
+
+pub async fn revoke_role(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<RoleRequest>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let (state, collection, contract) = controls(&state, &query)?;
+    let role = role_id(&request.role)?;
+    println!("Revoking {} from {:?} on {}...", request.role, request.account, collection);
+    let receipt = tx::submit(&state, contract.revoke_role(role.0, request.account)).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
+
+pub async fn has_role(
+    State(state): State<AppState>,
+    Path((role, account)): Path<(String, Address)>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<RoleResponse>, ApiError> {
+    let (_, _, contract) = controls(&state, &query)?;
+    let has_role = contract
+        .has_role(role_id(&role)?.0, account)
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read role", e))?;
+    Ok(Json(RoleResponse { role, account, has_role }))
+}
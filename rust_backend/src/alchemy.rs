@@ -0,0 +1,112 @@
+use ethers::types::{Address, U256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::state::AppState;
+
+/// Alchemy's NFT API, available on the same key as an Alchemy RPC endpoint. Used as a
+/// fallback when the contract can't be read directly, and to refresh Alchemy's cached
+/// metadata after it changes on-chain.
+pub struct AlchemyNftApi {
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OwnedNfts {
+    owned_nfts: Vec<OwnedNft>,
+    page_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OwnedNft {
+    contract: NftContract,
+    token_id: String,
+}
+
+#[derive(Deserialize)]
+struct NftContract {
+    address: Address,
+}
+
+impl AlchemyNftApi {
+    /// Enabled when ALCHEMY_URL is an Alchemy endpoint (`https://<network>.g.alchemy.com/v2/<key>`).
+    pub fn from_rpc_url(rpc_url: &str) -> Option<Self> {
+        let (origin, key) = rpc_url.trim_end_matches('/').rsplit_once("/v2/")?;
+        if !origin.ends_with(".g.alchemy.com") || key.is_empty() || key.contains('/') {
+            return None;
+        }
+        println!("ALCHEMY_NFT_API: Enabled");
+        Some(AlchemyNftApi {
+            base_url: format!("{}/nft/v3/{}", origin, key),
+        })
+    }
+
+    async fn get(&self, state: &AppState, method: &str, query: &[(&str, String)]) -> Result<Value, String> {
+        state
+            .http
+            .get(format!("{}/{}", self.base_url, method))
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call the Alchemy NFT API: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Alchemy {} failed: {}", method, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Alchemy {} response: {}", method, e))
+    }
+
+    /// Tokens `owner` holds in any of `contracts`, per Alchemy's index.
+    pub async fn tokens_of_owner(
+        &self,
+        state: &AppState,
+        owner: Address,
+        contracts: &[Address],
+    ) -> Result<Vec<(Address, U256)>, String> {
+        let mut tokens = Vec::new();
+        let mut page_key = None;
+        loop {
+            let mut query = vec![("owner", format!("{:?}", owner)), ("withMetadata", "false".to_string())];
+            query.extend(contracts.iter().map(|contract| ("contractAddresses[]", format!("{:?}", contract))));
+            if let Some(key) = page_key.take() {
+                query.push(("pageKey", key));
+            }
+            let page: OwnedNfts = serde_json::from_value(self.get(state, "getNFTsForOwner", &query).await?)
+                .map_err(|e| format!("Unexpected Alchemy getNFTsForOwner response: {}", e))?;
+            for nft in page.owned_nfts {
+                let token_id = U256::from_dec_str(&nft.token_id).map_err(|_| "Alchemy returned an invalid token ID")?;
+                tokens.push((nft.contract.address, token_id));
+            }
+            match page.page_key {
+                Some(key) => page_key = Some(key),
+                None => return Ok(tokens),
+            }
+        }
+    }
+
+    /// The token's metadata as last fetched by Alchemy.
+    pub async fn metadata(&self, state: &AppState, contract: Address, token_id: u64) -> Result<Value, String> {
+        let query = [("contractAddress", format!("{:?}", contract)), ("tokenId", token_id.to_string())];
+        let nft = self.get(state, "getNFTMetadata", &query).await?;
+        match &nft["raw"]["metadata"] {
+            Value::Object(metadata) if !metadata.is_empty() => Ok(Value::Object(metadata.clone())),
+            _ => Ok(json!({ "token_uri": nft["raw"]["tokenUri"] })),
+        }
+    }
+
+    /// Asks Alchemy to re-read the token's metadata from the contract.
+    pub async fn refresh(&self, state: &AppState, contract: Address, token_id: u64) -> Result<(), String> {
+        state
+            .http
+            .post(format!("{}/refreshNftMetadata", self.base_url))
+            .json(&json!({ "contractAddress": format!("{:?}", contract), "tokenId": token_id.to_string() }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call the Alchemy NFT API: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Alchemy refreshNftMetadata failed: {}", e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, H256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::AllowlistMint;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::merkle::MerkleTree;
+use crate::registry;
+use crate::state::AppState;
+use crate::tx::{self, TransactionResponse};
+
+#[derive(Deserialize)]
+pub struct UploadAllowlist {
+    addresses: Vec<Address>,
+    /// Collection whose mints must carry a proof from this allowlist.
+    collection: Option<String>,
+    /// Also push the new root on-chain via `setMerkleRoot`.
+    #[serde(default)]
+    publish_root: bool,
+}
+
+#[derive(Serialize)]
+pub struct AllowlistResponse {
+    name: String,
+    root: H256,
+    addresses: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root_transaction: Option<TransactionResponse>,
+}
+
+#[derive(Serialize)]
+pub struct ProofResponse {
+    address: Address,
+    root: H256,
+    proof: Vec<H256>,
+}
+
+pub fn addresses(db: &Db, name: &str) -> Result<Option<Vec<Address>>, String> {
+    let conn = db.lock().unwrap();
+    let exists = conn
+        .query_row("SELECT 1 FROM allowlists WHERE name = ?1", params![name], |_| Ok(()))
+        .optional()
+        .map_err(|e| format!("Failed to load allowlist: {}", e))?;
+    if exists.is_none() {
+        return Ok(None);
+    }
+    let mut stmt = conn
+        .prepare("SELECT address FROM allowlist_entries WHERE allowlist = ?1")
+        .map_err(|e| format!("Failed to load allowlist: {}", e))?;
+    let rows = stmt
+        .query_map(params![name], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to load allowlist: {}", e))?;
+    let mut addresses = Vec::new();
+    for row in rows {
+        let address = row.map_err(|e| format!("Failed to load allowlist: {}", e))?;
+        addresses.push(address.parse().map_err(|_| format!("Invalid address in allowlist: {}", address))?);
+    }
+    Ok(Some(addresses))
+}
+
+fn tree(db: &Db, name: &str) -> Result<MerkleTree, ApiError> {
+    let addresses = addresses(db, name)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown allowlist: {}", name)))?;
+    Ok(MerkleTree::new(&addresses))
+}
+
+/// Proof that `address` is on the allowlist, or 403 when it is not.
+pub fn proof_for(db: &Db, name: &str, address: Address) -> Result<Vec<H256>, ApiError> {
+    tree(db, name)?.proof(address).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("{:?} is not on the {} allowlist", address, name),
+        )
+    })
+}
+
+pub async fn upload(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<UploadAllowlist>,
+) -> Result<Json<AllowlistResponse>, ApiError> {
+    if request.addresses.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Allowlist must contain at least one address"));
+    }
+    let tree = MerkleTree::new(&request.addresses);
+    let root = tree.root();
+    let collection = match &request.collection {
+        Some(name) => Some(registry::resolve(&state.db, Some(name))?),
+        None => None,
+    };
+
+    {
+        let mut conn = state.db.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| format!("Failed to store allowlist: {}", e))?;
+        tx.execute(
+            "INSERT INTO allowlists (name, root) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET root = excluded.root, updated_at = CURRENT_TIMESTAMP",
+            params![name, format!("{:?}", root)],
+        )
+        .and_then(|_| tx.execute("DELETE FROM allowlist_entries WHERE allowlist = ?1", params![name]))
+        .map_err(|e| format!("Failed to store allowlist: {}", e))?;
+        for address in &request.addresses {
+            tx.execute(
+                "INSERT OR IGNORE INTO allowlist_entries (allowlist, address) VALUES (?1, ?2)",
+                params![name, format!("{:?}", address)],
+            )
+            .map_err(|e| format!("Failed to store allowlist: {}", e))?;
+        }
+        if let Some(collection) = &collection {
+            tx.execute(
+                "UPDATE collections SET allowlist = ?1 WHERE name = ?2",
+                params![name, collection.name],
+            )
+            .map_err(|e| format!("Failed to attach allowlist: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to store allowlist: {}", e))?;
+    }
+    println!("Allowlist {} stored with root {:?}", name, root);
+
+    let root_transaction = match (&collection, request.publish_root) {
+        (Some(collection), true) => {
+            let chain = state.on(collection)?;
+            let contract = AllowlistMint::new(collection.address, chain.client.clone());
+            let receipt = tx::submit(&chain, contract.set_merkle_root(root.0)).await?;
+            Some(tx::response(&chain, &receipt))
+        }
+        (None, true) => {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "publish_root requires a collection"));
+        }
+        _ => None,
+    };
+
+    Ok(Json(AllowlistResponse {
+        name,
+        root,
+        addresses: tree.leaf_count(),
+        root_transaction,
+    }))
+}
+
+pub async fn show(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<AllowlistResponse>, ApiError> {
+    let tree = tree(&state.db, &name)?;
+    Ok(Json(AllowlistResponse {
+        root: tree.root(),
+        addresses: tree.leaf_count(),
+        name,
+        root_transaction: None,
+    }))
+}
+
+pub async fn proof(
+    State(state): State<AppState>,
+    Path((name, address)): Path<(String, Address)>,
+) -> Result<Json<ProofResponse>, ApiError> {
+    let tree = tree(&state.db, &name)?;
+    let proof = tree.proof(address).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("{:?} is not on the {} allowlist", address, name))
+    })?;
+    Ok(Json(ProofResponse {
+        address,
+        root: tree.root(),
+        proof,
+    }))
+}
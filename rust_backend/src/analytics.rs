@@ -0,0 +1,319 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+/// How many months of history the zipcode trends cover, including the current one.
+const TREND_MONTHS: u32 = 12;
+const MAX_SMOOTHING: usize = 24;
+
+#[derive(Deserialize)]
+pub struct TrendQuery {
+    zip: Option<u64>,
+    /// Inclusive start date, `YYYY-MM-DD`.
+    from: Option<String>,
+    /// Inclusive end date, `YYYY-MM-DD`.
+    to: Option<String>,
+    /// `day`, `week`, `month` (the default), `quarter` or `year`.
+    bucket: Option<String>,
+    /// Adds a trailing moving average of the bucket averages over this many buckets.
+    smoothing: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct PriceTrends {
+    bucket: String,
+    zipcode: Option<u64>,
+    smoothing: Option<usize>,
+    points: Vec<TrendPoint>,
+}
+
+#[derive(Serialize)]
+pub struct TrendPoint {
+    /// First day of the bucket, `YYYY-MM-DD`.
+    period: String,
+    valuations: u64,
+    average: f64,
+    median: f64,
+    min: f64,
+    max: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    smoothed: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct ZipcodeAnalytics {
+    zipcode: u64,
+    /// Properties minted in the zipcode.
+    mints: u64,
+    /// Each property's latest valuation.
+    valuation: Summary,
+    price_per_sqft: Summary,
+    months: Vec<MonthTrend>,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    average: Option<f64>,
+    median: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct MonthTrend {
+    /// `YYYY-MM`.
+    month: String,
+    mints: u64,
+    valuations: u64,
+    average: Option<f64>,
+    median: Option<f64>,
+    /// Change in the average valuation from the previous calendar month, in percent.
+    change_percent: Option<f64>,
+}
+
+impl Summary {
+    fn of(mut values: Vec<f64>) -> Self {
+        Summary {
+            average: average(&values),
+            median: median(&mut values),
+        }
+    }
+}
+
+pub fn average(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+pub fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    })
+}
+
+/// The month before a `YYYY-MM` month.
+fn previous_month(month: &str) -> Option<String> {
+    let (year, month) = month.split_once('-')?;
+    let (year, month): (u32, u32) = (year.parse().ok()?, month.parse().ok()?);
+    Some(match month {
+        1 => format!("{:04}-12", year - 1),
+        _ => format!("{:04}-{:02}", year, month - 1),
+    })
+}
+
+pub async fn zipcode(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(zipcode): Path<u64>,
+) -> Result<Json<ZipcodeAnalytics>, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let error = |e: rusqlite::Error| format!("Failed to compute zipcode analytics: {}", e);
+
+    // Latest valuation and living area of every property in the zipcode.
+    let mut stmt = conn
+        .prepare(
+            "SELECT coalesce((SELECT v.price FROM valuations v
+                              WHERE v.collection = m.collection AND v.token_id = m.token_id
+                                AND v.valuation_type = 'market'
+                              ORDER BY v.id DESC LIMIT 1), m.price),
+                    json_extract(m.details, '$.sqft_living')
+             FROM mints m JOIN collections c ON c.name = m.collection
+             WHERE c.organization IS ?1 AND json_extract(m.details, '$.zipcode') = ?2",
+        )
+        .map_err(error)?;
+    let properties = stmt
+        .query_map(params![tenant.organization_id(), zipcode], |row| {
+            Ok((row.get::<_, f64>(0)?, row.get::<_, Option<f64>>(1)?))
+        })
+        .map_err(error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(error)?;
+    if properties.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("No properties have been minted in zipcode {}", zipcode),
+        ));
+    }
+    let prices: Vec<f64> = properties.iter().map(|(price, _)| *price).collect();
+    let per_sqft: Vec<f64> = properties
+        .iter()
+        .filter_map(|(price, sqft)| sqft.filter(|sqft| *sqft > 0.0).map(|sqft| price / sqft))
+        .collect();
+
+    let since = format!("-{} months", TREND_MONTHS - 1);
+    let mut months: BTreeMap<String, (u64, Vec<f64>)> = BTreeMap::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT strftime('%Y-%m', v.created_at), v.price
+             FROM valuations v
+             JOIN mints m ON m.collection = v.collection AND m.token_id = v.token_id
+             JOIN collections c ON c.name = m.collection
+             WHERE c.organization IS ?1 AND json_extract(m.details, '$.zipcode') = ?2
+               AND v.valuation_type = 'market' AND v.created_at >= date('now', 'start of month', ?3)",
+        )
+        .map_err(error)?;
+    let rows = stmt
+        .query_map(params![tenant.organization_id(), zipcode, since], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })
+        .map_err(error)?;
+    for row in rows {
+        let (month, price) = row.map_err(error)?;
+        months.entry(month).or_default().1.push(price);
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT strftime('%Y-%m', m.created_at), count(*)
+             FROM mints m JOIN collections c ON c.name = m.collection
+             WHERE c.organization IS ?1 AND json_extract(m.details, '$.zipcode') = ?2
+               AND m.created_at >= date('now', 'start of month', ?3)
+             GROUP BY 1",
+        )
+        .map_err(error)?;
+    let rows = stmt
+        .query_map(params![tenant.organization_id(), zipcode, since], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+        })
+        .map_err(error)?;
+    for row in rows {
+        let (month, mints) = row.map_err(error)?;
+        months.entry(month).or_default().0 = mints;
+    }
+
+    let mut trends: Vec<MonthTrend> = Vec::new();
+    for (month, (mints, mut prices)) in months {
+        let average = average(&prices);
+        let previous = trends
+            .last()
+            .filter(|last| previous_month(&month).as_deref() == Some(last.month.as_str()))
+            .and_then(|last| last.average);
+        trends.push(MonthTrend {
+            change_percent: average.zip(previous).map(|(now, before)| (now - before) / before * 100.0),
+            valuations: prices.len() as u64,
+            median: median(&mut prices),
+            average,
+            mints,
+            month,
+        });
+    }
+
+    Ok(Json(ZipcodeAnalytics {
+        zipcode,
+        mints: properties.len() as u64,
+        valuation: Summary::of(prices),
+        price_per_sqft: Summary::of(per_sqft),
+        months: trends,
+    }))
+}
+
+/// SQL for the first day of the bucket containing `v.created_at`.
+fn bucket_start(bucket: &str) -> Option<&'static str> {
+    Some(match bucket {
+        "day" => "date(v.created_at)",
+        "week" => "date(v.created_at, '-6 days', 'weekday 1')",
+        "month" => "date(v.created_at, 'start of month')",
+        "quarter" => {
+            "date(v.created_at, 'start of month',
+                  printf('-%d months', (cast(strftime('%m', v.created_at) AS INTEGER) - 1) % 3))"
+        }
+        "year" => "date(v.created_at, 'start of year')",
+        _ => return None,
+    })
+}
+
+fn validate_date(conn: &rusqlite::Connection, name: &str, value: Option<&str>) -> Result<Option<String>, ApiError> {
+    let Some(value) = value else { return Ok(None) };
+    let date: Option<String> = conn
+        .query_row("SELECT date(?1)", params![value], |row| row.get(0))
+        .map_err(|e| format!("Failed to parse {}: {}", name, e))?;
+    date.map(Some).ok_or_else(|| {
+        ApiError::new(StatusCode::BAD_REQUEST, format!("{} must be a date (YYYY-MM-DD). Found: {}", name, value))
+    })
+}
+
+/// Valuations bucketed over time for charting.
+pub async fn price_trends(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(query): Query<TrendQuery>,
+) -> Result<Json<PriceTrends>, ApiError> {
+    let bucket = query.bucket.unwrap_or_else(|| "month".to_string());
+    let start = bucket_start(&bucket).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("bucket must be day, week, month, quarter or year. Found: {}", bucket),
+        )
+    })?;
+    if let Some(window) = query.smoothing.filter(|window| !(1..=MAX_SMOOTHING).contains(window)) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("smoothing must be between 1 and {}. Found: {}", MAX_SMOOTHING, window),
+        ));
+    }
+
+    let conn = state.db.lock().unwrap();
+    let from = validate_date(&conn, "from", query.from.as_deref())?;
+    let to = validate_date(&conn, "to", query.to.as_deref())?;
+    let error = |e: rusqlite::Error| format!("Failed to compute price trends: {}", e);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {}, v.price
+             FROM valuations v
+             JOIN mints m ON m.collection = v.collection AND m.token_id = v.token_id
+             JOIN collections c ON c.name = m.collection
+             WHERE c.organization IS ?1 AND v.valuation_type = 'market'
+               AND (?2 IS NULL OR json_extract(m.details, '$.zipcode') = ?2)
+               AND (?3 IS NULL OR date(v.created_at) >= ?3)
+               AND (?4 IS NULL OR date(v.created_at) <= ?4)",
+            start
+        ))
+        .map_err(error)?;
+    let rows = stmt
+        .query_map(params![tenant.organization_id(), query.zip, from, to], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+        })
+        .map_err(error)?;
+    let mut buckets: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for row in rows {
+        let (period, price) = row.map_err(error)?;
+        buckets.entry(period).or_default().push(price);
+    }
+
+    let mut points: Vec<TrendPoint> = buckets
+        .into_iter()
+        .map(|(period, mut prices)| TrendPoint {
+            valuations: prices.len() as u64,
+            average: average(&prices).unwrap_or_default(),
+            median: median(&mut prices).unwrap_or_default(),
+            min: prices.first().copied().unwrap_or_default(),
+            max: prices.last().copied().unwrap_or_default(),
+            smoothed: None,
+            period,
+        })
+        .collect();
+    // Buckets without valuations are skipped, so the window spans buckets that have data.
+    if let Some(window) = query.smoothing {
+        let averages: Vec<f64> = points.iter().map(|point| point.average).collect();
+        for (i, point) in points.iter_mut().enumerate() {
+            point.smoothed = average(&averages[(i + 1).saturating_sub(window)..=i]);
+        }
+    }
+
+    Ok(Json(PriceTrends {
+        bucket,
+        zipcode: query.zip,
+        smoothing: query.smoothing,
+        points,
+    }))
+}
@@ -0,0 +1,252 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, Signature};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+use crate::consensus;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::mint::{self, HouseDetails};
+use crate::money::{Currency, Money};
+use crate::nfts;
+use crate::organizations::Tenant;
+use crate::private;
+use crate::registry;
+use crate::state::AppState;
+use crate::tx::{self, TransactionResponse};
+use crate::valuations::{self, NewValuation, ValuationType};
+use crate::CollectionQuery;
+
+/// Attributes describing an appraisal, replaced whenever a new one is applied.
+const APPRAISAL_TRAITS: &[&str] = &["Valuation Source", "Appraiser License", "Model Estimate"];
+
+#[derive(Deserialize)]
+pub struct AppraisalRequest {
+    price: f64,
+    /// The appraiser's Ethereum address; `signature` must be theirs.
+    appraiser: Address,
+    license_number: String,
+    /// EIP-191 signature of the message built by `message`.
+    signature: String,
+    notes: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Appraisal {
+    pub price: f64,
+    /// The model's latest estimate when the appraisal was made.
+    pub model_price: Option<f64>,
+    pub appraiser: String,
+    pub license_number: String,
+    pub signature: String,
+    pub notes: Option<String>,
+    pub transaction_hash: String,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct AppraisalResponse {
+    collection: String,
+    token_id: u64,
+    price: f64,
+    model_price: Option<f64>,
+    #[serde(flatten)]
+    transaction: TransactionResponse,
+}
+
+/// What the appraiser signs, binding the price to the token and their license.
+pub fn message(collection: &str, token_id: u64, price: f64, license_number: &str) -> String {
+    format!(
+        "I appraise token {} of collection {} at ${} under license {}",
+        token_id, collection, price, license_number
+    )
+}
+
+/// Whether `signature` is `signer`'s EIP-191 signature of `message`.
+pub fn signed_by(signature: &str, message: &str, signer: Address) -> bool {
+    Signature::from_str(signature.trim_start_matches("0x"))
+        .is_ok_and(|signature| signature.verify(message, signer).is_ok())
+}
+
+/// The latest appraisal of the token, which supersedes model prices until replaced.
+pub fn latest(db: &Db, collection: &str, token_id: u64) -> Result<Option<Appraisal>, String> {
+    Ok(list(db, collection, token_id, 1)?.pop())
+}
+
+fn list(db: &Db, collection: &str, token_id: u64, limit: u32) -> Result<Vec<Appraisal>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT price, model_price, appraiser, license_number, signature, notes, transaction_hash, created_at
+             FROM appraisals WHERE collection = ?1 AND token_id = ?2 ORDER BY id DESC LIMIT ?3",
+        )
+        .map_err(|e| format!("Failed to load appraisals: {}", e))?;
+    let rows = stmt
+        .query_map(params![collection, token_id, limit], |row| {
+            Ok(Appraisal {
+                price: row.get(0)?,
+                model_price: row.get(1)?,
+                appraiser: row.get(2)?,
+                license_number: row.get(3)?,
+                signature: row.get(4)?,
+                notes: row.get(5)?,
+                transaction_hash: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })
+        .map_err(|e| format!("Failed to load appraisals: {}", e))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to load appraisals: {}", e))
+}
+
+/// Sets the token's price in `metadata` to the appraised one and notes the model's estimate
+/// alongside it.
+pub fn apply(metadata: &mut Value, details: &HouseDetails, price: Money, license_number: &str, model_price: Option<Money>) {
+    mint::reprice(metadata, details, price);
+    metadata["description"] = json!(format!("A {} bedroom house appraised at ${}", details.bedrooms, price.to_f64()));
+    let Some(attributes) = metadata["attributes"].as_array_mut() else { return };
+    attributes.retain(|attribute| {
+        !APPRAISAL_TRAITS.contains(&attribute["trait_type"].as_str().unwrap_or_default())
+    });
+    attributes.push(json!({ "trait_type": "Valuation Source", "value": "Licensed Appraiser" }));
+    attributes.push(json!({ "trait_type": "Appraiser License", "value": license_number }));
+    if let Some(model_price) = model_price {
+        attributes.push(json!({ "trait_type": "Model Estimate", "value": model_price.to_f64() }));
+    }
+}
+
+pub async fn submit(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<AppraisalRequest>,
+) -> Result<Json<AppraisalResponse>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    if !(request.price.is_finite() && request.price > 0.0) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "price must be a positive number"));
+    }
+    if request.license_number.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "license_number is required"));
+    }
+    let message = message(&collection.name, token_id, request.price, &request.license_number);
+    if !signed_by(&request.signature, &message, request.appraiser) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("signature is not {:?}'s signature of the appraisal", request.appraiser),
+        )
+        .with_details(json!({ "message": message })));
+    }
+
+    let stored = {
+        let conn = state.db.lock().unwrap();
+        let license = consensus::registered_license(&conn, &format!("{:?}", request.appraiser))?;
+        if license != request.license_number {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                format!("{:?} is registered under a different license number", request.appraiser),
+            ));
+        }
+        let used: bool = conn
+            .query_row(
+                "SELECT count(*) > 0 FROM appraisals WHERE signature = ?1",
+                params![request.signature],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to load appraisals: {}", e))?;
+        if used {
+            return Err(ApiError::new(StatusCode::CONFLICT, "This appraisal has already been submitted"));
+        }
+        conn.query_row(
+            "SELECT m.details, m.metadata,
+                    (SELECT price FROM valuations v WHERE v.collection = m.collection AND v.token_id = m.token_id
+                       AND v.source = 'model' AND v.valuation_type = 'market' ORDER BY v.id DESC LIMIT 1)
+             FROM mints m WHERE m.collection = ?1 AND m.token_id = ?2",
+            params![collection.name, token_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<f64>>(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+        .map(|(details, metadata, model_price)| {
+            private::restore(&conn, state.config.private.as_ref(), &collection.name, token_id, details)
+                .map(|details| (details, metadata, model_price))
+        })
+        .transpose()?
+    };
+    let (details, metadata, model_price) = stored.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} was not minted through this service", token_id))
+    })?;
+    let details: HouseDetails = serde_json::from_str(&details).map_err(|_| {
+        ApiError::new(StatusCode::CONFLICT, format!("Property details for token {} are no longer available", token_id))
+    })?;
+    let mut metadata: Value = serde_json::from_str(&metadata).map_err(|e| format!("Invalid stored metadata: {}", e))?;
+    let price = Money::from_f64(request.price, Currency::USD);
+    let model_estimate = model_price.map(|model_price| Money::from_f64(model_price, Currency::USD));
+    apply(&mut metadata, &details, price, &request.license_number, model_estimate);
+
+    println!(
+        "Applying appraisal of token {} of {} at {} by {:?}...",
+        token_id, collection.name, request.price, request.appraiser
+    );
+    let receipt =
+        valuations::write_metadata(&state, &tenant, &collection, token_id, price, &metadata).await?;
+    let transaction_hash = format!("{:?}", receipt.transaction_hash);
+    let appraiser = format!("{:?}", request.appraiser);
+
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO appraisals
+                (collection, token_id, price, model_price, appraiser, license_number, signature, notes, transaction_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                collection.name,
+                token_id,
+                request.price,
+                model_price,
+                appraiser,
+                request.license_number,
+                request.signature,
+                request.notes,
+                transaction_hash,
+            ],
+        )
+        .map_err(|e| format!("Failed to record appraisal: {}", e))?;
+    }
+    valuations::record(
+        &state.db,
+        NewValuation {
+            collection: &collection.name,
+            token_id,
+            price,
+            source: "appraiser",
+            valuation_type: ValuationType::Market,
+            actor: &appraiser,
+            transaction_hash: Some(&transaction_hash),
+        },
+    )?;
+    nfts::spawn_marketplace_refresh(&state, &collection, token_id);
+
+    Ok(Json(AppraisalResponse {
+        transaction: tx::response(&state.on(&collection)?, &receipt),
+        collection: collection.name,
+        token_id,
+        price: request.price,
+        model_price,
+    }))
+}
+
+/// Every appraisal of the token, newest first, next to the model estimate it replaced.
+pub async fn history(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<Appraisal>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    Ok(Json(list(&state.db, &collection.name, token_id, u32::MAX)?))
+}
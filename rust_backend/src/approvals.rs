@@ -0,0 +1,64 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::jobs::{self, MintJob};
+use crate::money::Money;
+use crate::organizations::{Caller, Tenant};
+use crate::state::AppState;
+
+pub struct ApprovalConfig {
+    /// Model prices above this need a second user's approval before minting.
+    pub threshold: f64,
+}
+
+impl ApprovalConfig {
+    /// Enabled by APPROVAL_THRESHOLD.
+    pub fn from_env() -> Option<Self> {
+        let threshold = optional_env("APPROVAL_THRESHOLD")?
+            .parse()
+            .expect("APPROVAL_THRESHOLD must be a number");
+        println!("APPROVAL_THRESHOLD: {}", threshold);
+        Some(ApprovalConfig { threshold })
+    }
+}
+
+/// Whether a mint at the model's `price` has to be approved by someone other than its requester.
+pub fn required(state: &AppState, price: Money) -> bool {
+    state.config.approvals.as_ref().is_some_and(|approvals| price.to_f64() > approvals.threshold)
+}
+
+/// Approves a high-value mint. The approver must be able to mint for the job's organization and
+/// be a different caller from whoever requested it; the job then moves on to whatever else it
+/// waits for, or to a worker.
+pub async fn approve(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    caller: Caller,
+    Path(id): Path<i64>,
+) -> Result<Json<MintJob>, ApiError> {
+    let Json(job) = jobs::get_job(State(state.clone()), tenant, Path(id)).await?;
+    let conflict = |job: &MintJob| {
+        ApiError::new(StatusCode::CONFLICT, format!("Mint job {} is {} and not pending approval", id, job.status))
+            .with_details(serde_json::json!({ "status": job.status }))
+    };
+    if job.status != jobs::PENDING_APPROVAL {
+        return Err(conflict(&job));
+    }
+    let Caller(Some(approver)) = caller else {
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Approving a mint needs an API key"));
+    };
+    if job.requested_by.as_deref() == Some(approver.as_str()) {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "A mint must be approved by someone other than whoever requested it",
+        ));
+    }
+    if !jobs::approve(&state, id, &approver)? {
+        return Err(conflict(&jobs::get(&state.db, id)?.unwrap_or(job)));
+    }
+    println!("Mint job {} was approved by {}", id, approver);
+    Ok(Json(jobs::get(&state.db, id)?.ok_or("Mint job disappeared")?))
+}
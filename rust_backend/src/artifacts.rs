@@ -0,0 +1,146 @@
+use ethers::abi::Abi;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::audit;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::simulation;
+
+pub const BUNDLED_CONTRACT: &str = "RealEstateNFT";
+
+// Contract name -> (version, ABI) pairs sorted by ascending version.
+type AbiIndex = HashMap<String, Vec<(String, Arc<Abi>)>>;
+
+/// ABIs keyed by contract name and version, loaded from `ARTIFACTS_DIR` laid out as
+/// `<dir>/<ContractName>/<version>.json`. Files may hold a bare ABI array or a
+/// Hardhat artifact. The ABI compiled into the binary is used when no artifact matches.
+pub struct ArtifactStore {
+    dir: Option<PathBuf>,
+    abis: RwLock<AbiIndex>,
+    bundled: Arc<Abi>,
+}
+
+impl ArtifactStore {
+    pub fn new(dir: Option<PathBuf>, bundled: Abi) -> Self {
+        let store = ArtifactStore {
+            dir,
+            abis: RwLock::new(HashMap::new()),
+            bundled: Arc::new(bundled),
+        };
+        if let Err(e) = store.reload() {
+            panic!("Failed to load contract artifacts: {}", e);
+        }
+        store
+    }
+
+    pub fn reload(&self) -> Result<usize, String> {
+        let Some(dir) = &self.dir else { return Ok(0) };
+        let loaded = load_dir(dir)?;
+        let count = loaded.values().map(Vec::len).sum();
+        *self.abis.write().unwrap() = loaded;
+        println!("Loaded {} contract artifact(s) from {}", count, dir.display());
+        Ok(count)
+    }
+
+    /// Resolves an ABI; without a version the highest loaded version is used.
+    pub fn abi(&self, contract_name: &str, version: Option<&str>) -> Result<Arc<Abi>, ApiError> {
+        let abis = self.abis.read().unwrap();
+        let versions = abis.get(contract_name).map(Vec::as_slice).unwrap_or_default();
+        let found = match version {
+            Some(version) => versions.iter().find(|(v, _)| v == version),
+            None => versions.last(),
+        };
+        match found {
+            Some((_, abi)) => Ok(abi.clone()),
+            None if version.is_none() && contract_name == BUNDLED_CONTRACT => Ok(self.bundled.clone()),
+            None => Err(format!(
+                "No ABI loaded for contract {} version {}",
+                contract_name,
+                version.unwrap_or("latest")
+            )
+            .into()),
+        }
+    }
+
+    pub fn list(&self) -> Vec<(String, String)> {
+        let abis = self.abis.read().unwrap();
+        let mut list: Vec<_> = abis
+            .iter()
+            .flat_map(|(name, versions)| versions.iter().map(move |(v, _)| (name.clone(), v.clone())))
+            .collect();
+        list.sort();
+        list
+    }
+}
+
+fn load_dir(dir: &Path) -> Result<AbiIndex, String> {
+    let mut loaded = AbiIndex::new();
+    let contracts = std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for contract in contracts.flatten().filter(|entry| entry.path().is_dir()) {
+        let name = contract.file_name().to_string_lossy().to_string();
+        let files = std::fs::read_dir(contract.path()).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let version = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let abi = parse_artifact(&path)?;
+            simulation::register_errors(&abi);
+            loaded.entry(name.clone()).or_default().push((version, Arc::new(abi)));
+        }
+    }
+    for versions in loaded.values_mut() {
+        versions.sort_by_key(|(version, _)| version_key(version));
+    }
+    Ok(loaded)
+}
+
+fn parse_artifact(path: &Path) -> Result<Abi, String> {
+    let contents = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let json: Value = serde_json::from_slice(&contents).map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))?;
+    let abi = match json {
+        Value::Object(mut artifact) => artifact.remove("abi").unwrap_or_default(),
+        abi => abi,
+    };
+    serde_json::from_value(abi).map_err(|e| format!("Invalid ABI in {}: {}", path.display(), e))
+}
+
+// Orders "v1.10" after "v1.9"; non-numeric parts compare as zero.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Reloads artifacts whenever the process receives SIGHUP.
+pub fn reload_on_sighup(store: Arc<ArtifactStore>, db: Db) {
+    tokio::spawn(async move {
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+            .expect("Failed to install SIGHUP handler");
+        while hangup.recv().await.is_some() {
+            println!("SIGHUP received, reloading contract artifacts...");
+            let reloaded = store.reload();
+            if let Err(e) = &reloaded {
+                eprintln!("Artifact reload failed, keeping previous ABIs: {}", e);
+            }
+            let entry = audit::Entry {
+                actor: "system",
+                ip: None,
+                method: "SIGHUP",
+                path: "artifacts/reload",
+                payload_hash: None,
+                status: None,
+                success: reloaded.is_ok(),
+            };
+            if let Err(e) = audit::append(&db, entry) {
+                eprintln!("{}", e);
+            }
+        }
+    });
+}
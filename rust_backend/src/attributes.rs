@@ -0,0 +1,120 @@
+use serde_json::Value;
+
+use crate::config::optional_env;
+use crate::mint::HouseDetails;
+use crate::money::Money;
+use crate::private::PrivateConfig;
+
+pub const DEFAULT_FIELDS: &str = "bedrooms,bathrooms,sqft_living,sqft_lot,age,price_per_sqft,recently_renovated,basement";
+
+/// The HouseDetails fields that can be published as traits, with their trait names. Coordinates
+/// are published through PUBLIC_LOCATION instead.
+const TRAITS: &[(&str, &str)] = &[
+    ("bedrooms", "Bedrooms"),
+    ("bathrooms", "Bathrooms"),
+    ("sqft_living", "Living Area"),
+    ("sqft_lot", "Lot Size"),
+    ("floors", "Floors"),
+    ("waterfront", "Waterfront"),
+    ("view", "View"),
+    ("condition", "Condition"),
+    ("grade", "Grade"),
+    ("sqft_above", "Above Ground Area"),
+    ("sqft_basement", "Basement Area"),
+    ("yr_built", "Year Built"),
+    ("yr_renovated", "Year Renovated"),
+    ("zipcode", "Zipcode"),
+    ("sqft_living15", "Neighbourhood Living Area"),
+    ("sqft_lot15", "Neighbourhood Lot Size"),
+];
+
+/// Traits computed from the details and price, published after the price.
+const DERIVED: &[(&str, &str)] = &[
+    ("age", "Property Age"),
+    ("price_per_sqft", "Price per Sqft"),
+    ("recently_renovated", "Recently Renovated"),
+    ("basement", "Has Basement"),
+];
+
+/// Which property details become traits in the public metadata.
+pub struct AttributeConfig {
+    fields: Vec<&'static str>,
+    /// How many years a renovation counts as recent for.
+    renovation_years: u64,
+}
+
+impl AttributeConfig {
+    /// METADATA_FIELDS lists the published fields and derived traits, in trait order. Private
+    /// fields can't be listed.
+    pub fn from_env(private: Option<&PrivateConfig>) -> Self {
+        let fields = optional_env("METADATA_FIELDS").unwrap_or_else(|| DEFAULT_FIELDS.to_string());
+        let renovation_years = optional_env("RECENT_RENOVATION_YEARS")
+            .map(|years| years.parse().expect("RECENT_RENOVATION_YEARS must be a number"))
+            .unwrap_or(10);
+        let config = AttributeConfig::new(&fields, renovation_years, private);
+        println!("METADATA_FIELDS: {}", config.fields.join(", "));
+        config
+    }
+
+    /// `fields` is a comma-separated METADATA_FIELDS list.
+    pub fn new(fields: &str, renovation_years: u64, private: Option<&PrivateConfig>) -> Self {
+        let fields: Vec<&'static str> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| {
+                let (field, _) = TRAITS.iter().chain(DERIVED).find(|(name, _)| *name == field).unwrap_or_else(|| {
+                    let available: Vec<_> = TRAITS.iter().chain(DERIVED).map(|(name, _)| *name).collect();
+                    panic!("METADATA_FIELDS can't include {}. Available: {}", field, available.join(", "))
+                });
+                if private.is_some_and(|config| config.fields.iter().any(|private| private == field)) {
+                    panic!("METADATA_FIELDS would publish {}, which is listed in PRIVATE_FIELDS", field);
+                }
+                *field
+            })
+            .collect();
+        AttributeConfig {
+            fields,
+            renovation_years,
+        }
+    }
+
+    /// A trait for each published field of `details`.
+    pub fn attributes(&self, details: &HouseDetails) -> Vec<Value> {
+        let details = serde_json::to_value(details).unwrap_or_default();
+        self.fields
+            .iter()
+            .filter_map(|field| {
+                let (_, trait_type) = TRAITS.iter().find(|(name, _)| name == field)?;
+                Some(serde_json::json!({ "trait_type": trait_type, "value": details[field] }))
+            })
+            .collect()
+    }
+
+    /// The published derived traits. Ages are as of the valuation's `year`, not today, so
+    /// they match the price they sit beside.
+    pub fn derived(&self, details: &HouseDetails, price: Money) -> Vec<Value> {
+        self.fields
+            .iter()
+            .filter_map(|field| {
+                let (_, trait_type) = DERIVED.iter().find(|(name, _)| name == field)?;
+                let value = match *field {
+                    "age" => serde_json::json!(details.year.checked_sub(details.yr_built)?),
+                    "price_per_sqft" => serde_json::json!(price_per_sqft(details, price)?),
+                    "recently_renovated" => serde_json::json!(
+                        details.yr_renovated > 0
+                            && details.year.saturating_sub(details.yr_renovated) <= self.renovation_years
+                    ),
+                    "basement" => serde_json::json!(details.sqft_basement > 0),
+                    _ => return None,
+                };
+                Some(serde_json::json!({ "trait_type": trait_type, "value": value }))
+            })
+            .collect()
+    }
+}
+
+/// Rounded to cents; `None` without a living area to divide by.
+pub fn price_per_sqft(details: &HouseDetails, price: Money) -> Option<f64> {
+    (details.sqft_living > 0).then(|| (price.cents as f64 / details.sqft_living as f64).round() / 100.0)
+}
@@ -0,0 +1,240 @@
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, Query, State};
+use axum::http::{Method, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Json;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio_stream::Stream;
+
+use crate::auth;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+/// Limit for bodies that have to be read before the handler runs, such as signed requests and
+/// four-eyes confirmations. Matches axum's default extractor limit; the audit log itself hashes
+/// bodies as they stream through, so routes with a larger limit of their own are unaffected.
+const MAX_BUFFERED_BODY: usize = 2 * 1024 * 1024;
+
+pub struct Entry<'a> {
+    pub actor: &'a str,
+    pub ip: Option<String>,
+    pub method: &'a str,
+    pub path: &'a str,
+    pub payload_hash: Option<String>,
+    pub status: Option<u16>,
+    pub success: bool,
+}
+
+#[derive(Serialize)]
+pub struct AuditLog {
+    id: i64,
+    actor: String,
+    ip: Option<String>,
+    method: String,
+    path: String,
+    payload_hash: Option<String>,
+    status: Option<u16>,
+    result: String,
+    created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    actor: Option<String>,
+    method: Option<String>,
+    /// Path prefix, e.g. `/admin/roles`.
+    path: Option<String>,
+    /// `success` or `failure`.
+    result: Option<String>,
+    /// Inclusive bounds on `created_at`, as `YYYY-MM-DD[ HH:MM:SS]` (UTC). A date-only `until`
+    /// includes the whole day.
+    since: Option<String>,
+    until: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+/// Appends to the audit log. The table rejects updates and deletes.
+pub fn append(db: &Db, entry: Entry) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO audit_logs (actor, ip, method, path, payload_hash, status, result)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            entry.actor,
+            entry.ip,
+            entry.method,
+            entry.path,
+            entry.payload_hash,
+            entry.status,
+            if entry.success { "success" } else { "failure" },
+        ],
+    )
+    .map_err(|e| format!("Failed to write audit log: {}", e))?;
+    Ok(())
+}
+
+fn actor(state: &AppState, request: &Request<Body>) -> String {
+    if let Some(admin) = auth::admin_identity(state, request.headers()) {
+        return admin;
+    }
+    match (request.uri().path().strip_prefix("/webhooks/"), request.extensions().get::<Tenant>()) {
+        (Some(source), _) => format!("webhook:{}", source),
+        (None, Some(Tenant(Some(org)))) => format!("org:{}", org.id),
+        (None, _) => "anonymous".to_string(),
+    }
+}
+
+/// Reads the whole body so it can be hashed, returning a request that replays it.
+pub async fn buffer_body(request: Request<Body>) -> Result<(Request<Body>, Bytes), ApiError> {
+    let (parts, body) = request.into_parts();
+    let body: Bytes = hyper::body::to_bytes(http_body::Limited::new(body, MAX_BUFFERED_BODY))
+        .await
+        .map_err(|_| ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large"))?;
+    Ok((Request::from_parts(parts, Body::from(body.clone())), body))
+}
+
+/// A request body that hashes itself as the handler reads it, so the audit log never has to hold
+/// a body in memory or impose its own size limit. The digest is only set once the body has been
+/// read to the end.
+struct HashingBody {
+    inner: Body,
+    hasher: Option<Sha256>,
+    digest: Arc<Mutex<Option<String>>>,
+}
+
+impl Stream for HashingBody {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let polled = Pin::new(&mut self.inner).poll_next(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(chunk))) => {
+                if let Some(hasher) = &mut self.hasher {
+                    hasher.update(chunk);
+                }
+            }
+            Poll::Ready(None) => {
+                if let Some(hasher) = self.hasher.take() {
+                    *self.digest.lock().unwrap() = Some(ethers::utils::hex::encode(hasher.finalize()));
+                }
+            }
+            _ => {}
+        }
+        polled
+    }
+}
+
+/// Records every mutating request (anything but GET/HEAD/OPTIONS) with a SHA-256 hash of its
+/// body and the response status, including requests rejected before reaching a handler. Those
+/// are logged without a hash when their body was never read in full.
+pub async fn record(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+
+    let actor = actor(&state, &request);
+    let method = request.method().to_string();
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+
+    let (parts, body) = request.into_parts();
+    // Handlers needn't poll an empty body at all, so its hash is taken up front.
+    let digest = match http_body::Body::size_hint(&body).exact() {
+        Some(0) => Some(ethers::utils::hex::encode(Sha256::digest(b""))),
+        _ => None,
+    };
+    let digest = Arc::new(Mutex::new(digest));
+    let body = HashingBody {
+        inner: body,
+        hasher: Some(Sha256::new()),
+        digest: digest.clone(),
+    };
+    let request = Request::from_parts(parts, Body::wrap_stream(body));
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let payload_hash = digest.lock().unwrap().take();
+    let entry = Entry {
+        actor: &actor,
+        ip: Some(peer.ip().to_string()),
+        method: &method,
+        path: &path,
+        payload_hash,
+        status: Some(status.as_u16()),
+        success: status.is_success(),
+    };
+    if let Err(e) = append(&state.db, entry) {
+        eprintln!("{} {} was not audited: {}", method, path, e);
+    }
+    response
+}
+
+pub async fn list_audit_logs(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditLog>>, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, actor, ip, method, path, payload_hash, status, result, created_at
+             FROM audit_logs
+             WHERE (?1 IS NULL OR actor = ?1)
+               AND (?2 IS NULL OR method = ?2)
+               AND (?3 IS NULL OR substr(path, 1, length(?3)) = ?3)
+               AND (?4 IS NULL OR result = ?4)
+               AND (?5 IS NULL OR created_at >= ?5)
+               AND (?6 IS NULL OR CASE WHEN length(?6) = 10 THEN created_at < date(?6, '+1 day')
+                                       ELSE created_at <= ?6 END)
+             ORDER BY id DESC LIMIT ?7 OFFSET ?8",
+        )
+        .map_err(|e| format!("Failed to list audit logs: {}", e))?;
+    let rows = stmt
+        .query_map(
+            params![
+                query.actor,
+                query.method.map(|method| method.to_uppercase()),
+                query.path,
+                query.result,
+                query.since,
+                query.until,
+                query.limit.unwrap_or(100).min(1000),
+                query.offset.unwrap_or(0),
+            ],
+            |row| {
+                Ok(AuditLog {
+                    id: row.get(0)?,
+                    actor: row.get(1)?,
+                    ip: row.get(2)?,
+                    method: row.get(3)?,
+                    path: row.get(4)?,
+                    payload_hash: row.get(5)?,
+                    status: row.get(6)?,
+                    result: row.get(7)?,
+                    created_at: row.get(8)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Failed to list audit logs: {}", e))?;
+    let logs = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to list audit logs: {}", e))?;
+    Ok(Json(logs))
+}
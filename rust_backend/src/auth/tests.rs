@@ -0,0 +1,81 @@
+use super::{constant_time_eq, hmac_sha256_hex, verify_timestamped_signature, SIGNATURE_TOLERANCE_SECS};
+
+const SECRET: &str = "whsec_test";
+const BODY: &[u8] = br#"{"id":"evt_1"}"#;
+const NOW: u64 = 1_700_000_000;
+
+fn header(timestamp: u64, secret: &str, body: &[u8]) -> String {
+    let signature = hmac_sha256_hex(secret, &[timestamp.to_string().as_bytes(), b".", body]);
+    format!("t={},v1={}", timestamp, signature)
+}
+
+#[test]
+fn hmac_matches_rfc_4231() {
+    // Test case 2, with the message split across parts.
+    assert_eq!(
+        hmac_sha256_hex("Jefe", &[b"what do ya want ", b"for nothing?"]),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[test]
+fn accepts_a_fresh_signature() {
+    assert!(verify_timestamped_signature(&header(NOW, SECRET, BODY), BODY, SECRET, NOW));
+    assert!(verify_timestamped_signature(&header(NOW - 60, SECRET, BODY), BODY, SECRET, NOW));
+}
+
+#[test]
+fn accepts_any_matching_signature_during_secret_rotation() {
+    let old = header(NOW, "whsec_old", BODY);
+    let new = header(NOW, SECRET, BODY);
+    let rotated = format!("{}, {}", old, new.split_once(',').unwrap().1);
+    assert!(verify_timestamped_signature(&rotated, BODY, SECRET, NOW));
+}
+
+#[test]
+fn rejects_wrong_secret_or_tampered_body() {
+    assert!(!verify_timestamped_signature(&header(NOW, "whsec_other", BODY), BODY, SECRET, NOW));
+    assert!(!verify_timestamped_signature(&header(NOW, SECRET, BODY), br#"{"id":"evt_2"}"#, SECRET, NOW));
+}
+
+#[test]
+fn rejects_timestamps_outside_the_tolerance() {
+    let edge = NOW - SIGNATURE_TOLERANCE_SECS;
+    assert!(verify_timestamped_signature(&header(edge, SECRET, BODY), BODY, SECRET, NOW));
+    assert!(!verify_timestamped_signature(&header(edge - 1, SECRET, BODY), BODY, SECRET, NOW));
+    let future = NOW + SIGNATURE_TOLERANCE_SECS + 1;
+    assert!(!verify_timestamped_signature(&header(future, SECRET, BODY), BODY, SECRET, NOW));
+}
+
+#[test]
+fn rejects_a_replayed_signature_under_a_new_timestamp() {
+    let signed = header(NOW - 3600, SECRET, BODY);
+    let signature = signed.split_once(",v1=").unwrap().1;
+    let replayed = format!("t={},v1={}", NOW, signature);
+    assert!(!verify_timestamped_signature(&replayed, BODY, SECRET, NOW));
+}
+
+#[test]
+fn rejects_malformed_headers() {
+    let signature = header(NOW, SECRET, BODY);
+    let signature = signature.split_once(",v1=").unwrap().1;
+    for malformed in [
+        String::new(),
+        format!("v1={}", signature),
+        format!("t=,v1={}", signature),
+        format!("t=soon,v1={}", signature),
+        format!("t={}", NOW),
+        format!("t={},v0={}", NOW, signature),
+        format!("t={},v1={}", NOW, &signature[..63]),
+    ] {
+        assert!(!verify_timestamped_signature(&malformed, BODY, SECRET, NOW), "{}", malformed);
+    }
+}
+
+#[test]
+fn constant_time_eq_compares_lengths_and_bytes() {
+    assert!(constant_time_eq(b"abc", b"abc"));
+    assert!(!constant_time_eq(b"abc", b"abd"));
+    assert!(!constant_time_eq(b"abc", b"abcd"));
+    assert!(constant_time_eq(b"", b""));
+}
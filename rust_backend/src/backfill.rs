@@ -0,0 +1,509 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::U256;
+use ethers::utils::{format_ether, parse_ether};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::appraisals;
+use crate::cards;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::liens;
+use crate::mint;
+use crate::money::{Currency, Money};
+use crate::nfts;
+use crate::organizations::{self, Tenant};
+use crate::parcels;
+use crate::rarity;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::storage;
+use crate::tx;
+use crate::valuations;
+use crate::versions;
+
+const RUNNING: &str = "running";
+/// Stopped because the next batch would go over the gas budget; resume with a larger one.
+const PAUSED: &str = "paused";
+const FAILED: &str = "failed";
+const COMPLETED: &str = "completed";
+
+const DEFAULT_BATCH_SIZE: u32 = 25;
+const MAX_BATCH_SIZE: u32 = 500;
+const DEFAULT_BATCH_INTERVAL_SECS: u64 = 15;
+
+#[derive(Deserialize)]
+pub struct StartBackfill {
+    /// Also point each changed token's URI at the new metadata. Without it the metadata is only
+    /// generated and uploaded, which shows what would change.
+    #[serde(default)]
+    push_onchain: bool,
+    /// Tokens updated between checks of the gas budget.
+    #[serde(default = "default_batch_size")]
+    batch_size: u32,
+    /// Most the tokenURI updates may spend, in ETH; unlimited when absent.
+    gas_budget_eth: Option<String>,
+    /// Defaults to every token minted through this service.
+    #[serde(default)]
+    token_ids: Vec<u64>,
+}
+
+fn default_batch_size() -> u32 {
+    DEFAULT_BATCH_SIZE
+}
+
+#[derive(Deserialize)]
+pub struct ResumeBackfill {
+    /// Replaces the budget, e.g. to continue one that ran out.
+    gas_budget_eth: Option<String>,
+}
+
+#[derive(Default, Serialize)]
+pub struct Progress {
+    total: u64,
+    pending: u64,
+    /// The current template gives the same metadata.
+    unchanged: u64,
+    /// Uploaded without updating the token URI.
+    uploaded: u64,
+    /// Uploaded and written on-chain.
+    updated: u64,
+    failed: u64,
+}
+
+#[derive(Serialize)]
+pub struct Backfill {
+    pub id: i64,
+    pub collection: String,
+    pub push_onchain: bool,
+    pub batch_size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_budget_eth: Option<String>,
+    pub gas_spent_eth: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    pub tokens: Progress,
+    #[serde(skip)]
+    gas_budget: Option<U256>,
+    #[serde(skip)]
+    gas_spent: U256,
+}
+
+fn wei(value: Option<String>) -> Option<U256> {
+    value.and_then(|value| U256::from_dec_str(&value).ok())
+}
+
+fn parse_budget(budget: Option<&str>) -> Result<Option<U256>, ApiError> {
+    budget
+        .map(|budget| {
+            parse_ether(budget).map_err(|_| {
+                ApiError::new(StatusCode::BAD_REQUEST, format!("gas_budget_eth is not an amount of ETH: {}", budget))
+            })
+        })
+        .transpose()
+}
+
+pub fn load(db: &Db, id: i64) -> Result<Option<Backfill>, String> {
+    let conn = db.lock().unwrap();
+    let backfill = conn
+        .query_row(
+            "SELECT id, collection, push_onchain, batch_size, gas_budget_wei, gas_spent_wei, status, error,
+                    created_at, completed_at
+             FROM metadata_backfills WHERE id = ?1",
+            params![id],
+            |row| {
+                let gas_budget = wei(row.get(4)?);
+                let gas_spent = wei(row.get(5)?).unwrap_or_default();
+                Ok(Backfill {
+                    id: row.get(0)?,
+                    collection: row.get(1)?,
+                    push_onchain: row.get(2)?,
+                    batch_size: row.get(3)?,
+                    gas_budget_eth: gas_budget.map(format_ether),
+                    gas_spent_eth: format_ether(gas_spent),
+                    status: row.get(6)?,
+                    error: row.get(7)?,
+                    created_at: row.get(8)?,
+                    completed_at: row.get(9)?,
+                    tokens: Progress::default(),
+                    gas_budget,
+                    gas_spent,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load metadata backfill: {}", e))?;
+    let Some(mut backfill) = backfill else {
+        return Ok(None);
+    };
+    let mut stmt = conn
+        .prepare("SELECT status, COUNT(*) FROM metadata_backfill_tokens WHERE backfill_id = ?1 GROUP BY status")
+        .map_err(|e| format!("Failed to load metadata backfill: {}", e))?;
+    let counts = stmt
+        .query_map(params![id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load metadata backfill: {}", e))?;
+    let tokens = &mut backfill.tokens;
+    for (status, count) in counts {
+        tokens.total += count;
+        match status.as_str() {
+            "unchanged" => tokens.unchanged += count,
+            "uploaded" => tokens.uploaded += count,
+            "updated" => tokens.updated += count,
+            "failed" => tokens.failed += count,
+            _ => tokens.pending += count,
+        }
+    }
+    Ok(Some(backfill))
+}
+
+fn active(db: &Db, collection: &str) -> Result<Option<i64>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT id FROM metadata_backfills WHERE collection = ?1 AND status IN (?2, ?3)",
+        params![collection, RUNNING, PAUSED],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up metadata backfills: {}", e))
+}
+
+fn set_status(db: &Db, id: i64, status: &str, error: Option<&str>) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE metadata_backfills
+         SET status = ?2, error = ?3,
+             completed_at = CASE WHEN ?2 = 'completed' THEN CURRENT_TIMESTAMP ELSE completed_at END
+         WHERE id = ?1",
+        params![id, status, error],
+    )
+    .map_err(|e| format!("Failed to update metadata backfill: {}", e))?;
+    Ok(())
+}
+
+fn pending_tokens(db: &Db, id: i64) -> Result<Vec<u64>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare(
+        "SELECT token_id FROM metadata_backfill_tokens WHERE backfill_id = ?1 AND status = 'pending'
+         ORDER BY token_id",
+    )
+    .and_then(|mut stmt| stmt.query_map(params![id], |row| row.get(0))?.collect())
+    .map_err(|e| format!("Failed to load metadata backfill tokens: {}", e))
+}
+
+struct TokenResult<'a> {
+    status: &'a str,
+    changes: Option<usize>,
+    token_uri: Option<String>,
+    transaction_hash: Option<String>,
+    error: Option<String>,
+}
+
+fn mark_token(db: &Db, id: i64, token_id: u64, result: TokenResult) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE metadata_backfill_tokens
+         SET status = ?3, changes = ?4, token_uri = ?5, transaction_hash = ?6, error = ?7
+         WHERE backfill_id = ?1 AND token_id = ?2",
+        params![
+            id,
+            token_id,
+            result.status,
+            result.changes,
+            result.token_uri,
+            result.transaction_hash,
+            result.error
+        ],
+    )
+    .map_err(|e| format!("Failed to update metadata backfill token: {}", e))?;
+    Ok(())
+}
+
+fn add_gas_spent(db: &Db, id: i64, gas_spent: U256) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE metadata_backfills SET gas_spent_wei = ?2 WHERE id = ?1",
+        params![id, gas_spent.to_string()],
+    )
+    .map_err(|e| format!("Failed to update metadata backfill: {}", e))?;
+    Ok(())
+}
+
+fn stored_metadata(db: &Db, collection: &str, token_id: u64) -> Result<Value, String> {
+    let conn = db.lock().unwrap();
+    let metadata: String = conn
+        .query_row(
+            "SELECT metadata FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection, token_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?;
+    Ok(serde_json::from_str(&metadata).unwrap_or_default())
+}
+
+/// Builds the token's metadata again from its stored details with the current attribute
+/// template, keeping its listed price and any appraisal. Returns it with the listed price.
+async fn regenerate(state: &AppState, collection: &Collection, token_id: u64) -> Result<(Value, Money), ApiError> {
+    let (details, price, soulbound) = valuations::stored_details(state, &collection.name, token_id)?;
+    let verification = parcels::verify(state, &details).await;
+    let mut metadata = mint::public_metadata(state, &details, verification.as_ref(), price, soulbound).await;
+    liens::annotate(&state.db, &collection.name, token_id, &mut metadata)?;
+    rarity::annotate(state, &collection.name, Some(token_id), &mut metadata)?;
+    if let Some(appraisal) = appraisals::latest(&state.db, &collection.name, token_id)? {
+        let model_price = appraisal.model_price.map(|model_price| Money::from_f64(model_price, Currency::USD));
+        let appraised = Money::from_f64(appraisal.price, Currency::USD);
+        appraisals::apply(&mut metadata, &details, appraised, &appraisal.license_number, model_price);
+    }
+    let store = valuations::token_store(state, collection, token_id)?;
+    cards::attach(state, Some(store), &details, price, &mut metadata).await?;
+    Ok((metadata, price))
+}
+
+fn batch_interval() -> Duration {
+    let secs = optional_env("BACKFILL_BATCH_INTERVAL_SECS")
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(DEFAULT_BATCH_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Regenerates the backfill's pending tokens batch by batch. Before each batch of on-chain
+/// updates it checks that the batch fits in what is left of the gas budget, costing each update
+/// at the average so far, and pauses the backfill if it doesn't. A token that can't be
+/// regenerated is marked failed and skipped; a failed transaction stops the backfill.
+async fn backfill(state: &AppState, id: i64) -> Result<(), ApiError> {
+    let backfill = load(&state.db, id)?.ok_or("Metadata backfill disappeared")?;
+    if backfill.status != RUNNING {
+        return Ok(());
+    }
+    let collection = registry::resolve(&state.db, Some(&backfill.collection))?;
+    let tenant = match &collection.organization {
+        Some(org) => Tenant(Some(organizations::load(&state.db, org)?.ok_or("Collection organization was deleted")?)),
+        None => Tenant(None),
+    };
+    let chain = state.on(&collection)?;
+    let mut gas_spent = backfill.gas_spent;
+    let mut updated = backfill.tokens.updated;
+    let pending = pending_tokens(&state.db, id)?;
+    let batches: Vec<&[u64]> = pending.chunks(backfill.batch_size.max(1) as usize).collect();
+
+    for (i, batch) in batches.iter().enumerate() {
+        if let (true, Some(budget)) = (backfill.push_onchain, backfill.gas_budget) {
+            let estimate = match updated {
+                0 => U256::zero(),
+                updated => gas_spent / updated * batch.len(),
+            };
+            if gas_spent >= budget || gas_spent + estimate > budget {
+                let reason = format!(
+                    "Gas budget of {} ETH reached: {} ETH spent, the next batch needs about {} ETH",
+                    format_ether(budget),
+                    format_ether(gas_spent),
+                    format_ether(estimate)
+                );
+                println!("Metadata backfill {} paused. {}", id, reason);
+                set_status(&state.db, id, PAUSED, Some(&reason))?;
+                return Ok(());
+            }
+        }
+        for &token_id in batch.iter() {
+            let (metadata, price) = match regenerate(state, &collection, token_id).await {
+                Ok(regenerated) => regenerated,
+                Err(e) => {
+                    let failed = TokenResult {
+                        status: "failed",
+                        changes: None,
+                        token_uri: None,
+                        transaction_hash: None,
+                        error: Some(e.message),
+                    };
+                    mark_token(&state.db, id, token_id, failed)?;
+                    continue;
+                }
+            };
+            let changes = versions::diff(&stored_metadata(&state.db, &collection.name, token_id)?, &metadata).len();
+            let result = if changes == 0 {
+                TokenResult {
+                    status: "unchanged",
+                    changes: Some(0),
+                    token_uri: None,
+                    transaction_hash: None,
+                    error: None,
+                }
+            } else if backfill.push_onchain {
+                let receipt =
+                    valuations::write_metadata(&chain, &tenant, &collection, token_id, price, &metadata).await?;
+                gas_spent += tx::gas_cost(&receipt);
+                updated += 1;
+                add_gas_spent(&state.db, id, gas_spent)?;
+                nfts::spawn_marketplace_refresh(state, &collection, token_id);
+                TokenResult {
+                    status: "updated",
+                    changes: Some(changes),
+                    token_uri: None,
+                    transaction_hash: Some(format!("{:?}", receipt.transaction_hash)),
+                    error: None,
+                }
+            } else {
+                let store = valuations::token_store(state, &collection, token_id)?;
+                match storage::put(state, store, &metadata).await {
+                    Ok(token_uri) => TokenResult {
+                        status: "uploaded",
+                        changes: Some(changes),
+                        token_uri: (!storage::is_inline(store)).then_some(token_uri),
+                        transaction_hash: None,
+                        error: None,
+                    },
+                    Err(e) => TokenResult {
+                        status: "failed",
+                        changes: Some(changes),
+                        token_uri: None,
+                        transaction_hash: None,
+                        error: Some(e.message),
+                    },
+                }
+            };
+            mark_token(&state.db, id, token_id, result)?;
+        }
+        println!("Metadata backfill {}: batch {} of {} done", id, i + 1, batches.len());
+        if backfill.push_onchain && i + 1 < batches.len() {
+            tokio::time::sleep(batch_interval()).await;
+        }
+    }
+    set_status(&state.db, id, COMPLETED, None)?;
+    println!("Metadata backfill {} of collection {} completed", id, collection.name);
+    Ok(())
+}
+
+pub fn spawn(state: AppState, id: i64) {
+    tokio::spawn(async move {
+        if let Err(e) = backfill(&state, id).await {
+            eprintln!("Metadata backfill {} failed: {}", id, e.message);
+            if let Err(e) = set_status(&state.db, id, FAILED, Some(&e.message)) {
+                eprintln!("{}", e);
+            }
+        }
+    });
+}
+
+/// Picks up backfills that were running when the server stopped.
+pub fn resume_running(state: &AppState) {
+    let ids: Result<Vec<i64>, String> = {
+        let conn = state.db.lock().unwrap();
+        conn.prepare("SELECT id FROM metadata_backfills WHERE status = ?1 ORDER BY id")
+            .and_then(|mut stmt| stmt.query_map(params![RUNNING], |row| row.get(0))?.collect())
+            .map_err(|e| format!("Failed to load metadata backfills: {}", e))
+    };
+    match ids {
+        Ok(ids) => ids.into_iter().for_each(|id| spawn(state.clone(), id)),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// Regenerates the metadata of a collection's tokens with the current template, e.g. after
+/// changing METADATA_FIELDS, and optionally writes the changed ones on-chain.
+pub async fn start_backfill(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<StartBackfill>,
+) -> Result<(StatusCode, Json<Backfill>), ApiError> {
+    let collection = registry::resolve(&state.db, Some(&name))?;
+    if !(1..=MAX_BATCH_SIZE).contains(&request.batch_size) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("batch_size must be between 1 and {}", MAX_BATCH_SIZE),
+        ));
+    }
+    let gas_budget = parse_budget(request.gas_budget_eth.as_deref())?;
+    if let Some(id) = active(&state.db, &collection.name)? {
+        return Err(ApiError::new(StatusCode::CONFLICT, "The collection already has a metadata backfill in progress")
+            .with_details(json!({ "backfill": id })));
+    }
+
+    let id = {
+        let mut conn = state.db.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| format!("Failed to start metadata backfill: {}", e))?;
+        let token_ids: Vec<u64> = tx
+            .prepare("SELECT token_id FROM mints WHERE collection = ?1 ORDER BY token_id")
+            .and_then(|mut stmt| stmt.query_map(params![collection.name], |row| row.get(0))?.collect())
+            .map_err(|e| format!("Failed to load mints: {}", e))?;
+        let unknown: Vec<u64> =
+            request.token_ids.iter().copied().filter(|token_id| !token_ids.contains(token_id)).collect();
+        if !unknown.is_empty() {
+            return Err(ApiError::new(StatusCode::NOT_FOUND, "Some tokens were not minted through this service")
+                .with_details(json!({ "token_ids": unknown })));
+        }
+        let token_ids = if request.token_ids.is_empty() { token_ids } else { request.token_ids };
+        tx.execute(
+            "INSERT INTO metadata_backfills (collection, push_onchain, batch_size, gas_budget_wei, status)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                collection.name,
+                request.push_onchain,
+                request.batch_size,
+                gas_budget.map(|budget| budget.to_string()),
+                RUNNING
+            ],
+        )
+        .map_err(|e| format!("Failed to start metadata backfill: {}", e))?;
+        let id = tx.last_insert_rowid();
+        for token_id in &token_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO metadata_backfill_tokens (backfill_id, token_id) VALUES (?1, ?2)",
+                params![id, token_id],
+            )
+            .map_err(|e| format!("Failed to start metadata backfill: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to start metadata backfill: {}", e))?;
+        id
+    };
+    spawn(state.clone(), id);
+    let backfill = load(&state.db, id)?.ok_or("Metadata backfill disappeared")?;
+    println!(
+        "Regenerating metadata for {} tokens of collection {}{}",
+        backfill.tokens.total,
+        collection.name,
+        if backfill.push_onchain { " and updating their token URIs" } else { "" }
+    );
+    Ok((StatusCode::ACCEPTED, Json(backfill)))
+}
+
+pub async fn get_backfill(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Json<Backfill>, ApiError> {
+    let backfill = load(&state.db, id)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown metadata backfill: {}", id)))?;
+    Ok(Json(backfill))
+}
+
+/// Continues a paused or failed backfill from its first pending token.
+pub async fn resume_backfill(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    request: Option<Json<ResumeBackfill>>,
+) -> Result<Json<Backfill>, ApiError> {
+    let gas_budget = request.and_then(|Json(request)| request.gas_budget_eth);
+    let gas_budget = parse_budget(gas_budget.as_deref())?;
+    let resumed = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE metadata_backfills
+             SET status = ?2, error = NULL, gas_budget_wei = COALESCE(?5, gas_budget_wei)
+             WHERE id = ?1 AND status IN (?3, ?4)",
+            params![id, RUNNING, PAUSED, FAILED, gas_budget.map(|budget| budget.to_string())],
+        )
+        .map_err(|e| format!("Failed to resume metadata backfill: {}", e))?
+    };
+    let backfill = load(&state.db, id)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown metadata backfill: {}", id)))?;
+    if resumed == 0 {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Metadata backfill {} is {}", id, backfill.status)));
+    }
+    spawn(state.clone(), id);
+    Ok(Json(backfill))
+}
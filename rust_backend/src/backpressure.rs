@@ -0,0 +1,156 @@
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use serde_json::json;
+use std::sync::{Arc, RwLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+
+#[cfg(test)]
+mod tests;
+
+/// What shed requests are told to wait before retrying.
+const RETRY_AFTER_SECS: u64 = 5;
+
+/// How many requests a group of routes handles at once, and how many more may wait for a slot
+/// before new ones are shed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limit {
+    pub concurrency: usize,
+    pub queue: usize,
+}
+
+impl Limit {
+    /// `<PREFIX>_CONCURRENCY` and `<PREFIX>_QUEUE`.
+    fn from_env(prefix: &str, concurrency: usize, queue: usize) -> Self {
+        let limit = Limit::load(prefix, concurrency, queue).unwrap_or_else(|e| panic!("{}", e));
+        println!("{}_CONCURRENCY: {}", prefix, limit.describe());
+        limit
+    }
+
+    /// Like `from_env`, but reports an invalid setting instead of panicking.
+    fn load(prefix: &str, concurrency: usize, queue: usize) -> Result<Self, String> {
+        let number = |name: String, default: usize| match optional_env(&name) {
+            Some(value) => match value.parse() {
+                Ok(0) => Err(format!("{} must be at least 1", name)),
+                Ok(number) => Ok(number),
+                Err(_) => Err(format!("{} must be a number", name)),
+            },
+            None => Ok(default),
+        };
+        Ok(Limit {
+            concurrency: number(format!("{}_CONCURRENCY", prefix), concurrency)?,
+            queue: number(format!("{}_QUEUE", prefix), queue)?,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} ({} queued)", self.concurrency, self.queue)
+    }
+}
+
+pub struct BackpressureConfig {
+    /// Mints share the wallet's nonce, so only a few run at once, whether requested over HTTP or
+    /// run as jobs.
+    pub mint: Limit,
+    /// Predictions wait on the model service.
+    pub predict: Limit,
+}
+
+impl BackpressureConfig {
+    pub fn from_env() -> Self {
+        BackpressureConfig {
+            mint: Limit::from_env("MINT", 4, 32),
+            predict: Limit::from_env("PREDICT", 16, 64),
+        }
+    }
+
+    /// Like `from_env`, but reports an invalid setting instead of panicking.
+    pub fn load() -> Result<Self, String> {
+        Ok(BackpressureConfig {
+            mint: Limit::load("MINT", 4, 32)?,
+            predict: Limit::load("PREDICT", 16, 64)?,
+        })
+    }
+}
+
+/// The slots of a `Limit`, shared by every route it is applied to and by background work that
+/// takes a `slot`.
+#[derive(Clone)]
+pub struct Limiter {
+    running: Arc<Semaphore>,
+    /// Running and waiting requests together; background work doesn't count towards it.
+    admitted: Arc<Semaphore>,
+    /// Swapped by a config reload through `resize`.
+    limit: Arc<RwLock<Limit>>,
+}
+
+impl Limiter {
+    pub fn new(limit: Limit) -> Self {
+        Limiter {
+            running: Arc::new(Semaphore::new(limit.concurrency)),
+            admitted: Arc::new(Semaphore::new(limit.concurrency + limit.queue)),
+            limit: Arc::new(RwLock::new(limit)),
+        }
+    }
+
+    /// Applies a new limit and returns the previous one. Extra slots are usable at once; removed
+    /// ones are taken away as the requests holding them finish, nothing in progress is cut short.
+    pub fn resize(&self, limit: Limit) -> Limit {
+        let previous = std::mem::replace(&mut *self.limit.write().unwrap(), limit);
+        resize(&self.running, previous.concurrency, limit.concurrency);
+        resize(&self.admitted, previous.concurrency + previous.queue, limit.concurrency + limit.queue);
+        previous
+    }
+
+    /// Waits for a slot to run in, for work that has to run eventually rather than be shed.
+    pub async fn slot(&self) -> OwnedSemaphorePermit {
+        self.running.clone().acquire_owned().await.expect("Limiter semaphores are never closed")
+    }
+}
+
+fn resize(semaphore: &Arc<Semaphore>, from: usize, to: usize) {
+    if to >= from {
+        semaphore.add_permits(to - from);
+        return;
+    }
+    let busy = from - to - semaphore.forget_permits(from - to);
+    if busy > 0 {
+        // Queued ahead of new requests, so the freed slots go here first.
+        let semaphore = semaphore.clone();
+        tokio::spawn(async move {
+            if let Ok(permits) = semaphore.acquire_many_owned(busy as u32).await {
+                permits.forget();
+            }
+        });
+    }
+}
+
+/// Limits `router`'s routes together to `limiter`: requests beyond the concurrency wait in a
+/// bounded queue, and once that is full they are shed with 503 and Retry-After instead of
+/// piling up. Apply it before the other route layers so only requests that passed them count.
+pub fn limit<S: Clone + Send + Sync + 'static>(router: Router<S>, limiter: Limiter) -> Router<S> {
+    router.route_layer(middleware::from_fn_with_state(limiter, admit))
+}
+
+async fn admit(State(limiter): State<Limiter>, request: Request<Body>, next: Next<Body>) -> Response {
+    let Ok(_admitted) = limiter.admitted.clone().try_acquire_owned() else {
+        return overloaded();
+    };
+    let _running = limiter.slot().await;
+    next.run(request).await
+}
+
+fn overloaded() -> Response {
+    let mut response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Too many requests in progress; try again shortly")
+        .with_details(json!({ "retry_after_secs": RETRY_AFTER_SECS }))
+        .into_response();
+    response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    response
+}
@@ -0,0 +1,133 @@
+use axum::body::Body;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use hyper::service::Service;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+use super::{limit, Limit, Limiter};
+
+fn limiter(concurrency: usize, queue: usize) -> Limiter {
+    Limiter::new(Limit { concurrency, queue })
+}
+
+/// A route limited to `limiter` whose requests block until `gate` lets them through.
+fn router(limiter: &Limiter, gate: &Arc<Semaphore>) -> Router {
+    let gate = gate.clone();
+    let route = Router::new().route(
+        "/",
+        get(move || async move {
+            gate.acquire().await.unwrap().forget();
+        }),
+    );
+    limit(route, limiter.clone())
+}
+
+fn send(router: &Router) -> JoinHandle<Response> {
+    let mut router = router.clone();
+    tokio::spawn(async move { router.call(Request::get("/").body(Body::empty()).unwrap()).await.unwrap() })
+}
+
+/// Waits until `semaphore` has `available` permits, i.e. the requests sent so far are running or queued.
+async fn settle(semaphore: &Semaphore, available: usize) {
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while semaphore.available_permits() != available {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .expect("requests did not settle");
+}
+
+#[tokio::test]
+async fn sheds_requests_beyond_the_queue() {
+    let (limiter, gate) = (limiter(1, 1), Arc::new(Semaphore::new(0)));
+    let router = router(&limiter, &gate);
+    let (running, queued) = (send(&router), send(&router));
+    settle(&limiter.admitted, 0).await;
+
+    let shed = send(&router).await.unwrap();
+    assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(shed.headers()[RETRY_AFTER], "5");
+
+    gate.add_permits(2);
+    assert_eq!(running.await.unwrap().status(), StatusCode::OK);
+    assert_eq!(queued.await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn releases_slots_when_requests_finish() {
+    let (limiter, gate) = (limiter(1, 1), Arc::new(Semaphore::new(0)));
+    let router = router(&limiter, &gate);
+    let requests = [send(&router), send(&router)];
+    settle(&limiter.admitted, 0).await;
+    gate.add_permits(2);
+    for request in requests {
+        assert_eq!(request.await.unwrap().status(), StatusCode::OK);
+    }
+    assert_eq!(limiter.running.available_permits(), 1);
+    assert_eq!(limiter.admitted.available_permits(), 2);
+}
+
+#[tokio::test]
+async fn releases_slots_when_requests_are_cancelled() {
+    let (limiter, gate) = (limiter(1, 1), Arc::new(Semaphore::new(0)));
+    let router = router(&limiter, &gate);
+    let (running, queued) = (send(&router), send(&router));
+    settle(&limiter.admitted, 0).await;
+
+    // A client hanging up drops the request, whether it was running or still waiting.
+    queued.abort();
+    settle(&limiter.admitted, 1).await;
+    running.abort();
+    settle(&limiter.running, 1).await;
+    settle(&limiter.admitted, 2).await;
+
+    gate.add_permits(1);
+    assert_eq!(send(&router).await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn background_work_shares_the_slots() {
+    let (limiter, gate) = (limiter(1, 1), Arc::new(Semaphore::new(0)));
+    let router = router(&limiter, &gate);
+    let job = limiter.slot().await;
+    // The job holds the only slot, so one request waits and the next is shed.
+    let queued = send(&router);
+    settle(&limiter.admitted, 1).await;
+    let _waiting = send(&router);
+    settle(&limiter.admitted, 0).await;
+    assert_eq!(send(&router).await.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    drop(job);
+    gate.add_permits(1);
+    assert_eq!(queued.await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn growing_adds_slots_at_once() {
+    let limiter = limiter(1, 1);
+    let _first = limiter.slot().await;
+    let previous = limiter.resize(Limit { concurrency: 2, queue: 1 });
+    assert_eq!(previous, Limit { concurrency: 1, queue: 1 });
+    assert_eq!(limiter.running.available_permits(), 1);
+    assert_eq!(limiter.admitted.available_permits(), 3);
+}
+
+#[tokio::test]
+async fn shrinking_waits_for_slots_in_use() {
+    let limiter = limiter(2, 1);
+    let (first, second) = (limiter.slot().await, limiter.slot().await);
+    limiter.resize(Limit { concurrency: 1, queue: 1 });
+    // The first slot released is the one taken away; the next is usable again.
+    drop(first);
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(limiter.running.available_permits(), 0);
+    drop(second);
+    assert_eq!(limiter.running.available_permits(), 1);
+}
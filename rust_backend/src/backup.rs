@@ -0,0 +1,72 @@
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::db;
+
+/// Pages copied per step. The source is only locked while a step runs, so a live server keeps
+/// serving during a backup.
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+
+/// Checks the file is an intact database and returns its schema version.
+fn verify(conn: &Connection) -> Result<usize, String> {
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to check the database: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("The database is corrupt: {}", integrity));
+    }
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read the schema version: {}", e))
+}
+
+pub fn backup(database_path: &str, destination: &Path) -> Result<(), String> {
+    if destination.exists() {
+        return Err(format!("{} already exists", destination.display()));
+    }
+    let source = Connection::open_with_flags(database_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", database_path, e))?;
+    let mut target =
+        Connection::open(destination).map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    Backup::new(&source, &mut target)
+        .and_then(|backup| backup.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None))
+        .map_err(|e| format!("Failed to back up the database: {}", e))?;
+    let version = verify(&target)?;
+    println!("Backed up {} to {} (schema version {})", database_path, destination.display(), version);
+    Ok(())
+}
+
+/// Replaces the database with `source`, then applies any migrations newer than the backup.
+pub fn restore(database_path: &str, source: &Path, force: bool) -> Result<(), String> {
+    let backup = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    let version = verify(&backup)?;
+    if version > db::schema_version() {
+        return Err(format!(
+            "The backup has schema version {}, newer than this build supports ({}); restore it with a newer build",
+            version,
+            db::schema_version()
+        ));
+    }
+    drop(backup);
+    if Path::new(database_path).exists() && !force {
+        return Err(format!("{} already exists; pass --force to replace it", database_path));
+    }
+
+    let mut target = Connection::open(database_path).map_err(|e| format!("Failed to open {}: {}", database_path, e))?;
+    target
+        .restore(DatabaseName::Main, source, None::<fn(rusqlite::backup::Progress)>)
+        .map_err(|e| format!("Failed to restore the database: {}", e))?;
+    drop(target);
+    db::open(database_path);
+    println!(
+        "Restored {} from {} (schema version {} migrated to {})",
+        database_path,
+        source.display(),
+        version,
+        db::schema_version()
+    );
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::dry_run::{self, Fork};
+use crate::mint::{self, HouseDetails, MintRequest};
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+/// Latency percentiles of one pipeline stage, in milliseconds.
+#[derive(Serialize)]
+pub struct StageReport {
+    count: usize,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Serialize)]
+pub struct BenchReport {
+    houses: usize,
+    concurrency: usize,
+    mock_chain: bool,
+    elapsed_secs: f64,
+    /// Houses completed per second, failures included.
+    throughput_per_sec: f64,
+    errors: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sample_errors: Vec<String>,
+    stages: BTreeMap<&'static str, StageReport>,
+}
+
+/// A plausible King County house, varied deterministically by `index` so runs are comparable.
+pub fn synthetic_house(index: u64) -> HouseDetails {
+    let mut seed = index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut next = |range: u64| {
+        seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (seed >> 33) % range
+    };
+    let sqft_living = 600 + next(4_000);
+    let sqft_basement = if next(3) == 0 { next(sqft_living / 2) } else { 0 };
+    let sqft_lot = sqft_living + next(15_000);
+    let yr_built = 1900 + next(124);
+    HouseDetails {
+        name: format!("Bench House {}", index),
+        bedrooms: 1 + next(6),
+        bathrooms: 1.0 + next(7) as f64 * 0.5,
+        sqft_living,
+        sqft_lot,
+        floors: 1 + next(3),
+        waterfront: u64::from(next(100) == 0),
+        view: next(5),
+        condition: 1 + next(5),
+        grade: 4 + next(9),
+        sqft_above: sqft_living - sqft_basement,
+        sqft_basement,
+        yr_built,
+        yr_renovated: if next(10) == 0 { yr_built + next(2024 - yr_built + 1) } else { 0 },
+        zipcode: 98001 + next(199),
+        lat: 47.15 + next(640) as f64 / 1000.0,
+        long: -122.52 + next(1200) as f64 / 1000.0,
+        sqft_living15: sqft_living,
+        sqft_lot15: sqft_lot,
+        month: 1 + next(12),
+        year: 2014 + next(2),
+        image: None,
+    }
+}
+
+fn percentile(sorted: &[Duration], quantile: f64) -> f64 {
+    let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+    sorted[index].as_secs_f64() * 1000.0
+}
+
+fn stage_report(mut latencies: Vec<Duration>) -> StageReport {
+    latencies.sort();
+    StageReport {
+        count: latencies.len(),
+        p50_ms: percentile(&latencies, 0.5),
+        p90_ms: percentile(&latencies, 0.9),
+        p99_ms: percentile(&latencies, 0.99),
+        max_ms: percentile(&latencies, 1.0),
+    }
+}
+
+type Timings = Vec<(&'static str, Duration)>;
+
+/// Prices the house and builds its metadata, or with a mock chain runs the whole mint.
+async fn run_one(state: &AppState, index: u64, mock_chain: bool) -> Result<Timings, String> {
+    let details = synthetic_house(index);
+    let started = Instant::now();
+    if mock_chain {
+        let request = MintRequest {
+            details,
+            collection: None,
+            network: None,
+            recipient: None,
+            payer: None,
+            soulbound: false,
+            scheduled_at: None,
+            metadata_storage: None,
+            private: None,
+            force: false,
+        };
+        mint::execute(state, &Tenant(None), request, None, false).await.map_err(|e| e.to_string())?;
+        return Ok(vec![("mint", started.elapsed())]);
+    }
+    let price = mint::predict_price(state, &details).await.map_err(|e| e.to_string())?;
+    let predicted = started.elapsed();
+    let started = Instant::now();
+    mint::public_metadata(state, &details, None, price, false).await;
+    Ok(vec![("predict", predicted), ("metadata", started.elapsed())])
+}
+
+/// Pushes `count` synthetic houses through the prediction and metadata pipeline, `concurrency`
+/// at a time, and reports throughput and latency percentiles. With `mock_chain` each house is
+/// minted end to end on an Anvil fork instead. Bookkeeping goes to a scratch copy of the database.
+pub async fn run(state: AppState, count: usize, concurrency: usize, mock_chain: bool) -> Result<BenchReport, String> {
+    if count == 0 || concurrency == 0 {
+        return Err("--count and --concurrency must be at least 1".to_string());
+    }
+    let db = dry_run::snapshot(&state.db)?;
+    let fork = match mock_chain {
+        true => Some(Fork::open(&state.config.alchemy_url, state.chain.chain_id).await.map_err(|e| e.to_string())?),
+        false => None,
+    };
+    let state = match &fork {
+        Some(fork) => state.forked(None, &fork.rpc_url, db).map_err(|e| e.to_string())?,
+        None => {
+            let mut state = state;
+            state.db = db;
+            state
+        }
+    };
+
+    println!("Benchmarking {} houses, {} at a time...", count, concurrency);
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = JoinSet::new();
+    let started = Instant::now();
+    for index in 0..count as u64 {
+        let permit = permits.clone().acquire_owned().await.map_err(|e| e.to_string())?;
+        let state = state.clone();
+        tasks.spawn(async move {
+            let result = run_one(&state, index, mock_chain).await;
+            drop(permit);
+            result
+        });
+    }
+    let mut latencies: BTreeMap<&'static str, Vec<Duration>> = BTreeMap::new();
+    let mut errors = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        match result.map_err(|e| e.to_string()).and_then(|result| result) {
+            Ok(timings) => {
+                for (stage, latency) in timings {
+                    latencies.entry(stage).or_default().push(latency);
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+    Ok(BenchReport {
+        houses: count,
+        concurrency,
+        mock_chain,
+        elapsed_secs: elapsed,
+        throughput_per_sec: count as f64 / elapsed,
+        errors: errors.len(),
+        sample_errors: errors.into_iter().take(5).collect(),
+        stages: latencies.into_iter().map(|(stage, latencies)| (stage, stage_report(latencies))).collect(),
+    })
+}
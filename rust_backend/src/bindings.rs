@@ -0,0 +1,131 @@
+use ethers::contract::abigen;
+
+abigen!(RealEstateNFT, "./abi/RealEstateNFT.json");
+
+// ERC-165 introspection, for collections whose runtime ABI may not declare it.
+abigen!(
+    Erc165,
+    r#"[
+        function supportsInterface(bytes4 interfaceId) external view returns (bool)
+    ]"#
+);
+
+// Supply counters: ERC-721 Enumerable's totalSupply, and the bundled contract's mint counter,
+// which collections deployed from other artifacts may not have.
+abigen!(
+    SupplyCounters,
+    r#"[
+        function totalSupply() external view returns (uint256)
+        function tokenCounter() external view returns (uint256)
+    ]"#
+);
+
+// Optional extensions (OpenZeppelin Pausable/AccessControl) that newer collection
+// contracts may implement; calls against contracts without them revert in simulation.
+abigen!(
+    AdminControls,
+    r#"[
+        function pause() external
+        function unpause() external
+        function paused() external view returns (bool)
+        function grantRole(bytes32 role, address account) external
+        function revokeRole(bytes32 role, address account) external
+        function hasRole(bytes32 role, address account) external view returns (bool)
+    ]"#
+);
+
+// Collections that restrict minting to allowlisted recipients take a Merkle proof.
+abigen!(
+    AllowlistMint,
+    r#"[
+        function mintNFT(address to, string tokenURI, bytes32[] proof) external returns (uint256)
+        function setMerkleRoot(bytes32 root) external
+    ]"#
+);
+
+// Collections with non-transferable property records expose a separate mint entry point.
+abigen!(
+    SoulboundMint,
+    r#"[
+        function mintSoulbound(address to, string tokenURI) external returns (uint256)
+    ]"#
+);
+
+// Upgraded collection contracts that can re-mint a migrated token under its original ID.
+abigen!(
+    MigrationMint,
+    r#"[
+        function migrateToken(address to, uint256 tokenId, string tokenURI) external
+    ]"#
+);
+
+// ERC-2981 royalties with OpenZeppelin's ERC2981 setters.
+abigen!(
+    Royalties,
+    r#"[
+        function royaltyInfo(uint256 tokenId, uint256 salePrice) external view returns (address, uint256)
+        function setDefaultRoyalty(address receiver, uint96 feeNumerator) external
+        function setTokenRoyalty(uint256 tokenId, address receiver, uint96 feeNumerator) external
+    ]"#
+);
+
+// OpenSea contract-level metadata, on contracts that let the owner set it.
+abigen!(
+    ContractMetadataUri,
+    r#"[
+        function contractURI() external view returns (string)
+        function setContractURI(string uri) external
+    ]"#
+);
+
+// ERC-1155 vault that locks a property NFT and mints fungible ownership shares against it.
+abigen!(
+    FractionalVault,
+    r#"[
+        function fractionalize(address nft, uint256 tokenId, uint256 shares, address recipient) external returns (uint256)
+        function redeem(uint256 shareId) external
+        function balanceOf(address account, uint256 id) external view returns (uint256)
+        event Fractionalized(address indexed nft, uint256 indexed tokenId, uint256 indexed shareId, uint256 shares)
+        event TransferSingle(address indexed operator, address indexed from, address indexed to, uint256 id, uint256 value)
+        event TransferBatch(address indexed operator, address indexed from, address indexed to, uint256[] ids, uint256[] values)
+    ]"#
+);
+
+// ERC-4907 rentable NFTs: a time-limited `user` (tenant) separate from the owner.
+abigen!(
+    Rentable,
+    r#"[
+        function setUser(uint256 tokenId, address user, uint64 expires) external
+        function userOf(uint256 tokenId) external view returns (address)
+        function userExpires(uint256 tokenId) external view returns (uint256)
+    ]"#
+);
+
+// Marketplace escrow that swaps an approved NFT for ETH or ERC-20 payment.
+abigen!(
+    Escrow,
+    r#"[
+        function settle(address nft, uint256 tokenId, address seller, address buyer, address paymentToken, uint256 amount) external
+    ]"#
+);
+
+// ERC-20 token (e.g. USDC) used to charge mint fees.
+abigen!(
+    Erc20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function transferFrom(address from, address to, uint256 amount) external returns (bool)
+    ]"#
+);
+
+// Chainalysis sanctions screening oracle.
+abigen!(
+    SanctionsOracle,
+    r#"[
+        function isSanctioned(address addr) external view returns (bool)
+    ]"#
+);
+
+pub use fractional_vault::{FractionalVaultEvents, FractionalizedFilter};
+pub use real_estate_nft::{TransferFilter, REALESTATENFT_ABI, REALESTATENFT_BYTECODE};
@@ -0,0 +1,172 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, H256, U256};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+/// Where a token went: a registered collection, or any contract on any chain.
+#[derive(Deserialize)]
+pub struct RecordBridge {
+    destination_collection: Option<String>,
+    chain_id: Option<u64>,
+    contract: Option<Address>,
+    /// The token's ID on the destination contract; canonical bridges usually keep the origin's.
+    token_id: Option<String>,
+    /// Name of the bridge, e.g. `optimism-standard`.
+    bridge: Option<String>,
+    transaction_hash: Option<H256>,
+}
+
+#[derive(Serialize)]
+pub struct BridgedToken {
+    pub id: i64,
+    pub origin_collection: String,
+    pub origin_token_id: u64,
+    pub destination_chain_id: u64,
+    pub destination_contract: String,
+    pub destination_token_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination_collection: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<String>,
+    pub bridged_at: String,
+}
+
+/// Every bridge the token took part in, whether it was the origin or the destination copy.
+pub fn load(db: &Db, collection: &str, token_id: &str) -> Result<Vec<BridgedToken>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare(
+        "SELECT id, origin_collection, origin_token_id, destination_chain_id, destination_contract,
+                destination_token_id, destination_collection, bridge, transaction_hash, created_at
+         FROM bridged_tokens
+         WHERE (origin_collection = ?1 AND CAST(origin_token_id AS TEXT) = ?2)
+            OR (destination_collection = ?1 AND destination_token_id = ?2)
+         ORDER BY id",
+    )
+    .and_then(|mut stmt| {
+        stmt.query_map(params![collection, token_id], |row| {
+            Ok(BridgedToken {
+                id: row.get(0)?,
+                origin_collection: row.get(1)?,
+                origin_token_id: row.get(2)?,
+                destination_chain_id: row.get(3)?,
+                destination_contract: row.get(4)?,
+                destination_token_id: row.get(5)?,
+                destination_collection: row.get(6)?,
+                bridge: row.get(7)?,
+                transaction_hash: row.get(8)?,
+                bridged_at: row.get(9)?,
+            })
+        })?
+        .collect()
+    })
+    .map_err(|e| format!("Failed to load bridged tokens: {}", e))
+}
+
+/// Adds the token's bridge history to its metadata, so a property moved to another chain
+/// can still be traced back to its record here.
+pub fn annotate(db: &Db, collection: &str, token_id: u64, metadata: &mut Value) -> Result<(), String> {
+    let bridges = load(db, collection, &token_id.to_string())?;
+    if let (Some(metadata), false) = (metadata.as_object_mut(), bridges.is_empty()) {
+        metadata.insert("bridges".to_string(), serde_json::to_value(bridges).unwrap_or_default());
+    }
+    Ok(())
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<String>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<BridgedToken>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let token_id = U256::from_dec_str(&token_id)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "token_id must be a decimal number"))?;
+    Ok(Json(load(&state.db, &collection.name, &token_id.to_string())?))
+}
+
+/// Records that a token minted here was bridged to another contract.
+pub async fn record(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<RecordBridge>,
+) -> Result<(StatusCode, Json<BridgedToken>), ApiError> {
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let destination = match &request.destination_collection {
+        Some(name) => Some(registry::resolve(&state.db, Some(name))?),
+        None => None,
+    };
+    let (chain_id, contract) = match &destination {
+        Some(destination) => {
+            let chain_id = state.chain_of(destination).chain_id;
+            if request.chain_id.is_some_and(|id| id != chain_id)
+                || request.contract.is_some_and(|contract| contract != destination.address)
+            {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("chain_id and contract don't match collection {}", destination.name),
+                ));
+            }
+            (chain_id, destination.address)
+        }
+        None => match (request.chain_id, request.contract) {
+            (Some(chain_id), Some(contract)) => (chain_id, contract),
+            _ => {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    "Pass destination_collection, or chain_id and contract",
+                ))
+            }
+        },
+    };
+    if destination.as_ref().is_some_and(|destination| destination.name == collection.name) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "A token can't be bridged to its own collection"));
+    }
+    let destination_token_id = match &request.token_id {
+        Some(id) => U256::from_dec_str(id)
+            .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "token_id must be a decimal number"))?,
+        None => U256::from(token_id),
+    };
+
+    let id = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO bridged_tokens (origin_collection, origin_token_id, destination_chain_id, destination_contract,
+                                         destination_token_id, destination_collection, bridge, transaction_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                collection.name,
+                token_id,
+                chain_id,
+                format!("{:?}", contract),
+                destination_token_id.to_string(),
+                destination.as_ref().map(|destination| &destination.name),
+                request.bridge,
+                request.transaction_hash.map(|hash| format!("{:?}", hash)),
+            ],
+        )
+        .map_err(|e| format!("Failed to record bridged token: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    println!(
+        "Token {} of {} was bridged to token {} of {:?} on chain {}",
+        token_id, collection.name, destination_token_id, contract, chain_id
+    );
+    let bridged = load(&state.db, &collection.name, &token_id.to_string())?
+        .into_iter()
+        .find(|bridged| bridged.id == id)
+        .ok_or("Bridged token disappeared")?;
+    Ok((StatusCode::CREATED, Json(bridged)))
+}
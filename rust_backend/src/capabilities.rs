@@ -0,0 +1,224 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::utils::hex;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::bindings::Erc165;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+/// ERC-165 interfaces that API features depend on.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Interface {
+    Erc721,
+    Enumerable,
+    Metadata,
+    Royalties,
+    Rentable,
+}
+
+const INTERFACES: [Interface; 5] = [
+    Interface::Erc721,
+    Interface::Enumerable,
+    Interface::Metadata,
+    Interface::Royalties,
+    Interface::Rentable,
+];
+
+impl Interface {
+    fn id(self) -> [u8; 4] {
+        match self {
+            Interface::Erc721 => [0x80, 0xac, 0x58, 0xcd],
+            Interface::Enumerable => [0x78, 0x0e, 0x9d, 0x63],
+            Interface::Metadata => [0x5b, 0x5e, 0x13, 0x9f],
+            Interface::Royalties => [0x2a, 0x55, 0x20, 0x5a],
+            Interface::Rentable => [0xad, 0x09, 0x2b, 0x5c],
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interface::Erc721 => "ERC-721",
+            Interface::Enumerable => "ERC-721 Enumerable",
+            Interface::Metadata => "ERC-721 Metadata",
+            Interface::Royalties => "ERC-2981",
+            Interface::Rentable => "ERC-4907",
+        }
+    }
+
+    /// What the API can't do for collections without the interface.
+    fn features(self) -> &'static str {
+        match self {
+            Interface::Erc721 => "transfers",
+            Interface::Enumerable => "supply and enumeration",
+            Interface::Metadata => "token metadata",
+            Interface::Royalties => "royalties",
+            Interface::Rentable => "rentals",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Capability {
+    interface: &'static str,
+    interface_id: String,
+    /// `None` until the contract has been probed successfully.
+    supported: Option<bool>,
+    features: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct Capabilities {
+    collection: String,
+    address: String,
+    interfaces: Vec<Capability>,
+}
+
+/// Asks the contract which interfaces it supports and records the answers. Contracts without
+/// ERC-165 revert, and support none of them; node failures record nothing.
+pub async fn probe(state: &AppState, collection: &Collection) -> Result<(), String> {
+    let client = state.on(collection).map_err(|e| e.message)?.client;
+    let contract = Erc165::new(collection.address, client);
+    let mut supported = Vec::new();
+    for interface in INTERFACES {
+        match contract.supports_interface(interface.id()).call().await {
+            Ok(yes) => supported.push((interface, yes)),
+            Err(e) if e.is_revert() => supported.push((interface, false)),
+            Err(e) => {
+                return Err(format!("Failed to probe {} support of {}: {}", interface.name(), collection.name, e))
+            }
+        }
+    }
+    let conn = state.db.lock().unwrap();
+    for (interface, yes) in supported {
+        conn.execute(
+            "INSERT INTO collection_interfaces (collection, interface, supported) VALUES (?1, ?2, ?3)
+             ON CONFLICT (collection, interface) DO UPDATE SET
+                 supported = excluded.supported, probed_at = CURRENT_TIMESTAMP",
+            params![collection.name, interface.name(), yes],
+        )
+        .map_err(|e| format!("Failed to record capabilities: {}", e))?;
+    }
+    Ok(())
+}
+
+fn recorded(state: &AppState, collection: &Collection, interface: Interface) -> Result<Option<bool>, String> {
+    let conn = state.db.lock().unwrap();
+    conn.query_row(
+        "SELECT supported FROM collection_interfaces WHERE collection = ?1 AND interface = ?2",
+        params![collection.name, interface.name()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load capabilities: {}", e))
+}
+
+/// Whether the collection's contract implements the interface, probing collections that haven't
+/// been probed yet. If probing fails the contract is assumed to implement it.
+pub async fn supports(state: &AppState, collection: &Collection, interface: Interface) -> Result<bool, String> {
+    if let Some(supported) = recorded(state, collection, interface)? {
+        return Ok(supported);
+    }
+    match probe(state, collection).await {
+        Ok(()) => Ok(recorded(state, collection, interface)?.unwrap_or(true)),
+        Err(e) => {
+            eprintln!("{}", e);
+            Ok(true)
+        }
+    }
+}
+
+/// Rejects operations the collection's contract doesn't implement with 501.
+pub async fn require(state: &AppState, collection: &Collection, interface: Interface) -> Result<(), ApiError> {
+    if supports(state, collection, interface).await? {
+        return Ok(());
+    }
+    Err(ApiError::new(
+        StatusCode::NOT_IMPLEMENTED,
+        format!(
+            "Collection {} does not support {}, so {} are unavailable",
+            collection.name,
+            interface.name(),
+            interface.features()
+        ),
+    )
+    .with_details(json!({
+        "collection": collection.name,
+        "interface": interface.name(),
+        "interface_id": format!("0x{}", hex::encode(interface.id())),
+    })))
+}
+
+fn capabilities(state: &AppState, collection: &Collection) -> Result<Capabilities, String> {
+    let interfaces = INTERFACES
+        .into_iter()
+        .map(|interface| {
+            Ok(Capability {
+                interface: interface.name(),
+                interface_id: format!("0x{}", hex::encode(interface.id())),
+                supported: recorded(state, collection, interface)?,
+                features: interface.features(),
+            })
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Capabilities {
+        collection: collection.name.clone(),
+        address: format!("{:?}", collection.address),
+        interfaces,
+    })
+}
+
+pub async fn get_capabilities(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Capabilities>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    Ok(Json(capabilities(&state, &collection)?))
+}
+
+/// Re-probes a collection, e.g. after its contract was upgraded.
+pub async fn reprobe(State(state): State<AppState>, Path(name): Path<String>) -> Result<Json<Capabilities>, ApiError> {
+    let collection = registry::resolve(&state.db, Some(&name))?;
+    probe(&state, &collection)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, e))?;
+    Ok(Json(capabilities(&state, &collection)?))
+}
+
+/// Probes one collection in the background, logging what it lacks.
+pub fn spawn_probe(state: &AppState, collection: Collection) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = probe(&state, &collection).await {
+            eprintln!("{}", e);
+            return;
+        }
+        let missing: Vec<&str> = INTERFACES
+            .into_iter()
+            .filter(|&interface| recorded(&state, &collection, interface).ok().flatten() == Some(false))
+            .map(Interface::name)
+            .collect();
+        if !missing.is_empty() {
+            println!("Collection {} does not support {}", collection.name, missing.join(", "));
+        }
+    });
+}
+
+/// Probes every registered collection on startup.
+pub fn start(state: &AppState) {
+    match registry::list_all(&state.db) {
+        Ok(collections) => {
+            for collection in collections {
+                spawn_probe(state, collection);
+            }
+        }
+        Err(e) => eprintln!("{}", e),
+    }
+}
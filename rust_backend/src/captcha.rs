@@ -0,0 +1,92 @@
+use axum::extract::{ConnectInfo, State};
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+use std::env;
+use std::net::SocketAddr;
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::rbac::Role;
+use crate::state::AppState;
+
+pub const TOKEN_HEADER: &str = "X-Captcha-Token";
+
+#[derive(Clone, Copy, Debug)]
+pub enum CaptchaProvider {
+    HCaptcha,
+    Turnstile,
+}
+
+pub struct CaptchaConfig {
+    provider: CaptchaProvider,
+    secret: String,
+}
+
+impl CaptchaConfig {
+    /// Enabled by setting CAPTCHA_PROVIDER (`hcaptcha` or `turnstile`) and CAPTCHA_SECRET.
+    pub fn from_env() -> Option<Self> {
+        let provider = match optional_env("CAPTCHA_PROVIDER")?.to_lowercase().as_str() {
+            "hcaptcha" => CaptchaProvider::HCaptcha,
+            "turnstile" => CaptchaProvider::Turnstile,
+            other => panic!("Unsupported CAPTCHA_PROVIDER: {} (expected hcaptcha or turnstile)", other),
+        };
+        println!("CAPTCHA_PROVIDER: {:?}", provider);
+        Some(CaptchaConfig {
+            provider,
+            secret: env::var("CAPTCHA_SECRET").expect("CAPTCHA_SECRET is not set in .env"),
+        })
+    }
+}
+
+impl CaptchaProvider {
+    fn verify_url(self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            CaptchaProvider::Turnstile => "https://challenges.cloudflare.com/turnstile/v0/siteverify",
+        }
+    }
+}
+
+/// Route layer requiring anonymous callers to pass a CAPTCHA token in `X-Captcha-Token`.
+/// Admins and organization API keys are trusted and skip the check.
+pub async fn require<B>(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, ApiError> {
+    let Some(captcha) = &state.config.captcha else {
+        return Ok(next.run(request).await);
+    };
+    let authenticated = request.extensions().get::<Role>() == Some(&Role::Admin)
+        || matches!(request.extensions().get::<Tenant>(), Some(Tenant(Some(_))));
+    if authenticated {
+        return Ok(next.run(request).await);
+    }
+
+    let token = request
+        .headers()
+        .get(TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "CAPTCHA token is required"))?;
+    let remote_ip = peer.ip().to_string();
+    let result: Value = state
+        .http
+        .post(captcha.provider.verify_url())
+        .form(&[("secret", captcha.secret.as_str()), ("response", token), ("remoteip", remote_ip.as_str())])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to verify CAPTCHA: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse CAPTCHA verification: {}", e))?;
+
+    if result["success"].as_bool() != Some(true) {
+        return Err(ApiError::new(StatusCode::FORBIDDEN, "CAPTCHA verification failed")
+            .with_details(result["error-codes"].clone()));
+    }
+    Ok(next.run(request).await)
+}
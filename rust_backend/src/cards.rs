@@ -0,0 +1,135 @@
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use base64::Engine;
+use serde_json::Value;
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::maps::{self, Thumbnail, THUMBNAIL_SIZE};
+use crate::mint::HouseDetails;
+use crate::money::Money;
+use crate::report::money;
+use crate::state::AppState;
+use crate::storage::MetadataStore;
+
+pub const SVG_CONTENT_TYPE: &str = "image/svg+xml";
+const MAX_NAME_CHARS: usize = 32;
+/// Where the map thumbnail sits on the card.
+const MAP_X: u32 = 180;
+const MAP_Y: u32 = 120;
+
+pub struct CardConfig {
+    background: String,
+    accent: String,
+}
+
+impl CardConfig {
+    /// Enabled by PROPERTY_CARDS=true; PROPERTY_CARD_BACKGROUND and PROPERTY_CARD_ACCENT set
+    /// the colours.
+    pub fn from_env() -> Option<Self> {
+        if optional_env("PROPERTY_CARDS").is_none_or(|enabled| enabled != "true") {
+            return None;
+        }
+        let config = CardConfig {
+            background: optional_env("PROPERTY_CARD_BACKGROUND").unwrap_or_else(|| "#1f2937".to_string()),
+            accent: optional_env("PROPERTY_CARD_ACCENT").unwrap_or_else(|| "#f59e0b".to_string()),
+        };
+        println!("PROPERTY_CARDS: enabled");
+        Some(config)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The map thumbnail as an embedded SVG image, with its pin if the provider didn't draw one.
+fn map_image(config: &CardConfig, map: &Thumbnail) -> String {
+    let mut image = format!(
+        r#"<image x="{}" y="{}" width="{size}" height="{size}" href="data:image/png;base64,{}"/>"#,
+        MAP_X,
+        MAP_Y,
+        base64::engine::general_purpose::STANDARD.encode(&map.png),
+        size = THUMBNAIL_SIZE
+    );
+    if let Some((x, y)) = map.pin {
+        image.push_str(&format!(
+            r#"<circle cx="{:.1}" cy="{:.1}" r="8" fill="{}" stroke="white" stroke-width="3"/>"#,
+            f64::from(MAP_X) + x * f64::from(THUMBNAIL_SIZE),
+            f64::from(MAP_Y) + y * f64::from(THUMBNAIL_SIZE),
+            escape(&config.accent)
+        ));
+    }
+    image
+}
+
+/// A 600x600 SVG card showing the property's name, size, price and, if there is one, map.
+pub fn render(config: &CardConfig, details: &HouseDetails, price: Money, map: Option<&Thumbnail>) -> String {
+    let mut name: String = details.name.chars().take(MAX_NAME_CHARS).collect();
+    if details.name.chars().count() > MAX_NAME_CHARS {
+        name.push('…');
+    }
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="600" height="600" viewBox="0 0 600 600">
+<rect width="600" height="600" fill="{background}"/>
+<rect x="0" y="0" width="600" height="12" fill="{accent}"/>
+{map}
+<text x="40" y="90" font-family="Helvetica, Arial, sans-serif" font-size="34" font-weight="bold" fill="#ffffff">{name}</text>
+<text x="40" y="410" font-family="Helvetica, Arial, sans-serif" font-size="28" fill="#d1d5db">{bedrooms} bd · {bathrooms} ba · {sqft} sqft</text>
+<text x="40" y="520" font-family="Helvetica, Arial, sans-serif" font-size="56" font-weight="bold" fill="{accent}">{price}</text>
+</svg>"##,
+        background = escape(&config.background),
+        accent = escape(&config.accent),
+        name = escape(&name),
+        map = map.map(|map| map_image(config, map)).unwrap_or_default(),
+        bedrooms = details.bedrooms,
+        bathrooms = details.bathrooms,
+        sqft = money(details.sqft_living as f64).trim_start_matches('$'),
+        price = money(price.to_f64()),
+    )
+}
+
+/// Sets a generated image as the metadata `image` unless the mint brought its own photo: the
+/// property card, with the map thumbnail on it, or else the thumbnail alone. It is stored in
+/// `store` when that holds files. Stores that don't would put the whole image on-chain, so
+/// they get none; previews, without a store, inline it as a data URI.
+pub async fn attach(
+    state: &AppState,
+    store: Option<&dyn MetadataStore>,
+    details: &HouseDetails,
+    price: Money,
+    metadata: &mut Value,
+) -> Result<(), ApiError> {
+    if details.image.is_some() || (state.config.cards.is_none() && state.config.maps.is_none()) {
+        return Ok(());
+    }
+    if store.is_some_and(|store| !store.stores_files()) {
+        return Ok(());
+    }
+    // A missing map shouldn't hold up a mint.
+    let map = match &state.config.maps {
+        Some(maps) => maps::thumbnail(state, maps, details)
+            .await
+            .map_err(|e| eprintln!("{}", e))
+            .ok(),
+        None => None,
+    };
+    let (bytes, content_type) = match (&state.config.cards, map) {
+        (Some(config), map) => (render(config, details, price, map.as_ref()).into_bytes(), SVG_CONTENT_TYPE),
+        (None, Some(map)) => (map.png, "image/png"),
+        (None, None) => return Ok(()),
+    };
+    let uri = match store {
+        Some(store) => store.put_file(&state.http, Bytes::from(bytes), content_type).await.map_err(|e| {
+            eprintln!("{}", e);
+            ApiError::new(StatusCode::BAD_GATEWAY, format!("Failed to store property image in {}", store.name()))
+        })?,
+        None => format!(
+            "data:{};base64,{}",
+            content_type,
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+    };
+    metadata["image"] = serde_json::json!(uri);
+    Ok(())
+}
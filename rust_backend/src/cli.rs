@@ -0,0 +1,143 @@
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bench;
+use crate::config::Config;
+use crate::mint::{self, MintRequest};
+use crate::network;
+use crate::nfts;
+use crate::organizations::Tenant;
+use crate::payload::Versioned;
+use crate::registry;
+use crate::selftest;
+use crate::state::AppState;
+use crate::valuations;
+
+#[derive(Parser)]
+#[command(about = "Backend for the house valuation NFT platform")]
+pub struct Cli {
+    /// Also serve the built frontend and token share pages
+    #[arg(long, global = true)]
+    pub serve_frontend: bool,
+    /// Check the config, chain, contracts and prediction service, then exit
+    #[arg(long)]
+    pub check: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP and gRPC APIs (the default)
+    Serve,
+    /// Copy the database to a new file; safe while the server is running
+    Backup { path: PathBuf },
+    /// Replace the database with a backup; stop the server first
+    Restore {
+        path: PathBuf,
+        /// Overwrite an existing database
+        #[arg(long)]
+        force: bool,
+    },
+    /// Mint a property with the platform wallet, skipping checkout
+    Mint {
+        /// JSON file shaped like the POST /mint-nft body
+        #[arg(long)]
+        file: PathBuf,
+    },
+    /// Print a token's metadata
+    Metadata {
+        token_id: u64,
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// Re-price a token with the current model and update its metadata on-chain
+    Revalue {
+        token_id: u64,
+        #[arg(long)]
+        collection: Option<String>,
+    },
+    /// Load the configuration and report any problems without starting the server
+    VerifyConfig,
+    /// Run synthetic houses through the prediction and metadata pipeline and report latencies
+    Bench {
+        /// Number of synthetic houses
+        #[arg(long, default_value_t = 100)]
+        count: usize,
+        /// Houses in flight at once
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+        /// Also mint each house, on an Anvil fork of the chain (see ANVIL_PATH/DRY_RUN_FORK_URL)
+        #[arg(long)]
+        mock_chain: bool,
+    },
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| format!("Failed to encode output: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn load_state() -> AppState {
+    let state = AppState::new(Config::from_env());
+    registry::seed_from_env(&state.db, &state.config);
+    state
+}
+
+pub async fn mint(file: &Path) -> Result<(), String> {
+    let request = fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let Versioned(request): Versioned<MintRequest> =
+        serde_json::from_str(&request).map_err(|e| format!("Invalid mint request in {}: {}", file.display(), e))?;
+    if request.scheduled_at.is_some() {
+        return Err("Scheduled mints can only be made through the API".to_string());
+    }
+    let state = load_state();
+    network::guard(&state).await?;
+    let response = mint::execute(&state, &Tenant(None), request, None, false).await.map_err(|e| e.to_string())?;
+    print_json(&response)
+}
+
+pub async fn metadata(token_id: u64, collection: Option<&str>) -> Result<(), String> {
+    let state = load_state();
+    let collection = registry::resolve(&state.db, collection).map_err(|e| e.to_string())?;
+    let metadata = nfts::token_metadata(&state, &collection, token_id).await.map_err(|e| e.to_string())?;
+    print_json(&metadata)
+}
+
+pub async fn revalue(token_id: u64, collection: Option<&str>) -> Result<(), String> {
+    let state = load_state();
+    network::guard(&state).await?;
+    let collection = registry::resolve(&state.db, collection).map_err(|e| e.to_string())?;
+    let response = valuations::revalue_token(&state, &Tenant(None), collection, token_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    print_json(&response)
+}
+
+/// Exits non-zero unless every check passes.
+pub async fn check() -> Result<(), String> {
+    let config = std::panic::catch_unwind(Config::from_env).map_err(|_| "Configuration is invalid".to_string())?;
+    let state = AppState::new(config);
+    registry::seed_from_env(&state.db, &state.config);
+    println!();
+    if !selftest::report(&selftest::run(&state).await) {
+        return Err("Self-test failed".to_string());
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+pub async fn bench(count: usize, concurrency: usize, mock_chain: bool) -> Result<(), String> {
+    let report = bench::run(load_state(), count, concurrency, mock_chain).await?;
+    print_json(&report)
+}
+
+/// Settings are checked as they load, and an invalid one panics with a message naming it.
+pub fn verify_config() -> Result<(), String> {
+    std::panic::catch_unwind(Config::from_env).map_err(|_| "Configuration is invalid".to_string())?;
+    println!("Configuration is valid");
+    Ok(())
+}
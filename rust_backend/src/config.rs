@@ -0,0 +1,259 @@
+use ethers::types::Address;
+
+use crate::alchemy::AlchemyNftApi;
+use crate::approvals::ApprovalConfig;
+use crate::attributes::AttributeConfig;
+use crate::backpressure::BackpressureConfig;
+use crate::captcha::CaptchaConfig;
+use crate::cards::CardConfig;
+use crate::consensus::ConsensusConfig;
+use crate::email::EmailConfig;
+use crate::enrichment::EnrichmentConfig;
+use crate::explorer::Explorer;
+use crate::geofence::GeofenceConfig;
+use crate::kyc::KycConfig;
+use crate::liens::LienPolicy;
+use crate::maps::MapConfig;
+use crate::network::{self, Chain, Network, NetworkEnv, TxPolicy};
+use crate::opensea::OpenSeaConfig;
+use crate::outliers::OutlierConfig;
+use crate::parcels::ParcelConfig;
+use crate::pins::PinConfig;
+use crate::private::{PrivateConfig, PublicLocation};
+use crate::payments::{StripeConfig, TokenFee};
+use crate::queue::QueueConfig;
+use crate::rarity::RarityConfig;
+use crate::rbac::Role;
+use crate::sanctions::SanctionsConfig;
+use crate::storage::StorageConfig;
+use crate::subgraph::SubgraphConfig;
+use crate::valuations::ValuationModels;
+use std::env;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub struct Config {
+    pub alchemy_url: String,
+    pub private_key: String,
+    pub contract_address: Address,
+    pub chain: Arc<Chain>,
+    /// Extra chains mints can target with `network`.
+    pub networks: Vec<Network>,
+    pub database_path: String,
+    pub admin_api_key: Option<String>,
+    /// Named admins, so actions can be told apart and confirmed by someone else.
+    pub admin_api_keys: Vec<(String, String)>,
+    /// Dangerous admin actions need a second admin to confirm them.
+    pub four_eyes: bool,
+    pub artifacts_dir: Option<PathBuf>,
+    pub vault_address: Option<Address>,
+    pub escrow_address: Option<Address>,
+    pub usdc_address: Option<Address>,
+    pub stripe: Option<StripeConfig>,
+    pub token_fee: Option<TokenFee>,
+    pub kyc: Option<KycConfig>,
+    pub sanctions: Option<SanctionsConfig>,
+    /// Role for requests without credentials; `None` requires an API key everywhere.
+    pub anonymous_role: Option<Role>,
+    /// How far a signed request's timestamp may drift from the server clock.
+    pub signature_window_secs: u64,
+    pub captcha: Option<CaptchaConfig>,
+    pub geofence: Option<GeofenceConfig>,
+    pub grpc_port: u16,
+    pub model_version: Option<String>,
+    pub frontend_dir: PathBuf,
+    pub public_url: Option<String>,
+    pub queue: Option<QueueConfig>,
+    pub subgraph: Option<SubgraphConfig>,
+    pub alchemy_nft: Option<AlchemyNftApi>,
+    pub opensea: Option<OpenSeaConfig>,
+    /// How often to index Transfer events; 0 disables the indexer.
+    pub indexer_poll_secs: u64,
+    pub network_env: NetworkEnv,
+    /// Explicit opt-in to running against a mainnet.
+    pub allow_mainnet: bool,
+    pub enrichment: Option<EnrichmentConfig>,
+    pub parcels: Option<ParcelConfig>,
+    pub consensus: Option<ConsensusConfig>,
+    pub lien_policy: LienPolicy,
+    pub valuation_models: ValuationModels,
+    pub storage: StorageConfig,
+    pub pins: Option<PinConfig>,
+    pub private: Option<PrivateConfig>,
+    pub public_location: PublicLocation,
+    pub attributes: AttributeConfig,
+    pub rarity: Option<RarityConfig>,
+    pub cards: Option<CardConfig>,
+    pub maps: Option<MapConfig>,
+    pub email: Option<EmailConfig>,
+    pub outliers: Option<OutlierConfig>,
+    pub approvals: Option<ApprovalConfig>,
+    pub backpressure: BackpressureConfig,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let alchemy_url = env::var("ALCHEMY_URL").expect("ALCHEMY_URL is not set in .env");
+        if !alchemy_url.starts_with("http://") && !alchemy_url.starts_with("https://") {
+            panic!("ALCHEMY_URL must start with http:// or https://. Found: {}", alchemy_url);
+        }
+        println!("ALCHEMY_URL: {}", alchemy_url);
+
+        let private_key = env::var("PRIVATE_KEY").expect("PRIVATE_KEY is not set in .env");
+        println!("PRIVATE_KEY: {}", if private_key.is_empty() { "None" } else { "Loaded" });
+
+        let contract_address = env::var("CONTRACT_ADDRESS").expect("CONTRACT_ADDRESS is not set in .env");
+        println!("CONTRACT_ADDRESS: {}", contract_address);
+        let contract_address = contract_address.parse().expect("Invalid contract address");
+
+        let chain_id = network::chain_id_from_env();
+        println!("CHAIN_ID: {}", chain_id);
+
+        let network_env = NetworkEnv::from_env();
+        let allow_mainnet = optional_env("ALLOW_MAINNET").is_some_and(|allow| allow == "true");
+        let tx_policy = TxPolicy::from_env("", chain_id);
+        let networks = Network::from_env(&private_key);
+        let private = PrivateConfig::from_env();
+        let public_location = PublicLocation::from_env(private.as_ref());
+        let maps = MapConfig::from_env(&public_location);
+
+        let database_path = database_path();
+        println!("DATABASE_PATH: {}", database_path);
+
+        let admin_api_key = optional_env("ADMIN_API_KEY");
+        println!("ADMIN_API_KEY: {}", if admin_api_key.is_some() { "Loaded" } else { "None (admin API disabled)" });
+        let admin_api_keys: Vec<(String, String)> = optional_env("ADMIN_API_KEYS")
+            .map(|keys| {
+                keys.split(',')
+                    .map(|entry| {
+                        let (name, key) = entry.trim().split_once(':').expect("ADMIN_API_KEYS entries must be name:key");
+                        (name.to_string(), key.to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !admin_api_keys.is_empty() {
+            let names: Vec<&str> = admin_api_keys.iter().map(|(name, _)| name.as_str()).collect();
+            println!("ADMIN_API_KEYS: {}", names.join(", "));
+        }
+        let four_eyes = optional_env("FOUR_EYES").is_some_and(|four_eyes| four_eyes == "true");
+        if four_eyes {
+            let identities = admin_api_keys.len() + usize::from(admin_api_key.is_some());
+            assert!(identities >= 2, "FOUR_EYES needs at least two admin identities in ADMIN_API_KEY and ADMIN_API_KEYS");
+            println!("FOUR_EYES: dangerous admin actions need a second admin's confirmation");
+        }
+
+        let artifacts_dir = optional_env("ARTIFACTS_DIR").map(PathBuf::from);
+        if let Some(dir) = &artifacts_dir {
+            println!("ARTIFACTS_DIR: {}", dir.display());
+        }
+
+        let vault_address = optional_env("VAULT_ADDRESS").map(|address| {
+            println!("VAULT_ADDRESS: {}", address);
+            address.parse().expect("Invalid vault address")
+        });
+
+        let escrow_address = optional_env("ESCROW_ADDRESS").map(|address| {
+            println!("ESCROW_ADDRESS: {}", address);
+            address.parse().expect("Invalid escrow address")
+        });
+        let usdc_address = optional_env("USDC_ADDRESS").map(|address| address.parse().expect("Invalid USDC address"));
+
+        let token_fee = TokenFee::from_env(usdc_address);
+
+        // Unauthenticated callers can only read by default; ANONYMOUS_ROLE=agent lets them mint and trade.
+        let anonymous_role = match optional_env("ANONYMOUS_ROLE").as_deref() {
+            None => Some(Role::Viewer),
+            Some("none") => None,
+            Some(role) => Some(Role::parse(role).expect("ANONYMOUS_ROLE must be viewer, agent, appraiser or none")),
+        };
+        println!("ANONYMOUS_ROLE: {}", anonymous_role.map_or("none", Role::as_str));
+
+        let signature_window_secs = optional_env("SIGNATURE_WINDOW_SECS")
+            .map(|secs| secs.parse().expect("SIGNATURE_WINDOW_SECS must be a number"))
+            .unwrap_or(300);
+
+        let grpc_port = optional_env("GRPC_PORT")
+            .map(|port| port.parse().expect("GRPC_PORT must be a port number"))
+            .unwrap_or(50051);
+
+        let model_version = optional_env("MODEL_VERSION");
+
+        // Only used with --serve-frontend.
+        let frontend_dir = PathBuf::from(optional_env("FRONTEND_DIR").unwrap_or_else(|| "frontend/dist".to_string()));
+        let public_url = optional_env("PUBLIC_URL").map(|url| url.trim_end_matches('/').to_string());
+
+        let alchemy_nft = AlchemyNftApi::from_rpc_url(&alchemy_url);
+
+        let indexer_poll_secs = optional_env("INDEXER_POLL_SECS")
+            .map(|secs| secs.parse().expect("INDEXER_POLL_SECS must be a number"))
+            .unwrap_or(15);
+
+        Config {
+            alchemy_url,
+            private_key,
+            contract_address,
+            chain: Arc::new(Chain {
+                network: None,
+                chain_id,
+                tx_policy: RwLock::new(tx_policy),
+                explorer: Explorer::for_chain(chain_id),
+            }),
+            networks,
+            database_path,
+            admin_api_key,
+            admin_api_keys,
+            four_eyes,
+            artifacts_dir,
+            vault_address,
+            escrow_address,
+            usdc_address,
+            stripe: StripeConfig::from_env(),
+            token_fee,
+            kyc: KycConfig::from_env(),
+            sanctions: SanctionsConfig::from_env(),
+            anonymous_role,
+            signature_window_secs,
+            captcha: CaptchaConfig::from_env(),
+            geofence: GeofenceConfig::from_env(),
+            grpc_port,
+            model_version,
+            frontend_dir,
+            public_url,
+            queue: QueueConfig::from_env(),
+            subgraph: SubgraphConfig::from_env(),
+            alchemy_nft,
+            opensea: OpenSeaConfig::from_env(chain_id),
+            indexer_poll_secs,
+            network_env,
+            allow_mainnet,
+            enrichment: EnrichmentConfig::from_env(),
+            parcels: ParcelConfig::from_env(),
+            consensus: ConsensusConfig::from_env(),
+            lien_policy: LienPolicy::from_env(),
+            valuation_models: ValuationModels::from_env(),
+            storage: StorageConfig::from_env(),
+            pins: PinConfig::from_env(),
+            public_location,
+            attributes: AttributeConfig::from_env(private.as_ref()),
+            rarity: RarityConfig::from_env(),
+            cards: CardConfig::from_env(),
+            maps,
+            email: EmailConfig::from_env(),
+            outliers: OutlierConfig::from_env(),
+            approvals: ApprovalConfig::from_env(),
+            backpressure: BackpressureConfig::from_env(),
+            private,
+        }
+    }
+}
+
+/// The only setting backup and restore need, so they work without the rest of the config.
+pub fn database_path() -> String {
+    env::var("DATABASE_PATH").unwrap_or_else(|_| "rust_backend.db".to_string())
+}
+
+/// Reads an optional setting, treating blank values (as left by env.example) as unset.
+pub fn optional_env(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
@@ -0,0 +1,268 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::Address;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::analytics;
+use crate::appraisals;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::jobs::{self, MintJob};
+use crate::money::{Currency, Money};
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+pub struct ConsensusConfig {
+    /// Model prices at or above this need appraiser consensus before minting.
+    pub threshold: f64,
+    pub quorum: usize,
+}
+
+impl ConsensusConfig {
+    /// Enabled by CONSENSUS_THRESHOLD.
+    pub fn from_env() -> Option<Self> {
+        let threshold = optional_env("CONSENSUS_THRESHOLD")?
+            .parse()
+            .expect("CONSENSUS_THRESHOLD must be a number");
+        let quorum = optional_env("CONSENSUS_QUORUM")
+            .map(|quorum| quorum.parse().expect("CONSENSUS_QUORUM must be a number"))
+            .unwrap_or(3);
+        assert!(quorum > 0, "CONSENSUS_QUORUM must be at least 1");
+        println!("CONSENSUS_THRESHOLD: {} ({} appraisers)", threshold, quorum);
+        Some(ConsensusConfig { threshold, quorum })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Appraiser {
+    pub address: Address,
+    pub license_number: String,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ValuationRequest {
+    appraiser: Address,
+    price: f64,
+    /// EIP-191 signature of the message built by `message`.
+    signature: String,
+}
+
+#[derive(Serialize)]
+pub struct Valuation {
+    pub appraiser: String,
+    pub price: f64,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct ConsensusStatus {
+    job_id: i64,
+    status: String,
+    model_price: Option<Money>,
+    quorum: usize,
+    valuations: Vec<Valuation>,
+    /// The median of the valuations once the quorum is met.
+    consensus_price: Option<Money>,
+}
+
+/// What a registered appraiser signs to value a pending mint.
+pub fn message(job_id: i64, property: &str, price: f64) -> String {
+    format!("I appraise {} (mint job {}) at ${}", property, job_id, price)
+}
+
+/// The license number `appraiser` (a `{:?}`-formatted address) is registered under; only
+/// registered appraisers can value properties.
+pub fn registered_license(conn: &Connection, appraiser: &str) -> Result<String, ApiError> {
+    conn.query_row("SELECT license_number FROM appraisers WHERE address = ?1", params![appraiser], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to load appraisers: {}", e))?
+        .ok_or_else(|| ApiError::new(StatusCode::FORBIDDEN, format!("{} is not a registered appraiser", appraiser)))
+}
+
+/// Whether a mint at the model's `price` has to wait for appraisers.
+pub fn required(state: &AppState, price: Money) -> bool {
+    state.config.consensus.as_ref().is_some_and(|consensus| price.to_f64() >= consensus.threshold)
+}
+
+fn valuations(db: &Db, job_id: i64) -> Result<Vec<Valuation>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare(
+        "SELECT appraiser, price, created_at FROM consensus_valuations WHERE job_id = ?1 ORDER BY created_at, rowid",
+    )
+    .and_then(|mut stmt| {
+        stmt.query_map(params![job_id], |row| {
+            Ok(Valuation {
+                appraiser: row.get(0)?,
+                price: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })?
+        .collect()
+    })
+    .map_err(|e| format!("Failed to load consensus valuations: {}", e))
+}
+
+fn status(state: &AppState, job: &MintJob) -> Result<ConsensusStatus, String> {
+    Ok(ConsensusStatus {
+        job_id: job.id,
+        status: job.status.clone(),
+        model_price: job.model_price,
+        quorum: state.config.consensus.as_ref().map_or(0, |consensus| consensus.quorum),
+        valuations: valuations(&state.db, job.id)?,
+        consensus_price: job.consensus_price,
+    })
+}
+
+async fn job_for(state: &AppState, tenant: Tenant, id: i64) -> Result<MintJob, ApiError> {
+    let Json(job) = jobs::get_job(State(state.clone()), tenant, Path(id)).await?;
+    Ok(job)
+}
+
+/// Records a registered appraiser's valuation of a pending mint. Once the quorum is met the
+/// median becomes the mint price and the job moves on to be minted.
+pub async fn submit(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+    Json(request): Json<ValuationRequest>,
+) -> Result<Json<ConsensusStatus>, ApiError> {
+    let Some(consensus) = &state.config.consensus else {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Consensus valuation is not enabled"));
+    };
+    let job = job_for(&state, tenant, id).await?;
+    if job.status != jobs::AWAITING_APPRAISALS {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Mint job {} is {} and is not awaiting appraisals", id, job.status),
+        ));
+    }
+    if !(request.price.is_finite() && request.price > 0.0) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "price must be a positive number"));
+    }
+    let property = job.request["name"].as_str().unwrap_or_default();
+    let message = message(id, property, request.price);
+    if !appraisals::signed_by(&request.signature, &message, request.appraiser) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("signature is not {:?}'s signature of the valuation", request.appraiser),
+        )
+        .with_details(json!({ "message": message })));
+    }
+    let appraiser = format!("{:?}", request.appraiser);
+
+    let agreed = {
+        let conn = state.db.lock().unwrap();
+        registered_license(&conn, &appraiser)?;
+        let inserted = conn
+            .execute(
+                "INSERT OR IGNORE INTO consensus_valuations (job_id, appraiser, price, signature) VALUES (?1, ?2, ?3, ?4)",
+                params![id, appraiser, request.price, request.signature],
+            )
+            .map_err(|e| format!("Failed to record valuation: {}", e))?;
+        if inserted == 0 {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("{} has already valued mint job {}", appraiser, id),
+            ));
+        }
+        let mut prices: Vec<f64> = conn
+            .prepare("SELECT price FROM consensus_valuations WHERE job_id = ?1")
+            .and_then(|mut stmt| stmt.query_map(params![id], |row| row.get(0))?.collect())
+            .map_err(|e| format!("Failed to load consensus valuations: {}", e))?;
+        if prices.len() >= consensus.quorum {
+            analytics::median(&mut prices).map(|median| Money::from_f64(median, Currency::USD))
+        } else {
+            None
+        }
+    };
+
+    if let Some(price) = agreed {
+        let queued = {
+            let conn = state.db.lock().unwrap();
+            conn.execute(
+                "UPDATE mint_jobs
+                 SET consensus_price = ?3,
+                     consensus_price_cents = ?4,
+                     status = CASE WHEN scheduled_at > datetime('now') THEN ?5 ELSE ?6 END,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1 AND status = ?2",
+                params![id, jobs::AWAITING_APPRAISALS, price.to_f64(), price.cents, jobs::SCHEDULED, jobs::QUEUED],
+            )
+            .map_err(|e| format!("Failed to update mint job: {}", e))?;
+            conn.query_row("SELECT status FROM mint_jobs WHERE id = ?1", params![id], |row| row.get::<_, String>(0))
+                .optional()
+                .map_err(|e| format!("Failed to load mint job: {}", e))?
+        };
+        println!("Appraisers agreed on {} for mint job {}", price, id);
+        if queued.as_deref() == Some(jobs::QUEUED) {
+            jobs::spawn(state.clone(), id);
+        }
+    }
+    let job = jobs::get(&state.db, id)?.ok_or("Mint job disappeared")?;
+    Ok(Json(status(&state, &job)?))
+}
+
+pub async fn show(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+) -> Result<Json<ConsensusStatus>, ApiError> {
+    let job = job_for(&state, tenant, id).await?;
+    Ok(Json(status(&state, &job)?))
+}
+
+pub async fn list_appraisers(State(state): State<AppState>) -> Result<Json<Vec<Appraiser>>, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let appraisers = conn
+        .prepare("SELECT address, license_number, name FROM appraisers ORDER BY created_at")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                let address: String = row.get(0)?;
+                Ok(Appraiser {
+                    address: address.parse().unwrap_or_default(),
+                    license_number: row.get(1)?,
+                    name: row.get(2)?,
+                })
+            })?
+            .collect()
+        })
+        .map_err(|e| format!("Failed to load appraisers: {}", e))?;
+    Ok(Json(appraisers))
+}
+
+pub async fn register_appraiser(
+    State(state): State<AppState>,
+    Json(appraiser): Json<Appraiser>,
+) -> Result<(StatusCode, Json<Appraiser>), ApiError> {
+    if appraiser.license_number.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "license_number is required"));
+    }
+    let conn = state.db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO appraisers (address, license_number, name) VALUES (?1, ?2, ?3)
+         ON CONFLICT (address) DO UPDATE SET license_number = excluded.license_number, name = excluded.name",
+        params![format!("{:?}", appraiser.address), appraiser.license_number, appraiser.name],
+    )
+    .map_err(|e| format!("Failed to register appraiser: {}", e))?;
+    Ok((StatusCode::CREATED, Json(appraiser)))
+}
+
+/// Valuations already submitted by the appraiser still count towards their mints.
+pub async fn remove_appraiser(
+    State(state): State<AppState>,
+    Path(address): Path<Address>,
+) -> Result<StatusCode, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let removed = conn
+        .execute("DELETE FROM appraisers WHERE address = ?1", params![format!("{:?}", address)])
+        .map_err(|e| format!("Failed to remove appraiser: {}", e))?;
+    if removed == 0 {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("{:?} is not a registered appraiser", address)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,150 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::Address;
+use reqwest::Url;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::ContractMetadataUri;
+use crate::error::ApiError;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::tx::{self, TransactionResponse};
+use crate::CollectionQuery;
+
+const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+/// OpenSea's contract-level metadata, as returned by a collection's `contractURI()`.
+#[derive(Deserialize, Serialize)]
+pub struct ContractMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_link: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seller_fee_basis_points: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<Address>,
+}
+
+#[derive(Deserialize)]
+pub struct SetContractUri {
+    /// Defaults to this service's GET /contract-metadata for the collection.
+    uri: Option<String>,
+}
+
+/// The collection's metadata, falling back to its registry name and description.
+fn load(state: &AppState, collection: &Collection) -> Result<ContractMetadata, String> {
+    let conn = state.db.lock().unwrap();
+    let stored = conn
+        .query_row(
+            "SELECT name, description, image, external_link, seller_fee_basis_points, fee_recipient
+             FROM contract_metadata WHERE collection = ?1",
+            params![collection.name],
+            |row| {
+                Ok(ContractMetadata {
+                    name: row.get(0)?,
+                    description: row.get(1)?,
+                    image: row.get(2)?,
+                    external_link: row.get(3)?,
+                    seller_fee_basis_points: row.get(4)?,
+                    fee_recipient: row.get::<_, Option<String>>(5)?.and_then(|address| address.parse().ok()),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load contract metadata: {}", e))?;
+    let mut metadata = stored.unwrap_or(ContractMetadata {
+        name: None,
+        description: None,
+        image: None,
+        external_link: None,
+        seller_fee_basis_points: None,
+        fee_recipient: None,
+    });
+    metadata.name = metadata.name.or_else(|| Some(collection.name.clone()));
+    metadata.description = metadata.description.or_else(|| collection.description.clone());
+    Ok(metadata)
+}
+
+/// Served without credentials, since marketplaces fetch it through `contractURI()`.
+pub async fn get_contract_metadata(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<ContractMetadata>, ApiError> {
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    Ok(Json(load(&state, &collection)?))
+}
+
+pub async fn set_contract_metadata(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<ContractMetadata>,
+) -> Result<Json<ContractMetadata>, ApiError> {
+    let collection = registry::resolve(&state.db, Some(&name))?;
+    if request.seller_fee_basis_points.is_some_and(|fee| fee > MAX_FEE_BASIS_POINTS) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("seller_fee_basis_points must not exceed {}", MAX_FEE_BASIS_POINTS),
+        ));
+    }
+    if request.seller_fee_basis_points.is_some() != request.fee_recipient.is_some() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "seller_fee_basis_points and fee_recipient must be set together",
+        ));
+    }
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO contract_metadata
+                (collection, name, description, image, external_link, seller_fee_basis_points, fee_recipient)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (collection) DO UPDATE SET
+                 name = ?2, description = ?3, image = ?4, external_link = ?5, seller_fee_basis_points = ?6,
+                 fee_recipient = ?7, updated_at = CURRENT_TIMESTAMP",
+            params![
+                collection.name,
+                request.name,
+                request.description,
+                request.image,
+                request.external_link,
+                request.seller_fee_basis_points,
+                request.fee_recipient.map(|address| format!("{:?}", address)),
+            ],
+        )
+        .map_err(|e| format!("Failed to save contract metadata: {}", e))?;
+    }
+    Ok(Json(load(&state, &collection)?))
+}
+
+/// Points the contract's `contractURI()` at the metadata, on contracts with `setContractURI`.
+pub async fn set_contract_uri(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<SetContractUri>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let collection = registry::resolve(&state.db, Some(&name))?;
+    let uri = match (request.uri, &state.config.public_url) {
+        (Some(uri), _) => uri,
+        (None, Some(public_url)) => Url::parse_with_params(
+            &format!("{}/contract-metadata", public_url),
+            [("collection", collection.name.as_str())],
+        )
+        .map_err(|e| format!("PUBLIC_URL is not a valid URL: {}", e))?
+        .to_string(),
+        (None, None) => {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Pass a uri or set PUBLIC_URL to serve it from here"))
+        }
+    };
+    println!("Setting contract URI of {} to {}...", collection.name, uri);
+    let chain = state.on(&collection)?;
+    let contract = ContractMetadataUri::new(collection.address, chain.client.clone());
+    let receipt = tx::submit(&chain, contract.set_contract_uri(uri)).await?;
+    Ok(Json(tx::response(&chain, &receipt)))
+}
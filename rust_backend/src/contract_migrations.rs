@@ -0,0 +1,525 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::abi_check;
+use crate::bindings;
+use crate::capabilities;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::maintenance;
+use crate::mint;
+use crate::organizations::{self, Tenant};
+use crate::registry::{self, Collection, ContractRef};
+use crate::state::AppState;
+use crate::tx;
+
+const RUNNING: &str = "running";
+/// Bridge migrations whose tokens haven't all arrived on the new contract yet.
+const WAITING: &str = "waiting";
+const FAILED: &str = "failed";
+const COMPLETED: &str = "completed";
+
+/// How often a bridge migration checks the new contract for tokens still in transit.
+const BRIDGE_POLL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Mints every token again on the new contract, to its current owner, with the same ID and URI.
+    Remint,
+    /// Owners move their tokens over themselves; the migration waits until every token has arrived.
+    Bridge,
+}
+
+impl Strategy {
+    fn name(self) -> &'static str {
+        match self {
+            Strategy::Remint => "remint",
+            Strategy::Bridge => "bridge",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct StartMigration {
+    new_address: Address,
+    /// Defaults to the collection's current contract.
+    contract_name: Option<String>,
+    contract_version: Option<String>,
+    /// Defaults to the collection's current network.
+    network: Option<String>,
+    #[serde(default = "remint")]
+    strategy: Strategy,
+}
+
+fn remint() -> Strategy {
+    Strategy::Remint
+}
+
+#[derive(Default, Serialize)]
+pub struct Progress {
+    total: u64,
+    pending: u64,
+    migrated: u64,
+    /// Burned on the old contract, so there was nothing to move.
+    skipped: u64,
+}
+
+#[derive(Serialize)]
+pub struct Migration {
+    pub id: i64,
+    pub collection: String,
+    pub strategy: Strategy,
+    pub old_address: String,
+    pub new_address: String,
+    pub contract_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    pub tokens: Progress,
+}
+
+pub fn load(db: &Db, id: i64) -> Result<Option<Migration>, String> {
+    let conn = db.lock().unwrap();
+    let migration = conn
+        .query_row(
+            "SELECT id, collection, strategy, old_address, new_address, contract_name, contract_version, network,
+                    status, error, created_at, completed_at
+             FROM contract_migrations WHERE id = ?1",
+            params![id],
+            |row| {
+                let strategy: String = row.get(2)?;
+                Ok(Migration {
+                    id: row.get(0)?,
+                    collection: row.get(1)?,
+                    strategy: if strategy == "bridge" { Strategy::Bridge } else { Strategy::Remint },
+                    old_address: row.get(3)?,
+                    new_address: row.get(4)?,
+                    contract_name: row.get(5)?,
+                    contract_version: row.get(6)?,
+                    network: row.get(7)?,
+                    status: row.get(8)?,
+                    error: row.get(9)?,
+                    created_at: row.get(10)?,
+                    completed_at: row.get(11)?,
+                    tokens: Progress::default(),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load contract migration: {}", e))?;
+    let Some(mut migration) = migration else {
+        return Ok(None);
+    };
+    let mut stmt = conn
+        .prepare("SELECT status, COUNT(*) FROM contract_migration_tokens WHERE migration_id = ?1 GROUP BY status")
+        .map_err(|e| format!("Failed to load contract migration: {}", e))?;
+    let counts = stmt
+        .query_map(params![id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load contract migration: {}", e))?;
+    for (status, count) in counts {
+        migration.tokens.total += count;
+        match status.as_str() {
+            "migrated" => migration.tokens.migrated += count,
+            "skipped" => migration.tokens.skipped += count,
+            _ => migration.tokens.pending += count,
+        }
+    }
+    Ok(Some(migration))
+}
+
+fn active(db: &Db, collection: &str) -> Result<Option<i64>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT id FROM contract_migrations WHERE collection = ?1 AND status IN (?2, ?3, ?4)",
+        params![collection, RUNNING, WAITING, FAILED],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up contract migrations: {}", e))
+}
+
+fn set_status(db: &Db, id: i64, status: &str, error: Option<&str>) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE contract_migrations
+         SET status = ?2, error = ?3,
+             completed_at = CASE WHEN ?2 = 'completed' THEN CURRENT_TIMESTAMP ELSE completed_at END
+         WHERE id = ?1",
+        params![id, status, error],
+    )
+    .map_err(|e| format!("Failed to update contract migration: {}", e))?;
+    Ok(())
+}
+
+fn pending_tokens(db: &Db, id: i64) -> Result<Vec<u64>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare(
+        "SELECT token_id FROM contract_migration_tokens WHERE migration_id = ?1 AND status = 'pending'
+         ORDER BY token_id",
+    )
+    .and_then(|mut stmt| stmt.query_map(params![id], |row| row.get(0))?.collect())
+    .map_err(|e| format!("Failed to load contract migration tokens: {}", e))
+}
+
+fn mark_token(
+    db: &Db,
+    id: i64,
+    token_id: u64,
+    status: &str,
+    owner: Option<Address>,
+    transaction_hash: Option<String>,
+) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE contract_migration_tokens SET status = ?3, owner = ?4, transaction_hash = ?5, error = NULL
+         WHERE migration_id = ?1 AND token_id = ?2",
+        params![id, token_id, status, owner.map(|owner| format!("{:?}", owner)), transaction_hash],
+    )
+    .map_err(|e| format!("Failed to update contract migration token: {}", e))?;
+    Ok(())
+}
+
+fn token_error(db: &Db, id: i64, token_id: u64, error: &str) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE contract_migration_tokens SET error = ?3 WHERE migration_id = ?1 AND token_id = ?2",
+        params![id, token_id, error],
+    )
+    .map_err(|e| format!("Failed to update contract migration token: {}", e))?;
+    Ok(())
+}
+
+fn soulbound(db: &Db, collection: &str, token_id: u64) -> Result<bool, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT soulbound FROM mints WHERE collection = ?1 AND token_id = ?2",
+        params![collection, token_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map(Option::unwrap_or_default)
+    .map_err(|e| format!("Failed to look up mint: {}", e))
+}
+
+/// The collection as it will be registered once the migration completes.
+fn destination(collection: &Collection, migration: &Migration) -> Result<Collection, String> {
+    Ok(Collection {
+        address: migration.new_address.parse().map_err(|_| "Invalid destination address")?,
+        contract_name: migration.contract_name.clone(),
+        contract_version: migration.contract_version.clone(),
+        network: migration.network.clone(),
+        ..collection.clone()
+    })
+}
+
+/// Every token the old contract has minted, from its mint counter, or from the recorded mints
+/// when its contract has none.
+async fn token_ids(state: &AppState, collection: &Collection) -> Result<Vec<u64>, ApiError> {
+    let chain = state.on(collection)?;
+    let counters = bindings::SupplyCounters::new(collection.address, chain.client.clone());
+    if let Ok(count) = counters.token_counter().call().await {
+        return Ok((0..count.as_u64()).collect());
+    }
+    let conn = state.db.lock().unwrap();
+    let ids = conn
+        .prepare("SELECT token_id FROM mints WHERE collection = ?1 ORDER BY token_id")
+        .and_then(|mut stmt| stmt.query_map(params![collection.name], |row| row.get(0))?.collect())
+        .map_err(|e| format!("Failed to load mints: {}", e))?;
+    Ok(ids)
+}
+
+/// Moves one token onto the new contract under its old ID; returns the owner and, for reminted
+/// tokens, the transaction hash. `Ok(None)` means the token was burned on the old contract.
+async fn remint_token(
+    state: &AppState,
+    source: &Collection,
+    target: &Collection,
+    tenant: &Tenant,
+    token_id: u64,
+) -> Result<Option<(Address, String)>, ApiError> {
+    let id = U256::from(token_id);
+    let old = state.on(source)?.nft(source)?;
+    let owner = match old.owner_of(id).call().await {
+        Ok(owner) => owner,
+        Err(e) if e.is_revert() => return Ok(None),
+        Err(e) => return Err(format!("Failed to read owner of token {}: {}", token_id, e).into()),
+    };
+    let token_uri = old
+        .token_uri(id)
+        .call()
+        .await
+        .map_err(|e| format!("Failed to read URI of token {}: {}", token_id, e))?;
+
+    let chain = state.on(target)?;
+    let client = chain.client_for(tenant)?;
+    let abi = state.artifacts.abi(&target.contract_name, target.contract_version.as_deref())?;
+    let receipt = if abi.functions_by_name("migrateToken").is_ok() {
+        let contract = bindings::MigrationMint::new(target.address, client);
+        tx::submit(&chain, contract.migrate_token(owner, id, token_uri)).await?
+    } else {
+        // Contracts without migrateToken number tokens themselves, so IDs only carry over while
+        // the new contract's counter keeps pace with the old one.
+        let counters = bindings::SupplyCounters::new(target.address, client.clone());
+        let next = counters
+            .token_counter()
+            .call()
+            .await
+            .map_err(|e| format!("Failed to read the new contract's token counter: {}", e))?;
+        if next != id {
+            return Err(format!(
+                "The new contract would mint token {} as {}; deploy a contract with migrateToken to keep token IDs",
+                token_id, next
+            )
+            .into());
+        }
+        let minted = if soulbound(&state.db, &source.name, token_id)? {
+            let contract = bindings::SoulboundMint::new(target.address, client);
+            tx::submit(&chain, contract.mint_soulbound(owner, token_uri)).await?
+        } else {
+            let contract = state.nft_as(target, client)?;
+            tx::submit(&chain, contract.mint_nft(owner, token_uri)).await?
+        };
+        if mint::minted_token_id(&minted, target.address) != Some(id) {
+            return Err(format!("Token {} was reminted under a different ID", token_id).into());
+        }
+        minted
+    };
+    Ok(Some((owner, format!("{:?}", receipt.transaction_hash))))
+}
+
+/// Works through the migration's pending tokens in ID order, then points the collection at the
+/// new contract. Every token is checked on the new contract first, so a resumed migration never
+/// mints a token twice.
+async fn migrate(state: &AppState, id: i64) -> Result<(), ApiError> {
+    loop {
+        let migration = load(&state.db, id)?.ok_or("Contract migration disappeared")?;
+        if migration.status != RUNNING && migration.status != WAITING {
+            return Ok(());
+        }
+        let collection = registry::resolve(&state.db, Some(&migration.collection))?;
+        let source = Collection {
+            address: migration.old_address.parse().map_err(|_| "Invalid source address")?,
+            ..collection.clone()
+        };
+        let target = destination(&collection, &migration)?;
+        let tenant = match &collection.organization {
+            Some(org) => Tenant(Some(organizations::load(&state.db, org)?.ok_or("Collection organization was deleted")?)),
+            None => Tenant(None),
+        };
+        let new = state.on(&target)?.nft(&target)?;
+
+        let mut in_transit = 0;
+        for token_id in pending_tokens(&state.db, id)? {
+            if maintenance::current(&state.db).is_none() {
+                return Err("Maintenance ended before the migration finished".into());
+            }
+            if let Ok(owner) = new.owner_of(U256::from(token_id)).call().await {
+                mark_token(&state.db, id, token_id, "migrated", Some(owner), None)?;
+                continue;
+            }
+            if migration.strategy == Strategy::Bridge {
+                in_transit += 1;
+                continue;
+            }
+            match remint_token(state, &source, &target, &tenant, token_id).await {
+                Ok(Some((owner, transaction_hash))) => {
+                    println!("Migrated token {} of collection {} in {}", token_id, collection.name, transaction_hash);
+                    mark_token(&state.db, id, token_id, "migrated", Some(owner), Some(transaction_hash))?;
+                }
+                Ok(None) => mark_token(&state.db, id, token_id, "skipped", None, None)?,
+                Err(e) => {
+                    token_error(&state.db, id, token_id, &e.message)?;
+                    return Err(format!("Token {}: {}", token_id, e.message).into());
+                }
+            }
+        }
+        if in_transit > 0 {
+            if migration.status != WAITING {
+                println!("Contract migration {} is waiting for {} bridged tokens", id, in_transit);
+                set_status(&state.db, id, WAITING, None)?;
+            }
+            tokio::time::sleep(BRIDGE_POLL).await;
+            continue;
+        }
+
+        let contract = ContractRef {
+            name: &target.contract_name,
+            version: target.contract_version.as_deref(),
+        };
+        registry::register(&state.db, &collection.name, target.address, contract, None)?;
+        {
+            let conn = state.db.lock().unwrap();
+            conn.execute(
+                "UPDATE collections SET network = ?2 WHERE name = ?1",
+                params![collection.name, target.network],
+            )
+            .map_err(|e| format!("Failed to register collection: {}", e))?;
+        }
+        set_status(&state.db, id, COMPLETED, None)?;
+        println!(
+            "Collection {} now points at {:?}; update its address in the environment and end maintenance \
+             once it has been checked",
+            collection.name, target.address
+        );
+        capabilities::spawn_probe(state, target);
+        return Ok(());
+    }
+}
+
+pub fn spawn(state: AppState, id: i64) {
+    tokio::spawn(async move {
+        if let Err(e) = migrate(&state, id).await {
+            eprintln!("Contract migration {} failed: {}", id, e.message);
+            if let Err(e) = set_status(&state.db, id, FAILED, Some(&e.message)) {
+                eprintln!("{}", e);
+            }
+        }
+    });
+}
+
+/// Picks up migrations that were running when the server stopped.
+pub fn resume_running(state: &AppState) {
+    let ids: Result<Vec<i64>, String> = {
+        let conn = state.db.lock().unwrap();
+        conn.prepare("SELECT id FROM contract_migrations WHERE status IN (?1, ?2) ORDER BY id")
+            .and_then(|mut stmt| stmt.query_map(params![RUNNING, WAITING], |row| row.get(0))?.collect())
+            .map_err(|e| format!("Failed to load contract migrations: {}", e))
+    };
+    match ids {
+        Ok(ids) => ids.into_iter().for_each(|id| spawn(state.clone(), id)),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+fn require_maintenance(state: &AppState) -> Result<(), ApiError> {
+    if maintenance::current(&state.db).is_none() {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "Start maintenance before migrating a collection, so nothing is minted on the old contract meanwhile",
+        ));
+    }
+    Ok(())
+}
+
+/// Starts moving a collection to a new contract. The new contract has to be deployed already;
+/// the collection keeps using the old one until every token has been moved.
+pub async fn start_migration(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<StartMigration>,
+) -> Result<(StatusCode, Json<Migration>), ApiError> {
+    let collection = registry::resolve(&state.db, Some(&name))?;
+    require_maintenance(&state)?;
+    if let Some(id) = active(&state.db, &collection.name)? {
+        return Err(ApiError::new(StatusCode::CONFLICT, "The collection already has a migration in progress")
+            .with_details(json!({ "migration": id })));
+    }
+    if request.new_address == collection.address {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "The collection is already on that contract"));
+    }
+    let target = Collection {
+        address: request.new_address,
+        contract_name: request.contract_name.unwrap_or_else(|| collection.contract_name.clone()),
+        contract_version: request.contract_version.or_else(|| collection.contract_version.clone()),
+        network: request.network.or_else(|| collection.network.clone()),
+        ..collection.clone()
+    };
+    let abi = state.artifacts.abi(&target.contract_name, target.contract_version.as_deref())?;
+    match abi_check::missing_functions(&state.on(&target)?, &target, &abi).await {
+        Ok(None) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("No contract is deployed at {:?}", target.address),
+            ))
+        }
+        Ok(Some(missing)) if !missing.is_empty() => {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "The new contract doesn't match its ABI")
+                .with_details(json!({ "missing": missing })))
+        }
+        Ok(Some(_)) => {}
+        Err(e) => return Err(ApiError::new(StatusCode::BAD_GATEWAY, e)),
+    }
+    let token_ids = token_ids(&state, &collection).await?;
+
+    let id = {
+        let mut conn = state.db.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| format!("Failed to start contract migration: {}", e))?;
+        tx.execute(
+            "INSERT INTO contract_migrations
+                 (collection, strategy, old_address, new_address, contract_name, contract_version, network, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                collection.name,
+                request.strategy.name(),
+                format!("{:?}", collection.address),
+                format!("{:?}", target.address),
+                target.contract_name,
+                target.contract_version,
+                target.network,
+                RUNNING
+            ],
+        )
+        .map_err(|e| format!("Failed to start contract migration: {}", e))?;
+        let id = tx.last_insert_rowid();
+        for token_id in &token_ids {
+            tx.execute(
+                "INSERT INTO contract_migration_tokens (migration_id, token_id) VALUES (?1, ?2)",
+                params![id, token_id],
+            )
+            .map_err(|e| format!("Failed to start contract migration: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to start contract migration: {}", e))?;
+        id
+    };
+    println!(
+        "Migrating {} tokens of collection {} to {:?} ({})",
+        token_ids.len(),
+        collection.name,
+        target.address,
+        request.strategy.name()
+    );
+    spawn(state.clone(), id);
+    let migration = load(&state.db, id)?.ok_or("Contract migration disappeared")?;
+    Ok((StatusCode::ACCEPTED, Json(migration)))
+}
+
+pub async fn get_migration(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Json<Migration>, ApiError> {
+    let migration = load(&state.db, id)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown contract migration: {}", id)))?;
+    Ok(Json(migration))
+}
+
+/// Restarts a failed migration from the first token that hasn't moved.
+pub async fn resume_migration(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Json<Migration>, ApiError> {
+    require_maintenance(&state)?;
+    let resumed = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE contract_migrations SET status = ?2, error = NULL WHERE id = ?1 AND status = ?3",
+            params![id, RUNNING, FAILED],
+        )
+        .map_err(|e| format!("Failed to resume contract migration: {}", e))?
+    };
+    let migration = load(&state.db, id)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown contract migration: {}", id)))?;
+    if resumed == 0 {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Contract migration {} is {}", id, migration.status)));
+    }
+    spawn(state.clone(), id);
+    Ok(Json(migration))
+}
@@ -0,0 +1,129 @@
+use ethers::providers::Middleware;
+use ethers::types::{Address, BlockNumber};
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{AsyncCommands, Script};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use crate::state::EthClient;
+
+const REDIS_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a replica may hold a nonce between allocating it and broadcasting.
+const NONCE_LEASE: Duration = Duration::from_secs(60);
+
+/// Hands out the lowest unused nonce. Expired leases (a replica died before broadcasting)
+/// and released nonces become gaps that are filled first; anything below the chain's
+/// pending count has been used since and is dropped.
+const ALLOCATE_NONCE: &str = r#"
+local pending = tonumber(ARGV[1])
+local lease = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+for _, nonce in ipairs(redis.call('ZRANGEBYSCORE', KEYS[3], '-inf', now)) do
+    redis.call('ZREM', KEYS[3], nonce)
+    redis.call('ZADD', KEYS[2], nonce, nonce)
+end
+redis.call('ZREMRANGEBYSCORE', KEYS[2], '-inf', '(' .. pending)
+
+local nonce
+local gap = redis.call('ZRANGE', KEYS[2], 0, 0)
+if #gap > 0 then
+    nonce = tonumber(gap[1])
+    redis.call('ZREM', KEYS[2], gap[1])
+else
+    nonce = math.max(tonumber(redis.call('GET', KEYS[1]) or '0'), pending)
+    redis.call('SET', KEYS[1], nonce + 1)
+end
+redis.call('ZADD', KEYS[3], now + lease, nonce)
+return nonce
+"#;
+
+/// A lazily connected Redis client shared by the job queue and the nonce allocator.
+pub struct Redis {
+    client: redis::Client,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl Redis {
+    pub fn open(url: &str) -> Self {
+        Redis {
+            client: redis::Client::open(url).expect("Invalid REDIS_URL"),
+            connection: OnceCell::new(),
+        }
+    }
+
+    pub async fn connection(&self) -> Result<ConnectionManager, String> {
+        self.connection
+            .get_or_try_init(|| {
+                let config = ConnectionManagerConfig::new()
+                    .set_connection_timeout(REDIS_TIMEOUT)
+                    .set_response_timeout(REDIS_TIMEOUT)
+                    .set_number_of_retries(2)
+                    .set_factor(1)
+                    .set_max_delay(1000);
+                ConnectionManager::new_with_config(self.client.clone(), config)
+            })
+            .await
+            .cloned()
+            .map_err(|e| format!("Failed to connect to Redis: {}", e))
+    }
+}
+
+/// Assigns nonces for wallets shared by several replicas, so concurrent sends never collide.
+pub struct NonceAllocator {
+    redis: Arc<Redis>,
+    chain_id: u64,
+}
+
+impl NonceAllocator {
+    pub fn new(redis: Arc<Redis>, chain_id: u64) -> Self {
+        NonceAllocator { redis, chain_id }
+    }
+
+    fn keys(&self, wallet: Address) -> (String, String, String) {
+        let base = format!("nonces:{}:{:?}", self.chain_id, wallet);
+        (format!("{}:next", base), format!("{}:gaps", base), format!("{}:leases", base))
+    }
+
+    /// Leases the next nonce for `wallet`. Call `confirm` once the transaction is broadcast
+    /// or `release` if it never was.
+    pub async fn acquire(&self, client: &EthClient, wallet: Address) -> Result<u64, String> {
+        let pending = client
+            .get_transaction_count(wallet, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| format!("Failed to read the pending nonce: {}", e))?;
+        let (next, gaps, leases) = self.keys(wallet);
+        let mut conn = self.redis.connection().await?;
+        Script::new(ALLOCATE_NONCE)
+            .key(next)
+            .key(gaps)
+            .key(leases)
+            .arg(pending.as_u64())
+            .arg(NONCE_LEASE.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to allocate a nonce: {}", e))
+    }
+
+    pub async fn confirm(&self, wallet: Address, nonce: u64) -> Result<(), String> {
+        let (_, _, leases) = self.keys(wallet);
+        let mut conn = self.redis.connection().await?;
+        conn.zrem::<_, _, ()>(leases, nonce)
+            .await
+            .map_err(|e| format!("Failed to confirm nonce {}: {}", nonce, e))
+    }
+
+    /// Returns an unused nonce so the next transaction fills the gap.
+    pub async fn release(&self, wallet: Address, nonce: u64) -> Result<(), String> {
+        let (_, gaps, leases) = self.keys(wallet);
+        let mut conn = self.redis.connection().await?;
+        redis::pipe()
+            .zrem(leases, nonce)
+            .zadd(gaps, nonce, nonce)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to release nonce {}: {}", nonce, e))
+    }
+}
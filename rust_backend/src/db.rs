@@ -0,0 +1,558 @@
+use rusqlite::Connection;
+use std::sync::{Arc, Mutex};
+
+pub type Db = Arc<Mutex<Connection>>;
+
+// Applied in order; the index of the last applied migration is kept in `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS collections (
+        name TEXT PRIMARY KEY,
+        address TEXT NOT NULL,
+        description TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "ALTER TABLE collections ADD COLUMN contract_name TEXT NOT NULL DEFAULT 'RealEstateNFT';
+     ALTER TABLE collections ADD COLUMN contract_version TEXT;",
+    "CREATE TABLE allowlists (
+        name TEXT PRIMARY KEY,
+        root TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE allowlist_entries (
+        allowlist TEXT NOT NULL REFERENCES allowlists(name),
+        address TEXT NOT NULL,
+        PRIMARY KEY (allowlist, address)
+    );
+    ALTER TABLE collections ADD COLUMN allowlist TEXT;",
+    "CREATE TABLE fractionalizations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        share_id TEXT NOT NULL,
+        shares INTEGER NOT NULL,
+        vault TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        status TEXT NOT NULL DEFAULT 'active',
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        redeemed_at TEXT
+    );",
+    "CREATE TABLE mints (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        recipient TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        block_number INTEGER NOT NULL,
+        price REAL NOT NULL,
+        details TEXT NOT NULL,
+        metadata TEXT NOT NULL,
+        soulbound INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        UNIQUE (collection, token_id)
+    );
+    CREATE TABLE listings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        seller TEXT NOT NULL,
+        price TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'active',
+        settlement_tx TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE offers (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        listing_id INTEGER NOT NULL REFERENCES listings(id),
+        buyer TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT 'open',
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE mint_jobs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        status TEXT NOT NULL,
+        request TEXT NOT NULL,
+        result TEXT,
+        error TEXT,
+        payment_session TEXT,
+        payment_status TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE mint_fees (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER,
+        payer TEXT NOT NULL,
+        token TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        fee_transaction_hash TEXT NOT NULL,
+        mint_transaction_hash TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE kyc_verifications (
+        address TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        reference TEXT,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE sanctions_rejections (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        address TEXT NOT NULL,
+        action TEXT NOT NULL,
+        source TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE audit_logs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        actor TEXT NOT NULL,
+        ip TEXT,
+        method TEXT NOT NULL,
+        path TEXT NOT NULL,
+        payload_hash TEXT,
+        status INTEGER,
+        result TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX audit_logs_created_at ON audit_logs (created_at);
+    CREATE TRIGGER audit_logs_no_update BEFORE UPDATE ON audit_logs
+    BEGIN SELECT RAISE(ABORT, 'audit_logs is append-only'); END;
+    CREATE TRIGGER audit_logs_no_delete BEFORE DELETE ON audit_logs
+    BEGIN SELECT RAISE(ABORT, 'audit_logs is append-only'); END;",
+    "CREATE TABLE data_deletions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        owner TEXT NOT NULL,
+        summary TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE organizations (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        wallet_env TEXT,
+        default_collection TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE organization_api_keys (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        organization TEXT NOT NULL REFERENCES organizations(id),
+        key_hash TEXT NOT NULL UNIQUE,
+        label TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        revoked_at TEXT
+    );
+    CREATE TABLE organization_webhooks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        organization TEXT NOT NULL REFERENCES organizations(id),
+        url TEXT NOT NULL,
+        secret TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    ALTER TABLE collections ADD COLUMN organization TEXT REFERENCES organizations(id);
+    ALTER TABLE mint_jobs ADD COLUMN organization TEXT;",
+    "ALTER TABLE organization_api_keys ADD COLUMN role TEXT NOT NULL DEFAULT 'agent';
+    CREATE TABLE valuations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        price REAL NOT NULL,
+        source TEXT NOT NULL,
+        actor TEXT NOT NULL,
+        transaction_hash TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX valuations_token ON valuations (collection, token_id);
+    INSERT INTO valuations (collection, token_id, price, source, actor, transaction_hash, created_at)
+        SELECT collection, token_id, price, 'model', 'mint', transaction_hash, created_at FROM mints;",
+    // HMAC secrets can't be hashed; they are only readable by whoever can read the database.
+    "ALTER TABLE organization_api_keys ADD COLUMN signing_secret TEXT;",
+    "CREATE TABLE transactions (
+        transaction_hash TEXT PRIMARY KEY,
+        sender TEXT NOT NULL,
+        gas_used INTEGER NOT NULL,
+        gas_cost_wei TEXT NOT NULL,
+        success INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX transactions_created_at ON transactions (created_at);
+    CREATE TABLE predictions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        latency_ms INTEGER NOT NULL,
+        success INTEGER NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX predictions_created_at ON predictions (created_at);",
+    "ALTER TABLE mint_jobs ADD COLUMN scheduled_at TEXT;
+    CREATE INDEX mint_jobs_scheduled ON mint_jobs (status, scheduled_at);",
+    "CREATE TABLE mint_dead_letters (
+        job_id INTEGER PRIMARY KEY REFERENCES mint_jobs(id),
+        organization TEXT,
+        request TEXT NOT NULL,
+        errors TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 1,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        resolved_at TEXT
+    );",
+    "CREATE TABLE token_transfers (
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        from_address TEXT NOT NULL,
+        to_address TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        log_index INTEGER NOT NULL,
+        block_number INTEGER NOT NULL,
+        PRIMARY KEY (collection, transaction_hash, log_index)
+    );
+    CREATE INDEX token_transfers_token ON token_transfers (collection, token_id, block_number, log_index);
+    CREATE TABLE indexer_cursors (
+        collection TEXT PRIMARY KEY,
+        block_number INTEGER NOT NULL
+    );",
+    "CREATE TABLE enrichment_cache (
+        provider TEXT NOT NULL,
+        cell TEXT NOT NULL,
+        attributes TEXT NOT NULL,
+        fetched_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (provider, cell)
+    );",
+    "CREATE TABLE parcel_verifications (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        verified INTEGER NOT NULL,
+        parcel_id TEXT,
+        source TEXT NOT NULL,
+        mismatches TEXT NOT NULL,
+        record TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX parcel_verifications_token ON parcel_verifications (collection, token_id);",
+    "CREATE TABLE appraisals (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        price REAL NOT NULL,
+        model_price REAL,
+        appraiser TEXT NOT NULL,
+        license_number TEXT NOT NULL,
+        signature TEXT NOT NULL UNIQUE,
+        notes TEXT,
+        transaction_hash TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX appraisals_token ON appraisals (collection, token_id);",
+    "CREATE TABLE appraisers (
+        address TEXT PRIMARY KEY,
+        license_number TEXT NOT NULL,
+        name TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE consensus_valuations (
+        job_id INTEGER NOT NULL,
+        appraiser TEXT NOT NULL,
+        price REAL NOT NULL,
+        signature TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (job_id, appraiser)
+    );
+    ALTER TABLE mint_jobs ADD COLUMN model_price REAL;
+    ALTER TABLE mint_jobs ADD COLUMN consensus_price REAL;",
+    "CREATE TABLE disputes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        owner TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        requested_price REAL,
+        disputed_price REAL NOT NULL,
+        status TEXT NOT NULL,
+        reviewer TEXT,
+        resolution TEXT,
+        resolved_price REAL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX disputes_token ON disputes (collection, token_id);
+    CREATE TABLE dispute_evidence (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        dispute_id INTEGER NOT NULL REFERENCES disputes (id),
+        file_name TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        data BLOB NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE liens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        lender TEXT NOT NULL,
+        amount REAL NOT NULL,
+        document_hash TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL DEFAULT '',
+        release_transaction_hash TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        released_at TEXT
+    );
+    CREATE INDEX liens_token ON liens (collection, token_id);",
+    "ALTER TABLE valuations ADD COLUMN valuation_type TEXT NOT NULL DEFAULT 'market';",
+    "ALTER TABLE mints ADD COLUMN metadata_storage TEXT NOT NULL DEFAULT 'inline';",
+    "ALTER TABLE mints ADD COLUMN token_uri TEXT;
+    CREATE TABLE pin_status (
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        cid TEXT NOT NULL,
+        status TEXT NOT NULL,
+        pinned INTEGER NOT NULL,
+        gateways TEXT NOT NULL,
+        error TEXT,
+        repinned_at TEXT,
+        checked_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (collection, token_id)
+    );",
+    "CREATE TABLE metadata_versions (
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        version INTEGER NOT NULL,
+        metadata TEXT NOT NULL,
+        transaction_hash TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (collection, token_id, version)
+    );
+    -- Existing tokens start their history from the metadata they hold now.
+    INSERT INTO metadata_versions (collection, token_id, version, metadata, transaction_hash, created_at)
+    SELECT collection, token_id, 1, metadata, transaction_hash, created_at FROM mints;",
+    "CREATE TABLE private_fields (
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        data BLOB NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (collection, token_id)
+    );",
+    "CREATE TABLE map_thumbnails (
+        key TEXT PRIMARY KEY,
+        image BLOB NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE VIRTUAL TABLE search_index USING fts5(collection UNINDEXED, token_id UNINDEXED, name, description);
+    CREATE TRIGGER mints_search_insert AFTER INSERT ON mints BEGIN
+        INSERT INTO search_index (collection, token_id, name, description)
+        VALUES (new.collection, new.token_id, json_extract(new.metadata, '$.name'),
+                json_extract(new.metadata, '$.description'));
+    END;
+    CREATE TRIGGER mints_search_update AFTER UPDATE OF metadata ON mints BEGIN
+        DELETE FROM search_index WHERE collection = old.collection AND token_id = old.token_id;
+        INSERT INTO search_index (collection, token_id, name, description)
+        VALUES (new.collection, new.token_id, json_extract(new.metadata, '$.name'),
+                json_extract(new.metadata, '$.description'));
+    END;
+    CREATE TRIGGER mints_search_delete AFTER DELETE ON mints BEGIN
+        DELETE FROM search_index WHERE collection = old.collection AND token_id = old.token_id;
+    END;
+    INSERT INTO search_index (collection, token_id, name, description)
+    SELECT collection, token_id, json_extract(metadata, '$.name'), json_extract(metadata, '$.description')
+    FROM mints;",
+    "CREATE TABLE saved_searches (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        organization TEXT,
+        name TEXT NOT NULL,
+        q TEXT NOT NULL DEFAULT '',
+        filters TEXT NOT NULL DEFAULT '',
+        collection TEXT,
+        webhook_url TEXT,
+        webhook_secret TEXT,
+        email TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE TABLE saved_search_matches (
+        search_id INTEGER NOT NULL,
+        collection TEXT NOT NULL,
+        token_id INTEGER NOT NULL,
+        notified_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (search_id, collection, token_id)
+    );",
+    "CREATE TABLE contract_metadata (
+        collection TEXT PRIMARY KEY,
+        name TEXT,
+        description TEXT,
+        image TEXT,
+        external_link TEXT,
+        seller_fee_basis_points INTEGER,
+        fee_recipient TEXT,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE collection_interfaces (
+        collection TEXT NOT NULL,
+        interface TEXT NOT NULL,
+        supported INTEGER NOT NULL,
+        probed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (collection, interface)
+    );",
+    "CREATE TABLE pending_transactions (
+        transaction_hash TEXT PRIMARY KEY,
+        sender TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    // Mints recorded before block hashes were kept get theirs on the indexer's first pass.
+    "ALTER TABLE mints ADD COLUMN block_hash TEXT;
+    ALTER TABLE mints ADD COLUMN status TEXT NOT NULL DEFAULT 'confirmed';",
+    "ALTER TABLE collections ADD COLUMN network TEXT;
+    ALTER TABLE mint_jobs ADD COLUMN network TEXT;",
+    "CREATE TABLE bridged_tokens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        origin_collection TEXT NOT NULL,
+        origin_token_id INTEGER NOT NULL,
+        destination_chain_id INTEGER NOT NULL,
+        destination_contract TEXT NOT NULL,
+        destination_token_id TEXT NOT NULL,
+        destination_collection TEXT,
+        bridge TEXT,
+        transaction_hash TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    CREATE INDEX bridged_tokens_origin ON bridged_tokens (origin_collection, origin_token_id);
+    CREATE INDEX bridged_tokens_destination ON bridged_tokens (destination_collection, destination_token_id);",
+    "CREATE TABLE feature_flags (
+        name TEXT PRIMARY KEY,
+        enabled INTEGER NOT NULL,
+        percentage INTEGER NOT NULL,
+        organizations TEXT NOT NULL,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE maintenance (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        mode TEXT NOT NULL,
+        reason TEXT,
+        retry_after_secs INTEGER NOT NULL,
+        started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );",
+    "CREATE TABLE contract_migrations (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        strategy TEXT NOT NULL,
+        old_address TEXT NOT NULL,
+        new_address TEXT NOT NULL,
+        contract_name TEXT NOT NULL,
+        contract_version TEXT,
+        network TEXT,
+        status TEXT NOT NULL,
+        error TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        completed_at TEXT
+    );
+    CREATE TABLE contract_migration_tokens (
+        migration_id INTEGER NOT NULL REFERENCES contract_migrations(id),
+        token_id INTEGER NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        owner TEXT,
+        transaction_hash TEXT,
+        error TEXT,
+        PRIMARY KEY (migration_id, token_id)
+    );",
+    "CREATE TABLE metadata_backfills (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        push_onchain INTEGER NOT NULL,
+        batch_size INTEGER NOT NULL,
+        gas_budget_wei TEXT,
+        gas_spent_wei TEXT NOT NULL DEFAULT '0',
+        status TEXT NOT NULL,
+        error TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        completed_at TEXT
+    );
+    CREATE TABLE metadata_backfill_tokens (
+        backfill_id INTEGER NOT NULL REFERENCES metadata_backfills(id),
+        token_id INTEGER NOT NULL,
+        status TEXT NOT NULL DEFAULT 'pending',
+        changes INTEGER,
+        token_uri TEXT,
+        transaction_hash TEXT,
+        error TEXT,
+        PRIMARY KEY (backfill_id, token_id)
+    );",
+    "CREATE TABLE exchange_rates (
+        currency TEXT PRIMARY KEY,
+        rate TEXT NOT NULL,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+    ALTER TABLE mints ADD COLUMN price_cents INTEGER;
+    ALTER TABLE mints ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+    UPDATE mints SET price_cents = CAST(ROUND(price * 100) AS INTEGER);
+    ALTER TABLE valuations ADD COLUMN price_cents INTEGER;
+    ALTER TABLE valuations ADD COLUMN currency TEXT NOT NULL DEFAULT 'USD';
+    UPDATE valuations SET price_cents = CAST(ROUND(price * 100) AS INTEGER);",
+    "CREATE TABLE zipcode_medians (
+        zipcode INTEGER NOT NULL,
+        field TEXT NOT NULL,
+        median REAL NOT NULL,
+        samples INTEGER,
+        updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (zipcode, field)
+    );",
+    "ALTER TABLE mint_jobs ADD COLUMN outlier TEXT;",
+    "ALTER TABLE mint_jobs ADD COLUMN approval_price REAL;
+    ALTER TABLE mint_jobs ADD COLUMN requested_by TEXT;
+    ALTER TABLE mint_jobs ADD COLUMN approved_by TEXT;
+    ALTER TABLE mint_jobs ADD COLUMN approved_at TEXT;",
+    "CREATE TABLE admin_confirmations (
+        token TEXT PRIMARY KEY,
+        method TEXT NOT NULL,
+        path TEXT NOT NULL,
+        payload_hash TEXT NOT NULL,
+        body TEXT NOT NULL,
+        requested_by TEXT NOT NULL,
+        confirmed_by TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        expires_at TEXT NOT NULL,
+        confirmed_at TEXT
+    );",
+    "ALTER TABLE mint_jobs ADD COLUMN transaction_hash TEXT;",
+    "ALTER TABLE mint_jobs ADD COLUMN private_data BLOB;",
+    "ALTER TABLE saved_searches ADD COLUMN owner TEXT;",
+    "CREATE TABLE unpaid_mint_fees (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        collection TEXT NOT NULL,
+        token_id INTEGER,
+        payer TEXT NOT NULL,
+        token TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        mint_transaction_hash TEXT NOT NULL,
+        error TEXT NOT NULL,
+        fee_transaction_hash TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        paid_at TEXT
+    );",
+    "ALTER TABLE mint_jobs ADD COLUMN model_price_cents INTEGER;
+    ALTER TABLE mint_jobs ADD COLUMN consensus_price_cents INTEGER;
+    ALTER TABLE mint_jobs ADD COLUMN approval_price_cents INTEGER;
+    UPDATE mint_jobs SET model_price_cents = CAST(ROUND(model_price * 100) AS INTEGER),
+        consensus_price_cents = CAST(ROUND(consensus_price * 100) AS INTEGER),
+        approval_price_cents = CAST(ROUND(approval_price * 100) AS INTEGER);",
+];
+
+/// The schema version this build migrates databases to.
+pub fn schema_version() -> usize {
+    MIGRATIONS.len()
+}
+
+pub fn open(path: &str) -> Db {
+    let mut conn = Connection::open(path).expect("Failed to open the database");
+    migrate(&mut conn).expect("Failed to apply database migrations");
+    Arc::new(Mutex::new(conn))
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
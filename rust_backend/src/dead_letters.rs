@@ -0,0 +1,139 @@
+use axum::extract::State;
+use axum::Json;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::jobs::{self, MintJob};
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct DeadLetter {
+    job_id: i64,
+    request: Value,
+    /// One entry per failed attempt, oldest first.
+    errors: Value,
+    attempts: u32,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct RequeueRequest {
+    /// Jobs to requeue; all unresolved dead letters when omitted.
+    job_ids: Option<Vec<i64>>,
+}
+
+#[derive(Serialize)]
+pub struct RequeueResponse {
+    requeued: Vec<i64>,
+    /// Jobs that are not failed (anymore), not visible to the caller, or whose broadcast
+    /// transaction hasn't reverted and may still mint.
+    skipped: Vec<i64>,
+}
+
+/// Parks a failed job with its request and appends the failure to its error chain.
+pub fn record(db: &Db, job: &MintJob, error: &ApiError) -> Result<(), String> {
+    let entry = json!({
+        "status": error.status.as_u16(),
+        "error": error.message,
+        "details": error.details,
+    });
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO mint_dead_letters (job_id, organization, request, errors)
+         VALUES (?1, ?2, ?3, json_array(json_set(?4, '$.at', datetime('now'))))
+         ON CONFLICT (job_id) DO UPDATE SET
+             request = excluded.request,
+             errors = json_insert(errors, '$[#]', json_set(?4, '$.at', datetime('now'))),
+             attempts = attempts + 1,
+             resolved_at = NULL,
+             updated_at = CURRENT_TIMESTAMP",
+        params![job.id, job.organization, job.request.to_string(), entry.to_string()],
+    )
+    .map_err(|e| format!("Failed to record dead letter: {}", e))?;
+    Ok(())
+}
+
+/// Closes the dead letter once a retried job mints.
+pub fn resolve(db: &Db, job_id: i64) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_dead_letters SET resolved_at = CURRENT_TIMESTAMP WHERE job_id = ?1 AND resolved_at IS NULL",
+        params![job_id],
+    )
+    .map_err(|e| format!("Failed to resolve dead letter: {}", e))?;
+    Ok(())
+}
+
+fn unresolved(db: &Db, organization: Option<&str>) -> Result<Vec<DeadLetter>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT job_id, request, errors, attempts, created_at, updated_at FROM mint_dead_letters
+             WHERE resolved_at IS NULL AND organization IS ?1 ORDER BY updated_at DESC",
+        )
+        .map_err(|e| format!("Failed to list dead letters: {}", e))?;
+    let rows = stmt
+        .query_map(params![organization], |row| {
+            let request: String = row.get(1)?;
+            let errors: String = row.get(2)?;
+            Ok(DeadLetter {
+                job_id: row.get(0)?,
+                request: serde_json::from_str(&request).unwrap_or_default(),
+                errors: serde_json::from_str(&errors).unwrap_or_default(),
+                attempts: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("Failed to list dead letters: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to list dead letters: {}", e))
+}
+
+/// Failed mints awaiting operator review.
+pub async fn list_failed(State(state): State<AppState>, tenant: Tenant) -> Result<Json<Vec<DeadLetter>>, ApiError> {
+    Ok(Json(unresolved(&state.db, tenant.organization_id())?))
+}
+
+/// Requeues failed mints in bulk, e.g. once a drained wallet is topped up.
+pub async fn requeue(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(request): Json<RequeueRequest>,
+) -> Result<Json<RequeueResponse>, ApiError> {
+    let visible: Vec<i64> = unresolved(&state.db, tenant.organization_id())?
+        .into_iter()
+        .map(|letter| letter.job_id)
+        .collect();
+    let job_ids = request.job_ids.unwrap_or_else(|| visible.clone());
+
+    let mut response = RequeueResponse {
+        requeued: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for id in job_ids {
+        let settled = match jobs::get(&state.db, id)?.filter(|_| visible.contains(&id)) {
+            Some(job) => match jobs::unsettled_transaction(&state, &job).await {
+                Ok(unsettled) => unsettled.is_none(),
+                Err(e) => {
+                    eprintln!("Mint job {} was not requeued: {}", id, e.message);
+                    false
+                }
+            },
+            None => false,
+        };
+        if settled && jobs::requeue_failed(&state.db, id)? {
+            jobs::spawn(state.clone(), id);
+            response.requeued.push(id);
+        } else {
+            response.skipped.push(id);
+        }
+    }
+    println!("Requeued {} failed mint jobs", response.requeued.len());
+    Ok(Json(response))
+}
@@ -0,0 +1,111 @@
+use axum::extract::State;
+use axum::Json;
+use ethers::abi::Token;
+use ethers::contract::ContractFactory;
+use ethers::etherscan::verify::VerifyContract;
+use ethers::types::{Address, Chain};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::capabilities;
+use crate::config::optional_env;
+use crate::bindings::{REALESTATENFT_ABI, REALESTATENFT_BYTECODE};
+use crate::error::ApiError;
+use crate::registry;
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct DeployRequest {
+    collection: String,
+    name: String,
+    symbol: String,
+    description: Option<String>,
+    /// Organization that will own the new collection.
+    organization: Option<String>,
+    confirmations: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct DeployResponse {
+    collection: String,
+    address: Address,
+    transaction_hash: String,
+    verification: String,
+}
+
+pub async fn deploy_contract(
+    State(state): State<AppState>,
+    Json(request): Json<DeployRequest>,
+) -> Result<Json<DeployResponse>, ApiError> {
+    let abi = REALESTATENFT_ABI.clone();
+
+    // Older artifacts were compiled without the name/symbol constructor arguments.
+    let args = match abi.constructor() {
+        Some(constructor) if !constructor.inputs.is_empty() => {
+            vec![Token::String(request.name.clone()), Token::String(request.symbol.clone())]
+        }
+        _ => Vec::new(),
+    };
+    let encoded_args = ethers::abi::encode(&args);
+
+    println!("Deploying RealEstateNFT for collection {}...", request.collection);
+    let factory = ContractFactory::new(abi, REALESTATENFT_BYTECODE.clone(), state.client.clone());
+    let (contract, receipt) = factory
+        .deploy_tokens(args)
+        .map_err(|e| format!("Failed to build deployment: {}", e))?
+        .confirmations(request.confirmations.unwrap_or(1))
+        .send_with_receipt()
+        .await
+        .map_err(|e| format!("Deployment failed: {}", e))?;
+    let address = contract.address();
+    println!("Contract deployed at {:?}", address);
+
+    registry::register(
+        &state.db,
+        &request.collection,
+        address,
+        registry::ContractRef::default(),
+        request.description.as_deref(),
+    )?;
+    if let Some(org) = &request.organization {
+        registry::assign(&state.db, &request.collection, org)?;
+    }
+    capabilities::spawn_probe(&state, registry::resolve(&state.db, Some(&request.collection))?);
+
+    let verification = match verification_settings(state.chain.chain_id) {
+        Ok((client, source, compiler_version)) => {
+            let verify = VerifyContract::new(address, "RealEstateNFT".to_string(), source, compiler_version)
+                .constructor_arguments(Some(ethers::utils::hex::encode(&encoded_args)));
+            tokio::spawn(async move {
+                // Etherscan rejects submissions until it has indexed the deployment.
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                match client.submit_contract_verification(&verify).await {
+                    Ok(response) => println!("Etherscan verification submitted for {:?}: {}", address, response.result),
+                    Err(e) => eprintln!("Etherscan verification failed for {:?}: {}", address, e),
+                }
+            });
+            "submitted".to_string()
+        }
+        Err(reason) => format!("skipped: {}", reason),
+    };
+
+    Ok(Json(DeployResponse {
+        collection: request.collection,
+        address,
+        transaction_hash: format!("{:?}", receipt.transaction_hash),
+        verification,
+    }))
+}
+
+fn verification_settings(chain_id: u64) -> Result<(ethers::etherscan::Client, String, String), String> {
+    let api_key = optional_env("ETHERSCAN_API_KEY").ok_or("ETHERSCAN_API_KEY is not set")?;
+    let source_path = optional_env("VERIFY_SOURCE_PATH").ok_or("VERIFY_SOURCE_PATH is not set")?;
+    let compiler_version = optional_env("SOLC_VERSION").ok_or("SOLC_VERSION is not set")?;
+
+    let chain = Chain::try_from(chain_id).map_err(|_| format!("unknown chain {}", chain_id))?;
+    let client = ethers::etherscan::Client::new(chain, api_key)
+        .map_err(|e| format!("Etherscan is not available for chain {}: {}", chain_id, e))?;
+    let source = std::fs::read_to_string(&source_path)
+        .map_err(|e| format!("failed to read {}: {}", source_path, e))?;
+    Ok((client, source, compiler_version))
+}
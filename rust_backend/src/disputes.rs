@@ -0,0 +1,418 @@
+use axum::extract::{Multipart, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use ethers::types::{Address, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::appraisals;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::mint::{self, HouseDetails};
+use crate::money::{Currency, Money};
+use crate::nfts;
+use crate::organizations::Tenant;
+use crate::private;
+use crate::registry;
+use crate::simulation;
+use crate::state::AppState;
+use crate::valuations::{self, NewValuation, ValuationType};
+use crate::CollectionQuery;
+
+pub const OPEN: &str = "open";
+pub const UNDER_REVIEW: &str = "under_review";
+pub const RESOLVED: &str = "resolved";
+
+const MAX_EVIDENCE_FILES: usize = 10;
+/// Request body limit for filing a dispute, covering all of its evidence.
+pub const MAX_UPLOAD_BYTES: usize = 25 * 1024 * 1024;
+
+#[derive(Serialize)]
+pub struct Evidence {
+    pub id: i64,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+}
+
+#[derive(Serialize)]
+pub struct Dispute {
+    pub id: i64,
+    pub collection: String,
+    pub token_id: u64,
+    pub owner: String,
+    pub reason: String,
+    pub requested_price: Option<f64>,
+    /// The token's price when the dispute was filed.
+    pub disputed_price: f64,
+    pub status: String,
+    pub reviewer: Option<String>,
+    pub resolution: Option<String>,
+    pub resolved_price: Option<f64>,
+    pub evidence: Vec<Evidence>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewRequest {
+    reviewer: String,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveRequest {
+    resolution: String,
+    /// A corrected price; leave out to uphold the disputed valuation.
+    price: Option<f64>,
+}
+
+/// What the owner signs to file a dispute.
+pub fn message(collection: &str, token_id: u64, reason: &str) -> String {
+    format!("I own token {} of collection {} and dispute its valuation: {}", token_id, collection, reason)
+}
+
+fn load(db: &Db, id: i64) -> Result<Option<Dispute>, String> {
+    let conn = db.lock().unwrap();
+    let dispute = conn
+        .query_row(
+            "SELECT id, collection, token_id, owner, reason, requested_price, disputed_price, status, reviewer,
+                    resolution, resolved_price, created_at, updated_at
+             FROM disputes WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(Dispute {
+                    id: row.get(0)?,
+                    collection: row.get(1)?,
+                    token_id: row.get(2)?,
+                    owner: row.get(3)?,
+                    reason: row.get(4)?,
+                    requested_price: row.get(5)?,
+                    disputed_price: row.get(6)?,
+                    status: row.get(7)?,
+                    reviewer: row.get(8)?,
+                    resolution: row.get(9)?,
+                    resolved_price: row.get(10)?,
+                    evidence: Vec::new(),
+                    created_at: row.get(11)?,
+                    updated_at: row.get(12)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load dispute: {}", e))?;
+    let Some(mut dispute) = dispute else { return Ok(None) };
+    dispute.evidence = conn
+        .prepare("SELECT id, file_name, content_type, length(data) FROM dispute_evidence WHERE dispute_id = ?1 ORDER BY id")
+        .and_then(|mut stmt| {
+            stmt.query_map(params![id], |row| {
+                Ok(Evidence {
+                    id: row.get(0)?,
+                    file_name: row.get(1)?,
+                    content_type: row.get(2)?,
+                    size: row.get(3)?,
+                })
+            })?
+            .collect()
+        })
+        .map_err(|e| format!("Failed to load dispute evidence: {}", e))?;
+    Ok(Some(dispute))
+}
+
+/// Loads a dispute on one of the tenant's collections.
+fn find(state: &AppState, tenant: &Tenant, id: i64) -> Result<Dispute, ApiError> {
+    let dispute = load(&state.db, id)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Dispute {} not found", id)))?;
+    registry::resolve_for(&state.db, tenant, Some(&dispute.collection))
+        .map_err(|_| ApiError::new(StatusCode::NOT_FOUND, format!("Dispute {} not found", id)))?;
+    Ok(dispute)
+}
+
+
+fn wrong_status(dispute: &Dispute, action: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        format!("Dispute {} is {} and cannot be {}", dispute.id, dispute.status, action),
+    )
+    .with_details(json!({ "status": dispute.status }))
+}
+
+/// Files a dispute as a multipart form: `owner`, `signature`, `reason`, an optional
+/// `requested_price` and any number of `evidence` files.
+pub async fn file(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    mut form: Multipart,
+) -> Result<(StatusCode, Json<Dispute>), ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let bad_form = |e: axum::extract::multipart::MultipartError| {
+        ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid dispute form: {}", e))
+    };
+    let (mut owner, mut signature, mut reason, mut requested_price) = (None, None, None, None);
+    let mut evidence = Vec::new();
+    while let Some(field) = form.next_field().await.map_err(bad_form)? {
+        let name = field.name().unwrap_or_default().to_string();
+        if name == "evidence" {
+            if evidence.len() == MAX_EVIDENCE_FILES {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("At most {} evidence files can be attached", MAX_EVIDENCE_FILES),
+                ));
+            }
+            let file_name = field.file_name().unwrap_or("evidence").to_string();
+            let content_type = field.content_type().unwrap_or("application/octet-stream").to_string();
+            evidence.push((file_name, content_type, field.bytes().await.map_err(bad_form)?));
+            continue;
+        }
+        let value = field.text().await.map_err(bad_form)?;
+        match name.as_str() {
+            "owner" => owner = Some(value),
+            "signature" => signature = Some(value),
+            "reason" => reason = Some(value),
+            "requested_price" => requested_price = Some(value),
+            _ => {}
+        }
+    }
+    let missing = |field: &str| ApiError::new(StatusCode::BAD_REQUEST, format!("{} is required", field));
+    let owner: Address = owner
+        .ok_or_else(|| missing("owner"))?
+        .parse()
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "owner must be an address"))?;
+    let signature = signature.ok_or_else(|| missing("signature"))?;
+    let reason = reason.filter(|reason| !reason.trim().is_empty()).ok_or_else(|| missing("reason"))?;
+    let requested_price = match requested_price {
+        Some(price) => Some(
+            price
+                .parse::<f64>()
+                .ok()
+                .filter(|price| price.is_finite() && *price > 0.0)
+                .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "requested_price must be a positive number"))?,
+        ),
+        None => None,
+    };
+
+    let message = message(&collection.name, token_id, &reason);
+    if !appraisals::signed_by(&signature, &message, owner) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("signature is not {:?}'s signature of the dispute", owner),
+        )
+        .with_details(json!({ "message": message })));
+    }
+    let holder = state
+        .nft(&collection)?
+        .owner_of(U256::from(token_id))
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read token owner", e))?;
+    if holder != owner {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("Only the owner of token {} can dispute its valuation", token_id),
+        ));
+    }
+
+    let id = {
+        let mut conn = state.db.lock().unwrap();
+        let disputed_price: Option<f64> = conn
+            .query_row(
+                "SELECT price FROM mints WHERE collection = ?1 AND token_id = ?2",
+                params![collection.name, token_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?;
+        let disputed_price = disputed_price.ok_or_else(|| {
+            ApiError::new(StatusCode::NOT_FOUND, format!("Token {} was not minted through this service", token_id))
+        })?;
+        let active: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM disputes WHERE collection = ?1 AND token_id = ?2 AND status != ?3",
+                params![collection.name, token_id, RESOLVED],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load disputes: {}", e))?;
+        if let Some(active) = active {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Token {} already has an unresolved dispute", token_id),
+            )
+            .with_details(json!({ "dispute_id": active })));
+        }
+        let fail = |e: rusqlite::Error| format!("Failed to file dispute: {}", e);
+        let tx = conn.transaction().map_err(fail)?;
+        tx.execute(
+            "INSERT INTO disputes (collection, token_id, owner, reason, requested_price, disputed_price, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![collection.name, token_id, format!("{:?}", owner), reason, requested_price, disputed_price, OPEN],
+        )
+        .map_err(fail)?;
+        let id = tx.last_insert_rowid();
+        for (file_name, content_type, data) in &evidence {
+            tx.execute(
+                "INSERT INTO dispute_evidence (dispute_id, file_name, content_type, data) VALUES (?1, ?2, ?3, ?4)",
+                params![id, file_name, content_type, data.as_ref()],
+            )
+            .map_err(fail)?;
+        }
+        tx.commit().map_err(fail)?;
+        id
+    };
+    println!("Dispute {} filed against the valuation of token {} of {}", id, token_id, collection.name);
+    Ok((StatusCode::CREATED, Json(load(&state.db, id)?.ok_or("Dispute disappeared")?)))
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<Dispute>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let ids: Vec<i64> = {
+        let conn = state.db.lock().unwrap();
+        conn.prepare("SELECT id FROM disputes WHERE collection = ?1 AND token_id = ?2 ORDER BY id DESC")
+            .and_then(|mut stmt| stmt.query_map(params![collection.name, token_id], |row| row.get(0))?.collect())
+            .map_err(|e| format!("Failed to load disputes: {}", e))?
+    };
+    let mut disputes = Vec::new();
+    for id in ids {
+        disputes.extend(load(&state.db, id)?);
+    }
+    Ok(Json(disputes))
+}
+
+pub async fn show(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+) -> Result<Json<Dispute>, ApiError> {
+    Ok(Json(find(&state, &tenant, id)?))
+}
+
+pub async fn evidence(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path((id, evidence_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, ApiError> {
+    find(&state, &tenant, id)?;
+    let conn = state.db.lock().unwrap();
+    let (file_name, content_type, data): (String, String, Vec<u8>) = conn
+        .query_row(
+            "SELECT file_name, content_type, data FROM dispute_evidence WHERE dispute_id = ?1 AND id = ?2",
+            params![id, evidence_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load dispute evidence: {}", e))?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Evidence {} not found", evidence_id)))?;
+    let disposition = format!("attachment; filename=\"{}\"", file_name.replace(['"', '\\'], "_"));
+    Ok(([(header::CONTENT_TYPE, content_type), (header::CONTENT_DISPOSITION, disposition)], data))
+}
+
+/// Takes an open dispute into review.
+pub async fn review(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+    Json(request): Json<ReviewRequest>,
+) -> Result<Json<Dispute>, ApiError> {
+    let dispute = find(&state, &tenant, id)?;
+    if request.reviewer.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "reviewer is required"));
+    }
+    let updated = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE disputes SET status = ?3, reviewer = ?4, updated_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = ?2",
+            params![id, OPEN, UNDER_REVIEW, request.reviewer],
+        )
+        .map_err(|e| format!("Failed to update dispute: {}", e))?
+    };
+    if updated == 0 {
+        return Err(wrong_status(&dispute, "reviewed"));
+    }
+    Ok(Json(load(&state.db, id)?.ok_or("Dispute disappeared")?))
+}
+
+/// Closes a dispute under review, either upholding the valuation or correcting the token's
+/// price on-chain. Both outcomes are added to the token's valuation history.
+pub async fn resolve(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+    Json(request): Json<ResolveRequest>,
+) -> Result<Json<Dispute>, ApiError> {
+    let dispute = find(&state, &tenant, id)?;
+    if dispute.status != UNDER_REVIEW {
+        return Err(wrong_status(&dispute, "resolved"));
+    }
+    if request.resolution.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "resolution is required"));
+    }
+    if request.price.is_some_and(|price| !(price.is_finite() && price > 0.0)) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "price must be a positive number"));
+    }
+    let collection = registry::resolve_for(&state.db, &tenant, Some(&dispute.collection))?;
+    let token_id = dispute.token_id;
+
+    let transaction_hash = match request.price {
+        Some(price) => {
+            let stored = {
+                let conn = state.db.lock().unwrap();
+                conn.query_row(
+                    "SELECT details, metadata FROM mints WHERE collection = ?1 AND token_id = ?2",
+                    params![collection.name, token_id],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                )
+                .map_err(|e| format!("Failed to load token {}: {}", token_id, e))
+                .and_then(|(details, metadata)| {
+                    private::restore(&conn, state.config.private.as_ref(), &collection.name, token_id, details)
+                        .map(|details| (details, metadata))
+                })?
+            };
+            let details: HouseDetails = serde_json::from_str(&stored.0).map_err(|_| {
+                ApiError::new(
+                    StatusCode::CONFLICT,
+                    format!("Property details for token {} are no longer available", token_id),
+                )
+            })?;
+            let mut metadata: Value =
+                serde_json::from_str(&stored.1).map_err(|e| format!("Invalid stored metadata: {}", e))?;
+            let price = Money::from_f64(price, Currency::USD);
+            mint::reprice(&mut metadata, &details, price);
+            println!("Correcting token {} of {} to {} after dispute {}...", token_id, collection.name, price, id);
+            let receipt = valuations::write_metadata(&state, &tenant, &collection, token_id, price, &metadata).await?;
+            nfts::spawn_marketplace_refresh(&state, &collection, token_id);
+            Some(format!("{:?}", receipt.transaction_hash))
+        }
+        None => None,
+    };
+    let price = request.price.unwrap_or(dispute.disputed_price);
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE disputes SET status = ?3, resolution = ?4, resolved_price = ?5, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND status = ?2",
+            params![id, UNDER_REVIEW, RESOLVED, request.resolution, price],
+        )
+        .map_err(|e| format!("Failed to update dispute: {}", e))?;
+    }
+    valuations::record(
+        &state.db,
+        NewValuation {
+            collection: &collection.name,
+            token_id,
+            price: Money::from_f64(price, Currency::USD),
+            source: "dispute",
+            valuation_type: ValuationType::Market,
+            actor: dispute.reviewer.as_deref().unwrap_or("reviewer"),
+            transaction_hash: transaction_hash.as_deref(),
+        },
+    )?;
+    Ok(Json(load(&state.db, id)?.ok_or("Dispute disappeared")?))
+}
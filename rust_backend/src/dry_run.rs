@@ -0,0 +1,215 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{H256, U256};
+use ethers::utils::format_ether;
+use rusqlite::backup::Backup;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::mint::{self, MintRequest, MintResponse};
+use crate::organizations::Tenant;
+use crate::payload::Versioned;
+use crate::state::AppState;
+
+/// How long a spawned Anvil gets to fetch the fork block and start answering.
+const ANVIL_STARTUP: Duration = Duration::from_secs(30);
+const ANVIL_POLL: Duration = Duration::from_millis(250);
+
+/// One mint or a batch to rehearse in order on the same fork. The mints are read one by one so
+/// an invalid one is reported as such rather than as an unrecognized shape.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum DryRunRequest {
+    Batch(Vec<Value>),
+    One(Value),
+}
+
+#[derive(Serialize)]
+pub struct RehearsedMint {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mint: Option<MintResponse>,
+    /// The error the real mint would have returned.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_cost_wei: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DryRun {
+    /// `anvil` when one was started for this run, otherwise DRY_RUN_FORK_URL.
+    fork: String,
+    fork_block: u64,
+    chain_id: u64,
+    succeeded: usize,
+    failed: usize,
+    gas_used: u64,
+    gas_cost_wei: String,
+    gas_cost_eth: String,
+    mints: Vec<RehearsedMint>,
+}
+
+/// A fork to rehearse on: DRY_RUN_FORK_URL, or an Anvil started for the run and killed with it.
+pub struct Fork {
+    pub rpc_url: String,
+    anvil: Option<Child>,
+}
+
+impl Fork {
+    pub async fn open(upstream: &str, chain_id: u64) -> Result<Self, ApiError> {
+        if let Some(rpc_url) = optional_env("DRY_RUN_FORK_URL") {
+            return Ok(Fork { rpc_url, anvil: None });
+        }
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .map_err(|e| format!("Failed to find a free port for Anvil: {}", e))?
+            .port();
+        let anvil_path = optional_env("ANVIL_PATH").unwrap_or_else(|| "anvil".to_string());
+        let anvil = Command::new(&anvil_path)
+            .args(["--fork-url", upstream, "--port", &port.to_string(), "--chain-id", &chain_id.to_string(), "--silent"])
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                ApiError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    format!("Failed to start {} (install Foundry or set DRY_RUN_FORK_URL): {}", anvil_path, e),
+                )
+            })?;
+        let fork = Fork { rpc_url: format!("http://127.0.0.1:{}", port), anvil: Some(anvil) };
+        let provider = Provider::<Http>::try_from(fork.rpc_url.as_str()).map_err(|e| e.to_string())?;
+        let started = tokio::time::Instant::now();
+        while provider.get_block_number().await.is_err() {
+            if started.elapsed() > ANVIL_STARTUP {
+                return Err(ApiError::new(StatusCode::GATEWAY_TIMEOUT, "Anvil did not start in time"));
+            }
+            tokio::time::sleep(ANVIL_POLL).await;
+        }
+        Ok(fork)
+    }
+}
+
+/// An in-memory copy of the database, so the rehearsal's bookkeeping is thrown away with it.
+/// Webhooks are dropped so organizations aren't told about mints that never happened.
+pub fn snapshot(db: &Db) -> Result<Db, String> {
+    let source = db.lock().unwrap();
+    let mut copy = Connection::open_in_memory().map_err(|e| format!("Failed to open a scratch database: {}", e))?;
+    Backup::new(&source, &mut copy)
+        .and_then(|backup| backup.run_to_completion(256, Duration::ZERO, None))
+        .map_err(|e| format!("Failed to copy the database: {}", e))?;
+    copy.execute("DELETE FROM organization_webhooks", [])
+        .map_err(|e| format!("Failed to copy the database: {}", e))?;
+    Ok(Arc::new(Mutex::new(copy)))
+}
+
+fn error_json(err: &ApiError) -> Value {
+    json!({ "status": err.status.as_u16(), "error": err.message, "details": err.details })
+}
+
+/// Runs mints end to end against a fork of their chain and reports what would happen and what
+/// it would cost, without sending anything to the real chain. Metadata is still written to the
+/// requested store, as the gas depends on the token URI.
+pub async fn dry_run(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(request): Json<DryRunRequest>,
+) -> Result<Json<DryRun>, ApiError> {
+    let requests = match request {
+        DryRunRequest::Batch(requests) => requests,
+        DryRunRequest::One(request) => vec![request],
+    };
+    let requests = requests
+        .into_iter()
+        .map(|request| {
+            serde_json::from_value(request)
+                .map(|Versioned(request): Versioned<MintRequest>| request)
+                .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid mint request: {}", e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some(first) = requests.first() else {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Nothing to rehearse"));
+    };
+    let network = mint::target(&state, &tenant, first)?.network;
+    for request in &requests {
+        if mint::target(&state, &tenant, request)?.network != network {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "A dry run can only rehearse mints on one network"));
+        }
+    }
+    let upstream = match &network {
+        Some(name) => state
+            .config
+            .networks
+            .iter()
+            .find(|network| network.chain.network.as_deref() == Some(name))
+            .map(|network| network.rpc_url.clone())
+            .ok_or_else(|| format!("Network {} is not configured", name))?,
+        None => state.config.alchemy_url.clone(),
+    };
+    let chain_id = state.on_network(network.as_deref())?.chain.chain_id;
+
+    let fork = Fork::open(&upstream, chain_id).await?;
+    let forked = state.forked(network.as_deref(), &fork.rpc_url, snapshot(&state.db)?)?;
+    let fork_block = forked
+        .client
+        .get_block_number()
+        .await
+        .map_err(|e| format!("Failed to read the fork: {}", e))?
+        .as_u64();
+    println!("Rehearsing {} mint(s) on a fork of chain {} at block {}", requests.len(), chain_id, fork_block);
+
+    let mut mints = Vec::with_capacity(requests.len());
+    let mut total_gas = 0;
+    let mut total_cost = U256::zero();
+    for mut request in requests {
+        request.scheduled_at = None;
+        let rehearsed = match mint::execute(&forked, &tenant, request, None, false).await {
+            Ok(response) => {
+                let hash: H256 = response.transaction_hash.parse().unwrap_or_default();
+                let receipt = forked.client.get_transaction_receipt(hash).await.ok().flatten();
+                let gas_used = receipt.as_ref().and_then(|receipt| receipt.gas_used).unwrap_or_default();
+                let price = receipt.as_ref().and_then(|receipt| receipt.effective_gas_price).unwrap_or_default();
+                total_gas += gas_used.as_u64();
+                total_cost += gas_used * price;
+                RehearsedMint {
+                    success: true,
+                    mint: Some(response),
+                    error: None,
+                    gas_used: Some(gas_used.as_u64()),
+                    gas_cost_wei: Some((gas_used * price).to_string()),
+                }
+            }
+            Err(err) => RehearsedMint {
+                success: false,
+                mint: None,
+                error: Some(error_json(&err)),
+                gas_used: None,
+                gas_cost_wei: None,
+            },
+        };
+        mints.push(rehearsed);
+    }
+    let succeeded = mints.iter().filter(|mint| mint.success).count();
+    Ok(Json(DryRun {
+        fork: if fork.anvil.is_some() { "anvil".to_string() } else { fork.rpc_url.clone() },
+        fork_block,
+        chain_id,
+        succeeded,
+        failed: mints.len() - succeeded,
+        gas_used: total_gas,
+        gas_cost_wei: total_cost.to_string(),
+        gas_cost_eth: format_ether(total_cost),
+        mints,
+    }))
+}
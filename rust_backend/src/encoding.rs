@@ -0,0 +1,82 @@
+use axum::body::{Body, Full};
+use axum::http::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use axum::http::{HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde_json::Value;
+
+use crate::error::ApiError;
+
+const CBOR: &str = "application/cbor";
+const MSGPACK: &str = "application/msgpack";
+
+#[derive(Clone, Copy)]
+enum Format {
+    Cbor,
+    MessagePack,
+}
+
+impl Format {
+    /// The first compact format listed in `Accept`, if any. JSON stays the default.
+    fn from_accept(accept: &str) -> Option<Self> {
+        accept
+            .split(',')
+            .map(|media| media.split(';').next().unwrap_or_default().trim())
+            .find_map(|media| match media {
+                CBOR => Some(Format::Cbor),
+                MSGPACK | "application/x-msgpack" | "application/vnd.msgpack" => Some(Format::MessagePack),
+                _ => None,
+            })
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Cbor => CBOR,
+            Format::MessagePack => MSGPACK,
+        }
+    }
+
+    fn encode(self, value: &Value) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).map_err(|e| format!("Failed to encode CBOR: {}", e))?;
+                Ok(bytes)
+            }
+            Format::MessagePack => rmp_serde::to_vec(value).map_err(|e| format!("Failed to encode MessagePack: {}", e)),
+        }
+    }
+}
+
+/// Re-encodes JSON responses as CBOR or MessagePack when the client asks for them via `Accept`.
+pub async fn negotiate(request: Request<Body>, next: Next<Body>) -> Result<Response, ApiError> {
+    let format = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .and_then(Format::from_accept);
+    let response = next.run(request).await;
+    let Some(format) = format else {
+        return Ok(response);
+    };
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| format!("Failed to read response body: {}", e))?;
+    let value: Value = serde_json::from_slice(&body).map_err(|e| format!("Failed to decode response: {}", e))?;
+    let encoded = format.encode(&value)?;
+
+    parts.headers.insert(CONTENT_TYPE, HeaderValue::from_static(format.content_type()));
+    parts.headers.insert(VARY, HeaderValue::from_static("accept"));
+    parts.headers.remove(CONTENT_LENGTH);
+    Ok(Response::from_parts(parts, Full::from(encoded)).into_response())
+}
@@ -0,0 +1,223 @@
+use axum::async_trait;
+use reqwest::Client;
+use rusqlite::{params, OptionalExtension};
+use serde_json::{json, Value};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::mint::HouseDetails;
+use crate::state::AppState;
+
+/// FEMA's National Flood Hazard Layer, flood hazard zones.
+const FEMA_FLOOD_ZONES_URL: &str = "https://hazards.fema.gov/arcgis/rest/services/public/NFHL/MapServer/28/query";
+const WALK_SCORE_URL: &str = "https://api.walkscore.com/score";
+
+/// A source of extra metadata attributes for a location.
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// Also the cache key, so renaming a provider discards its cached results.
+    fn name(&self) -> &'static str;
+
+    /// OpenSea-style `{ trait_type, value }` attributes for the point.
+    async fn lookup(&self, http: &Client, lat: f64, long: f64) -> Result<Vec<Value>, String>;
+}
+
+pub struct EnrichmentConfig {
+    providers: Vec<Box<dyn Provider>>,
+    cache_days: u32,
+}
+
+impl EnrichmentConfig {
+    /// Enabled by ENRICHMENT_PROVIDERS, a comma-separated list of providers.
+    pub fn from_env() -> Option<Self> {
+        let names = optional_env("ENRICHMENT_PROVIDERS")?;
+        let providers: Vec<Box<dyn Provider>> = names
+            .split(',')
+            .map(str::trim)
+            .map(|name| -> Box<dyn Provider> {
+                match name {
+                    "flood_zone" => Box::new(FloodZone {
+                        url: optional_env("FLOOD_ZONE_URL").unwrap_or_else(|| FEMA_FLOOD_ZONES_URL.to_string()),
+                    }),
+                    "walk_score" => Box::new(WalkScore {
+                        api_key: optional_env("WALK_SCORE_API_KEY").expect("walk_score needs WALK_SCORE_API_KEY"),
+                    }),
+                    "school_district" => Box::new(SchoolDistrict {
+                        url: optional_env("SCHOOL_DISTRICT_URL").expect("school_district needs SCHOOL_DISTRICT_URL"),
+                    }),
+                    other => panic!(
+                        "ENRICHMENT_PROVIDERS entries must be flood_zone, walk_score or school_district. Found: {}",
+                        other
+                    ),
+                }
+            })
+            .collect();
+        let cache_days = optional_env("ENRICHMENT_CACHE_DAYS")
+            .map(|days| days.parse().expect("ENRICHMENT_CACHE_DAYS must be a number"))
+            .unwrap_or(30);
+        println!("ENRICHMENT_PROVIDERS: {} (cached for {} days)", names, cache_days);
+        Some(EnrichmentConfig { providers, cache_days })
+    }
+}
+
+/// Queries an ArcGIS feature layer for the attributes of the feature containing the point.
+pub async fn arcgis_feature(http: &Client, url: &str, lat: f64, long: f64, fields: &str) -> Result<Option<Value>, String> {
+    let response: Value = http
+        .get(url)
+        .query(&[
+            ("geometry", format!("{},{}", long, lat)),
+            ("geometryType", "esriGeometryPoint".to_string()),
+            ("inSR", "4326".to_string()),
+            ("spatialRel", "esriSpatialRelIntersects".to_string()),
+            ("outFields", fields.to_string()),
+            ("returnGeometry", "false".to_string()),
+            ("f", "json".to_string()),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to query {}: {}", url, e))?
+        .error_for_status()
+        .map_err(|e| format!("Query to {} failed: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response from {}: {}", url, e))?;
+    if let Some(error) = response.get("error") {
+        return Err(format!("Query to {} failed: {}", url, error));
+    }
+    Ok(response["features"].get(0).map(|feature| feature["attributes"].clone()))
+}
+
+struct FloodZone {
+    url: String,
+}
+
+#[async_trait]
+impl Provider for FloodZone {
+    fn name(&self) -> &'static str {
+        "flood_zone"
+    }
+
+    async fn lookup(&self, http: &Client, lat: f64, long: f64) -> Result<Vec<Value>, String> {
+        // Points outside any mapped zone have no flood data rather than minimal risk.
+        let Some(zone) = arcgis_feature(http, &self.url, lat, long, "FLD_ZONE,SFHA_TF").await? else {
+            return Ok(Vec::new());
+        };
+        Ok(vec![
+            json!({ "trait_type": "Flood Zone", "value": zone["FLD_ZONE"] }),
+            json!({ "trait_type": "Special Flood Hazard Area", "value": zone["SFHA_TF"] == "T" }),
+        ])
+    }
+}
+
+struct WalkScore {
+    api_key: String,
+}
+
+#[async_trait]
+impl Provider for WalkScore {
+    fn name(&self) -> &'static str {
+        "walk_score"
+    }
+
+    async fn lookup(&self, http: &Client, lat: f64, long: f64) -> Result<Vec<Value>, String> {
+        let response: Value = http
+            .get(WALK_SCORE_URL)
+            .query(&[
+                ("format", "json".to_string()),
+                ("lat", lat.to_string()),
+                ("lon", long.to_string()),
+                ("wsapikey", self.api_key.clone()),
+            ])
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call Walk Score: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Walk Score request failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Walk Score response: {}", e))?;
+        match response["status"].as_u64() {
+            Some(1) => Ok(vec![json!({ "trait_type": "Walk Score", "value": response["walkscore"] })]),
+            // 2 means the score is still being calculated. Failures aren't cached, so it will be
+            // picked up next time instead of being left out for ENRICHMENT_CACHE_DAYS.
+            Some(2) => Err("Walk Score is still being calculated".to_string()),
+            _ => Err(format!("Walk Score returned status {}", response["status"])),
+        }
+    }
+}
+
+struct SchoolDistrict {
+    url: String,
+}
+
+#[async_trait]
+impl Provider for SchoolDistrict {
+    fn name(&self) -> &'static str {
+        "school_district"
+    }
+
+    async fn lookup(&self, http: &Client, lat: f64, long: f64) -> Result<Vec<Value>, String> {
+        let district = arcgis_feature(http, &self.url, lat, long, "NAME").await?;
+        Ok(district
+            .map(|district| vec![json!({ "trait_type": "School District", "value": district["NAME"] })])
+            .unwrap_or_default())
+    }
+}
+
+/// Coordinates rounded to about 10 meters, so nearby lookups share a cache entry.
+fn cell(lat: f64, long: f64) -> String {
+    format!("{:.4},{:.4}", lat, long)
+}
+
+fn cached(db: &Db, provider: &str, cell: &str, cache_days: u32) -> Result<Option<Vec<Value>>, String> {
+    let conn = db.lock().unwrap();
+    let attributes: Option<String> = conn
+        .query_row(
+            "SELECT attributes FROM enrichment_cache
+             WHERE provider = ?1 AND cell = ?2 AND fetched_at >= datetime('now', ?3)",
+            params![provider, cell, format!("-{} days", cache_days)],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read enrichment cache: {}", e))?;
+    Ok(attributes.and_then(|attributes| serde_json::from_str(&attributes).ok()))
+}
+
+fn store(db: &Db, provider: &str, cell: &str, attributes: &[Value]) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO enrichment_cache (provider, cell, attributes) VALUES (?1, ?2, ?3)
+         ON CONFLICT (provider, cell) DO UPDATE SET attributes = excluded.attributes, fetched_at = CURRENT_TIMESTAMP",
+        params![provider, cell, serde_json::to_string(attributes).unwrap_or_default()],
+    )
+    .map_err(|e| format!("Failed to write enrichment cache: {}", e))?;
+    Ok(())
+}
+
+/// Extra attributes for the property from every configured provider. Enrichment is
+/// best-effort: a provider that fails is logged and left out rather than blocking the mint.
+pub async fn enrich(state: &AppState, details: &HouseDetails) -> Vec<Value> {
+    let Some(config) = &state.config.enrichment else { return Vec::new() };
+    let cell = cell(details.lat, details.long);
+    let mut attributes = Vec::new();
+    for provider in &config.providers {
+        match cached(&state.db, provider.name(), &cell, config.cache_days) {
+            Ok(Some(found)) => {
+                attributes.extend(found);
+                continue;
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+        match provider.lookup(&state.http, details.lat, details.long).await {
+            Ok(found) => {
+                if let Err(e) = store(&state.db, provider.name(), &cell, &found) {
+                    eprintln!("{}", e);
+                }
+                attributes.extend(found);
+            }
+            Err(e) => eprintln!("Enrichment from {} failed: {}", provider.name(), e),
+        }
+    }
+    attributes
+}
@@ -0,0 +1,67 @@
+use ethers::abi::{Abi, RawLog};
+use ethers::types::{Log, TransactionReceipt};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::simulation;
+
+/// A receipt log, decoded against the contract's ABI when one of its events matches.
+#[derive(Serialize)]
+pub struct ReceiptEvent {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_index: Option<u64>,
+    /// Event name; `None` for logs the ABI doesn't describe, which keep their raw topics and data.
+    pub event: Option<String>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub args: Map<String, Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+}
+
+fn decode(abi: &Abi, log: &Log) -> Option<(String, Map<String, Value>)> {
+    let topic = log.topics.first()?;
+    // Transfer and Approval share a signature between ERC-20 and ERC-721 but not their indexing.
+    abi.events().filter(|event| event.signature() == *topic).find_map(|event| {
+        let raw = RawLog { topics: log.topics.clone(), data: log.data.to_vec() };
+        let parsed = event.parse_log(raw).ok()?;
+        let args = parsed
+            .params
+            .into_iter()
+            .map(|param| (param.name, simulation::token_json(&param.value)))
+            .collect();
+        Some((event.name.clone(), args))
+    })
+}
+
+/// Every log in the receipt, in order, decoded against `abi` where it can be.
+pub fn decode_receipt(abi: &Abi, receipt: &TransactionReceipt) -> Vec<ReceiptEvent> {
+    receipt
+        .logs
+        .iter()
+        .map(|log| {
+            let address = format!("{:?}", log.address);
+            let log_index = log.log_index.map(|index| index.as_u64());
+            match decode(abi, log) {
+                Some((event, args)) => ReceiptEvent {
+                    address,
+                    log_index,
+                    event: Some(event),
+                    args,
+                    topics: Vec::new(),
+                    data: None,
+                },
+                None => ReceiptEvent {
+                    address,
+                    log_index,
+                    event: None,
+                    args: Map::new(),
+                    topics: log.topics.iter().map(|topic| format!("{:?}", topic)).collect(),
+                    data: Some(log.data.to_string()),
+                },
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,88 @@
+use ethers::types::{Address, H256, U256};
+use serde::Serialize;
+
+use crate::config::optional_env;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExplorerKind {
+    Etherscan,
+    Blockscout,
+}
+
+#[derive(Clone)]
+pub struct Explorer {
+    base_url: String,
+    kind: ExplorerKind,
+}
+
+#[derive(Serialize)]
+pub struct ExplorerLinks {
+    pub transaction: Option<String>,
+    pub token: Option<String>,
+    pub contract: String,
+}
+
+impl Explorer {
+    /// Uses EXPLORER_URL/EXPLORER_KIND when set, otherwise the well-known explorer
+    /// for the chain. Local development chains have no explorer.
+    pub fn for_chain(chain_id: u64) -> Option<Self> {
+        if let Some(base_url) = optional_env("EXPLORER_URL") {
+            let kind = match optional_env("EXPLORER_KIND").as_deref() {
+                Some("blockscout") => ExplorerKind::Blockscout,
+                Some("etherscan") | None => ExplorerKind::Etherscan,
+                Some(other) => panic!("EXPLORER_KIND must be etherscan or blockscout. Found: {}", other),
+            };
+            return Some(Explorer {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                kind,
+            });
+        }
+        Self::known(chain_id)
+    }
+
+    /// The well-known explorer for the chain, ignoring EXPLORER_URL.
+    pub fn known(chain_id: u64) -> Option<Self> {
+        let (base_url, kind) = match chain_id {
+            1 => ("https://etherscan.io", ExplorerKind::Etherscan),
+            11155111 => ("https://sepolia.etherscan.io", ExplorerKind::Etherscan),
+            17000 => ("https://holesky.etherscan.io", ExplorerKind::Etherscan),
+            10 => ("https://optimistic.etherscan.io", ExplorerKind::Etherscan),
+            11155420 => ("https://sepolia-optimism.etherscan.io", ExplorerKind::Etherscan),
+            137 => ("https://polygonscan.com", ExplorerKind::Etherscan),
+            80002 => ("https://amoy.polygonscan.com", ExplorerKind::Etherscan),
+            42161 => ("https://arbiscan.io", ExplorerKind::Etherscan),
+            421614 => ("https://sepolia.arbiscan.io", ExplorerKind::Etherscan),
+            8453 => ("https://basescan.org", ExplorerKind::Etherscan),
+            84532 => ("https://sepolia.basescan.org", ExplorerKind::Etherscan),
+            100 => ("https://gnosis.blockscout.com", ExplorerKind::Blockscout),
+            _ => return None,
+        };
+        Some(Explorer {
+            base_url: base_url.to_string(),
+            kind,
+        })
+    }
+
+    pub fn transaction_url(&self, hash: H256) -> String {
+        format!("{}/tx/{:?}", self.base_url, hash)
+    }
+
+    pub fn address_url(&self, address: Address) -> String {
+        format!("{}/address/{:?}", self.base_url, address)
+    }
+
+    pub fn token_url(&self, contract: Address, token_id: U256) -> String {
+        match self.kind {
+            ExplorerKind::Etherscan => format!("{}/nft/{:?}/{}", self.base_url, contract, token_id),
+            ExplorerKind::Blockscout => format!("{}/token/{:?}/instance/{}", self.base_url, contract, token_id),
+        }
+    }
+
+    pub fn links(&self, contract: Address, transaction: Option<H256>, token_id: Option<U256>) -> ExplorerLinks {
+        ExplorerLinks {
+            transaction: transaction.map(|hash| self.transaction_url(hash)),
+            token: token_id.map(|id| self.token_url(contract, id)),
+            contract: self.address_url(contract),
+        }
+    }
+}
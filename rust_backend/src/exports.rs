@@ -0,0 +1,146 @@
+use axum::body::StreamBody;
+use axum::extract::State;
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::{IntoResponse, Response};
+use rusqlite::params;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+const PAGE_SIZE: i64 = 500;
+const BURN_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+struct Holding {
+    id: i64,
+    collection: String,
+    token_id: u64,
+    owner: String,
+    metadata: Option<String>,
+}
+
+/// The next page of tokens after `after`, each with its owner as of the last indexed transfer
+/// (or its mint recipient if the indexer hasn't caught up).
+fn page(db: &Db, organization: Option<&str>, after: i64, until: i64) -> Result<Vec<Holding>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.id, m.collection, m.token_id, m.metadata,
+                    coalesce((SELECT t.to_address FROM token_transfers t
+                              WHERE t.collection = m.collection AND t.token_id = m.token_id
+                              ORDER BY t.block_number DESC, t.log_index DESC LIMIT 1), m.recipient)
+             FROM mints m JOIN collections c ON c.name = m.collection
+             WHERE c.organization IS ?1 AND m.id > ?2 AND m.id <= ?3
+             ORDER BY m.id LIMIT ?4",
+        )
+        .map_err(|e| format!("Failed to export holders: {}", e))?;
+    let rows = stmt
+        .query_map(params![organization, after, until, PAGE_SIZE], |row| {
+            Ok(Holding {
+                id: row.get(0)?,
+                collection: row.get(1)?,
+                token_id: row.get(2)?,
+                metadata: row.get(3)?,
+                owner: row.get(4)?,
+            })
+        })
+        .map_err(|e| format!("Failed to export holders: {}", e))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to export holders: {}", e))
+}
+
+/// Streams every current holding as one line each. The snapshot covers the tokens minted when
+/// the export started, and reads the database a page at a time so it never holds the lock long.
+type Lines = StreamBody<ReceiverStream<Result<String, Infallible>>>;
+
+fn snapshot(
+    state: &AppState,
+    tenant: &Tenant,
+    header: Option<&'static str>,
+    line: fn(&Holding) -> String,
+) -> Result<Lines, ApiError> {
+    let until: i64 = state
+        .db
+        .lock()
+        .unwrap()
+        .query_row("SELECT coalesce(max(id), 0) FROM mints", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to export holders: {}", e))?;
+    let db = state.db.clone();
+    let organization = tenant.organization_id().map(str::to_string);
+    let (sender, receiver) = mpsc::channel(4);
+    tokio::spawn(async move {
+        if let Some(header) = header {
+            if sender.send(Ok(header.to_string())).await.is_err() {
+                return;
+            }
+        }
+        let mut after = 0;
+        loop {
+            let holdings = match page(&db, organization.as_deref(), after, until) {
+                Ok(holdings) => holdings,
+                Err(e) => {
+                    // The status line has already been sent, so the export just ends early.
+                    eprintln!("{}", e);
+                    return;
+                }
+            };
+            let Some(last) = holdings.last() else { return };
+            after = last.id;
+            let chunk: String = holdings
+                .iter()
+                .filter(|holding| holding.owner != BURN_ADDRESS)
+                .map(line)
+                .collect();
+            if sender.send(Ok(chunk)).await.is_err() {
+                return;
+            }
+        }
+    });
+    Ok(StreamBody::new(ReceiverStream::new(receiver)))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub async fn holders_csv(State(state): State<AppState>, tenant: Tenant) -> Result<Response, ApiError> {
+    let body = snapshot(&state, &tenant, Some("collection,token_id,owner\n"), |holding| {
+        format!("{},{},{}\n", csv_field(&holding.collection), holding.token_id, holding.owner)
+    })?;
+    Ok((
+        [(CONTENT_TYPE, "text/csv"), (CONTENT_DISPOSITION, "attachment; filename=\"holders.csv\"")],
+        body,
+    )
+        .into_response())
+}
+
+pub async fn metadata_jsonl(State(state): State<AppState>, tenant: Tenant) -> Result<Response, ApiError> {
+    let body = snapshot(&state, &tenant, None, |holding| {
+        let metadata = holding
+            .metadata
+            .as_deref()
+            .and_then(|metadata| serde_json::from_str(metadata).ok())
+            .unwrap_or(Value::Null);
+        let line = json!({
+            "collection": holding.collection,
+            "token_id": holding.token_id,
+            "owner": holding.owner,
+            "metadata": metadata,
+        });
+        format!("{}\n", line)
+    })?;
+    Ok((
+        [(CONTENT_TYPE, "application/x-ndjson"), (CONTENT_DISPOSITION, "attachment; filename=\"metadata.jsonl\"")],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,88 @@
+use ethers::providers::Middleware;
+use ethers::signers::Signer;
+use ethers::types::{TransactionRequest, U256};
+use ethers::utils::parse_ether;
+use serde_json::Value;
+
+use crate::config::optional_env;
+use crate::network::{is_mainnet, NetworkEnv};
+use crate::state::AppState;
+
+/// What an empty wallet is topped up to, in ether.
+const DEV_BALANCE_ETH: u64 = 10_000;
+/// Sent from an unlocked test account on nodes that can't set balances.
+const DEV_TRANSFER_ETH: u64 = 100;
+
+/// The balance-setting RPC of local dev nodes, recognized by `web3_clientVersion`.
+fn set_balance_method(client_version: &str) -> Option<&'static str> {
+    let client_version = client_version.to_lowercase();
+    if client_version.starts_with("anvil") {
+        Some("anvil_setBalance")
+    } else if client_version.starts_with("hardhatnetwork") {
+        Some("hardhat_setBalance")
+    } else {
+        None
+    }
+}
+
+/// Funds an empty wallet on a local dev node so mints work without a manual faucet step: Anvil
+/// and Hardhat set the balance directly, other nodes send from their first funded unlocked
+/// account. Only runs with NETWORK_ENV=dev off mainnet; DEV_AUTO_FUND=false turns it off.
+pub async fn fund_dev_wallets(state: &AppState) {
+    if state.config.network_env != NetworkEnv::Dev || optional_env("DEV_AUTO_FUND").is_some_and(|fund| fund == "false")
+    {
+        return;
+    }
+    for state in state.chains() {
+        if is_mainnet(state.chain.chain_id) {
+            continue;
+        }
+        if let Err(e) = fund(&state).await {
+            eprintln!("Could not fund the dev wallet on chain {}: {}", state.chain.chain_id, e);
+        }
+    }
+}
+
+async fn fund(state: &AppState) -> Result<(), String> {
+    let provider = state.client.inner();
+    let wallet = state.client.signer().address();
+    let balance = provider
+        .get_balance(wallet, None)
+        .await
+        .map_err(|e| format!("Failed to read wallet balance: {}", e))?;
+    if !balance.is_zero() {
+        return Ok(());
+    }
+    let client_version = provider.client_version().await.unwrap_or_default();
+    if let Some(method) = set_balance_method(&client_version) {
+        let amount = parse_ether(DEV_BALANCE_ETH).map_err(|e| e.to_string())?;
+        provider
+            .request::<_, Value>(method, (wallet, amount))
+            .await
+            .map_err(|e| format!("{} failed: {}", method, e))?;
+        println!("Dev wallet {:?} funded with {} ETH via {}", wallet, DEV_BALANCE_ETH, method);
+        return Ok(());
+    }
+
+    let amount = parse_ether(DEV_TRANSFER_ETH).map_err(|e| e.to_string())?;
+    let accounts = provider
+        .get_accounts()
+        .await
+        .map_err(|e| format!("Failed to list the node's accounts: {}", e))?;
+    for account in accounts.into_iter().filter(|account| *account != wallet) {
+        let funds = provider.get_balance(account, None).await.unwrap_or_default();
+        if funds <= amount * U256::from(2) {
+            continue;
+        }
+        let tx = TransactionRequest::new().from(account).to(wallet).value(amount);
+        provider
+            .send_transaction(tx, None)
+            .await
+            .map_err(|e| format!("Failed to send from test account {:?}: {}", account, e))?
+            .await
+            .map_err(|e| format!("Failed to fund from test account {:?}: {}", account, e))?;
+        println!("Dev wallet {:?} funded with {} ETH from test account {:?}", wallet, DEV_TRANSFER_ETH, account);
+        return Ok(());
+    }
+    Err(format!("wallet {:?} is empty and the node has no funded test accounts", wallet))
+}
@@ -0,0 +1,198 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+/// Pinning token metadata to IPFS.
+pub const IPFS_STORAGE: &str = "ipfs_storage";
+/// Handing queued mint jobs to the shared Redis queue instead of running them in this process.
+pub const ASYNC_MINT: &str = "async_mint";
+/// Minting on the NETWORKS chains rather than only the primary one.
+pub const L2_NETWORKS: &str = "l2_networks";
+
+const FLAGS: &[&str] = &[IPFS_STORAGE, ASYNC_MINT, L2_NETWORKS];
+
+/// Who a feature is on for: the listed organizations, plus a stable `percentage` of the rest.
+/// `enabled: false` turns it off for everyone.
+#[derive(Serialize)]
+pub struct Flag {
+    name: &'static str,
+    enabled: bool,
+    percentage: u8,
+    organizations: Vec<String>,
+    /// `default`, `env` or `admin`; admin settings win over FEATURE_<NAME>.
+    source: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct SetFlag {
+    #[serde(default = "on")]
+    enabled: bool,
+    #[serde(default = "everyone")]
+    percentage: u8,
+    #[serde(default)]
+    organizations: Vec<String>,
+}
+
+fn on() -> bool {
+    true
+}
+
+fn everyone() -> u8 {
+    100
+}
+
+fn known(name: &str) -> Result<&'static str, ApiError> {
+    FLAGS.iter().copied().find(|flag| *flag == name).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Unknown feature flag: {}", name))
+            .with_details(json!({ "available": FLAGS }))
+    })
+}
+
+/// FEATURE_<NAME> is true, false or a percentage of organizations, and
+/// FEATURE_<NAME>_ORGANIZATIONS lists organizations that always get the feature. Features are on
+/// for everyone unless configured otherwise.
+fn from_env(name: &'static str) -> Flag {
+    let var = format!("FEATURE_{}", name.to_uppercase());
+    let setting = optional_env(&var);
+    let organizations: Vec<String> = optional_env(&format!("{}_ORGANIZATIONS", var))
+        .map(|orgs| orgs.split(',').map(str::trim).filter(|org| !org.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default();
+    let (enabled, percentage) = match setting.as_deref().map(|setting| setting.trim_end_matches('%')) {
+        None | Some("true") => (true, 100),
+        Some("false") => (false, 0),
+        Some(percentage) => match percentage.parse() {
+            Ok(percentage) if percentage <= 100 => (true, percentage),
+            _ => {
+                eprintln!("{} must be true, false or a percentage; ignoring {:?}", var, setting);
+                (true, 100)
+            }
+        },
+    };
+    let source = if setting.is_some() || !organizations.is_empty() { "env" } else { "default" };
+    Flag {
+        name,
+        enabled,
+        percentage,
+        organizations,
+        source,
+    }
+}
+
+fn load(db: &Db, name: &'static str) -> Result<Flag, String> {
+    let conn = db.lock().unwrap();
+    let row: Option<(bool, u8, String)> = conn
+        .query_row(
+            "SELECT enabled, percentage, organizations FROM feature_flags WHERE name = ?1",
+            params![name],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load feature flag: {}", e))?;
+    drop(conn);
+    Ok(match row {
+        Some((enabled, percentage, organizations)) => Flag {
+            name,
+            enabled,
+            percentage,
+            organizations: serde_json::from_str(&organizations).unwrap_or_default(),
+            source: "admin",
+        },
+        None => from_env(name),
+    })
+}
+
+/// Hashes the organization with the flag name, so each flag rolls out to a different slice of
+/// organizations and raising the percentage only ever adds to it.
+fn bucket(name: &str, organization: Option<&str>) -> u8 {
+    let digest = Sha256::digest(format!("{}:{}", name, organization.unwrap_or("platform")));
+    (u16::from_be_bytes([digest[0], digest[1]]) % 100) as u8
+}
+
+impl Flag {
+    fn on_for(&self, organization: Option<&str>) -> bool {
+        self.enabled
+            && (organization.is_some_and(|org| self.organizations.iter().any(|listed| listed == org))
+                || bucket(self.name, organization) < self.percentage)
+    }
+}
+
+/// Whether `name` is on for the organization, or for platform requests when it is `None`. A flag
+/// that can't be read falls back to its environment setting.
+pub fn enabled(db: &Db, name: &'static str, organization: Option<&str>) -> bool {
+    let flag = load(db, name).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        from_env(name)
+    });
+    flag.on_for(organization)
+}
+
+/// Refuses the request unless `name` is on for the tenant.
+pub fn require(state: &AppState, tenant: &Tenant, name: &'static str) -> Result<(), ApiError> {
+    if enabled(&state.db, name, tenant.organization_id()) {
+        return Ok(());
+    }
+    Err(ApiError::new(StatusCode::FORBIDDEN, format!("{} is not enabled for this organization", name))
+        .with_details(json!({ "feature": name })))
+}
+
+pub async fn list(State(state): State<AppState>) -> Result<Json<Vec<Flag>>, ApiError> {
+    let flags = FLAGS.iter().map(|name| load(&state.db, name)).collect::<Result<_, _>>()?;
+    Ok(Json(flags))
+}
+
+/// Overrides the environment setting until the flag is reset.
+pub async fn set(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<SetFlag>,
+) -> Result<Json<Flag>, ApiError> {
+    let name = known(&name)?;
+    if request.percentage > 100 {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "percentage must be between 0 and 100"));
+    }
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO feature_flags (name, enabled, percentage, organizations) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET enabled = ?2, percentage = ?3, organizations = ?4,
+                                             updated_at = CURRENT_TIMESTAMP",
+            params![
+                name,
+                request.enabled,
+                request.percentage,
+                serde_json::to_string(&request.organizations).unwrap_or_default()
+            ],
+        )
+        .map_err(|e| format!("Failed to save feature flag: {}", e))?;
+    }
+    let flag = load(&state.db, name)?;
+    println!(
+        "Feature {} {} for {}% of organizations and {} listed",
+        name,
+        if flag.enabled { "enabled" } else { "disabled" },
+        flag.percentage,
+        flag.organizations.len()
+    );
+    Ok(Json(flag))
+}
+
+/// Drops the admin override, going back to FEATURE_<NAME>.
+pub async fn reset(State(state): State<AppState>, Path(name): Path<String>) -> Result<Json<Flag>, ApiError> {
+    let name = known(&name)?;
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute("DELETE FROM feature_flags WHERE name = ?1", params![name])
+            .map_err(|e| format!("Failed to reset feature flag: {}", e))?;
+    }
+    Ok(Json(load(&state.db, name)?))
+}
@@ -0,0 +1,156 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::money::{self, Currency, Money};
+use crate::state::AppState;
+
+/// More than any token uses; keeps 10^decimals and the conversions well inside U256.
+const MAX_DECIMALS: u8 = 36;
+/// Decimals of the smallest unit of ETH, the wei.
+pub const WEI_DECIMALS: u8 = 18;
+
+/// A price as the unsigned integer a contract stores: `value` / 10^`decimals` of `symbol`. The
+/// decimals always travel with the value, so `53800055` with 2 decimals reads as 538000.55 USD
+/// and nothing has to guess the scale.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FixedPoint {
+    /// A decimal string, since JSON numbers lose precision past 2^53.
+    #[serde(with = "decimal_string")]
+    pub value: U256,
+    pub decimals: u8,
+    pub symbol: Currency,
+}
+
+mod decimal_string {
+    use ethers::types::U256;
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        U256::from_dec_str(&value).map_err(|_| D::Error::custom(format!("{} is not an unsigned integer", value)))
+    }
+}
+
+fn pow10(exponent: u8) -> U256 {
+    U256::exp10(usize::from(exponent))
+}
+
+fn check_decimals(decimals: u8) -> Result<(), String> {
+    if decimals > MAX_DECIMALS {
+        return Err(format!("decimals must be at most {}", MAX_DECIMALS));
+    }
+    Ok(())
+}
+
+/// The usual on-chain scale for a currency: wei for ETH, cents for fiat.
+pub fn default_decimals(symbol: Currency) -> u8 {
+    if symbol == Currency::ETH {
+        WEI_DECIMALS
+    } else {
+        2
+    }
+}
+
+/// `money` as a fixed-point amount of `symbol`, converting at the current exchange rates when
+/// the currencies differ. This is how a price should reach a contract; never through an f64.
+pub fn encode(db: &Db, money: Money, symbol: Currency, decimals: u8) -> Result<FixedPoint, ApiError> {
+    check_decimals(decimals).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+    let cents = u64::try_from(money.cents)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "On-chain prices can't be negative"))?;
+    let (from_rate, to_rate) = if money.currency == symbol {
+        (1, 1)
+    } else {
+        (money::rate(db, money.currency)?, money::rate(db, symbol)?)
+    };
+    // cents / 100 / from_rate * to_rate * 10^decimals, with one rounding at the end.
+    let value = money::mul_div(
+        U256::from(cents) * U256::from(to_rate),
+        pow10(decimals),
+        U256::from(from_rate) * U256::from(100),
+    );
+    Ok(FixedPoint { value, decimals, symbol })
+}
+
+/// The price a fixed-point amount stands for, in `currency`, rounded to the cent.
+pub fn decode(db: &Db, fixed: &FixedPoint, currency: Currency) -> Result<Money, ApiError> {
+    check_decimals(fixed.decimals).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+    let (from_rate, to_rate) = if fixed.symbol == currency {
+        (1, 1)
+    } else {
+        (money::rate(db, fixed.symbol)?, money::rate(db, currency)?)
+    };
+    let too_large = || ApiError::new(StatusCode::BAD_REQUEST, format!("{} is too large to price", fixed.value));
+    let scaled = fixed.value.checked_mul(U256::from(to_rate) * U256::from(100)).ok_or_else(too_large)?;
+    let cents = money::mul_div(scaled, U256::one(), U256::from(from_rate) * pow10(fixed.decimals));
+    if cents > U256::from(i64::MAX) {
+        return Err(too_large());
+    }
+    Ok(Money::new(cents.as_u64() as i64, currency))
+}
+
+#[derive(Deserialize)]
+pub struct EncodeQuery {
+    amount: String,
+    #[serde(default = "usd")]
+    currency: String,
+    /// Defaults to `currency`.
+    symbol: Option<String>,
+    /// Defaults to 18 for ETH and 2 otherwise.
+    decimals: Option<u8>,
+}
+
+#[derive(Deserialize)]
+pub struct DecodeQuery {
+    #[serde(default = "usd")]
+    currency: String,
+}
+
+fn usd() -> String {
+    Currency::USD.code().to_string()
+}
+
+#[derive(Serialize)]
+pub struct Encoded {
+    price: Money,
+    fixed_point: FixedPoint,
+}
+
+fn currency(code: &str) -> Result<Currency, ApiError> {
+    Currency::parse(code).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))
+}
+
+/// The integer a contract would store for a price, e.g. `?amount=538000.55&symbol=ETH` for wei.
+pub async fn encode_price(
+    State(state): State<AppState>,
+    Query(query): Query<EncodeQuery>,
+) -> Result<Json<Encoded>, ApiError> {
+    let price = Money::parse(&query.amount, currency(&query.currency)?)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+    let symbol = match &query.symbol {
+        Some(symbol) => currency(symbol)?,
+        None => price.currency,
+    };
+    let decimals = query.decimals.unwrap_or_else(|| default_decimals(symbol));
+    let fixed_point = encode(&state.db, price, symbol, decimals)?;
+    Ok(Json(Encoded { price, fixed_point }))
+}
+
+/// Reads a fixed-point amount, e.g. one returned by a contract, back as a price in `currency`.
+pub async fn decode_price(
+    State(state): State<AppState>,
+    Query(query): Query<DecodeQuery>,
+    Json(fixed_point): Json<FixedPoint>,
+) -> Result<Json<Encoded>, ApiError> {
+    let price = decode(&state.db, &fixed_point, currency(&query.currency)?)?;
+    Ok(Json(Encoded { price, fixed_point }))
+}
@@ -0,0 +1,161 @@
+use axum::body::Body;
+use axum::extract::{OriginalUri, State};
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::audit;
+use crate::auth;
+use crate::error::ApiError;
+use crate::organizations;
+use crate::state::AppState;
+
+pub const CONFIRMATION_HEADER: &str = "X-Confirmation-Token";
+/// How long a second admin has to confirm an action.
+const CONFIRMATION_TTL: &str = "+15 minutes";
+
+#[derive(Serialize)]
+pub struct Confirmation {
+    token: String,
+    method: String,
+    path: String,
+    /// The request body the confirming admin has to send again.
+    body: String,
+    requested_by: String,
+    created_at: String,
+    expires_at: String,
+}
+
+/// Route layer for dangerous admin actions when FOUR_EYES is on. The first admin's request only
+/// returns a confirmation token; the action runs when a different admin sends the same request
+/// (method, path and body) with the token in `X-Confirmation-Token` before it expires. Both
+/// steps land in the audit log.
+pub async fn require_confirmation(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, ApiError> {
+    if !state.config.four_eyes {
+        return Ok(next.run(request).await);
+    }
+    // The admin token's holder, not whatever organization API key came with the request.
+    let Some(admin) = auth::admin_identity(&state, request.headers()) else {
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, "Invalid or missing admin credentials"));
+    };
+    let method = request.method().to_string();
+    // Routes are nested under /admin, which axum strips from the request's own URI.
+    let uri = request.extensions().get::<OriginalUri>().map_or_else(|| request.uri(), |original| &original.0);
+    let path = uri.path_and_query().map(|path| path.as_str().to_string()).unwrap_or_default();
+    let token = request
+        .headers()
+        .get(CONFIRMATION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let (request, body) = audit::buffer_body(request).await?;
+    let payload_hash = ethers::utils::hex::encode(Sha256::digest(&body));
+
+    let Some(token) = token else {
+        let token = organizations::random_secret("confirm");
+        let expires_at: String = {
+            let conn = state.db.lock().unwrap();
+            conn.query_row(
+                "INSERT INTO admin_confirmations (token, method, path, payload_hash, body, requested_by, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now', ?7))
+                 RETURNING expires_at",
+                params![token, method, path, payload_hash, String::from_utf8_lossy(&body), admin, CONFIRMATION_TTL],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to save confirmation: {}", e))?
+        };
+        println!("{} {} by {} is waiting for a second admin", method, path, admin);
+        let response = json!({
+            "confirmation_token": token,
+            "expires_at": expires_at,
+            "requested_by": admin,
+            "message": format!("A different admin must send the same request with {} to run it", CONFIRMATION_HEADER),
+        });
+        return Ok((StatusCode::ACCEPTED, Json(response)).into_response());
+    };
+
+    let requested_by = {
+        let conn = state.db.lock().unwrap();
+        let pending: Option<String> = conn
+            .query_row(
+                "SELECT requested_by FROM admin_confirmations
+                 WHERE token = ?1 AND method = ?2 AND path = ?3 AND payload_hash = ?4
+                   AND confirmed_at IS NULL AND expires_at > datetime('now')",
+                params![token, method, path, payload_hash],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load confirmation: {}", e))?;
+        let Some(requested_by) = pending else {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "Unknown, expired or already used confirmation token, or a different request than it was issued for",
+            ));
+        };
+        if requested_by == admin {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "An action must be confirmed by a different admin than the one who requested it",
+            ));
+        }
+        let confirmed = conn
+            .execute(
+                "UPDATE admin_confirmations SET confirmed_by = ?2, confirmed_at = CURRENT_TIMESTAMP
+                 WHERE token = ?1 AND confirmed_at IS NULL",
+                params![token, admin],
+            )
+            .map_err(|e| format!("Failed to confirm: {}", e))?;
+        if confirmed == 0 {
+            return Err(ApiError::new(StatusCode::FORBIDDEN, "Confirmation token was already used"));
+        }
+        requested_by
+    };
+    let entry = audit::Entry {
+        actor: &admin,
+        ip: None,
+        method: "CONFIRM",
+        path: &format!("{} {} (requested by {})", method, path, requested_by),
+        payload_hash: Some(payload_hash),
+        status: None,
+        success: true,
+    };
+    if let Err(e) = audit::append(&state.db, entry) {
+        eprintln!("{}", e);
+    }
+    println!("{} {} requested by {} was confirmed by {}", method, path, requested_by, admin);
+    Ok(next.run(request).await)
+}
+
+/// Actions waiting for a second admin, with what to send to confirm them.
+pub async fn list_pending(State(state): State<AppState>) -> Result<Json<Vec<Confirmation>>, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let pending = conn
+        .prepare(
+            "SELECT token, method, path, body, requested_by, created_at, expires_at FROM admin_confirmations
+             WHERE confirmed_at IS NULL AND expires_at > datetime('now') ORDER BY created_at",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| {
+                Ok(Confirmation {
+                    token: row.get(0)?,
+                    method: row.get(1)?,
+                    path: row.get(2)?,
+                    body: row.get(3)?,
+                    requested_by: row.get(4)?,
+                    created_at: row.get(5)?,
+                    expires_at: row.get(6)?,
+                })
+            })?
+            .collect()
+        })
+        .map_err(|e| format!("Failed to load confirmations: {}", e))?;
+    Ok(Json(pending))
+}
@@ -0,0 +1,236 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::contract::parse_log;
+use ethers::types::{Address, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::bindings::{FractionalVault, FractionalVaultEvents, FractionalizedFilter};
+use crate::error::ApiError;
+use crate::liens;
+use crate::registry::{self, Collection};
+use crate::simulation;
+use crate::state::{AppState, EthClient};
+use crate::tx::{self, TransactionResponse};
+use crate::CollectionQuery;
+
+#[derive(Deserialize)]
+pub struct FractionalizeRequest {
+    shares: u64,
+    /// Receives the minted shares; defaults to the server wallet.
+    recipient: Option<Address>,
+}
+
+#[derive(Serialize)]
+pub struct FractionalizeResponse {
+    token_id: u64,
+    share_id: String,
+    shares: u64,
+    vault: Address,
+    approval: TransactionResponse,
+    transaction: TransactionResponse,
+}
+
+#[derive(Serialize)]
+pub struct Shareholder {
+    address: Address,
+    shares: String,
+}
+
+#[derive(Serialize)]
+pub struct ShareholdersResponse {
+    token_id: u64,
+    share_id: String,
+    total_shares: u64,
+    status: String,
+    shareholders: Vec<Shareholder>,
+}
+
+struct Position {
+    share_id: U256,
+    shares: u64,
+    block_number: u64,
+    status: String,
+}
+
+/// The vault for `collection`'s tokens. VAULT_ADDRESS is on the primary chain, so collections
+/// on other NETWORKS can't be fractionalized.
+fn vault(state: &AppState, collection: &Collection) -> Result<FractionalVault<EthClient>, ApiError> {
+    let address = state.config.vault_address.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_IMPLEMENTED, "Fractionalization is disabled; set VAULT_ADDRESS")
+    })?;
+    if let Some(network) = &collection.network {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("VAULT_ADDRESS is on the primary chain, but {} is on {}", collection.name, network),
+        ));
+    }
+    Ok(FractionalVault::new(address, state.client.clone()))
+}
+
+fn position(state: &AppState, collection: &Collection, token_id: u64) -> Result<Position, ApiError> {
+    let conn = state.db.lock().unwrap();
+    conn.query_row(
+        "SELECT share_id, shares, block_number, status FROM fractionalizations
+         WHERE collection = ?1 AND token_id = ?2 ORDER BY id DESC LIMIT 1",
+        params![collection.name, token_id],
+        |row| {
+            let share_id: String = row.get(0)?;
+            Ok(Position {
+                share_id: U256::from_dec_str(&share_id).unwrap_or_default(),
+                shares: row.get(1)?,
+                block_number: row.get(2)?,
+                status: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load fractionalization: {}", e))?
+    .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Token {} has not been fractionalized", token_id)))
+}
+
+pub async fn fractionalize(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<FractionalizeRequest>,
+) -> Result<Json<FractionalizeResponse>, ApiError> {
+    if request.shares == 0 {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "shares must be greater than zero"));
+    }
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let vault = vault(&state, &collection)?;
+    liens::require_unencumbered(&state, &collection.name, token_id)?;
+    let recipient = request.recipient.unwrap_or(state.client.address());
+
+    println!("Approving vault {:?} for token {}...", vault.address(), token_id);
+    let nft = state.nft(&collection)?;
+    let approval = tx::submit(&state, nft.approve(vault.address(), U256::from(token_id))).await?;
+
+    println!("Locking token {} in vault for {} shares...", token_id, request.shares);
+    let call = vault.fractionalize(collection.address, U256::from(token_id), U256::from(request.shares), recipient);
+    let receipt = tx::submit(&state, call).await?;
+    let share_id = receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == vault.address())
+        .find_map(|log| parse_log::<FractionalizedFilter>(log.clone()).ok())
+        .map(|event| event.share_id)
+        .ok_or("Vault did not emit a Fractionalized event")?;
+
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO fractionalizations (collection, token_id, share_id, shares, vault, transaction_hash, block_number)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                collection.name,
+                token_id,
+                share_id.to_string(),
+                request.shares,
+                format!("{:?}", vault.address()),
+                format!("{:?}", receipt.transaction_hash),
+                receipt.block_number.unwrap_or_default().as_u64(),
+            ],
+        )
+        .map_err(|e| format!("Failed to record fractionalization: {}", e))?;
+    }
+
+    Ok(Json(FractionalizeResponse {
+        token_id,
+        share_id: share_id.to_string(),
+        shares: request.shares,
+        vault: vault.address(),
+        approval: tx::response(&state, &approval),
+        transaction: tx::response(&state, &receipt),
+    }))
+}
+
+/// Replays the vault's ERC-1155 transfer events for the share ID to compute current balances.
+pub async fn shareholders(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<ShareholdersResponse>, ApiError> {
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let vault = vault(&state, &collection)?;
+    let position = position(&state, &collection, token_id)?;
+
+    let events = vault
+        .events()
+        .from_block(position.block_number)
+        .query()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read vault events", e))?;
+
+    let mut balances: BTreeMap<Address, U256> = BTreeMap::new();
+    let mut apply = |from: Address, to: Address, id: U256, value: U256| {
+        if id != position.share_id {
+            return;
+        }
+        if from != Address::zero() {
+            let balance = balances.entry(from).or_default();
+            *balance = balance.saturating_sub(value);
+        }
+        if to != Address::zero() {
+            *balances.entry(to).or_default() += value;
+        }
+    };
+    for event in events {
+        match event {
+            FractionalVaultEvents::TransferSingleFilter(t) => apply(t.from, t.to, t.id, t.value),
+            FractionalVaultEvents::TransferBatchFilter(t) => {
+                for (id, value) in t.ids.into_iter().zip(t.values) {
+                    apply(t.from, t.to, id, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let shareholders = balances
+        .into_iter()
+        .filter(|(_, shares)| !shares.is_zero())
+        .map(|(address, shares)| Shareholder {
+            address,
+            shares: shares.to_string(),
+        })
+        .collect();
+
+    Ok(Json(ShareholdersResponse {
+        token_id,
+        share_id: position.share_id.to_string(),
+        total_shares: position.shares,
+        status: position.status,
+        shareholders,
+    }))
+}
+
+/// Burns the full share supply (held by the server wallet) and releases the NFT back to it.
+pub async fn redeem(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let vault = vault(&state, &collection)?;
+    let position = position(&state, &collection, token_id)?;
+    if position.status != "active" {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Token {} is already redeemed", token_id)));
+    }
+
+    println!("Redeeming token {} from vault...", token_id);
+    let receipt = tx::submit(&state, vault.redeem(position.share_id)).await?;
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE fractionalizations SET status = 'redeemed', redeemed_at = CURRENT_TIMESTAMP
+             WHERE collection = ?1 AND token_id = ?2 AND status = 'active'",
+            params![collection.name, token_id],
+        )
+        .map_err(|e| format!("Failed to record redemption: {}", e))?;
+    }
+    Ok(Json(tx::response(&state, &receipt)))
+}
@@ -0,0 +1,95 @@
+use axum::extract::{Path, Query, State};
+use axum::response::Html;
+use axum::routing::get;
+use axum::Router;
+use rusqlite::{params, OptionalExtension};
+use serde_json::Value;
+use tower_http::services::{ServeDir, ServeFile};
+
+use crate::error::ApiError;
+use crate::nfts;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+/// Share pages plus the SPA, with unknown paths falling back to `index.html` for client-side routing.
+pub fn router(state: &AppState) -> Router<AppState> {
+    let dir = &state.config.frontend_dir;
+    println!("Serving frontend from {}...", dir.display());
+    Router::new()
+        .route("/p/:token_id", get(share_page))
+        .fallback_service(ServeDir::new(dir).fallback(ServeFile::new(dir.join("index.html"))))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn open_graph_tags(state: &AppState, token_id: u64, metadata: &Value) -> String {
+    let title = metadata["name"].as_str().map_or_else(|| format!("Property #{}", token_id), str::to_string);
+    let mut tags = vec![
+        ("og:type", "website".to_string()),
+        ("og:title", title.clone()),
+        ("twitter:card", "summary_large_image".to_string()),
+        ("twitter:title", title.clone()),
+    ];
+    if let Some(description) = metadata["description"].as_str() {
+        tags.push(("og:description", description.to_string()));
+        tags.push(("twitter:description", description.to_string()));
+    }
+    if let Some(image) = metadata["image"].as_str() {
+        tags.push(("og:image", image.to_string()));
+        tags.push(("twitter:image", image.to_string()));
+    }
+    if let Some(public_url) = &state.config.public_url {
+        tags.push(("og:url", format!("{}/p/{}", public_url, token_id)));
+    }
+
+    let mut html = format!("<title>{}</title>\n", escape(&title));
+    for (property, content) in tags {
+        html.push_str(&format!("<meta property=\"{}\" content=\"{}\">\n", property, escape(&content)));
+    }
+    html
+}
+
+/// Serves the SPA's `index.html` with OpenGraph tags for the token, so links unfurl in chats.
+pub async fn share_page(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Html<String>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    // Prefer the copy stored at mint time so crawlers don't each cost an RPC call.
+    let stored = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT metadata FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection.name, token_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+    };
+    let metadata = match stored.and_then(|metadata| serde_json::from_str(&metadata).ok()) {
+        Some(metadata) => metadata,
+        None => nfts::token_metadata(&state, &collection, token_id).await?,
+    };
+    let tags = open_graph_tags(&state, token_id, &metadata);
+
+    let index = tokio::fs::read_to_string(state.config.frontend_dir.join("index.html")).await;
+    let page = match index {
+        Ok(index) => match index.find("</head>") {
+            Some(head_end) => format!("{}{}{}", &index[..head_end], tags, &index[head_end..]),
+            None => index,
+        },
+        // No bundled SPA: crawlers only need the tags.
+        Err(_) => format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n{}</head>\n<body></body>\n</html>\n", tags),
+    };
+    Ok(Html(page))
+}
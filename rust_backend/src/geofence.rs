@@ -0,0 +1,117 @@
+use axum::extract::{ConnectInfo, State};
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use maxminddb::{geoip2, Reader};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+#[cfg(test)]
+mod tests;
+
+/// Error code returned to clients in restricted jurisdictions.
+pub const RESTRICTED_CODE: &str = "JURISDICTION_RESTRICTED";
+
+/// Country-level geofencing backed by a MaxMind GeoIP2/GeoLite2 Country database.
+pub struct GeofenceConfig {
+    reader: Reader<Vec<u8>>,
+    blocked: HashSet<String>,
+    /// When non-empty, only these countries may mint.
+    allowed: HashSet<String>,
+    block_unknown: bool,
+    /// How many proxies in front of the service append to X-Forwarded-For; 0 uses the peer.
+    trusted_proxies: usize,
+}
+
+fn countries(name: &str) -> HashSet<String> {
+    optional_env(name)
+        .map(|list| {
+            list.split(',')
+                .map(|code| code.trim().to_uppercase())
+                .filter(|code| !code.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn flag(name: &str) -> bool {
+    optional_env(name).is_some_and(|value| value == "true" || value == "1")
+}
+
+impl GeofenceConfig {
+    /// Enabled by setting GEOIP_DB_PATH.
+    pub fn from_env() -> Option<Self> {
+        let path = optional_env("GEOIP_DB_PATH")?;
+        let reader = Reader::open_readfile(&path)
+            .unwrap_or_else(|e| panic!("Failed to open GeoIP database {}: {}", path, e));
+        let config = GeofenceConfig {
+            reader,
+            blocked: countries("GEOFENCE_BLOCKED_COUNTRIES"),
+            allowed: countries("GEOFENCE_ALLOWED_COUNTRIES"),
+            block_unknown: flag("GEOFENCE_BLOCK_UNKNOWN"),
+            trusted_proxies: optional_env("GEOFENCE_TRUSTED_PROXIES")
+                .map(|count| count.parse().expect("GEOFENCE_TRUSTED_PROXIES must be a number"))
+                .unwrap_or_else(|| usize::from(flag("GEOFENCE_TRUST_FORWARDED"))),
+        };
+        println!(
+            "GEOIP_DB_PATH: {} (blocked: {:?}, allowed: {:?})",
+            path, config.blocked, config.allowed
+        );
+        Some(config)
+    }
+
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        let country: geoip2::Country = self.reader.lookup(ip).ok()?;
+        country.country?.iso_code.map(str::to_string)
+    }
+
+    fn permits(&self, country: Option<&str>) -> bool {
+        match country {
+            Some(code) => !self.blocked.contains(code) && (self.allowed.is_empty() || self.allowed.contains(code)),
+            None => !self.block_unknown,
+        }
+    }
+}
+
+/// The client's address in an X-Forwarded-For `header` written by `trusted_proxies` proxies:
+/// the hop that many places from the right, which the outermost one appended. Hops to the left
+/// of it came from the client and prove nothing.
+fn forwarded_client(header: &str, trusted_proxies: usize) -> Option<IpAddr> {
+    let hops: Vec<&str> = header.split(',').collect();
+    let hop = hops.len().checked_sub(trusted_proxies)?;
+    hops.get(hop)?.trim().parse().ok()
+}
+
+/// Route layer that blocks requests from restricted jurisdictions with HTTP 451.
+pub async fn require_permitted<B>(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, ApiError> {
+    let Some(geofence) = &state.config.geofence else {
+        return Ok(next.run(request).await);
+    };
+    // Behind load balancers the client is the hop the outermost one appended to X-Forwarded-For.
+    let forwarded = (geofence.trusted_proxies > 0)
+        .then(|| request.headers().get("X-Forwarded-For"))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| forwarded_client(value, geofence.trusted_proxies));
+    let ip = forwarded.unwrap_or(peer.ip());
+
+    let country = geofence.country(ip);
+    if !geofence.permits(country.as_deref()) {
+        eprintln!("Blocked {} {} from {} ({:?})", request.method(), request.uri().path(), ip, country);
+        return Err(ApiError::new(
+            StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+            "This service is not available in your jurisdiction",
+        )
+        .with_details(serde_json::json!({ "code": RESTRICTED_CODE, "country": country })));
+    }
+    Ok(next.run(request).await)
+}
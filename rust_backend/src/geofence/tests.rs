@@ -0,0 +1,27 @@
+use std::net::IpAddr;
+
+use super::forwarded_client;
+
+fn ip(address: &str) -> Option<IpAddr> {
+    Some(address.parse().unwrap())
+}
+
+#[test]
+fn one_proxy_uses_the_hop_it_appended() {
+    assert_eq!(forwarded_client("203.0.113.7", 1), ip("203.0.113.7"));
+    // Whatever the client put in front is ignored.
+    assert_eq!(forwarded_client("198.51.100.1, 203.0.113.7", 1), ip("203.0.113.7"));
+}
+
+#[test]
+fn several_proxies_count_from_the_right() {
+    assert_eq!(forwarded_client("198.51.100.1, 203.0.113.7, 10.0.0.2", 2), ip("203.0.113.7"));
+    assert_eq!(forwarded_client(" 2001:db8::1 ,10.0.0.2", 2), ip("2001:db8::1"));
+}
+
+#[test]
+fn too_few_hops_or_garbage_give_no_client() {
+    assert_eq!(forwarded_client("203.0.113.7", 2), None);
+    assert_eq!(forwarded_client("198.51.100.1, not-an-ip", 1), None);
+    assert_eq!(forwarded_client("", 1), None);
+}
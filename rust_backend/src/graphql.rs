@@ -0,0 +1,259 @@
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Schema, SimpleObject};
+use axum::response::Html;
+use axum::{Extension, Json};
+use ethers::types::{Address, U256};
+use rusqlite::params;
+use serde_json::{Map, Value};
+
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::ownership::{self, Transfer};
+use crate::registry;
+use crate::state::AppState;
+
+pub type ApiSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+pub fn schema(state: AppState) -> ApiSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .limit_depth(8)
+        .limit_complexity(500)
+        .finish()
+}
+
+pub async fn graphql(
+    Extension(schema): Extension<ApiSchema>,
+    tenant: Tenant,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request.data(tenant)).await)
+}
+
+pub async fn graphiql() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+fn gql(err: ApiError) -> async_graphql::Error {
+    async_graphql::Error::new(err.message)
+}
+
+/// A property NFT minted through this service.
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Property {
+    collection: String,
+    token_id: u64,
+    name: String,
+    /// Address the token was minted to.
+    recipient: String,
+    /// Latest valuation.
+    price: f64,
+    soulbound: bool,
+    transaction_hash: String,
+    minted_at: String,
+    details: Option<PropertyDetails>,
+}
+
+/// Public property characteristics; location and other personal data are not exposed.
+/// Fields listed in PRIVATE_FIELDS are null.
+#[derive(SimpleObject)]
+pub struct PropertyDetails {
+    bedrooms: Option<u64>,
+    bathrooms: Option<f64>,
+    sqft_living: Option<u64>,
+    sqft_lot: Option<u64>,
+    floors: Option<u64>,
+    waterfront: Option<bool>,
+    condition: Option<u64>,
+    grade: Option<u64>,
+    yr_built: Option<u64>,
+    yr_renovated: Option<u64>,
+    zipcode: Option<u64>,
+}
+
+#[derive(SimpleObject)]
+pub struct Valuation {
+    price: f64,
+    source: String,
+    /// market, insurance-replacement or tax-assessed.
+    valuation_type: String,
+    actor: String,
+    transaction_hash: Option<String>,
+    created_at: String,
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Owner {
+    address: String,
+}
+
+impl From<Map<String, Value>> for PropertyDetails {
+    /// From the stored details, which leave out the private fields.
+    fn from(details: Map<String, Value>) -> Self {
+        let number = |field: &str| details.get(field).and_then(Value::as_u64);
+        PropertyDetails {
+            bedrooms: number("bedrooms"),
+            bathrooms: details.get("bathrooms").and_then(Value::as_f64),
+            sqft_living: number("sqft_living"),
+            sqft_lot: number("sqft_lot"),
+            floors: number("floors"),
+            waterfront: number("waterfront").map(|waterfront| waterfront != 0),
+            condition: number("condition"),
+            grade: number("grade"),
+            yr_built: number("yr_built"),
+            yr_renovated: number("yr_renovated"),
+            zipcode: number("zipcode"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct PropertyFilter<'a> {
+    collection: Option<&'a str>,
+    token_id: Option<u64>,
+    recipient: Option<String>,
+    limit: u32,
+    offset: u32,
+}
+
+fn properties(ctx: &Context<'_>, filter: PropertyFilter) -> async_graphql::Result<Vec<Property>> {
+    let state = ctx.data::<AppState>()?;
+    let tenant = ctx.data::<Tenant>()?;
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn.prepare(
+        "SELECT m.collection, m.token_id, json_extract(m.details, '$.name'), m.recipient, m.price, m.soulbound,
+                m.transaction_hash, m.created_at, m.details
+         FROM mints m JOIN collections c ON c.name = m.collection
+         WHERE c.organization IS ?1 AND (?2 IS NULL OR m.collection = ?2)
+           AND (?3 IS NULL OR m.token_id = ?3) AND (?4 IS NULL OR m.recipient = ?4)
+         ORDER BY m.id DESC LIMIT ?5 OFFSET ?6",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            tenant.organization_id(),
+            filter.collection,
+            filter.token_id,
+            filter.recipient,
+            filter.limit.min(500),
+            filter.offset
+        ],
+        |row| {
+            let details: String = row.get(8)?;
+            Ok(Property {
+                collection: row.get(0)?,
+                token_id: row.get(1)?,
+                name: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                recipient: row.get(3)?,
+                price: row.get(4)?,
+                soulbound: row.get(5)?,
+                transaction_hash: row.get(6)?,
+                minted_at: row.get(7)?,
+                details: serde_json::from_str::<Map<String, Value>>(&details).ok().map(PropertyDetails::from),
+            })
+        },
+    )?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Properties, newest first.
+    async fn properties(
+        &self,
+        ctx: &Context<'_>,
+        collection: Option<String>,
+        #[graphql(default = 50)] limit: u32,
+        #[graphql(default = 0)] offset: u32,
+    ) -> async_graphql::Result<Vec<Property>> {
+        let filter = PropertyFilter {
+            collection: collection.as_deref(),
+            limit,
+            offset,
+            ..Default::default()
+        };
+        properties(ctx, filter)
+    }
+
+    async fn property(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "registry::DEFAULT_COLLECTION.to_string()")] collection: String,
+        token_id: u64,
+    ) -> async_graphql::Result<Option<Property>> {
+        let filter = PropertyFilter {
+            collection: Some(&collection),
+            token_id: Some(token_id),
+            limit: 1,
+            ..Default::default()
+        };
+        Ok(properties(ctx, filter)?.pop())
+    }
+
+    async fn owner(&self, address: String) -> async_graphql::Result<Owner> {
+        let address: Address = address.parse().map_err(|_| "Invalid address")?;
+        Ok(Owner {
+            address: format!("{:?}", address),
+        })
+    }
+}
+
+#[ComplexObject]
+impl Property {
+    /// Valuation history, oldest first.
+    async fn valuations(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Valuation>> {
+        let state = ctx.data::<AppState>()?;
+        let conn = state.db.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT price, source, actor, transaction_hash, created_at, valuation_type FROM valuations
+             WHERE collection = ?1 AND token_id = ?2 ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![self.collection, self.token_id], |row| {
+            Ok(Valuation {
+                price: row.get(0)?,
+                source: row.get(1)?,
+                actor: row.get(2)?,
+                transaction_hash: row.get(3)?,
+                created_at: row.get(4)?,
+                valuation_type: row.get(5)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Current on-chain owner.
+    async fn owner(&self, ctx: &Context<'_>) -> async_graphql::Result<String> {
+        let state = ctx.data::<AppState>()?;
+        let collection = registry::resolve(&state.db, Some(&self.collection)).map_err(gql)?;
+        let owner = state
+            .nft(&collection)
+            .map_err(gql)?
+            .owner_of(U256::from(self.token_id))
+            .call()
+            .await?;
+        Ok(format!("{:?}", owner))
+    }
+
+    /// On-chain transfers since the mint, oldest first.
+    async fn transfers(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Transfer>> {
+        let state = ctx.data::<AppState>()?;
+        let collection = registry::resolve(&state.db, Some(&self.collection)).map_err(gql)?;
+        ownership::transfers(state, &collection, self.token_id).await.map_err(gql)
+    }
+}
+
+#[ComplexObject]
+impl Owner {
+    /// Properties minted to this address.
+    async fn tokens(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Property>> {
+        let filter = PropertyFilter {
+            recipient: Some(self.address.clone()),
+            limit: 500,
+            ..Default::default()
+        };
+        properties(ctx, filter)
+    }
+}
@@ -0,0 +1,255 @@
+// tonic::Status is large, but it is the error type the generated service trait expects.
+#![allow(clippy::result_large_err)]
+
+use axum::http::StatusCode;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::auth;
+use crate::error::ApiError;
+use crate::jobs;
+use crate::maintenance;
+use crate::mint::{self, HouseDetails, MintRequest};
+use crate::nfts;
+use crate::organizations::{self, Caller, Tenant};
+use crate::rbac::{Permission, Role};
+use crate::registry;
+use crate::state::AppState;
+
+pub mod pb {
+    tonic::include_proto!("house_valuation.v1");
+}
+
+use pb::house_valuation_server::{HouseValuation, HouseValuationServer};
+
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct GrpcService {
+    state: AppState,
+}
+
+/// Serves the gRPC API on its own port next to the REST server.
+pub async fn serve(state: AppState, port: u16) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    println!("gRPC server running at {}...", addr);
+    let service = HouseValuationServer::new(GrpcService { state });
+    if let Err(err) = tonic::transport::Server::builder().add_service(service).serve(addr).await {
+        eprintln!("gRPC server error: {}", err);
+    }
+}
+
+fn status(err: ApiError) -> Status {
+    let message = err.message;
+    match err.status {
+        StatusCode::BAD_REQUEST => Status::invalid_argument(message),
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::FORBIDDEN | StatusCode::PAYMENT_REQUIRED | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => {
+            Status::permission_denied(message)
+        }
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        StatusCode::CONFLICT | StatusCode::UNPROCESSABLE_ENTITY => Status::failed_precondition(message),
+        StatusCode::NOT_IMPLEMENTED => Status::unimplemented(message),
+        StatusCode::SERVICE_UNAVAILABLE => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+impl From<pb::HouseDetails> for HouseDetails {
+    fn from(d: pb::HouseDetails) -> Self {
+        HouseDetails {
+            name: d.name,
+            bedrooms: d.bedrooms,
+            bathrooms: d.bathrooms,
+            sqft_living: d.sqft_living,
+            sqft_lot: d.sqft_lot,
+            floors: d.floors,
+            waterfront: d.waterfront,
+            view: d.view,
+            condition: d.condition,
+            grade: d.grade,
+            sqft_above: d.sqft_above,
+            sqft_basement: d.sqft_basement,
+            yr_built: d.yr_built,
+            yr_renovated: d.yr_renovated,
+            zipcode: d.zipcode,
+            lat: d.lat,
+            long: d.long,
+            sqft_living15: d.sqft_living15,
+            sqft_lot15: d.sqft_lot15,
+            month: d.month,
+            year: d.year,
+            image: d.image,
+        }
+    }
+}
+
+impl From<&jobs::MintJob> for pb::MintJob {
+    fn from(job: &jobs::MintJob) -> Self {
+        let result = job.result.as_ref();
+        let field = |name: &str| result.and_then(|r| r[name].as_str()).map(str::to_string);
+        pb::MintJob {
+            job_id: job.id,
+            status: job.status.clone(),
+            transaction_hash: field("transaction_hash"),
+            token_id: field("token_id"),
+            error: job.error.clone(),
+        }
+    }
+}
+
+fn parse_address(value: Option<String>, field: &str) -> Result<Option<ethers::types::Address>, Status> {
+    value
+        .map(|address| address.parse().map_err(|_| Status::invalid_argument(format!("Invalid {}", field))))
+        .transpose()
+}
+
+impl GrpcService {
+    /// Authenticates with the same credentials as REST (minus anonymous access) and checks the role.
+    fn authorize<T>(&self, request: &Request<T>, permission: Permission) -> Result<(Tenant, Caller), Status> {
+        let metadata = request.metadata();
+        let api_key = metadata.get("x-api-key").and_then(|value| value.to_str().ok());
+        let (tenant, role, caller) = if let Some(key) = api_key {
+            let (org, role, key_id) = organizations::by_api_key(&self.state.db, key)
+                .map_err(Status::internal)?
+                .ok_or_else(|| Status::unauthenticated("Invalid or revoked API key"))?;
+            (Tenant(Some(org)), role, Caller(Some(format!("key:{}", key_id))))
+        } else {
+            let bearer = metadata
+                .get("authorization")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "));
+            match bearer.and_then(|token| auth::admin_for_token(&self.state, token)) {
+                Some(admin) => (Tenant(None), Role::Admin, Caller(Some(admin))),
+                None => return Err(Status::unauthenticated("An API key is required")),
+            }
+        };
+        if !role.allows(permission) {
+            return Err(Status::permission_denied(format!(
+                "Role {} is not allowed to {:?}",
+                role.as_str(),
+                permission
+            )));
+        }
+        Ok((tenant, caller))
+    }
+}
+
+#[tonic::async_trait]
+impl HouseValuation for GrpcService {
+    type WatchMintJobStream = ReceiverStream<Result<pb::MintJob, Status>>;
+
+    async fn predict_price(&self, request: Request<pb::HouseDetails>) -> Result<Response<pb::PriceResponse>, Status> {
+        self.authorize(&request, Permission::Read)?;
+        let details = HouseDetails::from(request.into_inner());
+        let price = mint::predict_price(&self.state, &details).await.map_err(status)?;
+        Ok(Response::new(pb::PriceResponse { price: price.to_f64() }))
+    }
+
+    /// Mints are queued and wait for consensus, approval or review the same way REST mints do.
+    /// Stripe Checkout needs a browser, so paid deployments only mint through the REST API.
+    async fn mint(&self, request: Request<pb::MintRequest>) -> Result<Response<pb::MintJob>, Status> {
+        let (tenant, caller) = self.authorize(&request, Permission::Mint)?;
+        if self.state.config.stripe.is_some() {
+            return Err(Status::failed_precondition("Mints are paid through Stripe Checkout; use POST /mint-nft"));
+        }
+        // In queue mode the job is held by the worker instead.
+        if let Some(maintenance) = maintenance::current(&self.state.db).filter(|m| m.mode == maintenance::Mode::Reject) {
+            return Err(Status::unavailable(format!(
+                "The API is in maintenance; try again in {} seconds",
+                maintenance.retry_after_secs
+            )));
+        }
+        let request = request.into_inner();
+        let details = request.details.ok_or_else(|| Status::invalid_argument("details are required"))?;
+        let mut mint_request = MintRequest {
+            details: details.into(),
+            collection: request.collection,
+            network: None,
+            recipient: parse_address(request.recipient, "recipient")?,
+            payer: parse_address(request.payer, "payer")?,
+            soulbound: request.soulbound,
+            scheduled_at: None,
+            metadata_storage: request.metadata_storage,
+            private: None,
+            force: false,
+        };
+        // Fail fast on unknown collections instead of queueing a job that can't succeed.
+        let collection = mint::target(&self.state, &tenant, &mint_request).map_err(status)?;
+        mint_request.network = collection.network;
+        self.state
+            .config
+            .storage
+            .store_for(&self.state.db, &tenant, mint_request.metadata_storage.as_deref())
+            .map_err(status)?;
+
+        let holds = mint::holds(&self.state, &tenant, &mint_request).await.map_err(status)?;
+        if holds.any() {
+            let id = mint::create_held(&self.state, &tenant, &caller, &mint_request, &holds).map_err(status)?;
+            let job = jobs::get(&self.state.db, id)
+                .map_err(Status::internal)?
+                .ok_or_else(|| Status::internal("Mint job disappeared"))?;
+            return Ok(Response::new((&job).into()));
+        }
+        let id = jobs::create(&self.state, &mint_request, jobs::QUEUED, tenant.organization_id())
+            .map_err(status)?;
+        jobs::spawn(self.state.clone(), id);
+        Ok(Response::new(pb::MintJob {
+            job_id: id,
+            status: jobs::QUEUED.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    async fn watch_mint_job(
+        &self,
+        request: Request<pb::WatchMintJobRequest>,
+    ) -> Result<Response<Self::WatchMintJobStream>, Status> {
+        let (tenant, _) = self.authorize(&request, Permission::Read)?;
+        let id = request.into_inner().job_id;
+        let job = jobs::get(&self.state.db, id)
+            .map_err(Status::internal)?
+            .filter(|job| job.organization.as_deref() == tenant.organization_id())
+            .ok_or_else(|| Status::not_found(format!("Mint job {} not found", id)))?;
+
+        let (sender, receiver) = mpsc::channel(8);
+        let db = self.state.db.clone();
+        tokio::spawn(async move {
+            let mut last = job;
+            if sender.send(Ok((&last).into())).await.is_err() {
+                return;
+            }
+            while ![jobs::MINTED, jobs::FAILED, jobs::PAYMENT_EXPIRED, jobs::CANCELLED].contains(&last.status.as_str()) {
+                tokio::time::sleep(JOB_POLL_INTERVAL).await;
+                let job = match jobs::get(&db, id) {
+                    Ok(Some(job)) => job,
+                    Ok(None) => return,
+                    Err(e) => {
+                        let _ = sender.send(Err(Status::internal(e))).await;
+                        return;
+                    }
+                };
+                if job.status != last.status && sender.send(Ok((&job).into())).await.is_err() {
+                    return;
+                }
+                last = job;
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn get_metadata(&self, request: Request<pb::MetadataRequest>) -> Result<Response<pb::MetadataResponse>, Status> {
+        let (tenant, _) = self.authorize(&request, Permission::Read)?;
+        let request = request.into_inner();
+        let collection =
+            registry::resolve_for(&self.state.db, &tenant, request.collection.as_deref()).map_err(status)?;
+        let metadata = nfts::token_metadata(&self.state, &collection, request.token_id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(pb::MetadataResponse {
+            metadata_json: metadata.to_string(),
+        }))
+    }
+}
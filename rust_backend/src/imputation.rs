@@ -0,0 +1,246 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crate::analytics;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Fields of HouseDetails a price estimate can do without, filled in from the zipcode's medians.
+const IMPUTABLE: &[&str] = &[
+    "bedrooms",
+    "bathrooms",
+    "sqft_living",
+    "sqft_lot",
+    "floors",
+    "waterfront",
+    "view",
+    "condition",
+    "grade",
+    "sqft_above",
+    "sqft_basement",
+    "yr_built",
+    "yr_renovated",
+    "lat",
+    "long",
+    "sqft_living15",
+    "sqft_lot15",
+];
+/// The imputable fields that aren't whole numbers.
+const FRACTIONAL: &[&str] = &["bathrooms", "lat", "long"];
+/// Medians of these would place properties on the map, so they aren't published.
+const LOCATION: &[&str] = &["lat", "long"];
+/// Fewer minted properties than this would give away the properties themselves rather than a
+/// typical one for the zipcode.
+const MIN_ZIPCODE_SAMPLES: usize = 5;
+/// How often medians are recomputed from the mints.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// A field the client left out and the value the estimate used instead.
+#[derive(Serialize)]
+pub struct Imputed {
+    pub field: String,
+    /// Left out for the location fields, which would say where the zipcode's properties are.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// How many minted properties the median was taken over; unset for medians set by an admin.
+    pub samples: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ZipcodeMedians {
+    zipcode: u64,
+    medians: BTreeMap<String, f64>,
+}
+
+fn missing(details: &Map<String, Value>, field: &str) -> bool {
+    details.get(field).is_none_or(Value::is_null)
+}
+
+/// The stored medians of a zipcode, with how many properties each was taken over. Medians of
+/// fewer than MIN_ZIPCODE_SAMPLES properties are left out.
+fn medians(db: &Db, zipcode: u64) -> Result<HashMap<String, (f64, Option<u64>)>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT field, median, samples FROM zipcode_medians
+             WHERE zipcode = ?1 AND (samples IS NULL OR samples >= ?2)",
+        )
+        .map_err(|e| format!("Failed to load zipcode medians: {}", e))?;
+    let rows = stmt
+        .query_map(params![zipcode, MIN_ZIPCODE_SAMPLES], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| format!("Failed to load zipcode medians: {}", e))?;
+    Ok(rows)
+}
+
+/// Fills the fields missing from a partial HouseDetails with the medians of its zipcode, so it
+/// can be priced. The sale date defaults to today and the name to nothing; neither changes how
+/// precise the estimate is, so only the imputed property fields are returned.
+pub fn impute(db: &Db, details: &mut Map<String, Value>) -> Result<Vec<Imputed>, ApiError> {
+    if missing(details, "name") {
+        details.insert("name".to_string(), json!(""));
+    }
+    if missing(details, "month") || missing(details, "year") {
+        let (month, year): (u64, u64) = {
+            let conn = db.lock().unwrap();
+            conn.query_row(
+                "SELECT CAST(strftime('%m', 'now') AS INTEGER), CAST(strftime('%Y', 'now') AS INTEGER)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| format!("Failed to read the date: {}", e))?
+        };
+        if missing(details, "month") {
+            details.insert("month".to_string(), json!(month));
+        }
+        if missing(details, "year") {
+            details.insert("year".to_string(), json!(year));
+        }
+    }
+    let fields: Vec<&str> = IMPUTABLE.iter().copied().filter(|field| missing(details, field)).collect();
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(zipcode) = details.get("zipcode").and_then(Value::as_u64) else {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "zipcode is required to fill in missing fields"));
+    };
+    let medians = medians(db, zipcode)?;
+    let unavailable: Vec<&str> = fields.iter().copied().filter(|field| !medians.contains_key(*field)).collect();
+    if !unavailable.is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("No medians for zipcode {} to fill in the missing fields", zipcode),
+        )
+        .with_details(json!({ "missing": unavailable })));
+    }
+    let mut imputed = Vec::with_capacity(fields.len());
+    for field in fields {
+        let (median, samples) = medians[field];
+        let (value, json) = if FRACTIONAL.contains(&field) {
+            (median, json!(median))
+        } else {
+            (median.round(), json!(median.round() as u64))
+        };
+        details.insert(field.to_string(), json);
+        let value = (!LOCATION.contains(&field)).then_some(value);
+        imputed.push(Imputed { field: field.to_string(), value, samples });
+    }
+    Ok(imputed)
+}
+
+/// Recomputes every zipcode's medians from the properties minted there, skipping fields with
+/// fewer than MIN_ZIPCODE_SAMPLES values. Medians set by an admin are kept. Returns how many
+/// zipcodes were updated.
+pub fn refresh(db: &Db) -> Result<usize, String> {
+    let error = |e: rusqlite::Error| format!("Failed to refresh zipcode medians: {}", e);
+    let details: Vec<String> = {
+        let conn = db.lock().unwrap();
+        conn.prepare("SELECT details FROM mints")
+            .and_then(|mut stmt| stmt.query_map([], |row| row.get(0))?.collect())
+            .map_err(error)?
+    };
+    let mut values: BTreeMap<u64, BTreeMap<&str, Vec<f64>>> = BTreeMap::new();
+    for details in details {
+        let Ok(Value::Object(details)) = serde_json::from_str::<Value>(&details) else {
+            continue;
+        };
+        let Some(zip) = details.get("zipcode").and_then(Value::as_u64) else {
+            continue;
+        };
+        let fields = values.entry(zip).or_default();
+        for field in IMPUTABLE {
+            if let Some(value) = details.get(*field).and_then(Value::as_f64) {
+                fields.entry(field).or_default().push(value);
+            }
+        }
+    }
+    let conn = db.lock().unwrap();
+    for (zip, fields) in &mut values {
+        for (field, values) in fields {
+            let samples = values.len();
+            if samples < MIN_ZIPCODE_SAMPLES {
+                continue;
+            }
+            let Some(median) = analytics::median(values) else {
+                continue;
+            };
+            conn.execute(
+                "INSERT INTO zipcode_medians (zipcode, field, median, samples) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(zipcode, field) DO UPDATE SET median = ?3, samples = ?4, updated_at = CURRENT_TIMESTAMP
+                 WHERE samples IS NOT NULL",
+                params![zip, field, median, samples],
+            )
+            .map_err(error)?;
+        }
+    }
+    Ok(values.len())
+}
+
+/// Recomputes the medians every REFRESH_INTERVAL, starting now, off the mint path.
+pub fn start(state: &AppState) {
+    let db = state.db.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let db = db.clone();
+            match tokio::task::spawn_blocking(move || refresh(&db)).await {
+                Ok(Err(e)) => eprintln!("{}", e),
+                Err(e) => eprintln!("Zipcode median refresh panicked: {}", e),
+                Ok(Ok(_)) => {}
+            }
+        }
+    });
+}
+
+/// The medians missing fields of properties in `zipcode` are filled in with, except the
+/// location ones.
+pub async fn show(State(state): State<AppState>, Path(zipcode): Path<u64>) -> Result<Json<ZipcodeMedians>, ApiError> {
+    let medians: BTreeMap<String, f64> = medians(&state.db, zipcode)?
+        .into_iter()
+        .filter(|(field, _)| !LOCATION.contains(&field.as_str()))
+        .map(|(field, (median, _))| (field, median))
+        .collect();
+    if medians.is_empty() {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("No medians for zipcode {}", zipcode)));
+    }
+    Ok(Json(ZipcodeMedians { zipcode, medians }))
+}
+
+/// Sets medians for a zipcode, e.g. from public sales data where nothing has been minted yet.
+/// They take precedence over medians computed from mints.
+pub async fn set(
+    State(state): State<AppState>,
+    Path(zipcode): Path<u64>,
+    Json(medians): Json<BTreeMap<String, f64>>,
+) -> Result<Json<ZipcodeMedians>, ApiError> {
+    if let Some(field) = medians.keys().find(|field| !IMPUTABLE.contains(&field.as_str())) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("{} can't be imputed", field))
+            .with_details(json!({ "fields": IMPUTABLE })));
+    }
+    {
+        let conn = state.db.lock().unwrap();
+        for (field, median) in &medians {
+            conn.execute(
+                "INSERT INTO zipcode_medians (zipcode, field, median, samples) VALUES (?1, ?2, ?3, NULL)
+                 ON CONFLICT(zipcode, field) DO UPDATE SET median = ?3, samples = NULL, updated_at = CURRENT_TIMESTAMP",
+                params![zipcode, field, median],
+            )
+            .map_err(|e| format!("Failed to save zipcode medians: {}", e))?;
+        }
+    }
+    show(State(state), Path(zipcode)).await
+}
+
+/// Recomputes every zipcode's medians from the minted properties.
+pub async fn refresh_all(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let zipcodes = refresh(&state.db)?;
+    Ok(Json(json!({ "zipcodes": zipcodes })))
+}
@@ -0,0 +1,225 @@
+use ethers::providers::Middleware;
+use ethers::types::H256;
+use rusqlite::{params, OptionalExtension};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::organizations;
+use crate::registry::{self, Collection};
+use crate::saved_searches;
+use crate::state::AppState;
+
+/// Most providers cap `eth_getLogs` ranges; Alchemy allows 2000 blocks on free plans.
+const BLOCK_RANGE: u64 = 2000;
+/// Mints deeper than this are final; Ethereum finalizes after two epochs of 32 slots.
+const FINALITY_DEPTH: u64 = 64;
+
+struct RecentMint {
+    collection: String,
+    organization: Option<String>,
+    token_id: u64,
+    transaction_hash: String,
+    block_hash: Option<String>,
+    pending: bool,
+}
+
+/// Where indexing starts for a collection that has never been indexed: its first mint
+/// through this service. Collections without mints are skipped until they have one.
+fn cursor(state: &AppState, collection: &Collection) -> Result<Option<u64>, String> {
+    let conn = state.db.lock().unwrap();
+    let indexed: Option<u64> = conn
+        .query_row(
+            "SELECT block_number FROM indexer_cursors WHERE collection = ?1",
+            params![collection.name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load indexer cursor: {}", e))?;
+    if let Some(block) = indexed {
+        return Ok(Some(block + 1));
+    }
+    conn.query_row(
+        "SELECT min(block_number) FROM mints WHERE collection = ?1",
+        params![collection.name],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to load mints: {}", e))
+}
+
+/// Copies the collection's Transfer events up to `head` into `token_transfers`.
+async fn index(state: &AppState, collection: &Collection, head: u64) -> Result<(), String> {
+    let Some(mut from) = cursor(state, collection)? else { return Ok(()) };
+    let contract = state.nft(collection).map_err(|e| e.message)?;
+    while from <= head {
+        let to = (from + BLOCK_RANGE - 1).min(head);
+        let events = contract
+            .transfer_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await
+            .map_err(|e| format!("Failed to read transfers of {} in blocks {}-{}: {}", collection.name, from, to, e))?;
+
+        let minted: Vec<u64> = events
+            .iter()
+            .filter(|(event, _)| event.from.is_zero())
+            .map(|(event, _)| event.token_id.as_u64())
+            .collect();
+        let mut conn = state.db.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| format!("Failed to index transfers: {}", e))?;
+        // Re-reading a range after a crash is harmless: each log is keyed by its position.
+        for (event, meta) in events {
+            tx.execute(
+                "INSERT OR IGNORE INTO token_transfers
+                    (collection, token_id, from_address, to_address, transaction_hash, log_index, block_number)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    collection.name,
+                    event.token_id.as_u64(),
+                    format!("{:?}", event.from),
+                    format!("{:?}", event.to),
+                    format!("{:?}", meta.transaction_hash),
+                    meta.log_index.as_u64(),
+                    meta.block_number.as_u64(),
+                ],
+            )
+            .map_err(|e| format!("Failed to index transfers: {}", e))?;
+        }
+        tx.execute(
+            "INSERT INTO indexer_cursors (collection, block_number) VALUES (?1, ?2)
+             ON CONFLICT (collection) DO UPDATE SET block_number = excluded.block_number",
+            params![collection.name, to],
+        )
+        .map_err(|e| format!("Failed to index transfers: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to index transfers: {}", e))?;
+        drop(conn);
+        if let Err(e) = saved_searches::evaluate(state, collection, &minted) {
+            eprintln!("Saved search evaluation for {} failed: {}", collection.name, e);
+        }
+        from = to + 1;
+    }
+    Ok(())
+}
+
+fn recent_mints(state: &AppState, head: u64) -> Result<Vec<RecentMint>, String> {
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.collection, c.organization, m.token_id, m.transaction_hash, m.block_hash, m.status
+             FROM mints m JOIN collections c ON c.name = m.collection
+             WHERE (m.status = 'pending' OR m.block_number > ?1 OR m.block_hash IS NULL) AND c.network IS ?2",
+        )
+        .map_err(|e| format!("Failed to load recent mints: {}", e))?;
+    let rows = stmt
+        .query_map(params![head.saturating_sub(FINALITY_DEPTH), state.chain.network], |row| {
+            Ok(RecentMint {
+                collection: row.get(0)?,
+                organization: row.get(1)?,
+                token_id: row.get(2)?,
+                transaction_hash: row.get(3)?,
+                block_hash: row.get(4)?,
+                pending: row.get::<_, String>(5)? == "pending",
+            })
+        })
+        .map_err(|e| format!("Failed to load recent mints: {}", e))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to load recent mints: {}", e))
+}
+
+/// Re-checks mints that could still be reorged out. A mint whose transaction is no longer in
+/// the canonical chain goes back to `pending` until it is mined again, and owners are alerted.
+async fn check_finality(state: &AppState, head: u64) -> Result<(), String> {
+    for mint in recent_mints(state, head)? {
+        let Ok(hash) = mint.transaction_hash.parse::<H256>() else { continue };
+        let receipt = state
+            .client
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(|e| format!("Failed to read receipt of {}: {}", mint.transaction_hash, e))?
+            .filter(|receipt| receipt.status.is_none_or(|status| !status.is_zero()));
+        let included = receipt.and_then(|receipt| Some((receipt.block_hash?, receipt.block_number?)));
+        let event = match included {
+            Some((block_hash, block_number)) => {
+                let block_hash = format!("{:?}", block_hash);
+                if !mint.pending && mint.block_hash.as_deref() == Some(block_hash.as_str()) {
+                    continue;
+                }
+                let conn = state.db.lock().unwrap();
+                conn.execute(
+                    "UPDATE mints SET status = 'confirmed', block_hash = ?3, block_number = ?4
+                     WHERE collection = ?1 AND token_id = ?2",
+                    params![mint.collection, mint.token_id, block_hash, block_number.as_u64()],
+                )
+                .map_err(|e| format!("Failed to update mint: {}", e))?;
+                // Filling in a hash for the first time isn't news.
+                if !mint.pending && mint.block_hash.is_none() {
+                    continue;
+                }
+                println!(
+                    "Mint of token {} in {} is in block {} after a reorg",
+                    mint.token_id, mint.collection, block_number
+                );
+                "mint.reconfirmed"
+            }
+            None if mint.pending => continue,
+            None => {
+                let conn = state.db.lock().unwrap();
+                conn.execute(
+                    "UPDATE mints SET status = 'pending' WHERE collection = ?1 AND token_id = ?2",
+                    params![mint.collection, mint.token_id],
+                )
+                .map_err(|e| format!("Failed to update mint: {}", e))?;
+                eprintln!(
+                    "ALERT: mint of token {} in {} ({}) was dropped by a reorg; marked pending",
+                    mint.token_id, mint.collection, mint.transaction_hash
+                );
+                "mint.reorged"
+            }
+        };
+        if let Some(organization) = &mint.organization {
+            let data = json!({
+                "collection": mint.collection,
+                "token_id": mint.token_id,
+                "transaction_hash": mint.transaction_hash,
+            });
+            organizations::notify(state, organization, event, data);
+        }
+    }
+    Ok(())
+}
+
+/// Indexes every chain up to its own latest block.
+async fn index_all(state: &AppState) -> Result<(), String> {
+    let collections = registry::list_all(&state.db)?;
+    for state in state.chains() {
+        let head = state
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| format!("Failed to read the latest block: {}", e))?
+            .as_u64();
+        check_finality(&state, head).await?;
+        for collection in collections.iter().filter(|collection| collection.network == state.chain.network) {
+            index(&state, collection, head).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Keeps `token_transfers` in step with the chain so ownership can be read from the database.
+pub fn start(state: &AppState) {
+    let poll = state.config.indexer_poll_secs;
+    if poll == 0 {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(poll));
+        loop {
+            interval.tick().await;
+            if let Err(e) = index_all(&state).await {
+                eprintln!("Transfer indexer failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,591 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::providers::Middleware;
+use ethers::types::H256;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::db::Db;
+use crate::dead_letters;
+use crate::error::ApiError;
+use crate::features;
+use crate::maintenance;
+use crate::mint::{self, AgreedPrice, MintRequest};
+use crate::money::{Currency, Money};
+use crate::organizations::{self, Tenant};
+use crate::outliers::Outlier;
+use crate::payments;
+use crate::private;
+use crate::state::AppState;
+
+#[cfg(test)]
+mod tests;
+
+pub const AWAITING_PAYMENT: &str = "awaiting_payment";
+pub const QUEUED: &str = "queued";
+pub const MINTING: &str = "minting";
+pub const MINTED: &str = "minted";
+pub const FAILED: &str = "failed";
+pub const PAYMENT_EXPIRED: &str = "payment_expired";
+pub const SCHEDULED: &str = "scheduled";
+pub const CANCELLED: &str = "cancelled";
+/// Waiting for registered appraisers to agree on the price of a high-value property.
+pub const AWAITING_APPRAISALS: &str = "awaiting_appraisals";
+/// Waiting for maintenance to end.
+pub const HELD: &str = "held";
+/// Waiting for an admin to look at a predicted price the outlier check flagged.
+pub const AWAITING_REVIEW: &str = "awaiting_review";
+/// Waiting for a second user to approve a high-value mint.
+pub const PENDING_APPROVAL: &str = "pending_approval";
+
+const SCHEDULER_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Every status change a job may make. Jobs can only be cancelled before a worker picks
+/// them up, since a job in `minting` may already have broadcast its transaction.
+const TRANSITIONS: &[(&str, &[&str])] = &[
+    (AWAITING_PAYMENT, &[PENDING_APPROVAL, AWAITING_APPRAISALS, QUEUED, SCHEDULED, PAYMENT_EXPIRED, CANCELLED]),
+    (PENDING_APPROVAL, &[AWAITING_APPRAISALS, AWAITING_REVIEW, QUEUED, SCHEDULED, CANCELLED]),
+    (AWAITING_APPRAISALS, &[QUEUED, SCHEDULED, CANCELLED]),
+    (AWAITING_REVIEW, &[QUEUED, SCHEDULED, CANCELLED]),
+    (SCHEDULED, &[QUEUED, CANCELLED]),
+    (QUEUED, &[MINTING, HELD, CANCELLED]),
+    (HELD, &[QUEUED, CANCELLED]),
+    (MINTING, &[MINTED, FAILED]),
+    (FAILED, &[QUEUED]),
+];
+
+pub fn can_transition(from: &str, to: &str) -> bool {
+    TRANSITIONS
+        .iter()
+        .any(|(status, next)| *status == from && next.contains(&to))
+}
+
+#[derive(Serialize)]
+pub struct MintJob {
+    pub id: i64,
+    pub status: String,
+    pub request: Value,
+    pub result: Option<Value>,
+    pub error: Option<String>,
+    pub payment_session: Option<String>,
+    pub payment_status: Option<String>,
+    pub scheduled_at: Option<String>,
+    /// The model's price when it called for appraiser consensus.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_price: Option<Money>,
+    /// The appraisers' median, which the job mints at instead of the model's price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consensus_price: Option<Money>,
+    /// Why the outlier check held the job for review.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outlier: Option<Outlier>,
+    /// The model's price when it called for a second user's approval.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approval_price: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requested_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approved_by: Option<String>,
+    /// The NETWORKS chain the job mints on; `None` for the primary chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    /// The mint transaction of a failed attempt that got as far as broadcasting it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<String>,
+    #[serde(skip)]
+    pub organization: Option<String>,
+    /// The request's private fields, sealed; `request` leaves them out.
+    #[serde(skip)]
+    pub private_data: Option<Vec<u8>>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Stores the request with its private fields sealed away from the rest, since job responses
+/// echo the stored request back.
+pub fn create(state: &AppState, request: &MintRequest, status: &str, organization: Option<&str>) -> Result<i64, ApiError> {
+    let scheduled_at = request.scheduled_at.as_deref();
+    let mut request_json =
+        serde_json::to_value(request).map_err(|e| format!("Failed to serialize mint request: {}", e))?;
+    if let Some(request_json) = request_json.as_object_mut() {
+        request_json.remove("private");
+    }
+    let sealed = private::seal(state.config.private.as_ref(), &mut request_json, request.private.as_ref())?;
+    let conn = state.db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO mint_jobs (status, request, organization, scheduled_at, network, private_data)
+         VALUES (?1, ?2, ?3, datetime(?4), ?5, ?6)",
+        params![status, request_json.to_string(), organization, scheduled_at, request.network, sealed],
+    )
+    .map_err(|e| format!("Failed to create mint job: {}", e))?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get(db: &Db, id: i64) -> Result<Option<MintJob>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT id, status, request, result, error, payment_session, payment_status, created_at, updated_at,
+                organization, scheduled_at,
+                COALESCE(model_price_cents, CAST(ROUND(model_price * 100) AS INTEGER)),
+                COALESCE(consensus_price_cents, CAST(ROUND(consensus_price * 100) AS INTEGER)),
+                network, outlier,
+                COALESCE(approval_price_cents, CAST(ROUND(approval_price * 100) AS INTEGER)),
+                requested_by, approved_by, transaction_hash, private_data
+         FROM mint_jobs WHERE id = ?1",
+        params![id],
+        |row| {
+            let request: String = row.get(2)?;
+            let result: Option<String> = row.get(3)?;
+            let outlier: Option<String> = row.get(14)?;
+            Ok(MintJob {
+                id: row.get(0)?,
+                status: row.get(1)?,
+                request: serde_json::from_str(&request).unwrap_or_default(),
+                result: result.and_then(|r| serde_json::from_str(&r).ok()),
+                error: row.get(4)?,
+                payment_session: row.get(5)?,
+                payment_status: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+                organization: row.get(9)?,
+                scheduled_at: row.get(10)?,
+                model_price: usd(row.get(11)?),
+                consensus_price: usd(row.get(12)?),
+                network: row.get(13)?,
+                outlier: outlier.and_then(|o| serde_json::from_str(&o).ok()),
+                approval_price: usd(row.get(15)?),
+                requested_by: row.get(16)?,
+                approved_by: row.get(17)?,
+                transaction_hash: row.get(18)?,
+                private_data: row.get(19)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load mint job: {}", e))
+}
+
+/// Moves a job to `to` only if it is currently in `from`; returns whether it moved.
+pub fn transition(db: &Db, id: i64, from: &str, to: &str) -> Result<bool, String> {
+    if !can_transition(from, to) {
+        return Err(format!("Mint jobs cannot move from {} to {}", from, to));
+    }
+    let conn = db.lock().unwrap();
+    let updated = conn
+        .execute(
+            "UPDATE mint_jobs SET status = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = ?2",
+            params![id, from, to],
+        )
+        .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(updated == 1)
+}
+
+/// Parses a requested mint time into SQLite's UTC `datetime` format, rejecting past times.
+pub fn normalize_schedule(db: &Db, scheduled_at: &str) -> Result<String, ApiError> {
+    let conn = db.lock().unwrap();
+    let (normalized, future): (Option<String>, Option<bool>) = conn
+        .query_row("SELECT datetime(?1), datetime(?1) > datetime('now')", params![scheduled_at], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| format!("Failed to parse scheduled_at: {}", e))?;
+    match (normalized, future) {
+        (Some(normalized), Some(true)) => Ok(normalized),
+        (Some(_), _) => Err(ApiError::new(StatusCode::BAD_REQUEST, "scheduled_at must be in the future")),
+        (None, _) => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("scheduled_at must be an ISO 8601 timestamp. Found: {}", scheduled_at),
+        )),
+    }
+}
+
+/// Moves a paid job on: to a second user if it needs approval, to the appraisers if it still
+/// needs their consensus, to the scheduler if its time hasn't come yet, otherwise straight to a
+/// worker.
+pub fn release(state: &AppState, id: i64) -> Result<bool, String> {
+    let status = {
+        let conn = state.db.lock().unwrap();
+        let updated = conn
+            .execute(
+                "UPDATE mint_jobs
+                 SET status = CASE
+                         WHEN approval_price IS NOT NULL AND approved_by IS NULL THEN ?6
+                         WHEN model_price IS NOT NULL AND consensus_price IS NULL THEN ?5
+                         WHEN scheduled_at > datetime('now') THEN ?3
+                         ELSE ?4
+                     END,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1 AND status = ?2",
+                params![id, AWAITING_PAYMENT, SCHEDULED, QUEUED, AWAITING_APPRAISALS, PENDING_APPROVAL],
+            )
+            .map_err(|e| format!("Failed to update mint job: {}", e))?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        conn.query_row("SELECT status FROM mint_jobs WHERE id = ?1", params![id], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to load mint job: {}", e))?
+    };
+    if status == QUEUED {
+        spawn(state.clone(), id);
+    }
+    Ok(true)
+}
+
+/// Sends a job an admin approved in review on to the scheduler or a worker, minting it with
+/// `force` so the outlier check doesn't stop it again; returns whether it moved.
+pub fn release_reviewed(state: &AppState, id: i64) -> Result<bool, String> {
+    let status = {
+        let conn = state.db.lock().unwrap();
+        let updated = conn
+            .execute(
+                "UPDATE mint_jobs
+                 SET request = json_set(request, '$.force', json('true')),
+                     status = CASE WHEN scheduled_at > datetime('now') THEN ?3 ELSE ?4 END,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1 AND status = ?2",
+                params![id, AWAITING_REVIEW, SCHEDULED, QUEUED],
+            )
+            .map_err(|e| format!("Failed to update mint job: {}", e))?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        conn.query_row("SELECT status FROM mint_jobs WHERE id = ?1", params![id], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to load mint job: {}", e))?
+    };
+    if status == QUEUED {
+        spawn(state.clone(), id);
+    }
+    Ok(true)
+}
+
+/// Records `approver`'s approval of a high-value job and moves it on to the appraisers or an
+/// admin's review if it still needs them, to the scheduler if its time hasn't come yet,
+/// otherwise to a worker; returns whether it moved.
+pub fn approve(state: &AppState, id: i64, approver: &str) -> Result<bool, String> {
+    let status = {
+        let conn = state.db.lock().unwrap();
+        let updated = conn
+            .execute(
+                "UPDATE mint_jobs
+                 SET approved_by = ?3,
+                     approved_at = CURRENT_TIMESTAMP,
+                     status = CASE
+                         WHEN model_price IS NOT NULL AND consensus_price IS NULL THEN ?4
+                         WHEN outlier IS NOT NULL AND json_extract(request, '$.force') IS NOT 1 THEN ?5
+                         WHEN scheduled_at > datetime('now') THEN ?6
+                         ELSE ?7
+                     END,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?1 AND status = ?2",
+                params![id, PENDING_APPROVAL, approver, AWAITING_APPRAISALS, AWAITING_REVIEW, SCHEDULED, QUEUED],
+            )
+            .map_err(|e| format!("Failed to update mint job: {}", e))?;
+        if updated == 0 {
+            return Ok(false);
+        }
+        conn.query_row("SELECT status FROM mint_jobs WHERE id = ?1", params![id], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("Failed to load mint job: {}", e))?
+    };
+    if status == QUEUED {
+        spawn(state.clone(), id);
+    }
+    Ok(true)
+}
+
+pub fn set_payment(db: &Db, id: i64, session: Option<&str>, status: &str) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_jobs SET payment_session = COALESCE(?2, payment_session), payment_status = ?3,
+             updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![id, session, status],
+    )
+    .map_err(|e| format!("Failed to update mint job payment: {}", e))?;
+    Ok(())
+}
+
+fn finish(db: &Db, id: i64, result: Result<Value, String>) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    let (status, result, error) = match result {
+        Ok(result) => (MINTED, Some(result.to_string()), None),
+        Err(error) => (FAILED, None, Some(error)),
+    };
+    conn.execute(
+        "UPDATE mint_jobs SET status = ?2, result = ?3, error = ?4, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id, status, result, error],
+    )
+    .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(())
+}
+
+/// Mint job prices are in USD, the price model's currency.
+fn usd(cents: Option<i64>) -> Option<Money> {
+    cents.map(|cents| Money::new(cents, Currency::USD))
+}
+
+/// Marks a job as needing appraiser consensus before it can be minted.
+pub fn require_consensus(db: &Db, id: i64, model_price: Money) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_jobs SET model_price = ?2, model_price_cents = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id, model_price.to_f64(), model_price.cents],
+    )
+    .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(())
+}
+
+/// Marks a job as needing a second user's approval before it can be minted.
+pub fn require_approval(db: &Db, id: i64, model_price: Money, requested_by: Option<&str>) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_jobs
+         SET approval_price = ?2, approval_price_cents = ?3, requested_by = ?4, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?1",
+        params![id, model_price.to_f64(), model_price.cents, requested_by],
+    )
+    .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(())
+}
+
+pub fn set_error(db: &Db, id: i64, error: &str) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_jobs SET error = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id, error],
+    )
+    .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(())
+}
+
+/// Whether the job's organization has the async_mint feature, which hands jobs to Redis.
+fn queued_async(db: &Db, id: i64) -> bool {
+    let organization = get(db, id).ok().flatten().and_then(|job| job.organization);
+    features::enabled(db, features::ASYNC_MINT, organization.as_deref())
+}
+
+/// Runs a queued job in the background: on the shared Redis queue when there is one, else in this process.
+pub fn spawn(state: AppState, id: i64) {
+    tokio::spawn(async move {
+        if let Some(queue) = state.queue.as_ref().filter(|_| queued_async(&state.db, id)) {
+            match queue.push(id).await {
+                Ok(()) => return,
+                Err(e) => eprintln!("{}; running it in this process instead", e),
+            }
+        }
+        if let Err(e) = run(&state, id).await {
+            eprintln!("Mint job {} could not be processed: {}", id, e);
+        }
+    });
+}
+
+/// The tenant and request a job mints for, with the request's private fields unsealed.
+fn prepare(state: &AppState, job: &MintJob) -> Result<(Tenant, MintRequest), String> {
+    let tenant = match &job.organization {
+        Some(org) => Tenant(Some(organizations::load(&state.db, org)?.ok_or("Mint job organization was deleted")?)),
+        None => Tenant(None),
+    };
+    let mut request = job.request.clone();
+    if let Some(sealed) = &job.private_data {
+        private::unseal(state.config.private.as_ref(), &mut request, sealed)?;
+    }
+    let request =
+        serde_json::from_value(request).map_err(|e| format!("Invalid stored mint request: {}", e))?;
+    Ok((tenant, request))
+}
+
+/// Processes a queued job once a MINT_CONCURRENCY slot is free. Safe to call more than once:
+/// only the caller that moves it to `minting` runs it.
+pub async fn run(state: &AppState, id: i64) -> Result<(), String> {
+    let _slot = state.mint_slots.slot().await;
+    if maintenance::current(&state.db).is_some() {
+        if transition(&state.db, id, QUEUED, HELD)? {
+            println!("Mint job {} is held until maintenance ends", id);
+        }
+        return Ok(());
+    }
+    if !transition(&state.db, id, QUEUED, MINTING)? {
+        return Ok(());
+    }
+    let job = get(&state.db, id)?.ok_or("Mint job disappeared")?;
+
+    println!("Processing mint job {}...", id);
+    // Whatever happens from here, the job leaves `minting`, and every failure is dead-lettered.
+    let minted = match prepare(state, &job) {
+        Ok((tenant, request)) => {
+            // Appraisers have the last word; otherwise the job mints at the price its approver saw.
+            let agreed_price = job
+                .consensus_price
+                .map(|price| AgreedPrice { price, source: "consensus" })
+                .or(job.approval_price.map(|price| AgreedPrice { price, source: "approval" }));
+            mint::execute(state, &tenant, request, agreed_price, true).await
+        }
+        Err(e) => Err(ApiError::from(e)),
+    };
+    let result = match minted {
+        Ok(response) => {
+            if let Err(e) = dead_letters::resolve(&state.db, id) {
+                eprintln!("{}", e);
+            }
+            Ok(serde_json::to_value(response).unwrap_or_default())
+        }
+        Err(err) => {
+            eprintln!("Mint job {} failed: {}", id, err.message);
+            let hash = err.details.as_ref().and_then(|details| details["transaction_hash"].as_str());
+            if let Some(hash) = hash {
+                if let Err(e) = set_transaction_hash(&state.db, id, hash) {
+                    eprintln!("{}", e);
+                }
+            }
+            if let Err(e) = dead_letters::record(&state.db, &job, &err) {
+                eprintln!("{}", e);
+            }
+            Err(err.message)
+        }
+    };
+    finish(&state.db, id, result)
+}
+
+fn set_transaction_hash(db: &Db, id: i64, hash: &str) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_jobs SET transaction_hash = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id, hash],
+    )
+    .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(())
+}
+
+/// The transaction a failed job broadcast, unless its receipt shows it reverted: until then it
+/// may still mint, and retrying the job could mint the property twice.
+pub async fn unsettled_transaction(state: &AppState, job: &MintJob) -> Result<Option<String>, ApiError> {
+    let Some(hash) = &job.transaction_hash else {
+        return Ok(None);
+    };
+    let parsed: H256 = hash.parse().map_err(|_| format!("Invalid stored transaction hash {}", hash))?;
+    let receipt = state
+        .on_network(job.network.as_deref())?
+        .client
+        .get_transaction_receipt(parsed)
+        .await
+        .map_err(|e| format!("Failed to read receipt of {}: {}", hash, e))?;
+    let reverted = receipt.and_then(|receipt| receipt.status).is_some_and(|status| status.is_zero());
+    Ok((!reverted).then(|| hash.clone()))
+}
+
+/// Puts a failed job back in the queue, clearing its error; returns whether it moved. Callers
+/// check `unsettled_transaction` first.
+pub fn requeue_failed(db: &Db, id: i64) -> Result<bool, String> {
+    let conn = db.lock().unwrap();
+    let updated = conn
+        .execute(
+            "UPDATE mint_jobs SET status = ?3, error = NULL, result = NULL, transaction_hash = NULL,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND status = ?2",
+            params![id, FAILED, QUEUED],
+        )
+        .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(updated == 1)
+}
+
+fn due(db: &Db) -> Result<Vec<i64>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare("SELECT id FROM mint_jobs WHERE status = ?1 AND scheduled_at <= datetime('now') ORDER BY scheduled_at")
+        .and_then(|mut stmt| stmt.query_map(params![SCHEDULED], |row| row.get(0))?.collect())
+        .map_err(|e| format!("Failed to load scheduled mint jobs: {}", e))
+}
+
+/// Hands scheduled jobs to workers once their time comes.
+pub fn run_scheduler(state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+        loop {
+            interval.tick().await;
+            let due = match due(&state.db) {
+                Ok(due) => due,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
+            for id in due {
+                match transition(&state.db, id, SCHEDULED, QUEUED) {
+                    Ok(true) => {
+                        println!("Scheduled mint job {} is due", id);
+                        spawn(state.clone(), id);
+                    }
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Scheduled mint job {} could not be queued: {}", id, e),
+                }
+            }
+        }
+    });
+}
+
+pub async fn get_job(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+) -> Result<Json<MintJob>, ApiError> {
+    get(&state.db, id)?
+        .filter(|job| job.organization.as_deref() == tenant.organization_id())
+        .map(Json)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Mint job {} not found", id)))
+}
+
+fn invalid_transition(job: &MintJob, action: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        format!("Mint job {} is {} and cannot be {}", job.id, job.status, action),
+    )
+    .with_details(serde_json::json!({ "status": job.status }))
+}
+
+/// Cancels a job that hasn't been picked up by a worker yet, expiring its checkout session if it has one.
+pub async fn cancel_job(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+) -> Result<Json<MintJob>, ApiError> {
+    let Json(job) = get_job(State(state.clone()), tenant, Path(id)).await?;
+    if !can_transition(&job.status, CANCELLED) || !transition(&state.db, id, &job.status, CANCELLED)? {
+        // Also covers losing a race with a worker or the payment webhook.
+        let job = get(&state.db, id)?.unwrap_or(job);
+        return Err(invalid_transition(&job, "cancelled"));
+    }
+    if let (Some(stripe), Some(session)) = (&state.config.stripe, &job.payment_session) {
+        if job.status == AWAITING_PAYMENT {
+            if let Err(e) = payments::expire_checkout(&state, stripe, session).await {
+                eprintln!("Checkout session {} for cancelled mint job {} was not expired: {}", session, id, e);
+            }
+        }
+    }
+    println!("Cancelled mint job {}", id);
+    Ok(Json(get(&state.db, id)?.ok_or("Mint job disappeared")?))
+}
+
+/// Requeues a failed job with its original request, unless its transaction may still mint.
+pub async fn retry_job(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+) -> Result<Json<MintJob>, ApiError> {
+    let Json(job) = get_job(State(state.clone()), tenant, Path(id)).await?;
+    if job.status == FAILED {
+        if let Some(hash) = unsettled_transaction(&state, &job).await? {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Mint job {} broadcast {}, which hasn't reverted; retrying could mint twice", id, hash),
+            )
+            .with_details(serde_json::json!({ "transaction_hash": hash })));
+        }
+    }
+    if job.status != FAILED || !requeue_failed(&state.db, id)? {
+        let job = get(&state.db, id)?.unwrap_or(job);
+        return Err(invalid_transition(&job, "retried"));
+    }
+    println!("Retrying mint job {}", id);
+    spawn(state.clone(), id);
+    Ok(Json(get(&state.db, id)?.ok_or("Mint job disappeared")?))
+}
@@ -0,0 +1,75 @@
+use super::{
+    can_transition, AWAITING_APPRAISALS, AWAITING_PAYMENT, AWAITING_REVIEW, CANCELLED, FAILED, HELD, MINTED,
+    MINTING, PAYMENT_EXPIRED, PENDING_APPROVAL, QUEUED, SCHEDULED, TRANSITIONS,
+};
+
+const STATUSES: &[&str] = &[
+    AWAITING_PAYMENT,
+    PENDING_APPROVAL,
+    AWAITING_APPRAISALS,
+    AWAITING_REVIEW,
+    SCHEDULED,
+    QUEUED,
+    HELD,
+    MINTING,
+    MINTED,
+    FAILED,
+    PAYMENT_EXPIRED,
+    CANCELLED,
+];
+
+#[test]
+fn finished_jobs_never_move() {
+    for from in [MINTED, PAYMENT_EXPIRED, CANCELLED] {
+        for to in STATUSES {
+            assert!(!can_transition(from, to), "{} -> {}", from, to);
+        }
+    }
+}
+
+#[test]
+fn only_a_worker_moves_jobs_into_or_out_of_minting() {
+    for from in STATUSES {
+        assert_eq!(can_transition(from, MINTING), *from == QUEUED, "{} -> minting", from);
+    }
+    for to in STATUSES {
+        assert_eq!(can_transition(MINTING, to), [MINTED, FAILED].contains(to), "minting -> {}", to);
+    }
+}
+
+#[test]
+fn jobs_can_be_cancelled_until_a_worker_picks_them_up() {
+    for from in STATUSES {
+        let cancellable = ![MINTING, MINTED, FAILED, PAYMENT_EXPIRED, CANCELLED].contains(from);
+        assert_eq!(can_transition(from, CANCELLED), cancellable, "{} -> cancelled", from);
+    }
+}
+
+#[test]
+fn nothing_returns_to_awaiting_payment_and_only_failures_are_retried() {
+    for from in STATUSES {
+        assert!(!can_transition(from, AWAITING_PAYMENT), "{} -> awaiting_payment", from);
+    }
+    assert!(can_transition(FAILED, QUEUED));
+    assert!(!can_transition(FAILED, MINTING));
+    assert!(can_transition(HELD, QUEUED));
+}
+
+#[test]
+fn no_job_stays_put_or_moves_through_unknown_statuses() {
+    for status in STATUSES {
+        assert!(!can_transition(status, status), "{} -> {}", status, status);
+        assert!(!can_transition(status, "unknown"));
+        assert!(!can_transition("unknown", status));
+    }
+}
+
+#[test]
+fn transitions_only_name_known_statuses() {
+    for (from, next) in TRANSITIONS {
+        assert!(STATUSES.contains(from), "{}", from);
+        for to in *next {
+            assert!(STATUSES.contains(to), "{} -> {}", from, to);
+        }
+    }
+}
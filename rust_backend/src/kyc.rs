@@ -0,0 +1,237 @@
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use ethers::types::Address;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+pub const PENDING: &str = "pending";
+pub const VERIFIED: &str = "verified";
+pub const REJECTED: &str = "rejected";
+
+/// Identity verification providers that report results by webhook. The provider's
+/// reference/external user ID must be set to the wallet address being verified.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KycProvider {
+    Persona,
+    Sumsub,
+}
+
+pub struct KycConfig {
+    pub provider: KycProvider,
+    /// Swapped by a config reload, so the secret can be rotated without a restart.
+    pub webhook_secret: RwLock<String>,
+}
+
+#[derive(Serialize)]
+pub struct Verification {
+    pub address: String,
+    pub status: String,
+    pub provider: Option<String>,
+    pub reference: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SetVerification {
+    status: String,
+    reference: Option<String>,
+}
+
+/// A verification result extracted from a provider webhook.
+struct KycEvent {
+    address: Address,
+    status: &'static str,
+    reference: Option<String>,
+}
+
+impl KycConfig {
+    /// The KYC gate is enabled by setting KYC_PROVIDER (`persona` or `sumsub`).
+    pub fn from_env() -> Option<Self> {
+        let provider = match optional_env("KYC_PROVIDER")?.to_lowercase().as_str() {
+            "persona" => KycProvider::Persona,
+            "sumsub" => KycProvider::Sumsub,
+            other => panic!("Unsupported KYC_PROVIDER: {} (expected persona or sumsub)", other),
+        };
+        println!("KYC_PROVIDER: {:?} (mints require a verified recipient)", provider);
+        Some(KycConfig {
+            provider,
+            webhook_secret: RwLock::new(env::var("KYC_WEBHOOK_SECRET").expect("KYC_WEBHOOK_SECRET is not set in .env")),
+        })
+    }
+}
+
+impl KycProvider {
+    fn name(self) -> &'static str {
+        match self {
+            KycProvider::Persona => "persona",
+            KycProvider::Sumsub => "sumsub",
+        }
+    }
+
+    fn verify(self, headers: &HeaderMap, body: &[u8], secret: &str) -> bool {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default();
+        match self {
+            KycProvider::Persona => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+                // Persona sends one signature per active secret, separated by spaces during rotation.
+                header("Persona-Signature")
+                    .split(' ')
+                    .any(|signature| auth::verify_timestamped_signature(signature, body, secret, now))
+            }
+            KycProvider::Sumsub => {
+                let expected = auth::hmac_sha256_hex(secret, &[body]);
+                auth::constant_time_eq(header("X-Payload-Digest").as_bytes(), expected.as_bytes())
+            }
+        }
+    }
+
+    /// Extracts the verification outcome, or `None` for events that don't change it.
+    fn parse(self, event: &Value) -> Option<KycEvent> {
+        let (address, status, reference) = match self {
+            KycProvider::Persona => {
+                let inquiry = &event["data"]["attributes"]["payload"]["data"];
+                let status = match event["data"]["attributes"]["name"].as_str()? {
+                    "inquiry.approved" => VERIFIED,
+                    "inquiry.declined" | "inquiry.failed" => REJECTED,
+                    "inquiry.created" | "inquiry.completed" | "inquiry.marked-for-review" => PENDING,
+                    _ => return None,
+                };
+                (inquiry["attributes"]["reference-id"].as_str()?, status, inquiry["id"].as_str())
+            }
+            KycProvider::Sumsub => {
+                let status = match event["type"].as_str()? {
+                    "applicantReviewed" => match event["reviewResult"]["reviewAnswer"].as_str()? {
+                        "GREEN" => VERIFIED,
+                        _ => REJECTED,
+                    },
+                    "applicantCreated" | "applicantPending" | "applicantOnHold" => PENDING,
+                    _ => return None,
+                };
+                (event["externalUserId"].as_str()?, status, event["applicantId"].as_str())
+            }
+        };
+        Some(KycEvent {
+            address: address.parse().ok()?,
+            status,
+            reference: reference.map(str::to_string),
+        })
+    }
+}
+
+fn store(db: &Db, address: Address, status: &str, provider: &str, reference: Option<&str>) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO kyc_verifications (address, status, provider, reference) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (address) DO UPDATE SET status = ?2, provider = ?3,
+             reference = COALESCE(?4, reference), updated_at = CURRENT_TIMESTAMP",
+        params![format!("{:?}", address), status, provider, reference],
+    )
+    .map_err(|e| format!("Failed to store KYC status: {}", e))?;
+    Ok(())
+}
+
+pub fn status(db: &Db, address: Address) -> Result<Verification, String> {
+    let address = format!("{:?}", address);
+    let conn = db.lock().unwrap();
+    let stored = conn
+        .query_row(
+            "SELECT status, provider, reference, updated_at FROM kyc_verifications WHERE address = ?1",
+            params![address],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load KYC status: {}", e))?;
+    Ok(match stored {
+        Some((status, provider, reference, updated_at)) => Verification {
+            address,
+            status,
+            provider,
+            reference,
+            updated_at,
+        },
+        None => Verification {
+            address,
+            status: "unverified".to_string(),
+            provider: None,
+            reference: None,
+            updated_at: None,
+        },
+    })
+}
+
+/// Blocks mints to recipients without a verified identity when KYC is enabled.
+pub fn require_verified(state: &AppState, recipient: Address) -> Result<(), ApiError> {
+    if state.config.kyc.is_none() {
+        return Ok(());
+    }
+    let verification = status(&state.db, recipient)?;
+    if verification.status != VERIFIED {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("Recipient {:?} has not completed identity verification", recipient),
+        )
+        .with_details(serde_json::json!({ "kyc_status": verification.status })));
+    }
+    Ok(())
+}
+
+pub async fn get_status(
+    State(state): State<AppState>,
+    Path(address): Path<Address>,
+) -> Result<Json<Verification>, ApiError> {
+    Ok(Json(status(&state.db, address)?))
+}
+
+pub async fn kyc_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, ApiError> {
+    let kyc = state
+        .config
+        .kyc
+        .as_ref()
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "KYC is not enabled"))?;
+    if !kyc.provider.verify(&headers, &body, &kyc.webhook_secret.read().unwrap()) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid KYC webhook signature"));
+    }
+    let event: Value = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid webhook payload: {}", e)))?;
+
+    if let Some(event) = kyc.provider.parse(&event) {
+        store(&state.db, event.address, event.status, kyc.provider.name(), event.reference.as_deref())?;
+        println!("KYC status for {:?} is now {}", event.address, event.status);
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Manual override for verifications completed outside the provider.
+pub async fn set_status(
+    State(state): State<AppState>,
+    Path(address): Path<Address>,
+    Json(body): Json<SetVerification>,
+) -> Result<Json<Verification>, ApiError> {
+    let status = [PENDING, VERIFIED, REJECTED]
+        .into_iter()
+        .find(|status| *status == body.status)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid status {}; expected pending, verified or rejected", body.status),
+            )
+        })?;
+    store(&state.db, address, status, "manual", body.reference.as_deref())?;
+    Ok(Json(self::status(&state.db, address)?))
+}
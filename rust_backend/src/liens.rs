@@ -0,0 +1,240 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::H256;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::money::{Currency, Money};
+use crate::nfts;
+use crate::organizations::Tenant;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::valuations;
+use crate::CollectionQuery;
+
+/// Attributes describing the token's liens, rebuilt whenever one is attached or released.
+const LIEN_TRAITS: &[&str] = &["Liens", "Lien Holder", "Lien Balance"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LienPolicy {
+    /// Refuse transfers, listings and fractionalization while a lien is active.
+    Block,
+    /// Only annotate the metadata.
+    Allow,
+}
+
+impl LienPolicy {
+    pub fn from_env() -> Self {
+        match optional_env("LIEN_TRANSFER_POLICY").as_deref() {
+            None | Some("block") => LienPolicy::Block,
+            Some("allow") => LienPolicy::Allow,
+            Some(policy) => panic!("LIEN_TRANSFER_POLICY must be block or allow. Found: {}", policy),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AttachLien {
+    lender: String,
+    amount: f64,
+    /// Hash of the recorded mortgage or lien document.
+    document_hash: H256,
+}
+
+#[derive(Serialize)]
+pub struct Lien {
+    pub id: i64,
+    pub lender: String,
+    pub amount: f64,
+    pub document_hash: String,
+    pub active: bool,
+    pub transaction_hash: String,
+    pub release_transaction_hash: Option<String>,
+    pub created_at: String,
+    pub released_at: Option<String>,
+}
+
+fn load(db: &Db, collection: &str, token_id: u64, active_only: bool) -> Result<Vec<Lien>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare(
+        "SELECT id, lender, amount, document_hash, released_at IS NULL, transaction_hash, release_transaction_hash,
+                created_at, released_at
+         FROM liens WHERE collection = ?1 AND token_id = ?2 AND (released_at IS NULL OR NOT ?3)
+         ORDER BY id",
+    )
+    .and_then(|mut stmt| {
+        stmt.query_map(params![collection, token_id, active_only], |row| {
+            Ok(Lien {
+                id: row.get(0)?,
+                lender: row.get(1)?,
+                amount: row.get(2)?,
+                document_hash: row.get(3)?,
+                active: row.get(4)?,
+                transaction_hash: row.get(5)?,
+                release_transaction_hash: row.get(6)?,
+                created_at: row.get(7)?,
+                released_at: row.get(8)?,
+            })
+        })?
+        .collect()
+    })
+    .map_err(|e| format!("Failed to load liens: {}", e))
+}
+
+/// Replaces the lien attributes in `metadata` with the token's active liens.
+pub fn annotate(db: &Db, collection: &str, token_id: u64, metadata: &mut Value) -> Result<(), String> {
+    let liens = load(db, collection, token_id, true)?;
+    let Some(attributes) = metadata["attributes"].as_array_mut() else { return Ok(()) };
+    attributes.retain(|attribute| !LIEN_TRAITS.contains(&attribute["trait_type"].as_str().unwrap_or_default()));
+    if liens.is_empty() {
+        return Ok(());
+    }
+    attributes.push(json!({ "trait_type": "Liens", "value": liens.len() }));
+    for lien in &liens {
+        attributes.push(json!({ "trait_type": "Lien Holder", "value": lien.lender }));
+    }
+    let balance: f64 = liens.iter().map(|lien| lien.amount).sum();
+    attributes.push(json!({ "trait_type": "Lien Balance", "value": balance }));
+    Ok(())
+}
+
+/// Refuses to move a token with an active lien unless LIEN_TRANSFER_POLICY allows it.
+pub fn require_unencumbered(state: &AppState, collection: &str, token_id: u64) -> Result<(), ApiError> {
+    if state.config.lien_policy == LienPolicy::Allow {
+        return Ok(());
+    }
+    let liens = load(&state.db, collection, token_id, true)?;
+    if liens.is_empty() {
+        return Ok(());
+    }
+    Err(ApiError::new(
+        StatusCode::CONFLICT,
+        format!("Token {} has an active lien and cannot be transferred", token_id),
+    )
+    .with_details(json!({ "liens": liens.iter().map(|lien| lien.id).collect::<Vec<_>>() })))
+}
+
+/// Rewrites the token's on-chain metadata after its liens changed.
+async fn publish(state: &AppState, collection: &Collection, token_id: u64) -> Result<String, ApiError> {
+    let stored = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT COALESCE(price_cents, CAST(ROUND(price * 100) AS INTEGER)), metadata FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection.name, token_id],
+            |row| Ok((Money::new(row.get(0)?, Currency::USD), row.get::<_, String>(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+    };
+    let (price, metadata) = stored.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} was not minted through this service", token_id))
+    })?;
+    let mut metadata: Value = serde_json::from_str(&metadata).map_err(|e| format!("Invalid stored metadata: {}", e))?;
+    annotate(&state.db, &collection.name, token_id, &mut metadata)?;
+    let receipt = valuations::write_metadata(state, &Tenant(None), collection, token_id, price, &metadata).await?;
+    nfts::spawn_marketplace_refresh(state, collection, token_id);
+    Ok(format!("{:?}", receipt.transaction_hash))
+}
+
+pub async fn list(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<Lien>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    Ok(Json(load(&state.db, &collection.name, token_id, false)?))
+}
+
+pub async fn attach(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<AttachLien>,
+) -> Result<(StatusCode, Json<Lien>), ApiError> {
+    if request.lender.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "lender is required"));
+    }
+    if !(request.amount.is_finite() && request.amount > 0.0) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "amount must be a positive number"));
+    }
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let id = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO liens (collection, token_id, lender, amount, document_hash) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![collection.name, token_id, request.lender, request.amount, format!("{:?}", request.document_hash)],
+        )
+        .map_err(|e| format!("Failed to attach lien: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    println!("Attaching lien {} from {} to token {} of {}...", id, request.lender, token_id, collection.name);
+    let transaction_hash = match publish(&state, &collection, token_id).await {
+        Ok(hash) => hash,
+        Err(err) => {
+            // Not on-chain, so the lien never took effect.
+            let conn = state.db.lock().unwrap();
+            conn.execute("DELETE FROM liens WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to roll back lien {}: {}", id, e))?;
+            return Err(err);
+        }
+    };
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute("UPDATE liens SET transaction_hash = ?2 WHERE id = ?1", params![id, transaction_hash])
+            .map_err(|e| format!("Failed to record lien {}: {}", id, e))?;
+    }
+    let lien = load(&state.db, &collection.name, token_id, false)?
+        .into_iter()
+        .find(|lien| lien.id == id)
+        .ok_or("Lien disappeared")?;
+    Ok((StatusCode::CREATED, Json(lien)))
+}
+
+pub async fn release(
+    State(state): State<AppState>,
+    Path((token_id, id)): Path<(u64, i64)>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Lien>, ApiError> {
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    let released = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE liens SET released_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND collection = ?2 AND token_id = ?3 AND released_at IS NULL",
+            params![id, collection.name, token_id],
+        )
+        .map_err(|e| format!("Failed to release lien: {}", e))?
+    };
+    if released == 0 {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("Token {} has no active lien {}", token_id, id),
+        ));
+    }
+    println!("Releasing lien {} on token {} of {}...", id, token_id, collection.name);
+    let transaction_hash = match publish(&state, &collection, token_id).await {
+        Ok(hash) => hash,
+        Err(err) => {
+            let conn = state.db.lock().unwrap();
+            conn.execute("UPDATE liens SET released_at = NULL WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to restore lien {}: {}", id, e))?;
+            return Err(err);
+        }
+    };
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute("UPDATE liens SET release_transaction_hash = ?2 WHERE id = ?1", params![id, transaction_hash])
+            .map_err(|e| format!("Failed to record lien {}: {}", id, e))?;
+    }
+    let lien = load(&state.db, &collection.name, token_id, false)?
+        .into_iter()
+        .find(|lien| lien.id == id)
+        .ok_or("Lien disappeared")?;
+    Ok(Json(lien))
+}
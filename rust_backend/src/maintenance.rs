@@ -0,0 +1,190 @@
+use axum::extract::State;
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::jobs;
+use crate::state::AppState;
+
+const DEFAULT_RETRY_AFTER_SECS: u64 = 600;
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// New mints become held jobs that run once maintenance ends.
+    Queue,
+    /// New mints are refused with 503 and Retry-After.
+    Reject,
+}
+
+#[derive(Serialize)]
+pub struct Maintenance {
+    pub mode: Mode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub retry_after_secs: u64,
+    pub started_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct MaintenanceRequest {
+    enabled: bool,
+    #[serde(default = "queue")]
+    mode: Mode,
+    /// What clients are told to wait, in seconds; defaults to 10 minutes.
+    retry_after_secs: Option<u64>,
+    /// Shown to clients, e.g. "Contract migration".
+    reason: Option<String>,
+}
+
+fn queue() -> Mode {
+    Mode::Queue
+}
+
+#[derive(Serialize)]
+pub struct MaintenanceStatus {
+    enabled: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    maintenance: Option<Maintenance>,
+    /// Mint jobs waiting for maintenance to end.
+    held_jobs: usize,
+    /// Jobs handed back to the workers when maintenance ended.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resumed_jobs: Option<usize>,
+}
+
+/// The maintenance window in effect, if any. It's kept in the database so a restart during a
+/// migration doesn't reopen minting.
+pub fn current(db: &Db) -> Option<Maintenance> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT mode, reason, retry_after_secs, started_at FROM maintenance WHERE id = 1",
+        [],
+        |row| {
+            let mode: String = row.get(0)?;
+            Ok(Maintenance {
+                mode: if mode == "reject" { Mode::Reject } else { Mode::Queue },
+                reason: row.get(1)?,
+                retry_after_secs: row.get(2)?,
+                started_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to load maintenance mode: {}", e);
+        None
+    })
+}
+
+fn held(db: &Db) -> Result<Vec<i64>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare("SELECT id FROM mint_jobs WHERE status = ?1 ORDER BY id")
+        .and_then(|mut stmt| stmt.query_map(params![jobs::HELD], |row| row.get(0))?.collect())
+        .map_err(|e| format!("Failed to load held mint jobs: {}", e))
+}
+
+/// Hands jobs held during maintenance to the workers, oldest first. Does nothing while
+/// maintenance is still on.
+pub fn resume_held(state: &AppState) -> Result<usize, String> {
+    if current(&state.db).is_some() {
+        return Ok(0);
+    }
+    let mut resumed = 0;
+    for id in held(&state.db)? {
+        if jobs::transition(&state.db, id, jobs::HELD, jobs::QUEUED)? {
+            jobs::spawn(state.clone(), id);
+            resumed += 1;
+        }
+    }
+    if resumed > 0 {
+        println!("Resumed {} mint jobs held during maintenance", resumed);
+    }
+    Ok(resumed)
+}
+
+fn unavailable(maintenance: &Maintenance) -> Response {
+    let message = match &maintenance.reason {
+        Some(reason) => format!("The API is in maintenance ({}); try again later", reason),
+        None => "The API is in maintenance; try again later".to_string(),
+    };
+    let mut response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message)
+        .with_details(json!({ "maintenance": maintenance }))
+        .into_response();
+    response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(maintenance.retry_after_secs));
+    response
+}
+
+/// Route layer for writes that can't wait for maintenance to end: refused with 503.
+pub async fn reject_writes<B>(State(state): State<AppState>, request: Request<B>, next: Next<B>) -> Response {
+    match current(&state.db) {
+        Some(maintenance) => unavailable(&maintenance),
+        None => next.run(request).await,
+    }
+}
+
+/// Route layer for mints: refused with 503 in reject mode, otherwise let through to be held.
+pub async fn reject_mints<B>(State(state): State<AppState>, request: Request<B>, next: Next<B>) -> Response {
+    match current(&state.db) {
+        Some(maintenance) if maintenance.mode == Mode::Reject => unavailable(&maintenance),
+        _ => next.run(request).await,
+    }
+}
+
+fn status(state: &AppState, resumed_jobs: Option<usize>) -> Result<MaintenanceStatus, String> {
+    let maintenance = current(&state.db);
+    Ok(MaintenanceStatus {
+        enabled: maintenance.is_some(),
+        maintenance,
+        held_jobs: held(&state.db)?.len(),
+        resumed_jobs,
+    })
+}
+
+pub async fn get_maintenance(State(state): State<AppState>) -> Result<Json<MaintenanceStatus>, ApiError> {
+    Ok(Json(status(&state, None)?))
+}
+
+/// Starts or ends maintenance. Reads keep working throughout; ending it runs the held mints.
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(request): Json<MaintenanceRequest>,
+) -> Result<Json<MaintenanceStatus>, ApiError> {
+    if !request.enabled {
+        {
+            let conn = state.db.lock().unwrap();
+            conn.execute("DELETE FROM maintenance", [])
+                .map_err(|e| format!("Failed to end maintenance: {}", e))?;
+        }
+        println!("Maintenance ended");
+        let resumed = resume_held(&state)?;
+        return Ok(Json(status(&state, Some(resumed))?));
+    }
+    let retry_after_secs = request.retry_after_secs.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO maintenance (id, mode, reason, retry_after_secs) VALUES (1, ?1, ?2, ?3)
+             ON CONFLICT(id) DO UPDATE SET mode = ?1, reason = ?2, retry_after_secs = ?3",
+            params![
+                if request.mode == Mode::Reject { "reject" } else { "queue" },
+                request.reason,
+                retry_after_secs
+            ],
+        )
+        .map_err(|e| format!("Failed to start maintenance: {}", e))?;
+    }
+    println!(
+        "Maintenance started: new mints are {}{}",
+        if request.mode == Mode::Reject { "rejected" } else { "held" },
+        request.reason.as_deref().map(|reason| format!(" ({})", reason)).unwrap_or_default()
+    );
+    Ok(Json(status(&state, None)?))
+}
@@ -0,0 +1,153 @@
+use rusqlite::{params, OptionalExtension};
+use std::f64::consts::PI;
+
+use crate::config::optional_env;
+use crate::mint::HouseDetails;
+use crate::private::{self, PublicLocation};
+use crate::state::AppState;
+
+const MAPBOX_STATIC_URL: &str = "https://api.mapbox.com/styles/v1";
+const OSM_TILE_URL: &str = "https://tile.openstreetmap.org";
+/// Rendered size of the thumbnail, in pixels; Mapbox is asked for twice that for sharpness.
+pub const THUMBNAIL_SIZE: u32 = 240;
+
+enum Provider {
+    Mapbox { token: String, style: String },
+    Osm { tile_url: String },
+}
+
+pub struct MapConfig {
+    provider: Provider,
+    zoom: u8,
+    /// Centre the map on a grid cell this many kilometres across, without a pin, instead of
+    /// pinning the house. Follows PUBLIC_LOCATION.
+    blur_km: Option<f64>,
+    pin_color: String,
+}
+
+impl MapConfig {
+    /// Enabled by MAP_PROVIDER, mapbox (with MAPBOX_TOKEN) or osm. The map gives the location
+    /// away, so it is pinned only when PUBLIC_LOCATION is exact, blurred to the same grid when it
+    /// is fuzzed, and left out when it is hidden.
+    pub fn from_env(location: &PublicLocation) -> Option<Self> {
+        let provider = match optional_env("MAP_PROVIDER")?.as_str() {
+            "mapbox" => Provider::Mapbox {
+                token: optional_env("MAPBOX_TOKEN").expect("MAP_PROVIDER=mapbox needs MAPBOX_TOKEN"),
+                style: optional_env("MAPBOX_STYLE").unwrap_or_else(|| "mapbox/streets-v12".to_string()),
+            },
+            "osm" => Provider::Osm {
+                tile_url: optional_env("OSM_TILE_URL").unwrap_or_else(|| OSM_TILE_URL.to_string()),
+            },
+            other => panic!("MAP_PROVIDER must be mapbox or osm. Found: {}", other),
+        };
+        let zoom = optional_env("MAP_ZOOM")
+            .map(|zoom| zoom.parse().ok().filter(|zoom| *zoom <= 19).expect("MAP_ZOOM must be a number from 0 to 19"))
+            .unwrap_or(15);
+        let blur_km = match location {
+            PublicLocation::Hidden => {
+                println!("MAP_PROVIDER: ignored while PUBLIC_LOCATION is hidden");
+                return None;
+            }
+            PublicLocation::Fuzzed(km) => Some(*km),
+            PublicLocation::Exact => None,
+        };
+        let config = MapConfig {
+            provider,
+            zoom,
+            blur_km,
+            pin_color: optional_env("MAP_PIN_COLOR").unwrap_or_else(|| "f59e0b".to_string()),
+        };
+        println!(
+            "MAP_PROVIDER: {} (zoom {}, {})",
+            match config.provider {
+                Provider::Mapbox { .. } => "mapbox",
+                Provider::Osm { .. } => "osm",
+            },
+            config.zoom,
+            match config.blur_km {
+                Some(km) => format!("blurred to ~{} km", km),
+                None => "exact pin".to_string(),
+            }
+        );
+        Some(config)
+    }
+}
+
+/// A PNG map of the property's surroundings.
+pub struct Thumbnail {
+    pub png: Vec<u8>,
+    /// Where to draw the pin, as fractions of the width and height, when the provider didn't.
+    pub pin: Option<(f64, f64)>,
+}
+
+/// The OSM tile containing the point, and the point's position within it.
+fn tile(lat: f64, long: f64, zoom: u8) -> (u32, u32, f64, f64) {
+    let tiles = f64::from(1u32 << zoom);
+    let x = (long + 180.0) / 360.0 * tiles;
+    let lat = lat.clamp(-85.0511, 85.0511).to_radians();
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * tiles;
+    (x as u32, y as u32, x.fract(), y.fract())
+}
+
+/// The URL to fetch, a cache key that leaves out any access token, and where the pin goes.
+fn request(config: &MapConfig, lat: f64, long: f64) -> (String, String, Option<(f64, f64)>) {
+    let pinned = config.blur_km.is_none();
+    match &config.provider {
+        Provider::Mapbox { token, style } => {
+            let overlay = if pinned { format!("pin-s+{}({},{})/", config.pin_color, long, lat) } else { String::new() };
+            let url = format!(
+                "{}/{}/static/{}{},{},{}/{}x{}@2x",
+                MAPBOX_STATIC_URL, style, overlay, long, lat, config.zoom, THUMBNAIL_SIZE, THUMBNAIL_SIZE
+            );
+            (format!("{}?access_token={}", url, token), url, None)
+        }
+        Provider::Osm { tile_url } => {
+            let (x, y, pin_x, pin_y) = tile(lat, long, config.zoom);
+            let url = format!("{}/{}/{}/{}.png", tile_url.trim_end_matches('/'), config.zoom, x, y);
+            (url.clone(), url, pinned.then_some((pin_x, pin_y)))
+        }
+    }
+}
+
+/// The map thumbnail for the property, fetched once per location and then served from the
+/// database.
+pub async fn thumbnail(state: &AppState, config: &MapConfig, details: &HouseDetails) -> Result<Thumbnail, String> {
+    let (lat, long) = match config.blur_km {
+        Some(km) => private::fuzz(details.lat, details.long, km),
+        None => (details.lat, details.long),
+    };
+    let (url, key, pin) = request(config, lat, long);
+    let cached: Option<Vec<u8>> = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row("SELECT image FROM map_thumbnails WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to load map thumbnail: {}", e))?
+    };
+    if let Some(png) = cached {
+        return Ok(Thumbnail { png, pin });
+    }
+
+    let png = state
+        .http
+        .get(&url)
+        // OSM's tile usage policy asks for an identifying user agent.
+        .header("user-agent", concat!("rust_backend/", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch map thumbnail: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Map thumbnail request failed: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read map thumbnail: {}", e))?
+        .to_vec();
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO map_thumbnails (key, image) VALUES (?1, ?2)",
+            params![key, png],
+        )
+        .map_err(|e| format!("Failed to cache map thumbnail: {}", e))?;
+    }
+    Ok(Thumbnail { png, pin })
+}
@@ -0,0 +1,379 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::Escrow;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::liens;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::sanctions;
+use crate::simulation;
+use crate::state::AppState;
+use crate::tx::{self, TransactionResponse};
+
+#[derive(Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    Eth,
+    Usdc,
+}
+
+impl Currency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Currency::Eth => "ETH",
+            Currency::Usdc => "USDC",
+        }
+    }
+
+    fn parse(value: &str) -> Currency {
+        match value {
+            "USDC" => Currency::Usdc,
+            _ => Currency::Eth,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Listing {
+    pub id: i64,
+    pub collection: String,
+    pub token_id: u64,
+    pub seller: String,
+    /// Amount in the currency's base units (wei for ETH, 6 decimals for USDC).
+    pub price: String,
+    pub currency: Currency,
+    pub status: String,
+    pub created_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offers: Option<Vec<Offer>>,
+}
+
+#[derive(Serialize)]
+pub struct Offer {
+    pub id: i64,
+    pub listing_id: i64,
+    pub buyer: String,
+    pub amount: String,
+    pub status: String,
+    pub created_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct CreateListing {
+    collection: Option<String>,
+    token_id: u64,
+    price: String,
+    currency: Currency,
+}
+
+#[derive(Deserialize)]
+pub struct CreateOffer {
+    buyer: Address,
+    amount: String,
+}
+
+#[derive(Deserialize)]
+pub struct ListingQuery {
+    status: Option<String>,
+    collection: Option<String>,
+}
+
+const LISTING_COLUMNS: &str = "id, collection, token_id, seller, price, currency, status, created_at";
+
+fn row_to_listing(row: &rusqlite::Row) -> rusqlite::Result<Listing> {
+    Ok(Listing {
+        id: row.get(0)?,
+        collection: row.get(1)?,
+        token_id: row.get(2)?,
+        seller: row.get(3)?,
+        price: row.get(4)?,
+        currency: Currency::parse(&row.get::<_, String>(5)?),
+        status: row.get(6)?,
+        created_at: row.get(7)?,
+        offers: None,
+    })
+}
+
+fn row_to_offer(row: &rusqlite::Row) -> rusqlite::Result<Offer> {
+    Ok(Offer {
+        id: row.get(0)?,
+        listing_id: row.get(1)?,
+        buyer: row.get(2)?,
+        amount: row.get(3)?,
+        status: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+fn parse_amount(value: &str, field: &str) -> Result<U256, ApiError> {
+    match U256::from_dec_str(value) {
+        Ok(amount) if !amount.is_zero() => Ok(amount),
+        _ => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("{} must be a positive integer amount in base units", field),
+        )),
+    }
+}
+
+fn not_found(what: &str, id: i64) -> ApiError {
+    ApiError::new(StatusCode::NOT_FOUND, format!("{} {} not found", what, id))
+}
+
+pub fn active_listing(db: &Db, collection: &str, token_id: u64) -> Result<Option<Listing>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM listings WHERE collection = ?1 AND token_id = ?2
+             AND status IN ('active', 'pending_settlement')",
+            LISTING_COLUMNS
+        ),
+        params![collection, token_id],
+        row_to_listing,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load listing: {}", e))
+}
+
+fn load_listing(db: &Db, id: i64) -> Result<Listing, ApiError> {
+    let conn = db.lock().unwrap();
+    let mut listing = conn
+        .query_row(
+            &format!("SELECT {} FROM listings WHERE id = ?1", LISTING_COLUMNS),
+            params![id],
+            row_to_listing,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load listing: {}", e))?
+        .ok_or_else(|| not_found("Listing", id))?;
+
+    let mut stmt = conn
+        .prepare("SELECT id, listing_id, buyer, amount, status, created_at FROM offers WHERE listing_id = ?1 ORDER BY id")
+        .map_err(|e| format!("Failed to load offers: {}", e))?;
+    let offers = stmt
+        .query_map(params![id], row_to_offer)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load offers: {}", e))?;
+    listing.offers = Some(offers);
+    Ok(listing)
+}
+
+pub async fn create_listing(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Json(request): Json<CreateListing>,
+) -> Result<Json<Listing>, ApiError> {
+    parse_amount(&request.price, "price")?;
+    let collection = registry::resolve_for(&state.db, &tenant, request.collection.as_deref())?;
+    liens::require_unencumbered(&state, &collection.name, request.token_id)?;
+    if active_listing(&state.db, &collection.name, request.token_id)?.is_some() {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Token {} is already listed", request.token_id)));
+    }
+
+    // The current on-chain owner is the seller; settlement requires their approval of the escrow.
+    let seller = state
+        .nft(&collection)?
+        .owner_of(U256::from(request.token_id))
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read token owner", e))?;
+
+    let id = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO listings (collection, token_id, seller, price, currency) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                collection.name,
+                request.token_id,
+                format!("{:?}", seller),
+                request.price,
+                request.currency.as_str()
+            ],
+        )
+        .map_err(|e| format!("Failed to create listing: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    println!("Listed token {} of {} as listing {}", request.token_id, collection.name, id);
+    Ok(Json(load_listing(&state.db, id)?))
+}
+
+pub async fn list_listings(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(query): Query<ListingQuery>,
+) -> Result<Json<Vec<Listing>>, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM listings
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR collection = ?2)
+               AND collection IN (SELECT name FROM collections WHERE organization IS ?3)
+             ORDER BY id DESC",
+            LISTING_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to list listings: {}", e))?;
+    let listings = stmt
+        .query_map(params![query.status, query.collection, tenant.organization_id()], row_to_listing)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to list listings: {}", e))?;
+    Ok(Json(listings))
+}
+
+/// Loads a listing in a collection visible to the tenant.
+fn load_listing_for(db: &Db, tenant: &Tenant, id: i64) -> Result<Listing, ApiError> {
+    let listing = load_listing(db, id)?;
+    registry::resolve_for(db, tenant, Some(&listing.collection)).map_err(|_| not_found("Listing", id))?;
+    Ok(listing)
+}
+
+pub async fn get_listing(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+) -> Result<Json<Listing>, ApiError> {
+    Ok(Json(load_listing_for(&state.db, &tenant, id)?))
+}
+
+pub async fn cancel_listing(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Json<Listing>, ApiError> {
+    let updated = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE listings SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'active'",
+            params![id],
+        )
+        .and_then(|updated| {
+            conn.execute(
+                "UPDATE offers SET status = 'rejected' WHERE listing_id = ?1 AND status = 'open'",
+                params![id],
+            )?;
+            Ok(updated)
+        })
+        .map_err(|e| format!("Failed to cancel listing: {}", e))?
+    };
+    if updated == 0 {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Listing {} is not active", id)));
+    }
+    Ok(Json(load_listing(&state.db, id)?))
+}
+
+pub async fn make_offer(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(id): Path<i64>,
+    Json(request): Json<CreateOffer>,
+) -> Result<Json<Listing>, ApiError> {
+    parse_amount(&request.amount, "amount")?;
+    let listing = load_listing_for(&state.db, &tenant, id)?;
+    if listing.status != "active" {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Listing {} is not accepting offers", id)));
+    }
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO offers (listing_id, buyer, amount) VALUES (?1, ?2, ?3)",
+            params![id, format!("{:?}", request.buyer), request.amount],
+        )
+        .map_err(|e| format!("Failed to create offer: {}", e))?;
+    }
+    Ok(Json(load_listing(&state.db, id)?))
+}
+
+pub async fn accept_offer(State(state): State<AppState>, Path(offer_id): Path<i64>) -> Result<Json<Listing>, ApiError> {
+    let listing_id = {
+        let mut conn = state.db.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| format!("Failed to accept offer: {}", e))?;
+        let listing_id: i64 = tx
+            .query_row(
+                "SELECT o.listing_id FROM offers o JOIN listings l ON l.id = o.listing_id
+                 WHERE o.id = ?1 AND o.status = 'open' AND l.status = 'active'",
+                params![offer_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to accept offer: {}", e))?
+            .ok_or_else(|| ApiError::new(StatusCode::CONFLICT, format!("Offer {} is not open", offer_id)))?;
+        tx.execute("UPDATE offers SET status = 'accepted' WHERE id = ?1", params![offer_id])
+            .and_then(|_| {
+                tx.execute(
+                    "UPDATE offers SET status = 'rejected' WHERE listing_id = ?1 AND id != ?2 AND status = 'open'",
+                    params![listing_id, offer_id],
+                )
+            })
+            .and_then(|_| {
+                tx.execute(
+                    "UPDATE listings SET status = 'pending_settlement', updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                    params![listing_id],
+                )
+            })
+            .map_err(|e| format!("Failed to accept offer: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to accept offer: {}", e))?;
+        listing_id
+    };
+    println!("Accepted offer {} on listing {}", offer_id, listing_id);
+    Ok(Json(load_listing(&state.db, listing_id)?))
+}
+
+/// Settles an accepted offer through the escrow contract, which moves the NFT to the
+/// buyer and the payment (held in escrow or pulled from the buyer) to the seller.
+pub async fn settle(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Json<TransactionResponse>, ApiError> {
+    let escrow_address = state.config.escrow_address.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_IMPLEMENTED, "Settlement is disabled; set ESCROW_ADDRESS")
+    })?;
+    let listing = load_listing(&state.db, id)?;
+    if listing.status != "pending_settlement" {
+        return Err(ApiError::new(StatusCode::CONFLICT, format!("Listing {} has no accepted offer", id)));
+    }
+    let offer = listing
+        .offers
+        .iter()
+        .flatten()
+        .find(|offer| offer.status == "accepted")
+        .ok_or("Listing is pending settlement without an accepted offer")?;
+
+    let payment_token = match listing.currency {
+        Currency::Eth => Address::zero(),
+        Currency::Usdc => state.config.usdc_address.ok_or_else(|| {
+            ApiError::new(StatusCode::NOT_IMPLEMENTED, "USDC settlement is disabled; set USDC_ADDRESS")
+        })?,
+    };
+    let collection = registry::resolve(&state.db, Some(&listing.collection))?;
+    // The escrow and USDC_ADDRESS are on the primary chain.
+    if let Some(network) = &collection.network {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("ESCROW_ADDRESS is on the primary chain, but {} is on {}", collection.name, network),
+        ));
+    }
+    liens::require_unencumbered(&state, &collection.name, listing.token_id)?;
+    let parse = |value: &str| value.parse::<Address>().map_err(|_| format!("Invalid stored address {}", value));
+    let buyer = parse(&offer.buyer)?;
+    sanctions::screen(&state, buyer, "settlement").await?;
+
+    let escrow = Escrow::new(escrow_address, state.client.clone());
+    let call = escrow.settle(
+        collection.address,
+        U256::from(listing.token_id),
+        parse(&listing.seller)?,
+        buyer,
+        payment_token,
+        parse_amount(&offer.amount, "amount")?,
+    );
+    println!("Settling listing {} through escrow...", id);
+    let receipt = tx::submit(&state, call).await?;
+
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE listings SET status = 'sold', settlement_tx = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![id, format!("{:?}", receipt.transaction_hash)],
+        )
+        .and_then(|_| conn.execute("UPDATE offers SET status = 'settled' WHERE id = ?1", params![offer.id]))
+        .map_err(|e| format!("Failed to record settlement: {}", e))?;
+    }
+    Ok(Json(tx::response(&state, &receipt)))
+}
@@ -0,0 +1,69 @@
+use ethers::types::{Address, H256};
+use ethers::utils::keccak256;
+
+#[cfg(test)]
+mod tests;
+
+/// Merkle tree compatible with OpenZeppelin's `MerkleProof.verify`: leaves are
+/// `keccak256(abi.encodePacked(address))` and pairs are hashed in sorted order.
+pub struct MerkleTree {
+    layers: Vec<Vec<H256>>,
+}
+
+pub fn leaf(address: Address) -> H256 {
+    H256::from(keccak256(address.as_bytes()))
+}
+
+fn hash_pair(a: H256, b: H256) -> H256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut packed = [0u8; 64];
+    packed[..32].copy_from_slice(first.as_bytes());
+    packed[32..].copy_from_slice(second.as_bytes());
+    H256::from(keccak256(packed))
+}
+
+impl MerkleTree {
+    pub fn new(addresses: &[Address]) -> Self {
+        let mut leaves: Vec<H256> = addresses.iter().copied().map(leaf).collect();
+        leaves.sort();
+        leaves.dedup();
+
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash_pair(*a, *b),
+                    // An odd node is promoted to the next layer unchanged.
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+        MerkleTree { layers }
+    }
+
+    pub fn root(&self) -> H256 {
+        self.layers.last().and_then(|layer| layer.first()).copied().unwrap_or_default()
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn proof(&self, address: Address) -> Option<Vec<H256>> {
+        let mut index = self.layers[0].binary_search(&leaf(address)).ok()?;
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            let sibling = index ^ 1;
+            if let Some(node) = layer.get(sibling) {
+                proof.push(*node);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
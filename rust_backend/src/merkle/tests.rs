@@ -0,0 +1,68 @@
+use ethers::types::{Address, H256};
+
+use super::{hash_pair, leaf, MerkleTree};
+
+fn addresses(count: u64) -> Vec<Address> {
+    (1..=count).map(Address::from_low_u64_be).collect()
+}
+
+/// What OpenZeppelin's `MerkleProof.verify` does on-chain.
+fn verify(proof: &[H256], root: H256, address: Address) -> bool {
+    proof.iter().fold(leaf(address), |node, sibling| hash_pair(node, *sibling)) == root
+}
+
+#[test]
+fn every_member_has_a_proof_for_the_root() {
+    for count in 1..=9 {
+        let addresses = addresses(count);
+        let tree = MerkleTree::new(&addresses);
+        assert_eq!(tree.leaf_count(), addresses.len());
+        for address in &addresses {
+            let proof = tree.proof(*address).unwrap();
+            assert!(verify(&proof, tree.root(), *address), "{} leaves, {:?}", count, address);
+        }
+    }
+}
+
+#[test]
+fn outsiders_have_no_proof_and_members_proofs_do_not_transfer() {
+    let tree = MerkleTree::new(&addresses(5));
+    let outsider = Address::from_low_u64_be(99);
+    assert_eq!(tree.proof(outsider), None);
+    let proof = tree.proof(Address::from_low_u64_be(1)).unwrap();
+    assert!(!verify(&proof, tree.root(), outsider));
+}
+
+#[test]
+fn single_leaf_is_its_own_root() {
+    let address = Address::from_low_u64_be(7);
+    let tree = MerkleTree::new(&[address]);
+    assert_eq!(tree.root(), leaf(address));
+    assert_eq!(tree.proof(address), Some(Vec::new()));
+}
+
+#[test]
+fn two_leaves_hash_in_sorted_order() {
+    let (a, b) = (Address::from_low_u64_be(1), Address::from_low_u64_be(2));
+    let root = MerkleTree::new(&[a, b]).root();
+    assert_eq!(root, hash_pair(leaf(a), leaf(b)));
+    assert_eq!(root, hash_pair(leaf(b), leaf(a)));
+}
+
+#[test]
+fn root_ignores_order_and_duplicates() {
+    let mut addresses = addresses(6);
+    let root = MerkleTree::new(&addresses).root();
+    addresses.reverse();
+    addresses.push(addresses[0]);
+    let tree = MerkleTree::new(&addresses);
+    assert_eq!(tree.root(), root);
+    assert_eq!(tree.leaf_count(), 6);
+}
+
+#[test]
+fn empty_tree_has_zero_root() {
+    let tree = MerkleTree::new(&[]);
+    assert_eq!(tree.root(), H256::zero());
+    assert_eq!(tree.proof(Address::zero()), None);
+}
@@ -0,0 +1,699 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use ethers::contract::parse_log;
+use ethers::types::{Address, TransactionReceipt, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+use crate::allowlist;
+use crate::approvals;
+use crate::attributes::{self, AttributeConfig};
+use crate::bindings;
+use crate::cards;
+use crate::consensus;
+use crate::enrichment;
+use crate::error::ApiError;
+use crate::events::{self, ReceiptEvent};
+use crate::explorer::ExplorerLinks;
+use crate::imputation;
+use crate::features;
+use crate::jobs;
+use crate::kyc;
+use crate::maintenance;
+use crate::money::{self, Currency, Money};
+use crate::nfts;
+use crate::organizations::{self, Caller, Tenant};
+use crate::outliers;
+use crate::parcels::{self, Verification};
+use crate::payload::Versioned;
+use crate::payments::{self, FeePayment, UnpaidFee};
+use crate::private;
+use crate::rarity;
+use crate::registry::{self, Collection};
+use crate::sanctions;
+use crate::state::AppState;
+use crate::stats;
+use crate::storage::{self, StorageEstimate};
+use crate::tx;
+use crate::valuations::{self, ValuationQuery, ValuationType};
+use crate::versions;
+
+#[cfg(test)]
+mod tests;
+
+pub const PRICE_MODEL_URL: &str = "http://127.0.0.1:5000/predict";
+pub const PRICE_MODEL_BATCH_URL: &str = "http://127.0.0.1:5000/predict-batch";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct HouseDetails {
+    pub name: String,
+    pub bedrooms: u64,
+    pub bathrooms: f64,
+    pub sqft_living: u64,
+    pub sqft_lot: u64,
+    pub floors: u64,
+    pub waterfront: u64,
+    pub view: u64,
+    pub condition: u64,
+    pub grade: u64,
+    pub sqft_above: u64,
+    pub sqft_basement: u64,
+    pub yr_built: u64,
+    pub yr_renovated: u64,
+    pub zipcode: u64,
+    pub lat: f64,
+    pub long: f64,
+    pub sqft_living15: u64,
+    pub sqft_lot15: u64,
+    pub month: u64,
+    pub year: u64,
+    /// URI of a property photo, e.g. from POST /images.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct MintRequest {
+    #[serde(flatten)]
+    pub details: HouseDetails,
+    pub collection: Option<String>,
+    /// One of NETWORKS to mint on instead of the primary chain. Without `collection`, mints to
+    /// the network's own contract.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    pub recipient: Option<Address>,
+    /// Account the ERC-20 mint fee is pulled from; defaults to the recipient.
+    pub payer: Option<Address>,
+    /// Mint through the non-transferable entry point so the token stays bound to the owner of record.
+    #[serde(default)]
+    pub soulbound: bool,
+    /// Mint at this time (ISO 8601, UTC unless an offset is given) instead of right away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_at: Option<String>,
+    /// Which metadata store the token URI points at; defaults to METADATA_STORAGE.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata_storage: Option<String>,
+    /// Kept encrypted off-chain and out of the metadata, e.g. the owner's contact details.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Mint even if the outlier check flags the predicted price, instead of waiting for review.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize)]
+pub struct MintResponse {
+    pub transaction_hash: String,
+    pub token_id: Option<String>,
+    pub collection: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    pub soulbound: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeePayment>,
+    /// The fee, if it couldn't be collected after the mint; it is recorded as owed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unpaid_fee: Option<UnpaidFee>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<ExplorerLinks>,
+    pub metadata_storage: StorageEstimate,
+    /// The receipt's logs, decoded against the collection's ABI.
+    pub events: Vec<ReceiptEvent>,
+    pub message: String,
+}
+
+struct MintPlan {
+    collection: Collection,
+    recipient: Address,
+    proof: Option<Vec<H256>>,
+}
+
+/// What a mint has to wait for before a worker may run it.
+pub struct Holds {
+    /// The model's price when appraisers have to agree on one.
+    model_price: Option<Money>,
+    /// Why the predicted price needs an admin's review.
+    outlier: Option<outliers::Outlier>,
+    /// The model's price when someone other than the requester has to approve the mint.
+    approval_price: Option<Money>,
+}
+
+impl Holds {
+    pub fn any(&self) -> bool {
+        self.model_price.is_some() || self.outlier.is_some() || self.approval_price.is_some()
+    }
+}
+
+/// Prices the property if consensus, approvals or the outlier check are configured, and works
+/// out which of them hold the mint.
+pub async fn holds(state: &AppState, tenant: &Tenant, request: &MintRequest) -> Result<Holds, ApiError> {
+    let predicted = if state.config.consensus.is_some()
+        || state.config.approvals.is_some()
+        || (state.config.outliers.is_some() && !request.force)
+    {
+        plan(state, tenant, request).await?;
+        Some(predict_price(state, &request.details).await?)
+    } else {
+        None
+    };
+    // High-value properties wait for appraisers to agree on a price before they are minted.
+    let model_price = predicted.filter(|price| consensus::required(state, *price));
+    // Implausible predictions wait for an admin, unless appraisers will set the price anyway.
+    let outlier = match predicted {
+        Some(price) if model_price.is_none() && !request.force => outliers::check(state, &request.details, price)?,
+        _ => None,
+    };
+    // High-value mints wait for someone other than the requester to approve them, before anything else.
+    let approval_price = predicted.filter(|price| approvals::required(state, *price));
+    Ok(Holds {
+        model_price,
+        outlier,
+        approval_price,
+    })
+}
+
+/// Records what a job waits for besides payment.
+fn apply_holds(state: &AppState, id: i64, holds: &Holds, caller: &Caller) -> Result<(), ApiError> {
+    if let Some(approval_price) = holds.approval_price {
+        jobs::require_approval(&state.db, id, approval_price, caller.0.as_deref())?;
+        println!("Mint job {} at {} is pending approval", id, approval_price);
+    }
+    if let Some(model_price) = holds.model_price {
+        jobs::require_consensus(&state.db, id, model_price)?;
+        println!("Mint job {} at {} is awaiting appraiser consensus", id, model_price);
+    }
+    if let Some(outlier) = &holds.outlier {
+        outliers::hold(&state.db, id, outlier)?;
+        println!("Mint job {} at {} is awaiting review: {}", id, outlier.price, outlier.reasons.join("; "));
+    }
+    Ok(())
+}
+
+/// Creates the job for a mint that has to wait, in the status of the first thing it waits for;
+/// a mint that is only scheduled waits for its time.
+pub fn create_held(
+    state: &AppState,
+    tenant: &Tenant,
+    caller: &Caller,
+    request: &MintRequest,
+    holds: &Holds,
+) -> Result<i64, ApiError> {
+    let status = if holds.approval_price.is_some() {
+        jobs::PENDING_APPROVAL
+    } else if holds.model_price.is_some() {
+        jobs::AWAITING_APPRAISALS
+    } else if holds.outlier.is_some() {
+        jobs::AWAITING_REVIEW
+    } else {
+        jobs::SCHEDULED
+    };
+    let id = jobs::create(state, request, status, tenant.organization_id())?;
+    apply_holds(state, id, holds, caller)?;
+    Ok(id)
+}
+
+pub async fn mint_nft(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    caller: Caller,
+    Json(Versioned(mut request)): Json<Versioned<MintRequest>>,
+) -> Result<Response, ApiError> {
+    if let Some(scheduled_at) = &request.scheduled_at {
+        request.scheduled_at = Some(jobs::normalize_schedule(&state.db, scheduled_at)?);
+    }
+    // Jobs record the chain they mint on even when only the collection was given.
+    request.network = target(&state, &tenant, &request)?.network;
+    let holds = holds(&state, &tenant, &request).await?;
+    if let Some(stripe) = &state.config.stripe {
+        // Nobody pays for a mint that may be rejected in review.
+        if let Some(outlier) = &holds.outlier {
+            return Err(outliers::rejection(outlier));
+        }
+        // Validate up front so nobody pays for a mint that can never succeed.
+        plan(&state, &tenant, &request).await?;
+        let checkout = payments::start_checkout(&state, stripe, &tenant, &request).await?;
+        apply_holds(&state, checkout.job_id, &holds, &caller)?;
+        return Ok((StatusCode::ACCEPTED, Json(checkout)).into_response());
+    }
+    if request.scheduled_at.is_some() || holds.any() {
+        plan(&state, &tenant, &request).await?;
+        let id = create_held(&state, &tenant, &caller, &request, &holds)?;
+        let job = jobs::get(&state.db, id)?.ok_or("Mint job disappeared")?;
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+    // Reject mode is refused by the route layer; queue mode holds the mint as a job.
+    if maintenance::current(&state.db).is_some() {
+        plan(&state, &tenant, &request).await?;
+        let id = jobs::create(&state, &request, jobs::HELD, tenant.organization_id())?;
+        println!("Mint job {} is held until maintenance ends", id);
+        let job = jobs::get(&state.db, id)?.ok_or("Mint job disappeared")?;
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+    Ok(Json(execute(&state, &tenant, request, None, false).await?).into_response())
+}
+
+/// The collection a mint goes to: the requested one, else the requested network's own.
+pub fn target(state: &AppState, tenant: &Tenant, request: &MintRequest) -> Result<Collection, ApiError> {
+    let network = request.network.as_deref();
+    state.on_network(network)?;
+    let collection = registry::resolve_for(&state.db, tenant, request.collection.as_deref().or(network))?;
+    if network.is_some() && collection.network.as_deref() != network {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("Collection {} is not on network {}", collection.name, network.unwrap_or_default()),
+        ));
+    }
+    if collection.network.is_some() {
+        features::require(state, tenant, features::L2_NETWORKS)?;
+    }
+    Ok(collection)
+}
+
+async fn plan(state: &AppState, tenant: &Tenant, request: &MintRequest) -> Result<MintPlan, ApiError> {
+    let collection = target(state, tenant, request)?;
+    state.config.storage.store_for(&state.db, tenant, request.metadata_storage.as_deref())?;
+    if request.private.is_some() && state.config.private.is_none() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Private fields need PRIVATE_DATA_KEY to be set"));
+    }
+    // Minting to ourselves means the wallet on the collection's chain.
+    let recipient = request.recipient.unwrap_or(state.on(&collection)?.client_for(tenant)?.address());
+    kyc::require_verified(state, recipient)?;
+    sanctions::screen(state, recipient, "mint").await?;
+    if request.soulbound && collection.allowlist.is_some() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "Soulbound mints are not supported for allowlisted collections",
+        ));
+    }
+    let proof = match &collection.allowlist {
+        Some(name) => Some(allowlist::proof_for(&state.db, name, recipient)?),
+        None => None,
+    };
+    Ok(MintPlan {
+        collection,
+        recipient,
+        proof,
+    })
+}
+
+/// A price settled before the mint, which replaces the model's prediction.
+#[derive(Clone, Copy)]
+pub struct AgreedPrice {
+    pub price: Money,
+    /// Who settled it, recorded as the valuation's source: `consensus` or `approval`.
+    pub source: &'static str,
+}
+
+/// Predicts the price, builds the metadata and mints the token with the tenant's wallet. An
+/// `agreed_price`, from appraiser consensus or a second user's approval, replaces the
+/// prediction. `queued` mints are jobs whose consensus, approval and outlier holds were settled
+/// when they were requested, so they aren't checked again.
+pub async fn execute(
+    state: &AppState,
+    tenant: &Tenant,
+    request: MintRequest,
+    agreed_price: Option<AgreedPrice>,
+    queued: bool,
+) -> Result<MintResponse, ApiError> {
+    let MintPlan {
+        collection,
+        recipient,
+        proof,
+    } = plan(state, tenant, &request).await?;
+    // Fees and records stay on the primary chain; only the mint goes to the collection's.
+    let chain = state.on(&collection)?;
+    let client = chain.client_for(tenant)?;
+    let payload = &request.details;
+    let payer = request.payer.unwrap_or(recipient);
+    if let Some(fee) = &state.config.token_fee {
+        payments::check_token_fee(state, fee, payer).await?;
+    }
+
+    let price = match agreed_price {
+        Some(agreed) => agreed.price,
+        None => predict_price(state, payload).await?,
+    };
+    let checked = queued || agreed_price.is_some();
+    if !checked && consensus::required(state, price) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("A property valued at {} needs appraiser consensus; mint it through the API", price),
+        ));
+    }
+    if !checked && approvals::required(state, price) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("A property valued at {} needs a second user's approval; mint it through the API", price),
+        ));
+    }
+    if !checked && !request.force {
+        if let Some(outlier) = outliers::check(state, payload, price)? {
+            return Err(outliers::rejection(&outlier));
+        }
+    }
+    let verification = parcels::verify(state, payload).await;
+    let mut metadata = public_metadata(state, payload, verification.as_ref(), price, request.soulbound).await;
+    rarity::annotate(state, &collection.name, None, &mut metadata)?;
+    let store = state.config.storage.store_for(&state.db, tenant, request.metadata_storage.as_deref())?;
+    cards::attach(state, Some(store), payload, price, &mut metadata).await?;
+
+    println!("Preparing transaction to mint NFT in collection {}...", collection.name);
+    let metadata_uri = storage::put(state, store, &metadata).await?;
+    let estimate = storage::estimate(&state.config.storage, store, &metadata_uri);
+    if let Some(warning) = &estimate.warning {
+        eprintln!("{}", warning);
+    }
+    let minted = match proof {
+        Some(proof) => {
+            let contract = bindings::AllowlistMint::new(collection.address, client);
+            let proof = proof.into_iter().map(|node| node.0).collect();
+            tx::submit(&chain, contract.mint_nft(recipient, metadata_uri.clone(), proof)).await
+        }
+        None if request.soulbound => {
+            let contract = bindings::SoulboundMint::new(collection.address, client);
+            tx::submit(&chain, contract.mint_soulbound(recipient, metadata_uri.clone())).await
+        }
+        None => {
+            let contract = state.nft_as(&collection, client)?;
+            tx::submit(&chain, contract.mint_nft(recipient, metadata_uri.clone())).await
+        }
+    };
+    let receipt = minted?;
+    let transaction_hash = format!("{:?}", receipt.transaction_hash);
+    let token_id = minted_token_id(&receipt, collection.address);
+    // The fee is only pulled once the mint has succeeded, so a failed mint costs the payer nothing.
+    // The allowance was checked up front; if it has gone since, the mint stands and the fee is
+    // recorded as owed.
+    let (fee, unpaid_fee) = match &state.config.token_fee {
+        Some(fee) => match payments::collect_token_fee(state, fee, payer).await {
+            Ok(fee) => (Some(fee), None),
+            Err(e) => {
+                eprintln!("Failed to collect the fee for mint {}: {}", transaction_hash, e.message);
+                let token_id = token_id.map(|id| id.as_u64());
+                let unpaid = payments::record_unpaid_fee(
+                    &state.db,
+                    &collection.name,
+                    fee,
+                    payer,
+                    token_id,
+                    &transaction_hash,
+                    &e.message,
+                );
+                (None, unpaid.map_err(|e| eprintln!("{}", e)).ok())
+            }
+        },
+        None => (None, None),
+    };
+    let events = match state.artifacts.abi(&collection.contract_name, collection.contract_version.as_deref()) {
+        Ok(abi) => events::decode_receipt(&abi, &receipt),
+        Err(_) => events::decode_receipt(&bindings::REALESTATENFT_ABI, &receipt),
+    };
+    let mut details = serde_json::to_value(payload).unwrap_or_default();
+    let sealed = private::seal(state.config.private.as_ref(), &mut details, request.private.as_ref())?;
+
+    println!("NFT minted successfully with transaction hash: {}", transaction_hash);
+
+    // The mint is already on-chain, so bookkeeping failures are logged rather than returned.
+    let recorded = match token_id {
+        Some(id) => nfts::record_mint(
+            &state.db,
+            nfts::NewMint {
+                collection: &collection.name,
+                token_id: id.as_u64(),
+                recipient,
+                receipt: &receipt,
+                price,
+                details,
+                metadata: &metadata,
+                metadata_storage: store.name(),
+                token_uri: (!storage::is_inline(store)).then_some(metadata_uri.as_str()),
+                soulbound: request.soulbound,
+            },
+        ),
+        None => Err("no Transfer event in receipt".to_string()),
+    };
+    let recorded = recorded.and_then(|_| {
+        valuations::record(
+            &state.db,
+            valuations::NewValuation {
+                collection: &collection.name,
+                token_id: token_id.unwrap_or_default().as_u64(),
+                price,
+                source: agreed_price.map_or("model", |agreed| agreed.source),
+                valuation_type: ValuationType::Market,
+                actor: "mint",
+                transaction_hash: Some(&transaction_hash),
+            },
+        )
+    });
+    let recorded = recorded.and_then(|_| {
+        let token_id = token_id.unwrap_or_default().as_u64();
+        versions::record(&state.db, &collection.name, token_id, &metadata, &transaction_hash)
+    });
+    let recorded = recorded.and_then(|_| match &sealed {
+        Some(sealed) => private::store(&state.db, &collection.name, token_id.unwrap_or_default().as_u64(), sealed),
+        None => Ok(()),
+    });
+    let recorded = recorded.and_then(|_| match &verification {
+        Some(verification) => {
+            parcels::record(&state.db, &collection.name, token_id.unwrap_or_default().as_u64(), verification)
+        }
+        None => Ok(()),
+    });
+    if let Err(e) = recorded {
+        eprintln!("Mint {} was not recorded: {}", transaction_hash, e);
+    }
+    if let Some(fee) = &fee {
+        let token_id = token_id.map(|id| id.as_u64());
+        if let Err(e) = payments::record_token_fee(&state.db, &collection.name, fee, token_id, &transaction_hash) {
+            eprintln!("Fee for mint {} was not recorded: {}", transaction_hash, e);
+        }
+    }
+
+    let response = MintResponse {
+        transaction_hash,
+        token_id: token_id.map(|id| id.to_string()),
+        links: chain
+            .chain
+            .explorer
+            .as_ref()
+            .map(|explorer| explorer.links(collection.address, Some(receipt.transaction_hash), token_id)),
+        collection: collection.name,
+        network: collection.network,
+        soulbound: request.soulbound,
+        fee,
+        unpaid_fee,
+        metadata_storage: estimate,
+        events,
+        message: "NFT minted successfully.".to_string(),
+    };
+    if let Some(org) = tenant.organization_id() {
+        organizations::notify(state, org, "mint.completed", serde_json::to_value(&response).unwrap_or_default());
+    }
+    Ok(response)
+}
+
+/// Prices a property without minting it.
+#[derive(Deserialize)]
+pub struct PreviewQuery {
+    #[serde(default)]
+    soulbound: bool,
+}
+
+/// The metadata minting `details` would publish, without minting.
+pub async fn preview_metadata(
+    State(state): State<AppState>,
+    Query(query): Query<PreviewQuery>,
+    Json(details): Json<HouseDetails>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let price = predict_price(&state, &details).await?;
+    let verification = parcels::verify(&state, &details).await;
+    let mut metadata = public_metadata(&state, &details, verification.as_ref(), price, query.soulbound).await;
+    cards::attach(&state, None, &details, price, &mut metadata).await?;
+    Ok(Json(metadata))
+}
+
+#[derive(Deserialize)]
+pub struct PredictQuery {
+    #[serde(flatten)]
+    valuation: ValuationQuery,
+    /// Also quote the price in this currency, at the current exchange rate.
+    currency: Option<String>,
+}
+
+/// The model's price, as a plain number in USD for existing clients and as `value` in cents-exact
+/// money, converted when `currency` is given. Property fields left out are filled in from the
+/// zipcode's medians and listed under `imputed`, as the estimate is less precise for them.
+pub async fn predict(
+    State(state): State<AppState>,
+    Query(query): Query<PredictQuery>,
+    Json(mut details): Json<serde_json::Map<String, serde_json::Value>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let imputed = imputation::impute(&state.db, &mut details)?;
+    let details: HouseDetails = serde_json::from_value(serde_json::Value::Object(details))
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid property details: {}", e)))?;
+    let valuation_type = query.valuation.valuation_type;
+    let price = valuations::predict(&state, &details, valuation_type).await?;
+    let mut value = price;
+    if let Some(currency) = &query.currency {
+        let currency = Currency::parse(currency).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+        value = money::convert(&state.db, value, currency)?;
+    }
+    Ok(Json(serde_json::json!({
+        "price": price.to_f64(),
+        "value": value,
+        "valuation_type": valuation_type,
+        "imputed": imputed,
+    })))
+}
+
+/// Asks the Python model service for the property's market price.
+pub async fn predict_price(state: &AppState, details: &HouseDetails) -> Result<Money, ApiError> {
+    predict_with(state, PRICE_MODEL_URL, details).await
+}
+
+/// Asks the model service at `url` for the property's price.
+pub async fn predict_with(state: &AppState, url: &str, details: &HouseDetails) -> Result<Money, ApiError> {
+    let started = Instant::now();
+    let result = call_price_model(state, url, details).await;
+    stats::record_prediction(&state.db, started.elapsed(), result.is_ok());
+    result
+}
+
+/// Prices several properties in one call to the model service, falling back to one call per
+/// property for model services without a batch endpoint.
+pub async fn predict_batch(state: &AppState, houses: &[HouseDetails]) -> Result<Vec<Money>, ApiError> {
+    let started = Instant::now();
+    println!("Calling Python API for {} price predictions...", houses.len());
+    let response = state
+        .http
+        .post(PRICE_MODEL_BATCH_URL)
+        .json(houses)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call Python API: {}", e))?;
+    if response.status() == StatusCode::NOT_FOUND {
+        let mut prices = Vec::with_capacity(houses.len());
+        for details in houses {
+            prices.push(predict_price(state, details).await?);
+        }
+        return Ok(prices);
+    }
+    let result = async {
+        let price_data: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Python API response: {}", e))?;
+        let prices = price_data["prices"]
+            .as_array()
+            .map(|prices| {
+                prices
+                    .iter()
+                    .filter_map(serde_json::Value::as_f64)
+                    .map(|price| Money::from_f64(price, Currency::USD))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|prices| prices.len() == houses.len())
+            .ok_or("Price predictions missing or invalid in response")?;
+        Ok(prices)
+    }
+    .await;
+    stats::record_prediction(&state.db, started.elapsed(), result.is_ok());
+    result
+}
+
+async fn call_price_model(state: &AppState, url: &str, details: &HouseDetails) -> Result<Money, ApiError> {
+    println!("Calling Python API for price prediction...");
+    let response = state
+        .http
+        .post(url)
+        .json(details)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call Python API: {}", e))?;
+    let price_data: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Python API response: {}", e))?;
+    let price = price_data["price"]
+        .as_f64()
+        .ok_or("Price prediction missing or invalid in response")?;
+    let price = Money::from_f64(price, Currency::USD);
+    println!("Price prediction received: {}", price);
+    Ok(price)
+}
+
+/// The metadata a token for `details` is minted or revalued with, including its enrichment
+/// and parcel verification attributes.
+pub async fn public_metadata(
+    state: &AppState,
+    details: &HouseDetails,
+    verification: Option<&Verification>,
+    price: Money,
+    soulbound: bool,
+) -> serde_json::Value {
+    let mut attributes = enrichment::enrich(state, details).await;
+    attributes.extend(verification.into_iter().flat_map(Verification::attributes));
+    attributes.extend(state.config.public_location.attributes(details));
+    build_metadata(&state.config.attributes, details, price, soulbound, attributes)
+}
+
+/// The token metadata stored on-chain as the token URI, with `enrichment` attributes appended.
+/// Pure, so the same inputs always give the same JSON; the golden tests rely on that.
+pub fn build_metadata(
+    config: &AttributeConfig,
+    details: &HouseDetails,
+    price: Money,
+    soulbound: bool,
+    enrichment: Vec<serde_json::Value>,
+) -> serde_json::Value {
+    let mut attributes = config.attributes(details);
+    // Marketplaces sort and filter numeric traits, so the price is published as a number.
+    attributes.push(serde_json::json!({ "trait_type": "Price", "value": price.to_f64() }));
+    attributes.extend(config.derived(details, price));
+    let mut metadata = serde_json::json!({
+        "name": details.name,
+        "description": description(details, price),
+        "attributes": attributes
+    });
+    if let Some(image) = &details.image {
+        metadata["image"] = serde_json::json!(image);
+    }
+    if soulbound {
+        metadata["attributes"]
+            .as_array_mut()
+            .unwrap()
+            .push(serde_json::json!({ "trait_type": "Soulbound", "value": true }));
+    }
+    metadata["attributes"].as_array_mut().unwrap().extend(enrichment);
+    metadata
+}
+
+fn description(details: &HouseDetails, price: Money) -> String {
+    format!("A {} bedroom house priced at ${}", details.bedrooms, price.to_f64())
+}
+
+/// Updates the price in metadata built by `build_metadata`, keeping its other attributes.
+pub fn reprice(metadata: &mut serde_json::Value, details: &HouseDetails, price: Money) {
+    metadata["description"] = serde_json::json!(description(details, price));
+    for attribute in metadata["attributes"].as_array_mut().into_iter().flatten() {
+        if attribute["trait_type"] == "Price" {
+            attribute["value"] = serde_json::json!(price.to_f64());
+        } else if attribute["trait_type"] == "Price per Sqft" {
+            attribute["value"] = serde_json::json!(attributes::price_per_sqft(details, price));
+        }
+    }
+}
+
+/// The token ID assigned by the contract, taken from the mint's `Transfer` event.
+pub fn minted_token_id(receipt: &TransactionReceipt, contract: Address) -> Option<U256> {
+    receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == contract)
+        .filter_map(|log| parse_log::<bindings::TransferFilter>(log.clone()).ok())
+        .find(|transfer| transfer.from == Address::zero())
+        .map(|transfer| transfer.token_id)
+}
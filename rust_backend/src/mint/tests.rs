@@ -0,0 +1,253 @@
+use proptest::prelude::*;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use super::{build_metadata, reprice, HouseDetails};
+use crate::attributes::{AttributeConfig, DEFAULT_FIELDS};
+use crate::money::{Currency, Money};
+
+const ALL_FIELDS: &str = "bedrooms,bathrooms,sqft_living,sqft_lot,floors,waterfront,view,condition,grade,\
+    sqft_above,sqft_basement,yr_built,yr_renovated,zipcode,sqft_living15,sqft_lot15,\
+    age,price_per_sqft,recently_renovated,basement";
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Checks `value` against the subset of JSON Schema the ERC-721 metadata schema uses.
+fn validate(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        };
+        let actual = match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+        if !types.contains(&actual) {
+            return Err(format!("{} is a {}, expected {}", path, actual, types.join(" or ")));
+        }
+    }
+    if let Value::Object(object) = value {
+        for required in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+            if !object.contains_key(required) {
+                return Err(format!("{} is missing {}", path, required));
+            }
+        }
+        for (key, field) in object {
+            match schema["properties"].get(key) {
+                Some(property) => validate(property, field, &format!("{}.{}", path, key))?,
+                None if schema["additionalProperties"] == false => {
+                    return Err(format!("{} has unexpected property {}", path, key))
+                }
+                None => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate(item_schema, item, &format!("{}[{}]", path, i))?;
+        }
+    }
+    Ok(())
+}
+
+fn assert_erc721(metadata: &Value) {
+    let schema = std::fs::read_to_string(golden_dir().join("erc721-metadata.schema.json")).unwrap();
+    let schema: Value = serde_json::from_str(&schema).unwrap();
+    if let Err(e) = validate(&schema, metadata, "metadata") {
+        panic!("{}\n{:#}", e, metadata);
+    }
+}
+
+/// Compares against tests/golden/metadata/`name`.json. UPDATE_GOLDEN=1 rewrites the file instead.
+fn assert_golden(name: &str, metadata: &Value) {
+    let path = golden_dir().join("metadata").join(format!("{}.json", name));
+    let rendered = format!("{:#}\n", metadata);
+    if std::env::var("UPDATE_GOLDEN").is_ok_and(|update| update == "1") {
+        std::fs::write(&path, rendered).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {}. Run with UPDATE_GOLDEN=1 to create it", path.display(), e));
+    assert_eq!(rendered, expected, "{} changed. Run with UPDATE_GOLDEN=1 if that is intended", path.display());
+}
+
+fn sample_house() -> HouseDetails {
+    HouseDetails {
+        name: "7129 Carleton Ave S".to_string(),
+        bedrooms: 3,
+        bathrooms: 2.25,
+        sqft_living: 2570,
+        sqft_lot: 7242,
+        floors: 2,
+        waterfront: 0,
+        view: 0,
+        condition: 3,
+        grade: 7,
+        sqft_above: 2170,
+        sqft_basement: 400,
+        yr_built: 1951,
+        yr_renovated: 2008,
+        zipcode: 98125,
+        lat: 47.721,
+        long: -122.319,
+        sqft_living15: 1690,
+        sqft_lot15: 7639,
+        month: 12,
+        year: 2014,
+        image: None,
+    }
+}
+
+#[test]
+fn default_fields_match_golden() {
+    let config = AttributeConfig::new(DEFAULT_FIELDS, 10, None);
+    let metadata = build_metadata(&config, &sample_house(), Money::new(53_800_000, Currency::USD), false, Vec::new());
+    assert_erc721(&metadata);
+    assert_golden("default_fields", &metadata);
+}
+
+#[test]
+fn all_fields_soulbound_with_image_and_enrichment_match_golden() {
+    let config = AttributeConfig::new(ALL_FIELDS, 10, None);
+    let details = HouseDetails {
+        image: Some("ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".to_string()),
+        ..sample_house()
+    };
+    let enrichment = vec![
+        json!({ "trait_type": "Walk Score", "value": 71 }),
+        json!({ "trait_type": "Parcel Verified", "value": true }),
+    ];
+    let metadata = build_metadata(&config, &details, Money::new(60_425_050, Currency::USD), true, enrichment);
+    assert_erc721(&metadata);
+    assert_golden("all_fields_soulbound", &metadata);
+}
+
+#[test]
+fn reprice_matches_golden() {
+    let config = AttributeConfig::new(DEFAULT_FIELDS, 10, None);
+    let mut metadata = build_metadata(&config, &sample_house(), Money::new(53_800_000, Currency::USD), false, Vec::new());
+    reprice(&mut metadata, &sample_house(), Money::new(61_250_000, Currency::USD));
+    assert_erc721(&metadata);
+    assert_golden("repriced", &metadata);
+}
+
+prop_compose! {
+    fn house()(
+        name in "[A-Za-z0-9 .,'#-]{0,40}",
+        bedrooms in 0u64..12,
+        half_baths in 0u64..16,
+        sqft_living in 0u64..20_000,
+        sqft_lot in 0u64..200_000,
+        (floors, waterfront, view, condition, grade) in (1u64..4, 0u64..2, 0u64..5, 1u64..6, 1u64..14),
+        sqft_basement in 0u64..5_000,
+        yr_built in 1850u64..2016,
+        renovated in proptest::option::of(1900u64..2016),
+        zipcode in 98001u64..98200,
+        (lat, long) in (47.0f64..48.0, -123.0f64..-121.0),
+        (month, year) in (1u64..13, 2000u64..2030),
+        image in proptest::option::of("ipfs://[a-z0-9]{10,46}"),
+    ) -> HouseDetails {
+        HouseDetails {
+            name,
+            bedrooms,
+            bathrooms: half_baths as f64 * 0.5,
+            sqft_living,
+            sqft_lot,
+            floors,
+            waterfront,
+            view,
+            condition,
+            grade,
+            sqft_above: sqft_living.saturating_sub(sqft_basement),
+            sqft_basement,
+            yr_built,
+            yr_renovated: renovated.unwrap_or(0),
+            zipcode,
+            lat,
+            long,
+            sqft_living15: sqft_living,
+            sqft_lot15: sqft_lot,
+            month,
+            year,
+            image,
+        }
+    }
+}
+
+/// Any non-empty selection of METADATA_FIELDS, in any order.
+fn fields() -> impl Strategy<Value = String> {
+    let all: Vec<&'static str> = ALL_FIELDS.split(',').collect();
+    proptest::sample::subsequence(all.clone(), 1..=all.len())
+        .prop_shuffle()
+        .prop_map(|fields| fields.join(","))
+}
+
+/// A USD price up to a billion dollars, to the cent.
+fn price() -> impl Strategy<Value = Money> {
+    (0i64..100_000_000_000).prop_map(|cents| Money::new(cents, Currency::USD))
+}
+
+fn trait_value<'a>(metadata: &'a Value, trait_type: &str) -> Option<&'a Value> {
+    metadata["attributes"]
+        .as_array()?
+        .iter()
+        .find(|attribute| attribute["trait_type"] == trait_type)
+        .map(|attribute| &attribute["value"])
+}
+
+proptest! {
+    #[test]
+    fn metadata_is_valid_erc721(
+        details in house(),
+        fields in fields(),
+        price in price(),
+        soulbound in any::<bool>(),
+    ) {
+        let config = AttributeConfig::new(&fields, 10, None);
+        let metadata = build_metadata(&config, &details, price, soulbound, Vec::new());
+        assert_erc721(&metadata);
+
+        prop_assert_eq!(&metadata["name"], &json!(details.name));
+        prop_assert_eq!(trait_value(&metadata, "Price"), Some(&json!(price.to_f64())));
+        prop_assert_eq!(trait_value(&metadata, "Soulbound").is_some(), soulbound);
+        prop_assert_eq!(metadata.get("image").and_then(Value::as_str), details.image.as_deref());
+
+        let attributes = metadata["attributes"].as_array().unwrap();
+        let mut trait_types: Vec<&str> = attributes.iter().filter_map(|a| a["trait_type"].as_str()).collect();
+        let published = trait_types.len();
+        trait_types.sort();
+        trait_types.dedup();
+        prop_assert_eq!(trait_types.len(), published, "duplicate trait types in {:#}", metadata);
+        // One trait per field, except derived ones that can't be computed, plus the price.
+        prop_assert!(published <= fields.split(',').count() + 1 + usize::from(soulbound));
+    }
+
+    #[test]
+    fn metadata_is_deterministic(details in house(), fields in fields(), price in price()) {
+        let config = AttributeConfig::new(&fields, 10, None);
+        prop_assert_eq!(
+            build_metadata(&config, &details, price, false, Vec::new()),
+            build_metadata(&config, &details, price, false, Vec::new())
+        );
+    }
+
+    #[test]
+    fn reprice_matches_a_fresh_build(
+        details in house(),
+        fields in fields(),
+        (old_price, new_price) in (price(), price()),
+        soulbound in any::<bool>(),
+    ) {
+        let config = AttributeConfig::new(&fields, 10, None);
+        let mut repriced = build_metadata(&config, &details, old_price, soulbound, Vec::new());
+        reprice(&mut repriced, &details, new_price);
+        prop_assert_eq!(repriced, build_metadata(&config, &details, new_price, soulbound, Vec::new()));
+    }
+}
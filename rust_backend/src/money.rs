@@ -0,0 +1,386 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::U256;
+use rusqlite::{params, OptionalExtension};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+#[cfg(test)]
+mod tests;
+
+/// Decimal places kept for exchange rates; as many as wei, so ETH can be quoted per USD.
+pub const RATE_DECIMALS: u32 = 18;
+
+/// An ISO 4217 currency code such as USD, or ETH.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// What the price model quotes in, and what exchange rates are relative to.
+    pub const USD: Currency = Currency(*b"USD");
+    /// Priced through an ETH rate per USD like any currency; its on-chain unit is wei.
+    pub const ETH: Currency = Currency(*b"ETH");
+
+    pub fn parse(code: &str) -> Result<Self, String> {
+        let code = code.trim().to_ascii_uppercase();
+        match <[u8; 3]>::try_from(code.as_bytes()) {
+            Ok(bytes) if bytes.iter().all(u8::is_ascii_uppercase) => Ok(Currency(bytes)),
+            _ => Err(format!("{} is not a three-letter currency code", code)),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        // Only ever built from ASCII letters.
+        std::str::from_utf8(&self.0).unwrap()
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Currency::parse(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// An amount in whole cents. Serialized as a decimal string, e.g.
+/// `{ "amount": "538000.00", "currency": "USD" }`, so no client reads it back through a float.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Money {
+    pub cents: i64,
+    pub currency: Currency,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MoneyJson {
+    amount: String,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(cents: i64, currency: Currency) -> Self {
+        Money { cents, currency }
+    }
+
+    /// Rounds a float amount, such as the price model's output, to the nearest cent. This is the
+    /// one place a float becomes money.
+    pub fn from_f64(amount: f64, currency: Currency) -> Self {
+        Money::new((amount * 100.0).round() as i64, currency)
+    }
+
+    /// A decimal amount such as `1250.5`, read exactly.
+    pub fn parse(amount: &str, currency: Currency) -> Result<Self, String> {
+        let cents = parse_fixed(amount, 2).map_err(|e| format!("Invalid amount {}: {}", amount, e))?;
+        let cents = i64::try_from(cents).map_err(|_| format!("Amount {} is too large", amount))?;
+        Ok(Money::new(cents, currency))
+    }
+
+    /// The amount times `factor`, such as a valuation multiplier, rounded to the cent.
+    pub fn scale(self, factor: f64) -> Money {
+        Money::new((self.cents as f64 * factor).round() as i64, self.currency)
+    }
+
+    /// The amount as a number, for token metadata and the price model's inputs. Exact to the cent
+    /// for any realistic property price.
+    pub fn to_f64(self) -> f64 {
+        self.cents as f64 / 100.0
+    }
+
+    /// The amount without the currency, e.g. `-12.05`.
+    pub fn amount(&self) -> String {
+        format_fixed(i128::from(self.cents), 2)
+    }
+
+    /// Converts with rates quoted per USD, rounding half away from zero to the cent.
+    fn convert(self, from_rate: u128, to: Currency, to_rate: u128) -> Money {
+        let cents = mul_div(U256::from(self.cents.unsigned_abs()), U256::from(to_rate), U256::from(from_rate));
+        let cents = cents.min(U256::from(i64::MAX)).as_u64() as i64;
+        Money::new(if self.cents < 0 { -cents } else { cents }, to)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.amount(), self.currency)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoneyJson {
+            amount: self.amount(),
+            currency: self.currency,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = MoneyJson::deserialize(deserializer)?;
+        Money::parse(&json.amount, json.currency).map_err(D::Error::custom)
+    }
+}
+
+/// `a * b / c`, rounding half up. Callers keep `a * b` far below 2^256.
+pub fn mul_div(a: U256, b: U256, c: U256) -> U256 {
+    (a * b + c / 2) / c
+}
+
+/// Reads a decimal string as an integer count of 10^-`decimals` units, refusing anything finer.
+pub fn parse_fixed(value: &str, decimals: u32) -> Result<i128, String> {
+    let value = value.trim();
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err("not a decimal number".to_string());
+    }
+    if fraction.len() > decimals as usize {
+        return Err(format!("more than {} decimal places", decimals));
+    }
+    let padded = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    let units: i128 = padded.parse().map_err(|_| "too large".to_string())?;
+    Ok(if negative { -units } else { units })
+}
+
+pub fn format_fixed(units: i128, decimals: u32) -> String {
+    let scale = 10i128.pow(decimals);
+    let sign = if units < 0 { "-" } else { "" };
+    let units = units.abs();
+    format!("{}{}.{:0width$}", sign, units / scale, units % scale, width = decimals as usize)
+}
+
+/// A rate without trailing zeros, e.g. `0.92`.
+fn format_rate(rate: u128) -> String {
+    let formatted = format_fixed(rate.min(i128::MAX as u128) as i128, RATE_DECIMALS);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn parse_rate(rate: &str) -> Result<u128, String> {
+    match parse_fixed(rate, RATE_DECIMALS).map(u128::try_from) {
+        Ok(Ok(rate)) if rate > 0 => Ok(rate),
+        Ok(_) => Err(format!("Exchange rate {} must be positive", rate)),
+        Err(e) => Err(format!("Invalid exchange rate {}: {}", rate, e)),
+    }
+}
+
+#[derive(Serialize)]
+pub struct ExchangeRate {
+    currency: Currency,
+    /// Units of the currency per USD.
+    rate: String,
+    /// `env` for EXCHANGE_RATES, `admin` for rates set through the API.
+    source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
+    #[serde(skip)]
+    units: u128,
+}
+
+#[derive(Serialize)]
+pub struct ExchangeRates {
+    base: Currency,
+    rates: Vec<ExchangeRate>,
+}
+
+#[derive(Deserialize)]
+pub struct SetRate {
+    rate: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConvertQuery {
+    amount: String,
+    #[serde(default = "usd")]
+    from: String,
+    to: String,
+}
+
+fn usd() -> String {
+    Currency::USD.code().to_string()
+}
+
+#[derive(Serialize)]
+pub struct Conversion {
+    from: Money,
+    to: Money,
+    /// Units of `to` per unit of `from`.
+    rate: String,
+}
+
+/// EXCHANGE_RATES, e.g. `EUR=0.92,GBP=0.79`: units of each currency per USD.
+fn env_rates() -> Vec<ExchangeRate> {
+    let Some(rates) = optional_env("EXCHANGE_RATES") else {
+        return Vec::new();
+    };
+    rates
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(|entry| {
+            let parsed = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected CURRENCY=rate, got {}", entry))
+                .and_then(|(currency, rate)| Ok((Currency::parse(currency)?, parse_rate(rate)?)));
+            match parsed {
+                Ok((currency, units)) => Some(ExchangeRate {
+                    currency,
+                    rate: format_rate(units),
+                    source: "env",
+                    updated_at: None,
+                    units,
+                }),
+                Err(e) => {
+                    eprintln!("Ignoring EXCHANGE_RATES entry: {}", e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Every known rate per USD; rates set through the API override EXCHANGE_RATES.
+pub fn rates(db: &Db) -> Result<Vec<ExchangeRate>, String> {
+    let stored: Vec<(String, String, String)> = {
+        let conn = db.lock().unwrap();
+        conn.prepare("SELECT currency, rate, updated_at FROM exchange_rates ORDER BY currency")
+            .and_then(|mut stmt| stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?.collect())
+            .map_err(|e| format!("Failed to load exchange rates: {}", e))?
+    };
+    let mut rates: Vec<ExchangeRate> = stored
+        .into_iter()
+        .filter_map(|(currency, rate, updated_at)| {
+            let units = parse_rate(&rate).ok()?;
+            Some(ExchangeRate {
+                currency: Currency::parse(&currency).ok()?,
+                rate: format_rate(units),
+                source: "admin",
+                updated_at: Some(updated_at),
+                units,
+            })
+        })
+        .collect();
+    for rate in env_rates() {
+        if !rates.iter().any(|known| known.currency == rate.currency) {
+            rates.push(rate);
+        }
+    }
+    rates.sort_by(|a, b| a.currency.code().cmp(b.currency.code()));
+    Ok(rates)
+}
+
+/// Units of `currency` per USD, scaled by 10^RATE_DECIMALS.
+pub fn rate(db: &Db, currency: Currency) -> Result<u128, ApiError> {
+    if currency == Currency::USD {
+        return Ok(10u128.pow(RATE_DECIMALS));
+    }
+    rates(db)?
+        .into_iter()
+        .find(|rate| rate.currency == currency)
+        .map(|rate| rate.units)
+        .ok_or_else(|| {
+            ApiError::new(StatusCode::BAD_REQUEST, format!("No exchange rate for {}", currency))
+                .with_details(serde_json::json!({ "hint": "Set one with PUT /admin/exchange-rates/:currency" }))
+        })
+}
+
+/// `money` in `to` at the current rates.
+pub fn convert(db: &Db, money: Money, to: Currency) -> Result<Money, ApiError> {
+    if money.currency == to {
+        return Ok(money);
+    }
+    Ok(money.convert(rate(db, money.currency)?, to, rate(db, to)?))
+}
+
+fn currency(code: &str) -> Result<Currency, ApiError> {
+    Currency::parse(code).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))
+}
+
+pub async fn list_rates(State(state): State<AppState>) -> Result<Json<ExchangeRates>, ApiError> {
+    Ok(Json(ExchangeRates {
+        base: Currency::USD,
+        rates: rates(&state.db)?,
+    }))
+}
+
+pub async fn convert_amount(
+    State(state): State<AppState>,
+    Query(query): Query<ConvertQuery>,
+) -> Result<Json<Conversion>, ApiError> {
+    let from = Money::parse(&query.amount, currency(&query.from)?)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+    let to = currency(&query.to)?;
+    let (from_rate, to_rate) = (rate(&state.db, from.currency)?, rate(&state.db, to)?);
+    // Shown to RATE_DECIMALS like the stored rates; the conversion itself isn't rounded twice.
+    let cross_rate = mul_div(U256::from(to_rate), U256::from(10u128.pow(RATE_DECIMALS)), U256::from(from_rate));
+    Ok(Json(Conversion {
+        from,
+        to: from.convert(from_rate, to, to_rate),
+        rate: format_rate(cross_rate.min(U256::from(u128::MAX)).as_u128()),
+    }))
+}
+
+/// Sets the rate for a currency, overriding EXCHANGE_RATES.
+pub async fn set_rate(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    Json(request): Json<SetRate>,
+) -> Result<Json<ExchangeRates>, ApiError> {
+    let currency = currency(&code)?;
+    if currency == Currency::USD {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Rates are quoted per USD, so USD is always 1"));
+    }
+    let units = parse_rate(&request.rate).map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))?;
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO exchange_rates (currency, rate) VALUES (?1, ?2)
+             ON CONFLICT(currency) DO UPDATE SET rate = ?2, updated_at = CURRENT_TIMESTAMP",
+            params![currency.code(), format_rate(units)],
+        )
+        .map_err(|e| format!("Failed to save exchange rate: {}", e))?;
+    }
+    println!("Exchange rate for {} set to {} per USD", currency, format_rate(units));
+    list_rates(State(state)).await
+}
+
+/// Drops a rate set through the API, going back to EXCHANGE_RATES.
+pub async fn reset_rate(State(state): State<AppState>, Path(code): Path<String>) -> Result<Json<ExchangeRates>, ApiError> {
+    let currency = currency(&code)?;
+    let removed = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "DELETE FROM exchange_rates WHERE currency = ?1 RETURNING currency",
+            params![currency.code()],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to reset exchange rate: {}", e))?
+    };
+    if removed.is_none() {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("No exchange rate was set for {}", currency)));
+    }
+    list_rates(State(state)).await
+}
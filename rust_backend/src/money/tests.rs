@@ -0,0 +1,95 @@
+use ethers::types::U256;
+
+use super::{format_fixed, mul_div, parse_fixed, parse_rate, Currency, Money, RATE_DECIMALS};
+
+fn usd(cents: i64) -> Money {
+    Money::new(cents, Currency::USD)
+}
+
+fn eur(cents: i64) -> Money {
+    Money::new(cents, Currency::parse("EUR").unwrap())
+}
+
+/// A rate quoted to RATE_DECIMALS, e.g. `rate("0.92")`.
+fn rate(rate: &str) -> u128 {
+    parse_rate(rate).unwrap()
+}
+
+#[test]
+fn parse_fixed_reads_decimals_exactly() {
+    assert_eq!(parse_fixed("1250.5", 2), Ok(125050));
+    assert_eq!(parse_fixed("1250", 2), Ok(125000));
+    assert_eq!(parse_fixed(" 0.07 ", 2), Ok(7));
+    assert_eq!(parse_fixed(".5", 2), Ok(50));
+    assert_eq!(parse_fixed("3.", 2), Ok(300));
+    assert_eq!(parse_fixed("-12.05", 2), Ok(-1205));
+    assert_eq!(parse_fixed("0.000000000000000001", 18), Ok(1));
+}
+
+#[test]
+fn parse_fixed_refuses_what_it_cannot_read_exactly() {
+    assert!(parse_fixed("1.005", 2).is_err());
+    assert!(parse_fixed("", 2).is_err());
+    assert!(parse_fixed(".", 2).is_err());
+    assert!(parse_fixed("-", 2).is_err());
+    assert!(parse_fixed("1e3", 2).is_err());
+    assert!(parse_fixed("+1", 2).is_err());
+    assert!(parse_fixed("1,000", 2).is_err());
+    assert!(parse_fixed("--1", 2).is_err());
+    assert!(parse_fixed(&"9".repeat(40), 2).is_err());
+}
+
+#[test]
+fn format_fixed_round_trips() {
+    for (units, formatted) in [(125050, "1250.50"), (7, "0.07"), (-1205, "-12.05"), (-5, "-0.05"), (0, "0.00")] {
+        assert_eq!(format_fixed(units, 2), formatted);
+        assert_eq!(parse_fixed(formatted, 2), Ok(units));
+    }
+}
+
+#[test]
+fn money_parse_and_amount() {
+    assert_eq!(Money::parse("538000", Currency::USD), Ok(usd(53800000)));
+    assert_eq!(usd(53800000).amount(), "538000.00");
+    assert!(Money::parse("0.001", Currency::USD).is_err());
+    assert!(Money::parse("100000000000000000", Currency::USD).is_err());
+}
+
+#[test]
+fn mul_div_rounds_half_up() {
+    let n = |n: u64| U256::from(n);
+    assert_eq!(mul_div(n(10), n(1), n(4)), n(3));
+    assert_eq!(mul_div(n(9), n(1), n(4)), n(2));
+    assert_eq!(mul_div(n(6), n(1), n(4)), n(2));
+    // Products beyond u128 still divide exactly.
+    let big = U256::from(u128::MAX);
+    assert_eq!(mul_div(big, big, big), big);
+}
+
+#[test]
+fn convert_uses_rates_per_usd() {
+    let one = rate("1");
+    assert_eq!(usd(10000).convert(one, eur(0).currency, rate("0.92")), eur(9200));
+    assert_eq!(eur(9200).convert(rate("0.92"), Currency::USD, one), usd(10000));
+    // Cross rates go through USD without rounding in between.
+    let gbp = Currency::parse("GBP").unwrap();
+    assert_eq!(eur(10000).convert(rate("0.92"), gbp, rate("0.79")), Money::new(8587, gbp));
+}
+
+#[test]
+fn convert_rounds_half_away_from_zero() {
+    let eur = eur(0).currency;
+    // 1 cent at 0.5 is exactly half a cent.
+    assert_eq!(usd(1).convert(rate("1"), eur, rate("0.5")), Money::new(1, eur));
+    assert_eq!(usd(-1).convert(rate("1"), eur, rate("0.5")), Money::new(-1, eur));
+    assert_eq!(usd(-10000).convert(rate("1"), eur, rate("0.92")), Money::new(-9200, eur));
+}
+
+#[test]
+fn rates_must_be_positive_decimals() {
+    assert_eq!(rate("0.92"), 920_000_000_000_000_000);
+    assert_eq!(rate("1"), 10u128.pow(RATE_DECIMALS));
+    assert!(parse_rate("0").is_err());
+    assert!(parse_rate("-1").is_err());
+    assert!(parse_rate("abc").is_err());
+}
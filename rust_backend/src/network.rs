@@ -0,0 +1,375 @@
+use axum::http::StatusCode;
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, U256};
+use ethers::utils::{format_units, parse_units};
+use std::sync::{Arc, RwLock};
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::explorer::Explorer;
+use crate::state::AppState;
+
+const POLYGON_MIN_PRIORITY_FEE_GWEI: u64 = 30;
+
+/// Chains where gas and mints cost real money.
+const MAINNETS: &[u64] = &[1, 10, 56, 100, 137, 324, 8453, 42161, 42220, 43114, 59144, 534352];
+
+pub fn is_mainnet(chain_id: u64) -> bool {
+    MAINNETS.contains(&chain_id)
+}
+
+/// How a chain charges for gas.
+#[derive(Clone, Copy, PartialEq)]
+pub enum GasModel {
+    Eip1559,
+    /// Polygon PoS rejects transactions tipping less than 30 gwei.
+    Polygon,
+    /// Arbitrum bills the L1 calldata cost as extra L2 gas and ignores priority fees.
+    Arbitrum,
+    /// OP Stack chains (Optimism, Base) add an L1 data fee on top of the L2 gas.
+    OpStack,
+}
+
+impl GasModel {
+    fn as_str(self) -> &'static str {
+        match self {
+            GasModel::Eip1559 => "EIP-1559",
+            GasModel::Polygon => "Polygon",
+            GasModel::Arbitrum => "Arbitrum",
+            GasModel::OpStack => "OP Stack",
+        }
+    }
+}
+
+/// A network preset, selected with NETWORK instead of CHAIN_ID.
+pub struct ChainProfile {
+    pub name: &'static str,
+    pub chain_id: u64,
+    pub gas: GasModel,
+    /// Confirmations to wait for by default.
+    pub confirmations: usize,
+}
+
+const fn profile(name: &'static str, chain_id: u64, gas: GasModel, confirmations: usize) -> ChainProfile {
+    ChainProfile { name, chain_id, gas, confirmations }
+}
+
+// L2 sequencers order transactions within seconds, but their blocks come much faster, so a
+// couple of confirmations cost little; Polygon PoS still sees short reorgs.
+const PROFILES: &[ChainProfile] = &[
+    profile("ethereum", 1, GasModel::Eip1559, 3),
+    profile("sepolia", 11155111, GasModel::Eip1559, 1),
+    profile("polygon", 137, GasModel::Polygon, 32),
+    profile("polygon-amoy", 80002, GasModel::Polygon, 5),
+    profile("arbitrum", 42161, GasModel::Arbitrum, 2),
+    profile("arbitrum-sepolia", 421614, GasModel::Arbitrum, 1),
+    profile("optimism", 10, GasModel::OpStack, 2),
+    profile("optimism-sepolia", 11155420, GasModel::OpStack, 1),
+    profile("base", 8453, GasModel::OpStack, 2),
+    profile("base-sepolia", 84532, GasModel::OpStack, 1),
+    profile("hardhat", 31337, GasModel::Eip1559, 1),
+];
+
+impl ChainProfile {
+    pub fn named(name: &str) -> Option<&'static Self> {
+        PROFILES.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn for_chain(chain_id: u64) -> Option<&'static Self> {
+        PROFILES.iter().find(|profile| profile.chain_id == chain_id)
+    }
+}
+
+/// The chain selected by NETWORK, or CHAIN_ID, defaulting to a local Hardhat node.
+pub fn chain_id_from_env() -> u64 {
+    let chain_id = optional_env("CHAIN_ID").map(|id| id.parse::<u64>().expect("CHAIN_ID must be a number"));
+    let Some(network) = optional_env("NETWORK") else {
+        return chain_id.unwrap_or(31337); // Hardhat's default chain ID
+    };
+    let profile = ChainProfile::named(&network).unwrap_or_else(|| {
+        let names: Vec<&str> = PROFILES.iter().map(|profile| profile.name).collect();
+        panic!("NETWORK must be one of {}. Found: {}", names.join(", "), network)
+    });
+    if let Some(chain_id) = chain_id.filter(|id| *id != profile.chain_id) {
+        panic!("NETWORK {} is chain {}, but CHAIN_ID is {}", network, profile.chain_id, chain_id);
+    }
+    println!("NETWORK: {}", profile.name);
+    profile.chain_id
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NetworkEnv {
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl NetworkEnv {
+    pub fn from_env() -> Self {
+        let env = match optional_env("NETWORK_ENV").as_deref() {
+            None | Some("dev") => NetworkEnv::Dev,
+            Some("staging") => NetworkEnv::Staging,
+            Some("prod") => NetworkEnv::Prod,
+            Some(other) => panic!("NETWORK_ENV must be dev, staging or prod. Found: {}", other),
+        };
+        println!("NETWORK_ENV: {}", env.as_str());
+        env
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NetworkEnv::Dev => "dev",
+            NetworkEnv::Staging => "staging",
+            NetworkEnv::Prod => "prod",
+        }
+    }
+}
+
+/// The settings that differ between the chains the backend sends transactions to.
+pub struct Chain {
+    /// The NETWORKS entry; `None` for the primary chain from CHAIN_ID or NETWORK.
+    pub network: Option<String>,
+    pub chain_id: u64,
+    /// Swapped by a config reload; read it through `tx_policy()`.
+    pub tx_policy: RwLock<TxPolicy>,
+    pub explorer: Option<Explorer>,
+}
+
+impl Chain {
+    pub fn tx_policy(&self) -> TxPolicy {
+        *self.tx_policy.read().unwrap()
+    }
+
+    /// The variables this chain's settings are read from: unprefixed for the primary chain.
+    pub fn env_prefix(&self) -> String {
+        self.network.as_deref().map(env_prefix).unwrap_or_default()
+    }
+}
+
+/// An extra chain mints can target by name, with its own RPC endpoint, wallet and contract.
+pub struct Network {
+    pub chain: Arc<Chain>,
+    pub rpc_url: String,
+    pub private_key: String,
+    pub contract_address: Address,
+}
+
+impl Network {
+    /// Reads NETWORKS, a comma separated list of presets, each configured by NETWORK_<NAME>_*
+    /// variables. Wallets default to PRIVATE_KEY.
+    pub fn from_env(private_key: &str) -> Vec<Self> {
+        let Some(names) = optional_env("NETWORKS") else { return Vec::new() };
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let profile = ChainProfile::named(name)
+                    .unwrap_or_else(|| panic!("NETWORKS entry {} is not a known network", name));
+                let prefix = env_prefix(name);
+                let var = |key: &str| optional_env(&format!("{}{}", prefix, key));
+                let rpc_url = var("RPC_URL").unwrap_or_else(|| panic!("{}RPC_URL is not set", prefix));
+                let contract_address = var("CONTRACT_ADDRESS")
+                    .unwrap_or_else(|| panic!("{}CONTRACT_ADDRESS is not set", prefix))
+                    .parse()
+                    .unwrap_or_else(|_| panic!("Invalid {}CONTRACT_ADDRESS", prefix));
+                println!("Network {}: chain {}, contract {:?}", name, profile.chain_id, contract_address);
+                Network {
+                    chain: Arc::new(Chain {
+                        network: Some(name.to_string()),
+                        chain_id: profile.chain_id,
+                        tx_policy: RwLock::new(TxPolicy::from_env(&prefix, profile.chain_id)),
+                        explorer: Explorer::known(profile.chain_id),
+                    }),
+                    rpc_url,
+                    private_key: var("PRIVATE_KEY").unwrap_or_else(|| private_key.to_string()),
+                    contract_address,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Where a NETWORKS entry's variables live, e.g. NETWORK_BASE_SEPOLIA_ for base-sepolia.
+fn env_prefix(network: &str) -> String {
+    format!("NETWORK_{}_", network.to_uppercase().replace('-', "_"))
+}
+
+/// Limits applied to every transaction the backend sends.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TxPolicy {
+    /// Highest fee per gas the backend will pay; required on mainnets.
+    pub max_fee_per_gas: Option<U256>,
+    pub confirmations: usize,
+    pub gas: GasModel,
+}
+
+impl TxPolicy {
+    /// Known chains wait for their profile's confirmations by default, other mainnets for 3.
+    /// Mainnets never wait for fewer than 2. `prefix` namespaces the variables of NETWORKS entries.
+    pub fn from_env(prefix: &str, chain_id: u64) -> Self {
+        let policy = TxPolicy::load(prefix, chain_id).unwrap_or_else(|e| panic!("{}", e));
+        println!("Transactions: {}", policy.describe());
+        policy
+    }
+
+    /// Like `from_env`, but reports an invalid setting instead of panicking.
+    pub fn load(prefix: &str, chain_id: u64) -> Result<Self, String> {
+        let max_fee_per_gas = optional_env(&format!("{}MAX_FEE_GWEI", prefix))
+            .map(|gwei| {
+                parse_units(&gwei, "gwei")
+                    .map(Into::into)
+                    .map_err(|_| format!("{}MAX_FEE_GWEI must be a number of gwei", prefix))
+            })
+            .transpose()?;
+        let mainnet = is_mainnet(chain_id);
+        let confirmations = optional_env(&format!("{}CONFIRMATIONS", prefix))
+            .map(|count| count.parse().map_err(|_| format!("{}CONFIRMATIONS must be a number", prefix)))
+            .transpose()?
+            .unwrap_or(match ChainProfile::for_chain(chain_id) {
+                Some(profile) => profile.confirmations,
+                None if mainnet => 3,
+                None => 1,
+            });
+        let gas = ChainProfile::for_chain(chain_id).map_or(GasModel::Eip1559, |profile| profile.gas);
+        if mainnet && confirmations < 2 {
+            return Err(format!("{}CONFIRMATIONS must be at least 2 on mainnet. Found: {}", prefix, confirmations));
+        }
+        Ok(TxPolicy {
+            max_fee_per_gas,
+            confirmations,
+            gas,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} confirmations, fee cap {}, {} gas",
+            self.confirmations,
+            self.max_fee_per_gas.map_or("none".to_string(), |cap| format!("{} gwei", gwei(cap))),
+            self.gas.as_str()
+        )
+    }
+}
+
+fn gwei(wei: U256) -> String {
+    let gwei = format_units(wei, "gwei").unwrap_or_default();
+    match gwei.split_once('.') {
+        Some((whole, fraction)) if fraction.trim_end_matches('0').is_empty() => whole.to_string(),
+        _ => gwei.trim_end_matches('0').to_string(),
+    }
+}
+
+/// Refuses to run against a mainnet unless the deployment has opted in with ALLOW_MAINNET=true,
+/// is not a dev config, and caps fees. Every configured network is checked.
+pub async fn guard(state: &AppState) -> Result<(), String> {
+    for state in state.chains() {
+        guard_chain(&state).await?;
+    }
+    Ok(())
+}
+
+async fn guard_chain(state: &AppState) -> Result<(), String> {
+    let config = &state.config;
+    let chain = &state.chain;
+    let connected = state.client.get_chainid().await.ok().map(|id| id.as_u64());
+    if let Some(connected) = connected.filter(|id| *id != chain.chain_id) {
+        if is_mainnet(connected) || is_mainnet(chain.chain_id) {
+            return Err(format!(
+                "The RPC endpoint is on chain {} but {} is {}",
+                connected,
+                chain.network.as_deref().map_or("CHAIN_ID".to_string(), |network| format!("network {}", network)),
+                chain.chain_id
+            ));
+        }
+    }
+    if !is_mainnet(chain.chain_id) {
+        return Ok(());
+    }
+    if config.network_env == NetworkEnv::Dev {
+        return Err(format!("Chain {} is a mainnet, which NETWORK_ENV=dev can't use", chain.chain_id));
+    }
+    if !config.allow_mainnet {
+        return Err(format!(
+            "Chain {} is a mainnet; set ALLOW_MAINNET=true to send real transactions",
+            chain.chain_id
+        ));
+    }
+    if chain.tx_policy().max_fee_per_gas.is_none() {
+        return Err(match &chain.network {
+            Some(network) => format!("{}MAX_FEE_GWEI must be set on mainnet", env_prefix(network)),
+            None => "MAX_FEE_GWEI must be set on mainnet".to_string(),
+        });
+    }
+    println!("Mainnet enabled on chain {} ({})", chain.chain_id, config.network_env.as_str());
+    Ok(())
+}
+
+/// Fees for an EIP-1559 transaction under the chain's gas model.
+async fn eip1559_fees(state: &AppState) -> Result<(U256, U256), ApiError> {
+    let (mut max_fee, mut priority_fee) = state
+        .client
+        .estimate_eip1559_fees(None)
+        .await
+        .map_err(|e| format!("Failed to estimate fees: {}", e))?;
+    match state.chain.tx_policy().gas {
+        GasModel::Polygon => {
+            let minimum = U256::from(POLYGON_MIN_PRIORITY_FEE_GWEI) * U256::exp10(9);
+            if priority_fee < minimum {
+                max_fee += minimum - priority_fee;
+                priority_fee = minimum;
+            }
+        }
+        // The sequencer orders transactions first come, first served; tips are wasted.
+        GasModel::Arbitrum => {
+            max_fee -= priority_fee.min(max_fee);
+            priority_fee = U256::zero();
+        }
+        GasModel::Eip1559 | GasModel::OpStack => {}
+    }
+    Ok((max_fee, priority_fee))
+}
+
+/// Prices the transaction at the current fees, or refuses it if they are above the cap.
+/// Without a cap, only chains whose node estimates need correcting are priced here.
+pub async fn cap_fees(state: &AppState, tx: &mut TypedTransaction) -> Result<(), ApiError> {
+    let policy = state.chain.tx_policy();
+    let gas = policy.gas;
+    let Some(cap) = policy.max_fee_per_gas else {
+        if let (TypedTransaction::Eip1559(tx), GasModel::Polygon | GasModel::Arbitrum) = (tx, gas) {
+            let (max_fee, priority_fee) = eip1559_fees(state).await?;
+            tx.max_fee_per_gas = Some(max_fee);
+            tx.max_priority_fee_per_gas = Some(priority_fee);
+        }
+        return Ok(());
+    };
+    let too_expensive = |fee: U256| {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("Gas costs {} gwei, above the {} gwei cap; try again later", gwei(fee), gwei(cap)),
+        )
+    };
+    match tx {
+        TypedTransaction::Eip1559(tx) => {
+            let (max_fee, priority_fee) = eip1559_fees(state).await?;
+            if max_fee > cap {
+                return Err(too_expensive(max_fee));
+            }
+            tx.max_fee_per_gas = Some(max_fee);
+            tx.max_priority_fee_per_gas = Some(priority_fee);
+        }
+        tx => {
+            let gas_price = state
+                .client
+                .get_gas_price()
+                .await
+                .map_err(|e| format!("Failed to read the gas price: {}", e))?;
+            if gas_price > cap {
+                return Err(too_expensive(gas_price));
+            }
+            tx.set_gas_price(gas_price);
+        }
+    }
+    Ok(())
+}
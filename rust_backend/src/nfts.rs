@@ -0,0 +1,277 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, TransactionReceipt, U256};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::bridges;
+use crate::capabilities::{self, Interface};
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::liens;
+use crate::marketplace::{self, Listing};
+use crate::money::{Currency, Money};
+use crate::organizations::Tenant;
+use crate::registry::{self, Collection};
+use crate::sanctions;
+use crate::simulation;
+use crate::state::AppState;
+use crate::storage;
+use crate::tx::{self, TransactionResponse};
+use crate::CollectionQuery;
+
+pub struct NewMint<'a> {
+    pub collection: &'a str,
+    pub token_id: u64,
+    pub recipient: Address,
+    pub receipt: &'a TransactionReceipt,
+    pub price: Money,
+    pub details: Value,
+    pub metadata: &'a Value,
+    pub metadata_storage: &'a str,
+    /// Only kept for stores whose URIs point elsewhere; inline URIs repeat `metadata`.
+    pub token_uri: Option<&'a str>,
+    pub soulbound: bool,
+}
+
+#[derive(Serialize)]
+pub struct Nft {
+    pub collection: String,
+    /// The NETWORKS chain the token was minted on; absent for the primary chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    pub token_id: u64,
+    pub name: String,
+    pub recipient: String,
+    /// `value` as a number, kept for existing clients.
+    pub price: f64,
+    pub value: Money,
+    pub transaction_hash: String,
+    pub soulbound: bool,
+    pub minted_at: String,
+    /// `pending` while a reorg has dropped the mint transaction from the canonical chain.
+    pub status: String,
+    pub listing: Option<Listing>,
+}
+
+#[derive(Deserialize)]
+pub struct TransferRequest {
+    to: Address,
+}
+
+#[derive(Deserialize)]
+pub struct NftQuery {
+    collection: Option<String>,
+    /// Only return tokens minted on this NETWORKS chain; by default every chain is listed.
+    network: Option<String>,
+    /// Only return tokens with an active marketplace listing.
+    #[serde(default)]
+    listed: bool,
+    limit: Option<u32>,
+    offset: Option<u32>,
+}
+
+pub fn record_mint(db: &Db, mint: NewMint) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO mints (collection, token_id, recipient, transaction_hash, block_number, price, price_cents, details,
+                            metadata, soulbound, metadata_storage, token_uri, block_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            mint.collection,
+            mint.token_id,
+            format!("{:?}", mint.recipient),
+            format!("{:?}", mint.receipt.transaction_hash),
+            mint.receipt.block_number.unwrap_or_default().as_u64(),
+            mint.price.to_f64(),
+            mint.price.cents,
+            mint.details.to_string(),
+            mint.metadata.to_string(),
+            mint.soulbound,
+            mint.metadata_storage,
+            mint.token_uri,
+            mint.receipt.block_hash.map(|hash| format!("{:?}", hash)),
+        ],
+    )
+    .map_err(|e| format!("Failed to record mint: {}", e))?;
+    Ok(())
+}
+
+pub async fn list_nfts(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(query): Query<NftQuery>,
+) -> Result<Json<Vec<Nft>>, ApiError> {
+    let mut nfts = {
+        let conn = state.db.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT m.collection, m.token_id, json_extract(m.details, '$.name'), m.recipient, m.price,
+                        m.transaction_hash, m.soulbound, m.created_at, m.status, c.network,
+                        COALESCE(m.price_cents, CAST(ROUND(m.price * 100) AS INTEGER)), m.currency
+                 FROM mints m JOIN collections c ON c.name = m.collection
+                 WHERE (?1 IS NULL OR m.collection = ?1) AND c.organization IS ?4 AND (?5 IS NULL OR c.network = ?5)
+                   AND (NOT ?6 OR EXISTS (SELECT 1 FROM listings l WHERE l.collection = m.collection
+                                          AND l.token_id = m.token_id AND l.status IN ('active', 'pending_settlement')))
+                 ORDER BY m.id DESC LIMIT ?2 OFFSET ?3",
+            )
+            .map_err(|e| format!("Failed to list NFTs: {}", e))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    query.collection,
+                    query.limit.unwrap_or(50).min(500),
+                    query.offset.unwrap_or(0),
+                    tenant.organization_id(),
+                    query.network,
+                    query.listed,
+                ],
+                |row| {
+                    let currency = Currency::parse(&row.get::<_, String>(11)?).unwrap_or(Currency::USD);
+                    Ok(Nft {
+                        collection: row.get(0)?,
+                        network: row.get(9)?,
+                        token_id: row.get(1)?,
+                        name: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                        recipient: row.get(3)?,
+                        price: row.get(4)?,
+                        value: Money::new(row.get(10)?, currency),
+                        transaction_hash: row.get(5)?,
+                        soulbound: row.get(6)?,
+                        minted_at: row.get(7)?,
+                        status: row.get(8)?,
+                        listing: None,
+                    })
+                },
+            )
+            .map_err(|e| format!("Failed to list NFTs: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to list NFTs: {}", e))?
+    };
+
+    for nft in &mut nfts {
+        nft.listing = marketplace::active_listing(&state.db, &nft.collection, nft.token_id)?;
+    }
+    Ok(Json(nfts))
+}
+
+pub async fn nft_metadata(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let mut metadata = token_metadata(&state, &collection, token_id).await?;
+    bridges::annotate(&state.db, &collection.name, token_id, &mut metadata)?;
+    Ok(Json(metadata))
+}
+
+pub async fn token_metadata(state: &AppState, collection: &Collection, token_id: u64) -> Result<Value, ApiError> {
+    capabilities::require(state, collection, Interface::Metadata).await?;
+    let token_uri = match state.nft(collection)?.token_uri(U256::from(token_id)).call().await {
+        Ok(token_uri) => token_uri,
+        // A revert means the token doesn't exist; any other failure may just be the node.
+        Err(e) => match &state.config.alchemy_nft {
+            Some(alchemy) if !e.is_revert() => {
+                eprintln!("Failed to read token URI: {}; falling back to the Alchemy NFT API", e);
+                return Ok(alchemy.metadata(state, collection.address, token_id).await?);
+            }
+            _ => return Err(simulation::call_error("Failed to read token URI", e)),
+        },
+    };
+
+    // URIs from stores that aren't configured here are returned as they are.
+    match storage::get(state, &token_uri).await {
+        Some(Ok(metadata)) => Ok(metadata),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            Err(ApiError::new(StatusCode::BAD_GATEWAY, "Failed to fetch token metadata")
+                .with_details(json!({ "token_uri": token_uri })))
+        }
+        None => Ok(json!({ "token_uri": token_uri })),
+    }
+}
+
+#[derive(Serialize)]
+pub struct RefreshResult {
+    pub service: &'static str,
+    pub refreshed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Asks each configured marketplace indexer to re-read the token's metadata.
+pub async fn refresh_marketplaces(state: &AppState, collection: &Collection, token_id: u64) -> Vec<RefreshResult> {
+    let mut results = Vec::new();
+    if let Some(alchemy) = &state.config.alchemy_nft {
+        let outcome = alchemy.refresh(state, collection.address, token_id).await;
+        results.push(("alchemy", outcome));
+    }
+    if let Some(opensea) = &state.config.opensea {
+        let outcome = opensea.refresh(state, collection.address, token_id).await;
+        results.push(("opensea", outcome));
+    }
+    results
+        .into_iter()
+        .map(|(service, outcome)| RefreshResult {
+            service,
+            refreshed: outcome.is_ok(),
+            error: outcome.err(),
+        })
+        .collect()
+}
+
+/// Refreshes marketplaces in the background after the token's metadata changed.
+pub fn spawn_marketplace_refresh(state: &AppState, collection: &Collection, token_id: u64) {
+    let (state, collection) = (state.clone(), collection.clone());
+    tokio::spawn(async move {
+        for result in refresh_marketplaces(&state, &collection, token_id).await {
+            if let Some(error) = result.error {
+                eprintln!("Marketplace refresh for token {} failed: {}", token_id, error);
+            }
+        }
+    });
+}
+
+pub async fn refresh_marketplace(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<RefreshResult>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let results = refresh_marketplaces(&state, &collection, token_id).await;
+    if results.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "No marketplace indexer is configured"));
+    }
+    if results.iter().all(|result| !result.refreshed) {
+        return Err(ApiError::new(StatusCode::BAD_GATEWAY, "Every marketplace refresh failed").with_details(json!(results)));
+    }
+    Ok(Json(results))
+}
+
+/// Transfers a token held (or approved for transfer) by the backend wallet.
+pub async fn transfer(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<TransferRequest>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    sanctions::screen(&state, request.to, "transfer").await?;
+    let collection = registry::resolve(&state.db, query.collection.as_deref())?;
+    liens::require_unencumbered(&state, &collection.name, token_id)?;
+    capabilities::require(&state, &collection, Interface::Erc721).await?;
+    let chain = state.on(&collection)?;
+    let contract = chain.nft(&collection)?;
+    let owner = contract
+        .owner_of(U256::from(token_id))
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read token owner", e))?;
+    println!("Transferring token {} from {:?} to {:?}...", token_id, owner, request.to);
+    let receipt = tx::submit(&chain, contract.safe_transfer_from(owner, request.to, U256::from(token_id))).await?;
+    Ok(Json(tx::response(&chain, &receipt)))
+}
@@ -0,0 +1,49 @@
+use ethers::types::Address;
+
+use crate::config::optional_env;
+use crate::state::AppState;
+
+const API_URL: &str = "https://api.opensea.io/api/v2";
+
+pub struct OpenSeaConfig {
+    api_key: String,
+    chain: &'static str,
+}
+
+impl OpenSeaConfig {
+    /// Enabled by OPENSEA_API_KEY on chains OpenSea lists.
+    pub fn from_env(chain_id: u64) -> Option<Self> {
+        let api_key = optional_env("OPENSEA_API_KEY")?;
+        let chain = match chain_id {
+            1 => "ethereum",
+            11155111 => "sepolia",
+            10 => "optimism",
+            137 => "matic",
+            80002 => "amoy",
+            42161 => "arbitrum",
+            421614 => "arbitrum_sepolia",
+            8453 => "base",
+            84532 => "base_sepolia",
+            _ => {
+                println!("OPENSEA_API_KEY: Ignored (OpenSea does not support chain {})", chain_id);
+                return None;
+            }
+        };
+        println!("OPENSEA_API_KEY: Loaded ({})", chain);
+        Some(OpenSeaConfig { api_key, chain })
+    }
+
+    /// Queues a metadata refresh so OpenSea picks up an updated token URI.
+    pub async fn refresh(&self, state: &AppState, contract: Address, token_id: u64) -> Result<(), String> {
+        state
+            .http
+            .post(format!("{}/chain/{}/contract/{:?}/nfts/{}/refresh", API_URL, self.chain, contract, token_id))
+            .header("x-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call OpenSea: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("OpenSea metadata refresh failed: {}", e))?;
+        Ok(())
+    }
+}
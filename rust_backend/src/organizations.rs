@@ -0,0 +1,460 @@
+use axum::async_trait;
+use axum::extract::{FromRequestParts, Path, State};
+use axum::http::request::Parts;
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Json;
+use ethers::core::rand;
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, SocketAddr};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::rbac::Role;
+use crate::signing;
+use crate::registry;
+use crate::state::AppState;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+#[derive(Clone, Serialize)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    /// Name of the environment variable holding the organization's signing key.
+    pub wallet_env: Option<String>,
+    pub default_collection: Option<String>,
+    pub created_at: String,
+}
+
+/// The organization a request is made on behalf of, identified by its API key.
+/// `None` is the platform itself (no key, or an admin request).
+#[derive(Clone, Default)]
+pub struct Tenant(pub Option<Organization>);
+
+impl Tenant {
+    pub fn organization_id(&self) -> Option<&str> {
+        self.0.as_ref().map(|org| org.id.as_str())
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Tenant {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Tenant>().cloned().unwrap_or_default())
+    }
+}
+
+/// Who made a request, for policies that need two different people: `key:<id>` for an
+/// organization API key however it authenticated, `admin` or `admin:<name>` for admin tokens.
+/// `None` for anonymous callers, who can't be told apart.
+#[derive(Clone, Default)]
+pub struct Caller(pub Option<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<Caller>().cloned().unwrap_or_default())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateOrganization {
+    id: String,
+    name: String,
+    wallet_env: Option<String>,
+    default_collection: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKey {
+    label: Option<String>,
+    /// appraiser, agent (default) or viewer.
+    role: Option<Role>,
+    /// Also issue a secret for HMAC-signed requests.
+    #[serde(default)]
+    signing: bool,
+}
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    id: i64,
+    organization: String,
+    role: Role,
+    /// Shown once; only its hash is stored.
+    key: String,
+    /// Secret for `X-Signature` request signing, shown once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signing_secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateWebhook {
+    url: String,
+}
+
+#[derive(Serialize)]
+pub struct WebhookResponse {
+    id: i64,
+    organization: String,
+    url: String,
+    /// Signing secret for the `X-Webhook-Signature` header, shown once.
+    secret: String,
+}
+
+#[derive(Deserialize)]
+pub struct AssignCollection {
+    collection: String,
+}
+
+fn hash_key(key: &str) -> String {
+    ethers::utils::hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+pub fn random_secret(prefix: &str) -> String {
+    format!("{}_{}", prefix, ethers::utils::hex::encode(rand::random::<[u8; 32]>()))
+}
+
+fn row_to_organization(row: &rusqlite::Row) -> rusqlite::Result<Organization> {
+    Ok(Organization {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        wallet_env: row.get(2)?,
+        default_collection: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+const ORGANIZATION_COLUMNS: &str = "id, name, wallet_env, default_collection, created_at";
+
+pub fn load(db: &Db, id: &str) -> Result<Option<Organization>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        &format!("SELECT {} FROM organizations WHERE id = ?1", ORGANIZATION_COLUMNS),
+        params![id],
+        row_to_organization,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load organization: {}", e))
+}
+
+fn require(db: &Db, id: &str) -> Result<Organization, ApiError> {
+    load(db, id)?.ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown organization: {}", id)))
+}
+
+/// The organization, role and ID of an active API key.
+pub fn by_api_key(db: &Db, key: &str) -> Result<Option<(Organization, Role, i64)>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT o.id, o.name, o.wallet_env, o.default_collection, o.created_at, k.role, k.id
+         FROM organization_api_keys k JOIN organizations o ON o.id = k.organization
+         WHERE k.key_hash = ?1 AND k.revoked_at IS NULL",
+        params![hash_key(key)],
+        |row| {
+            let role = Role::parse(&row.get::<_, String>(5)?).unwrap_or(Role::Viewer);
+            Ok((row_to_organization(row)?, role, row.get(6)?))
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up API key: {}", e))
+}
+
+/// The organization, role and signing secret for an active signing key.
+pub fn by_signing_key(db: &Db, key_id: i64) -> Result<Option<(Organization, Role, String)>, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT o.id, o.name, o.wallet_env, o.default_collection, o.created_at, k.role, k.signing_secret
+         FROM organization_api_keys k JOIN organizations o ON o.id = k.organization
+         WHERE k.id = ?1 AND k.revoked_at IS NULL AND k.signing_secret IS NOT NULL",
+        params![key_id],
+        |row| {
+            let role = Role::parse(&row.get::<_, String>(5)?).unwrap_or(Role::Viewer);
+            Ok((row_to_organization(row)?, role, row.get(6)?))
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up signing key: {}", e))
+}
+
+/// Resolves the caller's tenant and role for every request: the admin bearer token, an
+/// organization's `X-Api-Key` or HMAC request signature, or the anonymous role.
+/// Unknown keys and bad signatures are rejected.
+pub async fn identify(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response, ApiError> {
+    if request.headers().contains_key(signing::SIGNATURE_HEADER) {
+        let (mut request, org, role, key_id) = signing::verify(&state, request).await?;
+        request.extensions_mut().insert(Tenant(Some(org)));
+        request.extensions_mut().insert(role);
+        request.extensions_mut().insert(Caller(Some(format!("key:{}", key_id))));
+        return Ok(next.run(request).await);
+    }
+
+    let mut request = request;
+    let key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let (tenant, role, caller) = match key {
+        Some(key) => {
+            let (org, role, key_id) = by_api_key(&state.db, &key)?
+                .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Invalid or revoked API key"))?;
+            (Tenant(Some(org)), Some(role), Caller(Some(format!("key:{}", key_id))))
+        }
+        None => match auth::admin_identity(&state, request.headers()) {
+            Some(admin) => (Tenant(None), Some(Role::Admin), Caller(Some(admin))),
+            None => (Tenant(None), state.config.anonymous_role, Caller(None)),
+        },
+    };
+    request.extensions_mut().insert(tenant);
+    request.extensions_mut().insert(caller);
+    if let Some(role) = role {
+        request.extensions_mut().insert(role);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Delivers an event to the organization's webhooks in the background. Deliveries are
+/// signed like Stripe's: `X-Webhook-Signature: t=<timestamp>,v1=<hmac of "t.body">`.
+pub fn notify(state: &AppState, organization: &str, event: &str, data: Value) {
+    let hooks: Vec<(String, String)> = {
+        let conn = state.db.lock().unwrap();
+        let result = conn
+            .prepare("SELECT url, secret FROM organization_webhooks WHERE organization = ?1")
+            .and_then(|mut stmt| {
+                stmt.query_map(params![organization], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect()
+            });
+        match result {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                eprintln!("Failed to load webhooks for {}: {}", organization, e);
+                return;
+            }
+        }
+    };
+    let body = serde_json::json!({ "type": event, "organization": organization, "data": data }).to_string();
+    for (url, secret) in hooks {
+        deliver(url, secret, body.clone());
+    }
+}
+
+/// Whether requests to `ip` would reach this host or its private network.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            None => {
+                let unique_local = ip.segments()[0] & 0xfe00 == 0xfc00;
+                let link_local = ip.segments()[0] & 0xffc0 == 0xfe80;
+                ip.is_loopback() || ip.is_unspecified() || unique_local || link_local
+            }
+        },
+    }
+}
+
+/// Resolves an http(s) webhook `url` to the address to deliver to, refusing it if any of its
+/// addresses is private, loopback or link-local.
+pub async fn public_address(url: &str) -> Result<(Url, SocketAddr), String> {
+    let parsed = Url::parse(url).map_err(|_| "is not a valid URL".to_string())?;
+    if parsed.scheme() != "https" && parsed.scheme() != "http" {
+        return Err("must be http(s)".to_string());
+    }
+    let host = parsed.host_str().ok_or("has no host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let addresses: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| format!("host {} does not resolve", host))?
+            .collect(),
+    };
+    match addresses.first() {
+        Some(&address) if !addresses.iter().any(|address| is_internal(address.ip())) => Ok((parsed, address)),
+        _ => Err("must point at a public address".to_string()),
+    }
+}
+
+/// POSTs a signed webhook body to `url` in the background. The host is resolved and checked
+/// again on every delivery and the request goes to that checked address without following
+/// redirects, so neither DNS rebinding nor a redirect can aim it at an internal address.
+pub fn deliver(url: String, secret: String, body: String) {
+    tokio::spawn(async move {
+        let result = async {
+            let (parsed, address) = public_address(&url).await?;
+            let mut http = Client::builder().redirect(Policy::none());
+            if let Some(domain) = parsed.domain() {
+                http = http.resolve(domain, address);
+            }
+            let http = http.build().map_err(|e| e.to_string())?;
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let signature =
+                auth::hmac_sha256_hex(&secret, &[timestamp.to_string().as_bytes(), b".", body.as_bytes()]);
+            let response = http
+                .post(parsed)
+                .header("Content-Type", "application/json")
+                .header("X-Webhook-Signature", format!("t={},v1={}", timestamp, signature))
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| e.to_string())?;
+            if !response.status().is_success() {
+                return Err(format!("responded with {}", response.status()));
+            }
+            Ok(())
+        };
+        if let Err(e) = result.await {
+            eprintln!("Webhook delivery to {} failed: {}", url, e);
+        }
+    });
+}
+
+pub async fn create_organization(
+    State(state): State<AppState>,
+    Json(request): Json<CreateOrganization>,
+) -> Result<Json<Organization>, ApiError> {
+    let valid_id = !request.id.is_empty()
+        && request.id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid_id {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "Organization id must be lowercase letters, digits and dashes",
+        ));
+    }
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO organizations (id, name, wallet_env, default_collection) VALUES (?1, ?2, ?3, ?4)",
+            params![request.id, request.name, request.wallet_env, request.default_collection],
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(err, _) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+                ApiError::new(StatusCode::CONFLICT, format!("Organization {} already exists", request.id))
+            }
+            e => format!("Failed to create organization: {}", e).into(),
+        })?;
+    }
+    println!("Created organization {}", request.id);
+    Ok(Json(require(&state.db, &request.id)?))
+}
+
+pub fn list(db: &Db) -> Result<Vec<Organization>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare(&format!("SELECT {} FROM organizations ORDER BY id", ORGANIZATION_COLUMNS))
+        .and_then(|mut stmt| stmt.query_map([], row_to_organization)?.collect())
+        .map_err(|e| format!("Failed to list organizations: {}", e))
+}
+
+pub async fn list_organizations(State(state): State<AppState>) -> Result<Json<Vec<Organization>>, ApiError> {
+    Ok(Json(list(&state.db)?))
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<CreateApiKey>,
+) -> Result<Json<ApiKeyResponse>, ApiError> {
+    require(&state.db, &id)?;
+    let role = request.role.unwrap_or(Role::Agent);
+    if role == Role::Admin {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Organization keys cannot have the admin role"));
+    }
+    let key = random_secret("org");
+    let signing_secret = request.signing.then(|| random_secret("sk"));
+    let key_id = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO organization_api_keys (organization, key_hash, label, role, signing_secret)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, hash_key(&key), request.label, role.as_str(), signing_secret],
+        )
+        .map_err(|e| format!("Failed to create API key: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    Ok(Json(ApiKeyResponse {
+        id: key_id,
+        organization: id,
+        role,
+        key,
+        signing_secret,
+    }))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Path((id, key_id)): Path<(String, i64)>,
+) -> Result<StatusCode, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let revoked = conn
+        .execute(
+            "UPDATE organization_api_keys SET revoked_at = CURRENT_TIMESTAMP
+             WHERE id = ?1 AND organization = ?2 AND revoked_at IS NULL",
+            params![key_id, id],
+        )
+        .map_err(|e| format!("Failed to revoke API key: {}", e))?;
+    if revoked == 0 {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("No active API key {} for {}", key_id, id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<CreateWebhook>,
+) -> Result<Json<WebhookResponse>, ApiError> {
+    require(&state.db, &id)?;
+    public_address(&request.url)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Webhook url {}", e)))?;
+    let secret = random_secret("whsec");
+    let webhook_id = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO organization_webhooks (organization, url, secret) VALUES (?1, ?2, ?3)",
+            params![id, request.url, secret],
+        )
+        .map_err(|e| format!("Failed to create webhook: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    Ok(Json(WebhookResponse {
+        id: webhook_id,
+        organization: id,
+        url: request.url,
+        secret,
+    }))
+}
+
+/// Moves a registered collection (and so its NFTs and listings) into the organization.
+pub async fn assign_collection(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<AssignCollection>,
+) -> Result<StatusCode, ApiError> {
+    require(&state.db, &id)?;
+    if !registry::assign(&state.db, &request.collection, &id)? {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("Unknown collection: {}", request.collection),
+        ));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
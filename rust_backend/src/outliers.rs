@@ -0,0 +1,157 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::analytics;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::jobs::{self, MintJob};
+use crate::mint::HouseDetails;
+use crate::money::Money;
+use crate::state::AppState;
+
+/// Fewer minted properties than this say too little about a zipcode to call a price unusual.
+const MIN_ZIPCODE_SAMPLES: usize = 10;
+
+pub struct OutlierConfig {
+    pub min_price: f64,
+    pub max_price: f64,
+    /// How many standard deviations from the zipcode's median price a prediction may be.
+    pub max_deviations: f64,
+}
+
+impl OutlierConfig {
+    /// On by default; OUTLIER_CHECK=false turns it off.
+    pub fn from_env() -> Option<Self> {
+        if optional_env("OUTLIER_CHECK").is_some_and(|check| check == "false") {
+            return None;
+        }
+        let number = |name: &str, default: f64| {
+            optional_env(name)
+                .map(|value| value.parse().unwrap_or_else(|_| panic!("{} must be a number", name)))
+                .unwrap_or(default)
+        };
+        let config = OutlierConfig {
+            min_price: number("OUTLIER_MIN_PRICE", 10_000.0),
+            max_price: number("OUTLIER_MAX_PRICE", 50_000_000.0),
+            max_deviations: number("OUTLIER_MAX_DEVIATIONS", 3.0),
+        };
+        println!(
+            "Outlier check: {} to {}, within {} standard deviations of the zipcode median",
+            config.min_price, config.max_price, config.max_deviations
+        );
+        Some(config)
+    }
+}
+
+/// A prediction the outlier check flagged, kept on the job while it waits for review.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Outlier {
+    pub price: f64,
+    pub reasons: Vec<String>,
+}
+
+/// Prices of the properties minted in `zipcode`.
+fn zipcode_prices(db: &Db, zipcode: u64) -> Result<Vec<f64>, String> {
+    let conn = db.lock().unwrap();
+    conn.prepare("SELECT price FROM mints WHERE json_extract(details, '$.zipcode') = ?1")
+        .and_then(|mut stmt| stmt.query_map(params![zipcode], |row| row.get(0))?.collect())
+        .map_err(|e| format!("Failed to load zipcode prices: {}", e))
+}
+
+/// Whether the model's `price` for a property is implausible enough that it shouldn't go
+/// on-chain unchecked: outside the configured bounds, or too far from what the zipcode's minted
+/// properties are worth.
+pub fn check(state: &AppState, details: &HouseDetails, price: Money) -> Result<Option<Outlier>, String> {
+    let Some(config) = &state.config.outliers else {
+        return Ok(None);
+    };
+    // The bounds and zipcode statistics are in dollars.
+    let price = price.to_f64();
+    let mut reasons = Vec::new();
+    if price < config.min_price {
+        reasons.push(format!("below the minimum of {}", config.min_price));
+    }
+    if price > config.max_price {
+        reasons.push(format!("above the maximum of {}", config.max_price));
+    }
+    let mut prices = zipcode_prices(&state.db, details.zipcode)?;
+    if prices.len() >= MIN_ZIPCODE_SAMPLES {
+        let mean = analytics::average(&prices).unwrap_or_default();
+        let deviation = (prices.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / prices.len() as f64).sqrt();
+        let median = analytics::median(&mut prices).unwrap_or_default();
+        if deviation > 0.0 && (price - median).abs() > config.max_deviations * deviation {
+            reasons.push(format!(
+                "more than {} standard deviations ({:.0}) from the zipcode {} median of {}",
+                config.max_deviations, deviation, details.zipcode, median
+            ));
+        }
+    }
+    Ok((!reasons.is_empty()).then_some(Outlier { price, reasons }))
+}
+
+/// The error for minting a flagged prediction without `force`.
+pub fn rejection(outlier: &Outlier) -> ApiError {
+    ApiError::new(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        format!(
+            "The predicted price of {} looks wrong: {}. Send force=true to mint it anyway",
+            outlier.price,
+            outlier.reasons.join("; ")
+        ),
+    )
+    .with_details(json!({ "price": outlier.price, "reasons": outlier.reasons }))
+}
+
+/// Holds a job until an admin has looked at its flagged price.
+pub fn hold(db: &Db, id: i64, outlier: &Outlier) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "UPDATE mint_jobs SET outlier = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id, serde_json::to_string(outlier).unwrap_or_default()],
+    )
+    .map_err(|e| format!("Failed to update mint job: {}", e))?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct Review {
+    approve: bool,
+    reason: Option<String>,
+}
+
+/// Approves a job held for an outlier price, minting it as if it had been sent with force=true,
+/// or rejects it, cancelling the job.
+pub async fn review(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(review): Json<Review>,
+) -> Result<Json<MintJob>, ApiError> {
+    let job = jobs::get(&state.db, id)?
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Mint job {} not found", id)))?;
+    let conflict = |job: &MintJob| {
+        ApiError::new(StatusCode::CONFLICT, format!("Mint job {} is {} and not awaiting review", id, job.status))
+            .with_details(json!({ "status": job.status }))
+    };
+    if job.status != jobs::AWAITING_REVIEW {
+        return Err(conflict(&job));
+    }
+    if !review.approve {
+        if !jobs::transition(&state.db, id, jobs::AWAITING_REVIEW, jobs::CANCELLED)? {
+            return Err(conflict(&jobs::get(&state.db, id)?.unwrap_or(job)));
+        }
+        let reason = review.reason.as_deref().unwrap_or("the predicted price was rejected");
+        jobs::set_error(&state.db, id, &format!("Rejected in review: {}", reason))?;
+        println!("Mint job {} was rejected in review", id);
+        return Ok(Json(jobs::get(&state.db, id)?.ok_or("Mint job disappeared")?));
+    }
+    if !jobs::release_reviewed(&state, id)? {
+        return Err(conflict(&jobs::get(&state.db, id)?.unwrap_or(job)));
+    }
+    println!("Mint job {} was approved in review", id);
+    Ok(Json(jobs::get(&state.db, id)?.ok_or("Mint job disappeared")?))
+}
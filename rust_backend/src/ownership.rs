@@ -0,0 +1,274 @@
+use axum::extract::{Path, Query, State};
+use async_graphql::SimpleObject;
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::providers::Middleware;
+use ethers::types::{Address, H256, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::registry::{self, Collection};
+use crate::simulation;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+#[derive(Serialize, SimpleObject)]
+pub struct Transfer {
+    pub from: String,
+    pub to: String,
+    pub transaction_hash: String,
+    pub block_number: u64,
+}
+
+#[derive(Serialize)]
+pub struct OwnedToken {
+    pub collection: String,
+    pub token_id: String,
+}
+
+/// Where to start scanning a collection's logs: its first mint through this service.
+pub fn first_block(state: &AppState, collection: &Collection) -> Result<u64, String> {
+    let conn = state.db.lock().unwrap();
+    conn.query_row(
+        "SELECT coalesce(min(block_number), 0) FROM mints WHERE collection = ?1",
+        params![collection.name],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to load mints: {}", e))
+}
+
+/// The token's transfer history, oldest first, from the subgraph when one is configured.
+pub async fn transfers(state: &AppState, collection: &Collection, token_id: u64) -> Result<Vec<Transfer>, ApiError> {
+    if let Some(subgraph) = &state.config.subgraph {
+        return Ok(subgraph.transfers(state, collection.address, token_id).await?);
+    }
+    let events = state
+        .nft(collection)?
+        .transfer_filter()
+        .topic3(H256::from_low_u64_be(token_id))
+        .from_block(first_block(state, collection)?)
+        .query_with_meta()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read transfer events", e))?;
+    Ok(events
+        .into_iter()
+        .map(|(event, meta)| Transfer {
+            from: format!("{:?}", event.from),
+            to: format!("{:?}", event.to),
+            transaction_hash: format!("{:?}", meta.transaction_hash),
+            block_number: meta.block_number.as_u64(),
+        })
+        .collect())
+}
+
+/// Tokens `owner` holds now across the tenant's collections, from the subgraph or Alchemy's
+/// index when available. Otherwise this replays the transfers into `owner` and keeps the
+/// tokens they still own.
+pub async fn tokens_of_owner(state: &AppState, tenant: &Tenant, owner: Address) -> Result<Vec<OwnedToken>, ApiError> {
+    let collections = registry::list(&state.db, tenant.organization_id())?;
+    let name_of = |address: Address| {
+        collections
+            .iter()
+            .find(|collection| collection.address == address)
+            .map(|collection| collection.name.clone())
+    };
+
+    if let Some(subgraph) = &state.config.subgraph {
+        let contracts: Vec<Address> = collections.iter().map(|collection| collection.address).collect();
+        let tokens = subgraph.tokens_of_owner(state, owner, &contracts).await?;
+        return Ok(tokens
+            .into_iter()
+            .filter_map(|(contract, token_id)| Some((name_of(contract)?, token_id).into()))
+            .collect());
+    }
+    if let Some(alchemy) = &state.config.alchemy_nft {
+        let contracts: Vec<Address> = collections.iter().map(|collection| collection.address).collect();
+        match alchemy.tokens_of_owner(state, owner, &contracts).await {
+            Ok(tokens) => {
+                return Ok(tokens
+                    .into_iter()
+                    .filter_map(|(contract, token_id)| Some((name_of(contract)?, token_id).into()))
+                    .collect())
+            }
+            Err(e) => eprintln!("{}; scanning transfer logs instead", e),
+        }
+    }
+
+    let mut owned = Vec::new();
+    for collection in &collections {
+        let contract = state.nft(collection)?;
+        let received = contract
+            .transfer_filter()
+            .topic2(owner)
+            .from_block(first_block(state, collection)?)
+            .query()
+            .await
+            .map_err(|e| simulation::call_error("Failed to read transfer events", e))?;
+        let mut token_ids: Vec<U256> = received.into_iter().map(|event| event.token_id).collect();
+        token_ids.sort();
+        token_ids.dedup();
+        for token_id in token_ids {
+            let current = contract
+                .owner_of(token_id)
+                .call()
+                .await
+                .map_err(|e| simulation::call_error("Failed to read token owner", e))?;
+            if current == owner {
+                owned.push((collection.name.clone(), token_id).into());
+            }
+        }
+    }
+    Ok(owned)
+}
+
+pub async fn token_transfers(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<Transfer>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    Ok(Json(transfers(&state, &collection, token_id).await?))
+}
+
+pub async fn owner_tokens(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(owner): Path<Address>,
+) -> Result<Json<Vec<OwnedToken>>, ApiError> {
+    Ok(Json(tokens_of_owner(&state, &tenant, owner).await?))
+}
+
+#[derive(Deserialize)]
+pub struct OwnerQuery {
+    collection: Option<String>,
+    at_block: Option<u64>,
+    /// ISO 8601 date or timestamp (UTC); resolved to the last block at or before it.
+    at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoricalOwner {
+    token_id: u64,
+    block_number: u64,
+    /// `None` if the token wasn't minted yet, or was burned, at that block.
+    owner: Option<Address>,
+    /// `indexer` for the transfer history, `archive` for an `ownerOf` call at the block.
+    source: &'static str,
+}
+
+fn parse_timestamp(db: &Db, at: &str) -> Result<u64, ApiError> {
+    let conn = db.lock().unwrap();
+    let seconds: Option<i64> = conn
+        .query_row("SELECT CAST(strftime('%s', ?1) AS INTEGER)", params![at], |row| row.get(0))
+        .map_err(|e| format!("Failed to parse at: {}", e))?;
+    seconds.and_then(|seconds| u64::try_from(seconds).ok()).ok_or_else(|| {
+        ApiError::new(StatusCode::BAD_REQUEST, format!("at must be an ISO 8601 date or timestamp. Found: {}", at))
+    })
+}
+
+/// The last block mined at or before `timestamp`, by binary search over block headers.
+async fn block_at(state: &AppState, timestamp: u64, head: u64) -> Result<u64, ApiError> {
+    let block_time = |number: u64| async move {
+        state
+            .client
+            .get_block(number)
+            .await
+            .map_err(|e| format!("Failed to read block {}: {}", number, e))?
+            .map(|block| block.timestamp.as_u64())
+            .ok_or_else(|| format!("Block {} not found", number))
+    };
+    if block_time(0).await? > timestamp {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "at is before the chain's genesis block"));
+    }
+    let (mut low, mut high) = (0, head);
+    while low < high {
+        let middle = low + (high - low).div_ceil(2);
+        if block_time(middle).await? <= timestamp {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// The owner according to the indexed transfers, if the indexer has covered `block` and seen
+/// the token by then.
+fn indexed_owner(state: &AppState, collection: &Collection, token_id: u64, block: u64) -> Result<Option<Address>, String> {
+    let conn = state.db.lock().unwrap();
+    let covered: Option<u64> = conn
+        .query_row(
+            "SELECT block_number FROM indexer_cursors WHERE collection = ?1",
+            params![collection.name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load indexer cursor: {}", e))?;
+    if covered.is_none_or(|covered| covered < block) {
+        return Ok(None);
+    }
+    let owner: Option<String> = conn
+        .query_row(
+            "SELECT to_address FROM token_transfers
+             WHERE collection = ?1 AND token_id = ?2 AND block_number <= ?3
+             ORDER BY block_number DESC, log_index DESC LIMIT 1",
+            params![collection.name, token_id, block],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load transfers: {}", e))?;
+    Ok(owner.and_then(|owner| owner.parse().ok()))
+}
+
+/// Who held the token at a past block or date, for disputes over historical ownership.
+pub async fn owner_at(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<OwnerQuery>,
+) -> Result<Json<HistoricalOwner>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let state = state.on(&collection)?;
+    let head = state
+        .client
+        .get_block_number()
+        .await
+        .map_err(|e| format!("Failed to read the latest block: {}", e))?
+        .as_u64();
+    let block_number = match (query.at_block, &query.at) {
+        (Some(_), Some(_)) => return Err(ApiError::new(StatusCode::BAD_REQUEST, "Pass at_block or at, not both")),
+        (Some(block), None) if block > head => {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("at_block is past the latest block {}", head)))
+        }
+        (Some(block), None) => block,
+        (None, Some(at)) => block_at(&state, parse_timestamp(&state.db, at)?, head).await?,
+        (None, None) => head,
+    };
+
+    if let Some(owner) = indexed_owner(&state, &collection, token_id, block_number)? {
+        let owner = (owner != Address::zero()).then_some(owner);
+        return Ok(Json(HistoricalOwner { token_id, block_number, owner, source: "indexer" }));
+    }
+    let owner = match state
+        .nft(&collection)?
+        .owner_of(U256::from(token_id))
+        .block(block_number)
+        .call()
+        .await
+    {
+        Ok(owner) => Some(owner),
+        Err(e) if e.is_revert() => None,
+        // Full nodes prune state older than ~128 blocks.
+        Err(e) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to read the owner at block {} (an archive node is required): {}", block_number, e),
+            ))
+        }
+    };
+    Ok(Json(HistoricalOwner { token_id, block_number, owner, source: "archive" }))
+}
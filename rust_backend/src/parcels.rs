@@ -0,0 +1,195 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::enrichment;
+use crate::error::ApiError;
+use crate::mint::HouseDetails;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+/// A county assessor's parcel layer, queried as an ArcGIS feature service. Field names differ
+/// between counties, so each one is configurable.
+pub struct ParcelConfig {
+    url: String,
+    id_field: String,
+    sqft_field: String,
+    year_built_field: String,
+    /// Allowed difference between the declared and recorded living area, in percent.
+    sqft_tolerance: f64,
+}
+
+#[derive(Serialize)]
+pub struct Verification {
+    pub verified: bool,
+    pub parcel_id: Option<String>,
+    /// The query that produced the record, so a dispute can be checked against the source.
+    pub source: String,
+    pub mismatches: Vec<String>,
+    /// The assessor's record as returned.
+    pub record: Value,
+}
+
+#[derive(Serialize)]
+pub struct StoredVerification {
+    #[serde(flatten)]
+    verification: Verification,
+    created_at: String,
+}
+
+impl ParcelConfig {
+    /// Enabled by PARCEL_API_URL.
+    pub fn from_env() -> Option<Self> {
+        let url = optional_env("PARCEL_API_URL")?;
+        let field = |name: &str, default: &str| optional_env(name).unwrap_or_else(|| default.to_string());
+        let config = ParcelConfig {
+            id_field: field("PARCEL_ID_FIELD", "PIN"),
+            sqft_field: field("PARCEL_SQFT_FIELD", "SQFT_LIVING"),
+            year_built_field: field("PARCEL_YEAR_BUILT_FIELD", "YR_BUILT"),
+            sqft_tolerance: optional_env("PARCEL_SQFT_TOLERANCE")
+                .map(|percent| percent.parse().expect("PARCEL_SQFT_TOLERANCE must be a number"))
+                .unwrap_or(10.0),
+            url,
+        };
+        println!("PARCEL_API_URL: {}", config.url);
+        Some(config)
+    }
+
+    fn compare(&self, details: &HouseDetails, record: &Value) -> Vec<String> {
+        let number = |field: &str| {
+            let value = &record[field];
+            value.as_f64().or_else(|| value.as_str().and_then(|value| value.trim().parse().ok()))
+        };
+        let mut mismatches = Vec::new();
+        match number(&self.sqft_field) {
+            Some(recorded) if recorded > 0.0 => {
+                let difference = (details.sqft_living as f64 - recorded).abs() / recorded * 100.0;
+                if difference > self.sqft_tolerance {
+                    mismatches.push(format!(
+                        "Living area is {} sqft but county records show {}",
+                        details.sqft_living, recorded
+                    ));
+                }
+            }
+            _ => mismatches.push("County records have no living area".to_string()),
+        }
+        match number(&self.year_built_field) {
+            Some(recorded) if recorded as u64 == details.yr_built => {}
+            Some(recorded) => mismatches.push(format!(
+                "Year built is {} but county records show {}",
+                details.yr_built, recorded
+            )),
+            None => mismatches.push("County records have no year built".to_string()),
+        }
+        mismatches
+    }
+}
+
+impl Verification {
+    pub fn attributes(&self) -> Vec<Value> {
+        let mut attributes = vec![json!({ "trait_type": "Verified", "value": self.verified })];
+        if let Some(parcel_id) = &self.parcel_id {
+            attributes.push(json!({ "trait_type": "Parcel ID", "value": parcel_id }));
+        }
+        attributes
+    }
+}
+
+/// Cross-checks the declared property against county records. `None` when verification is
+/// off or the assessor couldn't be reached, in which case the token carries no verdict.
+pub async fn verify(state: &AppState, details: &HouseDetails) -> Option<Verification> {
+    let config = state.config.parcels.as_ref()?;
+    let source = format!("{}?geometry={},{}", config.url, details.long, details.lat);
+    let fields = format!("{},{},{}", config.id_field, config.sqft_field, config.year_built_field);
+    let record = match enrichment::arcgis_feature(&state.http, &config.url, details.lat, details.long, &fields).await {
+        Ok(record) => record,
+        Err(e) => {
+            eprintln!("Parcel verification failed: {}", e);
+            return None;
+        }
+    };
+    let Some(record) = record else {
+        return Some(Verification {
+            verified: false,
+            parcel_id: None,
+            source,
+            mismatches: vec!["No parcel found at the property's coordinates".to_string()],
+            record: Value::Null,
+        });
+    };
+    let mismatches = config.compare(details, &record);
+    let parcel_id = match &record[&config.id_field] {
+        Value::Null => None,
+        Value::String(id) => Some(id.clone()),
+        id => Some(id.to_string()),
+    };
+    Some(Verification {
+        verified: mismatches.is_empty(),
+        parcel_id,
+        source,
+        mismatches,
+        record,
+    })
+}
+
+pub fn record(db: &Db, collection: &str, token_id: u64, verification: &Verification) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO parcel_verifications (collection, token_id, verified, parcel_id, source, mismatches, record)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            collection,
+            token_id,
+            verification.verified,
+            verification.parcel_id,
+            verification.source,
+            serde_json::to_string(&verification.mismatches).unwrap_or_default(),
+            verification.record.to_string(),
+        ],
+    )
+    .map_err(|e| format!("Failed to record parcel verification: {}", e))?;
+    Ok(())
+}
+
+/// Every verification of the token, newest first.
+pub async fn verifications(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Vec<StoredVerification>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT verified, parcel_id, source, mismatches, record, created_at FROM parcel_verifications
+             WHERE collection = ?1 AND token_id = ?2 ORDER BY id DESC",
+        )
+        .map_err(|e| format!("Failed to load parcel verifications: {}", e))?;
+    let rows = stmt
+        .query_map(params![collection.name, token_id], |row| {
+            let mismatches: String = row.get(3)?;
+            let record: String = row.get(4)?;
+            Ok(StoredVerification {
+                verification: Verification {
+                    verified: row.get(0)?,
+                    parcel_id: row.get(1)?,
+                    source: row.get(2)?,
+                    mismatches: serde_json::from_str(&mismatches).unwrap_or_default(),
+                    record: serde_json::from_str(&record).unwrap_or_default(),
+                },
+                created_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("Failed to load parcel verifications: {}", e))?;
+    Ok(Json(
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to load parcel verifications: {}", e))?,
+    ))
+}
@@ -0,0 +1,53 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// The mint payload version this server reads natively. Clients send theirs as `schema_version`;
+/// payloads without one are version 1, from before payloads were versioned.
+pub const CURRENT_VERSION: u64 = 2;
+
+/// Upgrades a payload by one version in place.
+type Adapter = fn(&mut Map<String, Value>) -> Result<(), String>;
+
+/// `ADAPTERS[n]` upgrades version `n + 1` to `n + 2`. When a field is added, renamed or changes
+/// shape, bump CURRENT_VERSION and append the adapter that fills or maps it for older clients.
+const ADAPTERS: &[Adapter] = &[v1_to_v2];
+
+/// Version 2 only introduced `schema_version` itself, so a version 1 payload is already valid.
+fn v1_to_v2(_payload: &mut Map<String, Value>) -> Result<(), String> {
+    Ok(())
+}
+
+/// Brings a payload of any supported version up to CURRENT_VERSION. `schema_version` is taken
+/// out, so the result reads like a payload from a current client.
+pub fn upgrade(mut payload: Map<String, Value>) -> Result<Map<String, Value>, String> {
+    let version = match payload.remove("schema_version") {
+        None | Some(Value::Null) => 1,
+        Some(version) => version
+            .as_u64()
+            .filter(|version| *version >= 1)
+            .ok_or_else(|| format!("schema_version must be a positive integer, got {}", version))?,
+    };
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "schema_version {} is newer than this server supports (up to {})",
+            version, CURRENT_VERSION
+        ));
+    }
+    for (from, adapter) in ADAPTERS.iter().enumerate().skip(version as usize - 1) {
+        adapter(&mut payload).map_err(|e| format!("Can't upgrade a version {} payload: {}", from + 1, e))?;
+    }
+    Ok(payload)
+}
+
+/// A request body that may come from an older client: it is upgraded to the current version
+/// before `T` reads it.
+pub struct Versioned<T>(pub T);
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let payload = Map::deserialize(deserializer)?;
+        let payload = upgrade(payload).map_err(D::Error::custom)?;
+        T::deserialize(Value::Object(payload)).map(Versioned).map_err(D::Error::custom)
+    }
+}
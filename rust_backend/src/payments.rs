@@ -0,0 +1,389 @@
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::Json;
+use ethers::types::{Address, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::env;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::auth;
+use crate::bindings::Erc20;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::jobs;
+use crate::mint::MintRequest;
+use crate::organizations::Tenant;
+use crate::simulation;
+use crate::state::AppState;
+use crate::tx;
+
+pub struct StripeConfig {
+    secret_key: String,
+    /// Swapped by a config reload, so the secret can be rotated without a restart.
+    pub webhook_secret: RwLock<String>,
+    fee_cents: u64,
+    currency: String,
+    success_url: String,
+    cancel_url: String,
+}
+
+/// A mint fee charged on-chain in an ERC-20 token.
+pub struct TokenFee {
+    token: Address,
+    amount: U256,
+    recipient: Option<Address>,
+}
+
+#[derive(Serialize)]
+pub struct FeePayment {
+    pub token: Address,
+    pub amount: String,
+    pub payer: Address,
+    pub recipient: Address,
+    pub transaction_hash: String,
+}
+
+/// A fee a mint went ahead without because it could no longer be pulled from the payer. It
+/// stays owed until an admin collects it.
+#[derive(Serialize)]
+pub struct UnpaidFee {
+    pub id: i64,
+    pub collection: String,
+    pub token_id: Option<u64>,
+    pub payer: String,
+    pub token: String,
+    pub amount: String,
+    pub mint_transaction_hash: String,
+    pub error: String,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct CheckoutResponse {
+    pub job_id: i64,
+    status: &'static str,
+    checkout_url: String,
+}
+
+impl StripeConfig {
+    /// The payment gate is enabled by setting STRIPE_SECRET_KEY.
+    pub fn from_env() -> Option<Self> {
+        let secret_key = optional_env("STRIPE_SECRET_KEY")?;
+        println!("STRIPE_SECRET_KEY: Loaded (mints require payment)");
+        Some(StripeConfig {
+            secret_key,
+            webhook_secret: RwLock::new(
+                env::var("STRIPE_WEBHOOK_SECRET").expect("STRIPE_WEBHOOK_SECRET is not set in .env"),
+            ),
+            fee_cents: env::var("MINT_FEE_CENTS")
+                .expect("MINT_FEE_CENTS is not set in .env")
+                .parse()
+                .expect("MINT_FEE_CENTS must be a number"),
+            currency: optional_env("MINT_FEE_CURRENCY").unwrap_or_else(|| "usd".to_string()),
+            success_url: env::var("STRIPE_SUCCESS_URL").expect("STRIPE_SUCCESS_URL is not set in .env"),
+            cancel_url: env::var("STRIPE_CANCEL_URL").expect("STRIPE_CANCEL_URL is not set in .env"),
+        })
+    }
+}
+
+impl TokenFee {
+    /// The token fee is enabled by setting MINT_FEE_TOKEN_AMOUNT; the token defaults to USDC.
+    pub fn from_env(usdc_address: Option<Address>) -> Option<Self> {
+        let amount = optional_env("MINT_FEE_TOKEN_AMOUNT")?;
+        let amount = U256::from_dec_str(&amount).expect("MINT_FEE_TOKEN_AMOUNT must be a number in base units");
+        let token = optional_env("MINT_FEE_TOKEN")
+            .map(|address| address.parse().expect("Invalid MINT_FEE_TOKEN address"))
+            .or(usdc_address)
+            .expect("MINT_FEE_TOKEN_AMOUNT is set but neither MINT_FEE_TOKEN nor USDC_ADDRESS is");
+        let recipient = optional_env("MINT_FEE_RECIPIENT")
+            .map(|address| address.parse().expect("Invalid MINT_FEE_RECIPIENT address"));
+        println!("MINT_FEE_TOKEN_AMOUNT: {} of token {:?}", amount, token);
+        Some(TokenFee { token, amount, recipient })
+    }
+}
+
+/// Checks that `payer` holds the fee and has approved the backend wallet to pull it.
+pub async fn check_token_fee(state: &AppState, fee: &TokenFee, payer: Address) -> Result<(), ApiError> {
+    let token = Erc20::new(fee.token, state.client.clone());
+    let balance = token
+        .balance_of(payer)
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read fee token balance", e))?;
+    let allowance = token
+        .allowance(payer, state.client.address())
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read fee token allowance", e))?;
+
+    let shortfall = if balance < fee.amount {
+        Some("balance")
+    } else if allowance < fee.amount {
+        Some("allowance")
+    } else {
+        None
+    };
+    match shortfall {
+        Some(what) => Err(ApiError::new(
+            StatusCode::PAYMENT_REQUIRED,
+            format!("Insufficient fee token {} for {:?}", what, payer),
+        )
+        .with_details(serde_json::json!({
+            "token": fee.token,
+            "fee": fee.amount.to_string(),
+            "balance": balance.to_string(),
+            "allowance": allowance.to_string(),
+            "spender": state.client.address(),
+        }))),
+        None => Ok(()),
+    }
+}
+
+/// Pulls the fee from `payer` with `transferFrom`.
+pub async fn collect_token_fee(state: &AppState, fee: &TokenFee, payer: Address) -> Result<FeePayment, ApiError> {
+    let recipient = fee.recipient.unwrap_or(state.client.address());
+    let token = Erc20::new(fee.token, state.client.clone());
+    println!("Collecting mint fee of {} from {:?}...", fee.amount, payer);
+    let receipt = tx::submit(state, token.transfer_from(payer, recipient, fee.amount)).await?;
+    Ok(FeePayment {
+        token: fee.token,
+        amount: fee.amount.to_string(),
+        payer,
+        recipient,
+        transaction_hash: format!("{:?}", receipt.transaction_hash),
+    })
+}
+
+/// Records the fee collected for the mint in `mint_transaction_hash`.
+pub fn record_token_fee(
+    db: &Db,
+    collection: &str,
+    payment: &FeePayment,
+    token_id: Option<u64>,
+    mint_transaction_hash: &str,
+) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO mint_fees (collection, token_id, payer, token, amount, fee_transaction_hash, mint_transaction_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            collection,
+            token_id,
+            format!("{:?}", payment.payer),
+            format!("{:?}", payment.token),
+            payment.amount,
+            payment.transaction_hash,
+            mint_transaction_hash,
+        ],
+    )
+    .map_err(|e| format!("Failed to record mint fee: {}", e))?;
+    Ok(())
+}
+
+/// Records that the fee for the mint in `mint_transaction_hash` couldn't be pulled from `payer`.
+pub fn record_unpaid_fee(
+    db: &Db,
+    collection: &str,
+    fee: &TokenFee,
+    payer: Address,
+    token_id: Option<u64>,
+    mint_transaction_hash: &str,
+    error: &str,
+) -> Result<UnpaidFee, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "INSERT INTO unpaid_mint_fees (collection, token_id, payer, token, amount, mint_transaction_hash, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         RETURNING id, collection, token_id, payer, token, amount, mint_transaction_hash, error, created_at",
+        params![
+            collection,
+            token_id,
+            format!("{:?}", payer),
+            format!("{:?}", fee.token),
+            fee.amount.to_string(),
+            mint_transaction_hash,
+            error,
+        ],
+        unpaid_fee,
+    )
+    .map_err(|e| format!("Failed to record unpaid mint fee: {}", e))
+}
+
+fn unpaid_fee(row: &rusqlite::Row) -> rusqlite::Result<UnpaidFee> {
+    Ok(UnpaidFee {
+        id: row.get(0)?,
+        collection: row.get(1)?,
+        token_id: row.get(2)?,
+        payer: row.get(3)?,
+        token: row.get(4)?,
+        amount: row.get(5)?,
+        mint_transaction_hash: row.get(6)?,
+        error: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// Fees still owed for mints that went ahead without them, oldest first.
+pub async fn list_unpaid_fees(State(state): State<AppState>) -> Result<Json<Vec<UnpaidFee>>, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let unpaid = conn
+        .prepare(
+            "SELECT id, collection, token_id, payer, token, amount, mint_transaction_hash, error, created_at
+             FROM unpaid_mint_fees WHERE paid_at IS NULL ORDER BY id",
+        )
+        .and_then(|mut stmt| stmt.query_map([], unpaid_fee)?.collect())
+        .map_err(|e| format!("Failed to load unpaid mint fees: {}", e))?;
+    Ok(Json(unpaid))
+}
+
+/// Tries again to pull an unpaid fee from its payer, who has to have approved it again.
+pub async fn collect_unpaid_fee(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<FeePayment>, ApiError> {
+    let unpaid = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT id, collection, token_id, payer, token, amount, mint_transaction_hash, error, created_at
+             FROM unpaid_mint_fees WHERE id = ?1 AND paid_at IS NULL",
+            params![id],
+            unpaid_fee,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load unpaid mint fee: {}", e))?
+    };
+    let unpaid = unpaid.ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("No unpaid mint fee {}", id)))?;
+    let fee = TokenFee {
+        token: unpaid.token.parse().map_err(|_| format!("Invalid fee token {}", unpaid.token))?,
+        amount: U256::from_dec_str(&unpaid.amount).map_err(|_| format!("Invalid fee amount {}", unpaid.amount))?,
+        recipient: state.config.token_fee.as_ref().and_then(|fee| fee.recipient),
+    };
+    let payer: Address = unpaid.payer.parse().map_err(|_| format!("Invalid fee payer {}", unpaid.payer))?;
+    check_token_fee(&state, &fee, payer).await?;
+    let payment = collect_token_fee(&state, &fee, payer).await?;
+    record_token_fee(&state.db, &unpaid.collection, &payment, unpaid.token_id, &unpaid.mint_transaction_hash)?;
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE unpaid_mint_fees SET fee_transaction_hash = ?2, paid_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![id, payment.transaction_hash],
+        )
+        .map_err(|e| format!("Failed to update unpaid mint fee: {}", e))?;
+    }
+    println!("Collected unpaid mint fee {} in {}", id, payment.transaction_hash);
+    Ok(Json(payment))
+}
+
+/// Parks the mint as a job awaiting payment and opens a Stripe Checkout session for the fee.
+pub async fn start_checkout(
+    state: &AppState,
+    stripe: &StripeConfig,
+    tenant: &Tenant,
+    request: &MintRequest,
+) -> Result<CheckoutResponse, ApiError> {
+    let job_id = jobs::create(state, request, jobs::AWAITING_PAYMENT, tenant.organization_id())?;
+    let job = job_id.to_string();
+    let fee = stripe.fee_cents.to_string();
+    let product = format!("Property NFT mint: {}", request.details.name);
+    let form = [
+        ("mode", "payment"),
+        ("success_url", stripe.success_url.as_str()),
+        ("cancel_url", stripe.cancel_url.as_str()),
+        ("client_reference_id", job.as_str()),
+        ("metadata[mint_job_id]", job.as_str()),
+        ("line_items[0][quantity]", "1"),
+        ("line_items[0][price_data][currency]", stripe.currency.as_str()),
+        ("line_items[0][price_data][unit_amount]", fee.as_str()),
+        ("line_items[0][price_data][product_data][name]", product.as_str()),
+    ];
+
+    let session: Value = state
+        .http
+        .post("https://api.stripe.com/v1/checkout/sessions")
+        .basic_auth(&stripe.secret_key, None::<&str>)
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call Stripe: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Stripe rejected the checkout session: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Stripe response: {}", e))?;
+
+    let session_id = session["id"].as_str().ok_or("Stripe response is missing the session id")?;
+    let checkout_url = session["url"].as_str().ok_or("Stripe response is missing the checkout url")?;
+    jobs::set_payment(&state.db, job_id, Some(session_id), "pending")?;
+    println!("Mint job {} awaiting payment in checkout session {}", job_id, session_id);
+
+    Ok(CheckoutResponse {
+        job_id,
+        status: jobs::AWAITING_PAYMENT,
+        checkout_url: checkout_url.to_string(),
+    })
+}
+
+/// Closes a checkout session so it can no longer be paid.
+pub async fn expire_checkout(state: &AppState, stripe: &StripeConfig, session_id: &str) -> Result<(), String> {
+    state
+        .http
+        .post(format!("https://api.stripe.com/v1/checkout/sessions/{}/expire", session_id))
+        .basic_auth(&stripe.secret_key, None::<&str>)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call Stripe: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Stripe rejected the expiry: {}", e))?;
+    Ok(())
+}
+
+pub async fn stripe_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, ApiError> {
+    let stripe = state
+        .config
+        .stripe
+        .as_ref()
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "Payments are not enabled"))?;
+    let signature = headers
+        .get("Stripe-Signature")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if !auth::verify_timestamped_signature(signature, &body, &stripe.webhook_secret.read().unwrap(), now) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Invalid Stripe signature"));
+    }
+
+    let event: Value = serde_json::from_slice(&body)
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("Invalid webhook payload: {}", e)))?;
+    let session = &event["data"]["object"];
+    let Some(job_id) = session["metadata"]["mint_job_id"].as_str().and_then(|id| id.parse().ok()) else {
+        // Not one of our checkout sessions; acknowledge so Stripe stops retrying.
+        return Ok(StatusCode::OK);
+    };
+
+    match event["type"].as_str() {
+        Some("checkout.session.completed") | Some("checkout.session.async_payment_succeeded")
+            if session["payment_status"] == "paid" =>
+        {
+            jobs::set_payment(&state.db, job_id, None, "paid")?;
+            if jobs::release(&state, job_id)? {
+                println!("Payment confirmed for mint job {}", job_id);
+            }
+        }
+        Some("checkout.session.expired") | Some("checkout.session.async_payment_failed") => {
+            jobs::set_payment(&state.db, job_id, None, "failed")?;
+            jobs::transition(&state.db, job_id, jobs::AWAITING_PAYMENT, jobs::PAYMENT_EXPIRED)?;
+            println!("Payment failed or expired for mint job {}", job_id);
+        }
+        _ => {}
+    }
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,317 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::money::{Currency, Money};
+use crate::organizations::{self, Tenant};
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::valuations;
+
+const PINATA_PIN_LIST_URL: &str = "https://api.pinata.cloud/data/pinList";
+const CHECK_GATEWAYS: &str = "https://ipfs.io/ipfs/,https://dweb.link/ipfs/";
+const GATEWAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const PINNED: &str = "pinned";
+pub const UNPINNED: &str = "unpinned";
+/// Pinned, but no gateway could serve it.
+pub const UNREACHABLE: &str = "unreachable";
+pub const REPINNED: &str = "repinned";
+pub const REPIN_FAILED: &str = "repin_failed";
+
+pub struct PinConfig {
+    list_url: String,
+    jwt: String,
+    gateways: Vec<String>,
+    interval_secs: u64,
+}
+
+impl PinConfig {
+    /// Enabled along with the ipfs metadata store, by IPFS_PINNING_JWT.
+    pub fn from_env() -> Option<Self> {
+        let jwt = optional_env("IPFS_PINNING_JWT")?;
+        let gateways = optional_env("IPFS_CHECK_GATEWAYS").unwrap_or_else(|| CHECK_GATEWAYS.to_string());
+        let interval_secs = optional_env("PIN_CHECK_INTERVAL_SECS")
+            .map(|secs| secs.parse().expect("PIN_CHECK_INTERVAL_SECS must be a number"))
+            .unwrap_or(6 * 60 * 60);
+        println!("IPFS_CHECK_GATEWAYS: {} (every {}s)", gateways, interval_secs);
+        Some(PinConfig {
+            list_url: optional_env("IPFS_PIN_LIST_URL").unwrap_or_else(|| PINATA_PIN_LIST_URL.to_string()),
+            jwt,
+            gateways: gateways.split(',').map(|gateway| gateway.trim().to_string()).collect(),
+            interval_secs,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PinStatusQuery {
+    collection: Option<String>,
+    /// Check the pin and gateways now instead of returning the last result.
+    #[serde(default)]
+    refresh: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct GatewayCheck {
+    pub gateway: String,
+    pub reachable: bool,
+}
+
+#[derive(Serialize)]
+pub struct PinStatus {
+    pub collection: String,
+    pub token_id: u64,
+    pub cid: String,
+    pub status: String,
+    pub pinned: bool,
+    pub gateways: Vec<GatewayCheck>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub repinned_at: Option<String>,
+    pub checked_at: String,
+}
+
+/// The CID an `ipfs://` token URI points at.
+pub fn cid(token_uri: &str) -> Option<&str> {
+    let path = token_uri.strip_prefix("ipfs://")?;
+    path.split('/').next().filter(|cid| !cid.is_empty())
+}
+
+fn load(state: &AppState, collection: &str, token_id: u64) -> Result<Option<PinStatus>, String> {
+    let conn = state.db.lock().unwrap();
+    conn.query_row(
+        "SELECT cid, status, pinned, gateways, error, repinned_at, checked_at
+         FROM pin_status WHERE collection = ?1 AND token_id = ?2",
+        params![collection, token_id],
+        |row| {
+            Ok(PinStatus {
+                collection: collection.to_string(),
+                token_id,
+                cid: row.get(0)?,
+                status: row.get(1)?,
+                pinned: row.get(2)?,
+                gateways: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+                error: row.get(4)?,
+                repinned_at: row.get(5)?,
+                checked_at: row.get(6)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load pin status: {}", e))
+}
+
+/// Whether the pinning service still pins `cid`.
+async fn is_pinned(state: &AppState, config: &PinConfig, cid: &str) -> Result<bool, String> {
+    let response: Value = state
+        .http
+        .get(&config.list_url)
+        .bearer_auth(&config.jwt)
+        .query(&[("hashContains", cid), ("status", "pinned")])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to query pins: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Pin query failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid pin list: {}", e))?;
+    Ok(response["count"].as_u64().unwrap_or(0) > 0)
+}
+
+async fn check_gateways(state: &AppState, config: &PinConfig, cid: &str) -> Vec<GatewayCheck> {
+    let mut checks = Vec::new();
+    for gateway in &config.gateways {
+        let response = state.http.head(format!("{}{}", gateway, cid)).timeout(GATEWAY_TIMEOUT).send().await;
+        checks.push(GatewayCheck {
+            gateway: gateway.clone(),
+            reachable: response.is_ok_and(|response| response.status().is_success()),
+        });
+    }
+    checks
+}
+
+/// Re-uploads the stored metadata copy. Pinning the same JSON yields the same CID; if the
+/// service produces a different one, the token URI is rewritten on-chain to match.
+async fn repin(state: &AppState, collection: &Collection, token_id: u64, cid: &str) -> Result<(), String> {
+    let (price, metadata) = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT COALESCE(price_cents, CAST(ROUND(price * 100) AS INTEGER)), metadata FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection.name, token_id],
+            |row| Ok((Money::new(row.get(0)?, Currency::USD), row.get::<_, String>(1)?)),
+        )
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+    };
+    let metadata: Value = serde_json::from_str(&metadata).map_err(|e| format!("Invalid stored metadata: {}", e))?;
+    let store = state.config.storage.store(Some("ipfs")).map_err(|e| e.to_string())?;
+    let token_uri = store.put(&state.http, &metadata).await?;
+    if self::cid(&token_uri) == Some(cid) {
+        return Ok(());
+    }
+    println!("Re-pinning token {} of {} moved it from {} to {}", token_id, collection.name, cid, token_uri);
+    let tenant = match &collection.organization {
+        Some(org) => Tenant(Some(organizations::load(&state.db, org)?.ok_or("Collection organization was deleted")?)),
+        None => Tenant(None),
+    };
+    valuations::write_metadata(state, &tenant, collection, token_id, price, &metadata)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Checks the token's CID against the pinning service and gateways, re-pinning it from the
+/// stored metadata when `repair` is set and it is unpinned or unreachable.
+pub async fn check(
+    state: &AppState,
+    config: &PinConfig,
+    collection: &Collection,
+    token_id: u64,
+    repair: bool,
+) -> Result<PinStatus, ApiError> {
+    let token_uri = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT token_uri FROM mints WHERE collection = ?1 AND token_id = ?2 AND metadata_storage = 'ipfs'",
+            params![collection.name, token_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+        .flatten()
+    };
+    let cid = token_uri.as_deref().and_then(cid).ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} is not stored on IPFS", token_id))
+    })?;
+
+    let (pinned, mut error) = match is_pinned(state, config, cid).await {
+        Ok(pinned) => (pinned, None),
+        Err(e) => (false, Some(e)),
+    };
+    let gateways = check_gateways(state, config, cid).await;
+    let reachable = gateways.iter().any(|check| check.reachable);
+    let mut status = match (pinned, reachable) {
+        (true, true) => PINNED,
+        (false, _) => UNPINNED,
+        (true, false) => UNREACHABLE,
+    };
+    let mut repinned = false;
+    // A failed pin query alone doesn't mean the content is gone.
+    if repair && status != PINNED && error.is_none() {
+        match repin(state, collection, token_id, cid).await {
+            Ok(()) => {
+                status = REPINNED;
+                repinned = true;
+            }
+            Err(e) => {
+                status = REPIN_FAILED;
+                error = Some(e);
+            }
+        }
+    }
+
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO pin_status (collection, token_id, cid, status, pinned, gateways, error, repinned_at, checked_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, CASE WHEN ?8 THEN CURRENT_TIMESTAMP END, CURRENT_TIMESTAMP)
+             ON CONFLICT (collection, token_id) DO UPDATE SET
+                 cid = ?3, status = ?4, pinned = ?5, gateways = ?6, error = ?7,
+                 repinned_at = CASE WHEN ?8 THEN CURRENT_TIMESTAMP ELSE repinned_at END,
+                 checked_at = CURRENT_TIMESTAMP",
+            params![
+                collection.name,
+                token_id,
+                cid,
+                status,
+                pinned,
+                serde_json::to_string(&gateways).unwrap_or_default(),
+                error,
+                repinned,
+            ],
+        )
+        .map_err(|e| format!("Failed to record pin status: {}", e))?;
+    }
+    Ok(load(state, &collection.name, token_id)?.ok_or("Pin status disappeared")?)
+}
+
+/// Checks every IPFS-stored token and re-pins the ones that went missing.
+async fn check_all(state: &AppState, config: &PinConfig) -> Result<(), String> {
+    let tokens = {
+        let conn = state.db.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT collection, token_id FROM mints WHERE metadata_storage = 'ipfs' AND token_uri IS NOT NULL")
+            .map_err(|e| format!("Failed to list IPFS tokens: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))
+            .map_err(|e| format!("Failed to list IPFS tokens: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to list IPFS tokens: {}", e))?
+    };
+    for (name, token_id) in tokens {
+        // A collection that can't be resolved, e.g. one since removed, only skips its own tokens.
+        let collection = match registry::resolve(&state.db, Some(&name)) {
+            Ok(collection) => collection,
+            Err(e) => {
+                eprintln!("Pin check for token {} of {} failed: {}", token_id, name, e);
+                continue;
+            }
+        };
+        match check(state, config, &collection, token_id, true).await {
+            Ok(status) if status.status != PINNED => {
+                eprintln!("Token {} of {} ({}) was {}", token_id, collection.name, status.cid, status.status)
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Pin check for token {} of {} failed: {}", token_id, collection.name, e),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the re-pin job every PIN_CHECK_INTERVAL_SECS; 0 disables it.
+pub fn start(state: &AppState) {
+    let Some(config) = &state.config.pins else {
+        return;
+    };
+    if config.interval_secs == 0 {
+        return;
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        let config = state.config.pins.as_ref().unwrap();
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_all(&state, config).await {
+                eprintln!("Re-pin job failed: {}", e);
+            }
+        }
+    });
+}
+
+pub async fn pin_status(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<PinStatusQuery>,
+) -> Result<Json<PinStatus>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let config = state
+        .config
+        .pins
+        .as_ref()
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "IPFS pinning is not configured"))?;
+    if !query.refresh {
+        if let Some(status) = load(&state, &collection.name, token_id)? {
+            return Ok(Json(status));
+        }
+    }
+    Ok(Json(check(&state, config, &collection, token_id, false).await?))
+}
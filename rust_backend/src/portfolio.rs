@@ -0,0 +1,113 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use ethers::types::Address;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::ownership;
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct Portfolio {
+    owner: String,
+    tokens: usize,
+    /// Sum of the latest valuations of the tokens this service valued.
+    total_value: f64,
+    /// Sum of the same tokens' valuations at mint.
+    minted_value: f64,
+    change: f64,
+    change_percent: Option<f64>,
+    holdings: Vec<Holding>,
+}
+
+#[derive(Serialize)]
+pub struct Holding {
+    collection: String,
+    token_id: String,
+    name: Option<String>,
+    /// Null for tokens that were not minted through this service.
+    value: Option<f64>,
+    valued_at: Option<String>,
+    minted_value: Option<f64>,
+    change: Option<f64>,
+    change_percent: Option<f64>,
+}
+
+fn percent(change: f64, base: f64) -> Option<f64> {
+    (base != 0.0).then(|| change / base * 100.0)
+}
+
+/// Mint valuation, latest valuation, when it was made, and the property name.
+type Valued = (f64, f64, String, Option<String>);
+
+fn valuations(state: &AppState, collection: &str, token_id: u64) -> Result<Option<Valued>, String> {
+    let conn = state.db.lock().unwrap();
+    conn.query_row(
+        "SELECT (SELECT price FROM valuations
+                 WHERE collection = ?1 AND token_id = ?2 AND valuation_type = 'market' ORDER BY id LIMIT 1),
+                v.price, v.created_at,
+                (SELECT json_extract(details, '$.name') FROM mints WHERE collection = ?1 AND token_id = ?2)
+         FROM valuations v WHERE v.collection = ?1 AND v.token_id = ?2 AND v.valuation_type = 'market'
+         ORDER BY v.id DESC LIMIT 1",
+        params![collection, token_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load valuations: {}", e))
+}
+
+/// Values everything `owner` holds in the tenant's collections at the latest valuations.
+pub async fn portfolio(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(owner): Path<Address>,
+) -> Result<Json<Portfolio>, ApiError> {
+    let tokens = ownership::tokens_of_owner(&state, &tenant, owner).await?;
+    let mut holdings = Vec::with_capacity(tokens.len());
+    let (mut total_value, mut minted_value) = (0.0, 0.0);
+    for token in tokens {
+        let valued = match token.token_id.parse() {
+            Ok(token_id) => valuations(&state, &token.collection, token_id)?,
+            Err(_) => None,
+        };
+        let holding = match valued {
+            Some((minted, value, valued_at, name)) => {
+                total_value += value;
+                minted_value += minted;
+                Holding {
+                    collection: token.collection,
+                    token_id: token.token_id,
+                    name,
+                    value: Some(value),
+                    valued_at: Some(valued_at),
+                    minted_value: Some(minted),
+                    change: Some(value - minted),
+                    change_percent: percent(value - minted, minted),
+                }
+            }
+            None => Holding {
+                collection: token.collection,
+                token_id: token.token_id,
+                name: None,
+                value: None,
+                valued_at: None,
+                minted_value: None,
+                change: None,
+                change_percent: None,
+            },
+        };
+        holdings.push(holding);
+    }
+
+    Ok(Json(Portfolio {
+        owner: format!("{:?}", owner),
+        tokens: holdings.len(),
+        total_value,
+        minted_value,
+        change: total_value - minted_value,
+        change_percent: percent(total_value - minted_value, minted_value),
+        holdings,
+    }))
+}
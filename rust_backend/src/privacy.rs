@@ -0,0 +1,184 @@
+use axum::extract::{Path, State};
+use axum::Json;
+use ethers::types::Address;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::error::ApiError;
+use crate::private;
+use crate::state::AppState;
+
+/// Replaces scrubbed off-chain JSON (house details, coordinates, mint requests).
+const REDACTED: &str = r#"{"redacted":true}"#;
+
+#[derive(Serialize)]
+pub struct DeletionResponse {
+    owner: String,
+    mints_scrubbed: usize,
+    mint_jobs_scrubbed: usize,
+    dead_letters_scrubbed: usize,
+    parcel_records_scrubbed: usize,
+    dispute_evidence_deleted: usize,
+    private_records_deleted: usize,
+    kyc_records_deleted: usize,
+    /// Kept for legal and financial record-keeping.
+    retained: &'static [&'static str],
+}
+
+const RETAINED: &[&str] = &["listings", "offers", "mint_fees", "sanctions_rejections", "audit_logs"];
+
+fn rows(conn: &Connection, sql: &str, owner: &str) -> Result<Vec<Value>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("Failed to export data: {}", e))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+    let mut rows = stmt.query(params![owner]).map_err(|e| format!("Failed to export data: {}", e))?;
+    let mut out = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| format!("Failed to export data: {}", e))? {
+        let mut object = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(i).map_err(|e| format!("Failed to export data: {}", e))? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(n) => json!(n),
+                ValueRef::Real(n) => json!(n),
+                // JSON columns are exported as nested objects rather than strings.
+                ValueRef::Text(text) => {
+                    let text = String::from_utf8_lossy(text);
+                    match serde_json::from_str::<Value>(&text) {
+                        Ok(value) if value.is_object() => value,
+                        _ => Value::String(text.into_owned()),
+                    }
+                }
+                ValueRef::Blob(blob) => Value::String(ethers::utils::hex::encode(blob)),
+            };
+            object.insert(column.clone(), value);
+        }
+        out.push(Value::Object(object));
+    }
+    Ok(out)
+}
+
+/// Everything held off-chain about an address, as a data subject access export.
+pub async fn export(State(state): State<AppState>, Path(owner): Path<Address>) -> Result<Json<Value>, ApiError> {
+    let owner = format!("{:?}", owner);
+    let conn = state.db.lock().unwrap();
+    Ok(Json(json!({
+        "owner": owner,
+        "mints": rows(&conn, "SELECT * FROM mints WHERE recipient = ?1", &owner)?,
+        "mint_jobs": rows(
+            &conn,
+            "SELECT * FROM mint_jobs WHERE lower(json_extract(request, '$.recipient')) = ?1",
+            &owner,
+        )?,
+        "mint_dead_letters": rows(
+            &conn,
+            "SELECT * FROM mint_dead_letters WHERE lower(json_extract(request, '$.recipient')) = ?1",
+            &owner,
+        )?,
+        "parcel_verifications": rows(
+            &conn,
+            "SELECT p.* FROM parcel_verifications p
+             JOIN mints m ON m.collection = p.collection AND m.token_id = p.token_id
+             WHERE m.recipient = ?1",
+            &owner,
+        )?,
+        "disputes": rows(&conn, "SELECT * FROM disputes WHERE owner = ?1", &owner)?,
+        "dispute_evidence": rows(
+            &conn,
+            "SELECT e.id, e.dispute_id, e.file_name, e.content_type, length(e.data) AS size, e.created_at
+             FROM dispute_evidence e JOIN disputes d ON d.id = e.dispute_id
+             WHERE d.owner = ?1",
+            &owner,
+        )?,
+        "private_fields": private::export(&conn, state.config.private.as_ref(), &owner)?,
+        "mint_fees": rows(&conn, "SELECT * FROM mint_fees WHERE payer = ?1", &owner)?,
+        "kyc": rows(&conn, "SELECT * FROM kyc_verifications WHERE address = ?1", &owner)?,
+        "listings": rows(&conn, "SELECT * FROM listings WHERE seller = ?1", &owner)?,
+        "offers": rows(&conn, "SELECT * FROM offers WHERE buyer = ?1", &owner)?,
+        "sanctions_rejections": rows(&conn, "SELECT * FROM sanctions_rejections WHERE address = ?1", &owner)?,
+    })))
+}
+
+/// Scrubs personal data for an address. Token IDs, transaction hashes and the metadata
+/// mirrored on-chain are preserved so on-chain references stay resolvable; the request
+/// itself is recorded by the audit log and in `data_deletions`.
+pub async fn delete(
+    State(state): State<AppState>,
+    Path(owner): Path<Address>,
+) -> Result<Json<DeletionResponse>, ApiError> {
+    let owner = format!("{:?}", owner);
+    let mut conn = state.db.lock().unwrap();
+    let tx = conn.transaction().map_err(|e| format!("Failed to delete data: {}", e))?;
+    let fail = |e: rusqlite::Error| format!("Failed to delete data: {}", e);
+
+    let mints_scrubbed = tx
+        .execute("UPDATE mints SET details = ?2 WHERE recipient = ?1", params![owner, REDACTED])
+        .map_err(fail)?;
+    let mint_jobs_scrubbed = tx
+        .execute(
+            "UPDATE mint_jobs SET request = ?2, private_data = NULL, updated_at = CURRENT_TIMESTAMP
+             WHERE lower(json_extract(request, '$.recipient')) = ?1",
+            params![owner, REDACTED],
+        )
+        .map_err(fail)?;
+    let dead_letters_scrubbed = tx
+        .execute(
+            "UPDATE mint_dead_letters SET request = ?2, updated_at = CURRENT_TIMESTAMP
+             WHERE lower(json_extract(request, '$.recipient')) = ?1",
+            params![owner, REDACTED],
+        )
+        .map_err(fail)?;
+    // Assessor records can name the property's owner; the verdict and its source are kept.
+    let parcel_records_scrubbed = tx
+        .execute(
+            "UPDATE parcel_verifications SET record = ?2
+             WHERE (collection, token_id) IN (SELECT collection, token_id FROM mints WHERE recipient = ?1)",
+            params![owner, REDACTED],
+        )
+        .map_err(fail)?;
+    // Disputes stay in the valuation history; the documents filed with them are removed.
+    let dispute_evidence_deleted = tx
+        .execute(
+            "DELETE FROM dispute_evidence WHERE dispute_id IN (SELECT id FROM disputes WHERE owner = ?1)",
+            params![owner],
+        )
+        .map_err(fail)?;
+    let private_records_deleted = tx
+        .execute(
+            "DELETE FROM private_fields
+             WHERE (collection, token_id) IN (SELECT collection, token_id FROM mints WHERE recipient = ?1)",
+            params![owner],
+        )
+        .map_err(fail)?;
+    let kyc_records_deleted = tx
+        .execute("DELETE FROM kyc_verifications WHERE address = ?1", params![owner])
+        .map_err(fail)?;
+    let summary = json!({
+        "mints_scrubbed": mints_scrubbed,
+        "mint_jobs_scrubbed": mint_jobs_scrubbed,
+        "dead_letters_scrubbed": dead_letters_scrubbed,
+        "parcel_records_scrubbed": parcel_records_scrubbed,
+        "dispute_evidence_deleted": dispute_evidence_deleted,
+        "private_records_deleted": private_records_deleted,
+        "kyc_records_deleted": kyc_records_deleted,
+    });
+    tx.execute(
+        "INSERT INTO data_deletions (owner, summary) VALUES (?1, ?2)",
+        params![owner, summary.to_string()],
+    )
+    .map_err(fail)?;
+    tx.commit().map_err(fail)?;
+
+    println!("Scrubbed off-chain data for {}: {}", owner, summary);
+    Ok(Json(DeletionResponse {
+        owner,
+        mints_scrubbed,
+        mint_jobs_scrubbed,
+        dead_letters_scrubbed,
+        parcel_records_scrubbed,
+        dispute_evidence_deleted,
+        private_records_deleted,
+        kyc_records_deleted,
+        retained: RETAINED,
+    }))
+}
@@ -0,0 +1,279 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{Map, Value};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::mint::HouseDetails;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+const NONCE_BYTES: usize = 12;
+/// Kilometres per degree of latitude, and of longitude at the equator.
+const KM_PER_DEGREE: f64 = 111.32;
+
+pub struct PrivateConfig {
+    cipher: Aes256Gcm,
+    /// HouseDetails fields kept out of the stored details and public metadata.
+    pub fields: Vec<String>,
+}
+
+impl PrivateConfig {
+    /// Enabled by PRIVATE_DATA_KEY, a 32-byte AES key as hex.
+    pub fn from_env() -> Option<Self> {
+        let key = optional_env("PRIVATE_DATA_KEY")?;
+        let key = ethers::utils::hex::decode(key.trim_start_matches("0x"))
+            .ok()
+            .filter(|key| key.len() == 32)
+            .expect("PRIVATE_DATA_KEY must be 32 bytes of hex");
+        let fields: Vec<String> = optional_env("PRIVATE_FIELDS")
+            .unwrap_or_else(|| "lat,long".to_string())
+            .split(',')
+            .map(|field| field.trim().to_string())
+            .collect();
+        for field in &fields {
+            if !is_detail_field(field) || field == "name" {
+                panic!("PRIVATE_FIELDS entries must be property detail fields other than name. Found: {}", field);
+            }
+        }
+        println!("PRIVATE_FIELDS: {}", fields.join(", "));
+        Some(PrivateConfig {
+            cipher: Aes256Gcm::new_from_slice(&key).expect("PRIVATE_DATA_KEY must be 32 bytes of hex"),
+            fields,
+        })
+    }
+
+    /// A random nonce followed by the AES-GCM ciphertext.
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher.encrypt(&nonce, plaintext).map_err(|_| "Failed to encrypt private fields")?;
+        Ok([&nonce[..], &ciphertext].concat())
+    }
+
+    fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_BYTES {
+            return Err("Private fields are corrupt".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_BYTES);
+        let nonce: [u8; NONCE_BYTES] = nonce.try_into().expect("split at the nonce length");
+        self.cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| "Private fields can't be decrypted with PRIVATE_DATA_KEY".to_string())
+    }
+}
+
+/// How a property's coordinates appear in its public metadata.
+pub enum PublicLocation {
+    Hidden,
+    /// Snapped to the centre of a grid cell this many kilometres across.
+    Fuzzed(f64),
+    Exact,
+}
+
+impl PublicLocation {
+    /// PUBLIC_LOCATION is hidden (the default), fuzzed or exact; fuzzed coordinates are rounded
+    /// to LOCATION_PRECISION_KM. Exact coordinates can't be published while they are private.
+    pub fn from_env(private: Option<&PrivateConfig>) -> Self {
+        let location = match optional_env("PUBLIC_LOCATION").as_deref() {
+            None | Some("hidden") => PublicLocation::Hidden,
+            Some("fuzzed") => {
+                let km: f64 = optional_env("LOCATION_PRECISION_KM")
+                    .map(|km| km.parse().expect("LOCATION_PRECISION_KM must be a number"))
+                    .unwrap_or(1.0);
+                if km <= 0.0 {
+                    panic!("LOCATION_PRECISION_KM must be positive");
+                }
+                PublicLocation::Fuzzed(km)
+            }
+            Some("exact") => {
+                let private_fields = private.map(|config| config.fields.as_slice()).unwrap_or_default();
+                if private_fields.iter().any(|field| field == "lat" || field == "long") {
+                    panic!("PUBLIC_LOCATION=exact would publish coordinates listed in PRIVATE_FIELDS");
+                }
+                PublicLocation::Exact
+            }
+            Some(other) => panic!("PUBLIC_LOCATION must be hidden, fuzzed or exact. Found: {}", other),
+        };
+        match &location {
+            PublicLocation::Hidden => println!("PUBLIC_LOCATION: hidden"),
+            PublicLocation::Fuzzed(km) => println!("PUBLIC_LOCATION: fuzzed to ~{} km", km),
+            PublicLocation::Exact => println!("PUBLIC_LOCATION: exact"),
+        }
+        location
+    }
+
+    /// Latitude and Longitude attributes for the public metadata, if any are published.
+    pub fn attributes(&self, details: &HouseDetails) -> Vec<Value> {
+        let (lat, long) = match self {
+            PublicLocation::Hidden => return Vec::new(),
+            PublicLocation::Fuzzed(km) => fuzz(details.lat, details.long, *km),
+            PublicLocation::Exact => (details.lat, details.long),
+        };
+        let mut attributes = vec![
+            serde_json::json!({ "trait_type": "Latitude", "value": lat }),
+            serde_json::json!({ "trait_type": "Longitude", "value": long }),
+        ];
+        if let PublicLocation::Fuzzed(km) = self {
+            attributes.push(serde_json::json!({ "trait_type": "Location Precision", "value": format!("~{} km", km) }));
+        }
+        attributes
+    }
+}
+
+/// The centre of the `km`-wide grid cell containing the point. The grid is fixed, so every
+/// revaluation publishes the same cell and repeated metadata can't be averaged back to the house.
+pub fn fuzz(lat: f64, long: f64, km: f64) -> (f64, f64) {
+    let lat_step = km / KM_PER_DEGREE;
+    let lat = ((lat / lat_step).floor() + 0.5) * lat_step;
+    // Longitude degrees shrink towards the poles; the cell's latitude keeps the step stable.
+    let long_step = (km / (KM_PER_DEGREE * lat.to_radians().cos().max(0.01))).min(360.0);
+    let long = ((long / long_step).floor() + 0.5) * long_step;
+    let round = |degrees: f64| (degrees * 1e5).round() / 1e5;
+    (round(lat.clamp(-90.0, 90.0)), round(long.clamp(-180.0, 180.0)))
+}
+
+/// Moves the private fields out of `details` and encrypts them together with the mint's own
+/// private fields. `None` when there is nothing to keep private.
+pub fn seal(
+    config: Option<&PrivateConfig>,
+    details: &mut Value,
+    extra: Option<&Map<String, Value>>,
+) -> Result<Option<Vec<u8>>, ApiError> {
+    let Some(config) = config else {
+        return match extra {
+            Some(_) => Err(ApiError::new(StatusCode::BAD_REQUEST, "Private fields need PRIVATE_DATA_KEY to be set")),
+            None => Ok(None),
+        };
+    };
+    let mut record = extra.cloned().unwrap_or_default();
+    if let Some(details) = details.as_object_mut() {
+        for field in &config.fields {
+            if let Some(value) = details.remove(field) {
+                record.insert(field.clone(), value);
+            }
+        }
+    }
+    if record.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(config.encrypt(Value::Object(record).to_string().as_bytes())?))
+}
+
+pub fn store(db: &Db, collection: &str, token_id: u64, sealed: &[u8]) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO private_fields (collection, token_id, data) VALUES (?1, ?2, ?3)",
+        params![collection, token_id, sealed],
+    )
+    .map_err(|e| format!("Failed to store private fields: {}", e))?;
+    Ok(())
+}
+
+/// Whether `field` is one of the HouseDetails fields.
+fn is_detail_field(field: &str) -> bool {
+    serde_json::to_value(HouseDetails::default()).unwrap_or_default().get(field).is_some()
+}
+
+fn load(
+    conn: &Connection,
+    config: Option<&PrivateConfig>,
+    collection: &str,
+    token_id: u64,
+) -> Result<Option<Map<String, Value>>, String> {
+    let sealed: Option<Vec<u8>> = conn
+        .query_row(
+            "SELECT data FROM private_fields WHERE collection = ?1 AND token_id = ?2",
+            params![collection, token_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load private fields: {}", e))?;
+    let Some(sealed) = sealed else {
+        return Ok(None);
+    };
+    let config = config.ok_or_else(|| format!("Token {} has private fields but PRIVATE_DATA_KEY is not set", token_id))?;
+    serde_json::from_slice(&config.decrypt(&sealed)?).map_err(|e| format!("Invalid private fields: {}", e))
+}
+
+/// Stored property `details` with their private fields decrypted back in, for internal use
+/// such as re-pricing. The result must not be published.
+pub fn restore(
+    conn: &Connection,
+    config: Option<&PrivateConfig>,
+    collection: &str,
+    token_id: u64,
+    details: String,
+) -> Result<String, String> {
+    let Some(record) = load(conn, config, collection, token_id)? else {
+        return Ok(details);
+    };
+    let mut details: Value = serde_json::from_str(&details).map_err(|e| format!("Invalid stored details: {}", e))?;
+    if let Some(details) = details.as_object_mut() {
+        // Whatever was private when the token was minted, even if PRIVATE_FIELDS changed since.
+        details.extend(record.into_iter().filter(|(field, _)| is_detail_field(field)));
+    }
+    Ok(details.to_string())
+}
+
+/// Puts the private fields `seal` moved out of a stored mint request back into it.
+pub fn unseal(config: Option<&PrivateConfig>, request: &mut Value, sealed: &[u8]) -> Result<(), String> {
+    let config = config.ok_or("Mint job has private fields but PRIVATE_DATA_KEY is not set")?;
+    let record: Map<String, Value> =
+        serde_json::from_slice(&config.decrypt(sealed)?).map_err(|e| format!("Invalid private fields: {}", e))?;
+    let request = request.as_object_mut().ok_or("Invalid stored mint request")?;
+    let (details, extra): (Map<String, Value>, Map<String, Value>) =
+        record.into_iter().partition(|(field, _)| is_detail_field(field));
+    request.extend(details);
+    if !extra.is_empty() {
+        request.insert("private".to_string(), Value::Object(extra));
+    }
+    Ok(())
+}
+
+/// The token's private fields, for callers allowed to see them.
+pub async fn get_private(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let record = {
+        let conn = state.db.lock().unwrap();
+        load(&conn, state.config.private.as_ref(), &collection.name, token_id)?
+    };
+    let record = record.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} has no private fields", token_id))
+    })?;
+    Ok(Json(Value::Object(record)))
+}
+
+/// The decrypted private fields of every token minted to `owner`, for data subject exports.
+pub fn export(conn: &Connection, config: Option<&PrivateConfig>, owner: &str) -> Result<Vec<Value>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.collection, p.token_id FROM private_fields p
+             JOIN mints m ON m.collection = p.collection AND m.token_id = p.token_id
+             WHERE m.recipient = ?1",
+        )
+        .map_err(|e| format!("Failed to export private fields: {}", e))?;
+    let tokens = stmt
+        .query_map(params![owner], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))
+        .map_err(|e| format!("Failed to export private fields: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to export private fields: {}", e))?;
+    tokens
+        .into_iter()
+        .map(|(collection, token_id)| {
+            let fields = load(conn, config, &collection, token_id)?;
+            Ok(serde_json::json!({ "collection": collection, "token_id": token_id, "fields": fields }))
+        })
+        .collect()
+}
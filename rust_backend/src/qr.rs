@@ -0,0 +1,99 @@
+use axum::extract::{Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use ethers::types::U256;
+use qrcode::QrCode;
+use serde::Deserialize;
+
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+
+const PIXELS_PER_MODULE: usize = 8;
+// The QR spec asks for a four-module light border so scanners can find the code.
+const QUIET_ZONE: usize = 4;
+
+#[derive(Deserialize)]
+pub struct QrQuery {
+    collection: Option<String>,
+    /// `explorer` or `eip681`. Defaults to the explorer page when the chain has one.
+    link: Option<String>,
+}
+
+/// An EIP-681 link that asks the wallet to call `ownerOf(token_id)` on the collection.
+pub fn eip681_link(state: &AppState, collection: &Collection, token_id: u64) -> String {
+    format!(
+        "ethereum:{:?}@{}/ownerOf?uint256={}",
+        collection.address,
+        state.chain_of(collection).chain_id,
+        token_id
+    )
+}
+
+/// Where a printed QR code should send people to check the on-chain record.
+pub fn verification_url(state: &AppState, collection: &Collection, token_id: u64) -> String {
+    match &state.chain_of(collection).explorer {
+        Some(explorer) => explorer.token_url(collection.address, U256::from(token_id)),
+        None => eip681_link(state, collection, token_id),
+    }
+}
+
+/// Renders `code` as a grayscale PNG.
+pub fn render_png(code: &QrCode) -> Result<Vec<u8>, String> {
+    let modules = code.width();
+    let size = (modules + 2 * QUIET_ZONE) * PIXELS_PER_MODULE;
+    let mut pixels = vec![255u8; size * size];
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color != qrcode::Color::Dark {
+            continue;
+        }
+        let left = (i % modules + QUIET_ZONE) * PIXELS_PER_MODULE;
+        let top = (i / modules + QUIET_ZONE) * PIXELS_PER_MODULE;
+        for y in top..top + PIXELS_PER_MODULE {
+            pixels[y * size + left..y * size + left + PIXELS_PER_MODULE].fill(0);
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, size as u32, size as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    writer.finish().map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    Ok(png)
+}
+
+pub async fn token_qr(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<QrQuery>,
+) -> Result<Response, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let link = match query.link.as_deref() {
+        None => verification_url(&state, &collection, token_id),
+        Some("eip681") => eip681_link(&state, &collection, token_id),
+        Some("explorer") => {
+            let chain = state.chain_of(&collection);
+            let explorer = chain.explorer.as_ref().ok_or_else(|| {
+                ApiError::new(StatusCode::BAD_REQUEST, "No block explorer is configured for this chain")
+            })?;
+            explorer.token_url(collection.address, U256::from(token_id))
+        }
+        Some(other) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("link must be explorer or eip681. Found: {}", other),
+            ))
+        }
+    };
+    let code = QrCode::new(&link).map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    Ok(([(CONTENT_TYPE, "image/png")], render_png(&code)?).into_response())
+}
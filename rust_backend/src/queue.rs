@@ -0,0 +1,210 @@
+use redis::{AsyncCommands, Script};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::optional_env;
+use crate::coordination::Redis;
+use crate::dead_letters;
+use crate::error::ApiError;
+use crate::jobs;
+use crate::state::AppState;
+
+const PENDING: &str = "mint_jobs:pending";
+const PROCESSING: &str = "mint_jobs:processing";
+const LEASE_PREFIX: &str = "mint_jobs:lease:";
+const IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// Moves the next pending job to the processing list and leases it in one step, so a
+/// reaper never sees a claimed job without a lease.
+const CLAIM: &str = r#"
+local id = redis.call('RPOPLPUSH', KEYS[1], KEYS[2])
+if id then
+    redis.call('SET', KEYS[3] .. id, ARGV[1], 'PX', ARGV[2])
+end
+return id
+"#;
+
+pub struct QueueConfig {
+    pub url: String,
+    workers: usize,
+    lease: Duration,
+}
+
+impl QueueConfig {
+    /// Enabled by REDIS_URL.
+    pub fn from_env() -> Option<Self> {
+        let url = optional_env("REDIS_URL")?;
+        let workers = optional_env("MINT_WORKERS")
+            .map(|workers| workers.parse().expect("MINT_WORKERS must be a number"))
+            .unwrap_or(4);
+        let lease = optional_env("MINT_LEASE_SECS")
+            .map(|secs| secs.parse().expect("MINT_LEASE_SECS must be a number"))
+            .unwrap_or(60);
+        println!("REDIS_URL: Loaded ({} mint workers, {}s leases)", workers, lease);
+        Some(QueueConfig {
+            url,
+            workers,
+            lease: Duration::from_secs(lease),
+        })
+    }
+}
+
+/// A mint job queue shared by every replica. Workers hold a lease on each job they run and
+/// renew it while the job is in flight; a job whose lease lapses is reclaimed by the reaper.
+pub struct JobQueue {
+    redis: Arc<Redis>,
+    workers: usize,
+    lease: Duration,
+    worker_id: String,
+}
+
+impl JobQueue {
+    pub fn new(redis: Arc<Redis>, config: &QueueConfig) -> Self {
+        let host = env::var("HOSTNAME").unwrap_or_else(|_| "replica".to_string());
+        JobQueue {
+            redis,
+            workers: config.workers,
+            lease: config.lease,
+            worker_id: format!("{}-{}", host, std::process::id()),
+        }
+    }
+
+    pub async fn push(&self, id: i64) -> Result<(), String> {
+        let mut conn = self.redis.connection().await?;
+        conn.lpush::<_, _, ()>(PENDING, id)
+            .await
+            .map_err(|e| format!("Failed to enqueue mint job {}: {}", id, e))
+    }
+
+    async fn claim(&self) -> Result<Option<i64>, String> {
+        let mut conn = self.redis.connection().await?;
+        Script::new(CLAIM)
+            .key(PENDING)
+            .key(PROCESSING)
+            .key(LEASE_PREFIX)
+            .arg(&self.worker_id)
+            .arg(self.lease.as_millis() as u64)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to claim a mint job: {}", e))
+    }
+
+    async fn heartbeat(&self, id: i64) -> Result<(), String> {
+        let mut conn = self.redis.connection().await?;
+        conn.pexpire::<_, ()>(format!("{}{}", LEASE_PREFIX, id), self.lease.as_millis() as i64)
+            .await
+            .map_err(|e| format!("Failed to renew the lease on mint job {}: {}", id, e))
+    }
+
+    async fn release(&self, id: i64) -> Result<(), String> {
+        let mut conn = self.redis.connection().await?;
+        redis::pipe()
+            .lrem(PROCESSING, 1, id)
+            .del(format!("{}{}", LEASE_PREFIX, id))
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to release mint job {}: {}", id, e))
+    }
+
+    /// Jobs in the processing list whose worker stopped renewing the lease.
+    async fn abandoned(&self) -> Result<Vec<i64>, String> {
+        let mut conn = self.redis.connection().await?;
+        let processing: Vec<i64> = conn
+            .lrange(PROCESSING, 0, -1)
+            .await
+            .map_err(|e| format!("Failed to list processing mint jobs: {}", e))?;
+        let mut abandoned = Vec::new();
+        for id in processing {
+            let leased: bool = conn
+                .exists(format!("{}{}", LEASE_PREFIX, id))
+                .await
+                .map_err(|e| format!("Failed to check the lease on mint job {}: {}", id, e))?;
+            if !leased {
+                abandoned.push(id);
+            }
+        }
+        Ok(abandoned)
+    }
+}
+
+async fn work(state: AppState, queue: Arc<JobQueue>) {
+    loop {
+        let id = match queue.claim().await {
+            Ok(Some(id)) => id,
+            Ok(None) => {
+                tokio::time::sleep(IDLE_POLL).await;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                tokio::time::sleep(IDLE_POLL).await;
+                continue;
+            }
+        };
+
+        let heartbeat = {
+            let queue = queue.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(queue.lease / 3);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = queue.heartbeat(id).await {
+                        eprintln!("{}", e);
+                    }
+                }
+            })
+        };
+        if let Err(e) = jobs::run(&state, id).await {
+            eprintln!("Mint job {} could not be processed: {}", id, e);
+        }
+        heartbeat.abort();
+        if let Err(e) = queue.release(id).await {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+/// Reclaims jobs from workers that died. A job that never started goes back in the queue; one
+/// that was mid-mint may already have broadcast its transaction, so it is failed into the
+/// dead-letter queue for an operator to check on-chain instead of being sent twice.
+async fn reap(state: &AppState, queue: &JobQueue) -> Result<(), String> {
+    for id in queue.abandoned().await? {
+        if jobs::transition(&state.db, id, jobs::MINTING, jobs::FAILED)? {
+            let error = ApiError::from(
+                "The worker running this mint stopped responding; check the chain for its transaction before retrying",
+            );
+            if let Some(job) = jobs::get(&state.db, id)? {
+                jobs::set_error(&state.db, id, &error.message)?;
+                dead_letters::record(&state.db, &job, &error)?;
+            }
+            eprintln!("Mint job {} lost its worker mid-mint", id);
+        } else if jobs::get(&state.db, id)?.is_some_and(|job| job.status == jobs::QUEUED) {
+            queue.push(id).await?;
+            println!("Requeued mint job {} from a lost worker", id);
+        }
+        queue.release(id).await?;
+    }
+    Ok(())
+}
+
+/// Starts this replica's workers and lease reaper.
+pub fn start(state: &AppState) {
+    let Some(queue) = state.queue.clone() else { return };
+    for _ in 0..queue.workers {
+        let state = state.clone();
+        let queue = queue.clone();
+        tokio::spawn(work(state, queue));
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(queue.lease);
+        loop {
+            interval.tick().await;
+            if let Err(e) = reap(&state, &queue).await {
+                eprintln!("Mint job reaper failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,164 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+/// Numeric traits that identify rather than measure a property, so ranking them means nothing.
+const UNRANKED: &[&str] = &["Latitude", "Longitude", "Zipcode"];
+const PERCENTILE_SUFFIX: &str = " Percentile";
+
+pub struct RarityConfig {
+    /// Traits whose percentile is added to minted and revalued metadata.
+    traits: Vec<String>,
+}
+
+impl RarityConfig {
+    /// Enabled by RARITY_TRAITS, a list of trait types such as `Living Area,Price`.
+    pub fn from_env() -> Option<Self> {
+        let traits: Vec<String> = optional_env("RARITY_TRAITS")?
+            .split(',')
+            .map(|trait_type| trait_type.trim().to_string())
+            .filter(|trait_type| !trait_type.is_empty())
+            .collect();
+        println!("RARITY_TRAITS: {}", traits.join(", "));
+        Some(RarityConfig { traits })
+    }
+}
+
+#[derive(Serialize)]
+pub struct TraitRarity {
+    pub trait_type: String,
+    pub value: f64,
+    /// 1 for the highest value in the collection; ties share a rank.
+    pub rank: usize,
+    /// Share of tokens with this value or lower.
+    pub percentile: f64,
+    /// Share of tokens with this value or higher, e.g. 5 for "top 5%".
+    pub top_percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct Rarity {
+    pub collection: String,
+    pub token_id: u64,
+    pub tokens: usize,
+    pub traits: Vec<TraitRarity>,
+}
+
+/// The token's rankable numeric traits.
+fn numeric_traits(metadata: &Value) -> Vec<(String, f64)> {
+    metadata["attributes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|attribute| {
+            let trait_type = attribute["trait_type"].as_str()?;
+            if UNRANKED.contains(&trait_type) || trait_type.ends_with(PERCENTILE_SUFFIX) {
+                return None;
+            }
+            Some((trait_type.to_string(), attribute["value"].as_f64()?))
+        })
+        .collect()
+}
+
+/// The metadata of every token in the collection other than `except`.
+fn collection_metadata(db: &Db, collection: &str, except: Option<u64>) -> Result<Vec<Value>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare("SELECT token_id, metadata FROM mints WHERE collection = ?1")
+        .map_err(|e| format!("Failed to load collection metadata: {}", e))?;
+    let rows = stmt
+        .query_map(params![collection], |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to load collection metadata: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load collection metadata: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .filter(|(token_id, _)| Some(*token_id) != except)
+        .filter_map(|(_, metadata)| serde_json::from_str(&metadata).ok())
+        .collect())
+}
+
+/// Ranks each numeric trait of `metadata` against the same trait across `others`.
+fn rank(metadata: &Value, others: &[Value]) -> Vec<TraitRarity> {
+    let others: Vec<_> = others.iter().map(numeric_traits).collect();
+    numeric_traits(metadata)
+        .into_iter()
+        .map(|(trait_type, value)| {
+            let mut values: Vec<f64> = others
+                .iter()
+                .flatten()
+                .filter(|(other, _)| *other == trait_type)
+                .map(|(_, other)| *other)
+                .collect();
+            values.push(value);
+            let share = |count: usize| (count as f64 * 10_000.0 / values.len() as f64).round() / 100.0;
+            TraitRarity {
+                rank: values.iter().filter(|other| **other > value).count() + 1,
+                percentile: share(values.iter().filter(|other| **other <= value).count()),
+                top_percent: share(values.iter().filter(|other| **other >= value).count()),
+                trait_type,
+                value,
+            }
+        })
+        .collect()
+}
+
+/// Replaces the RARITY_TRAITS percentiles in metadata for a token of `collection`, ranked
+/// against the collection's other tokens. `token_id` is `None` for a token not yet minted.
+pub fn annotate(state: &AppState, collection: &str, token_id: Option<u64>, metadata: &mut Value) -> Result<(), String> {
+    let Some(config) = &state.config.rarity else {
+        return Ok(());
+    };
+    let ranked = rank(metadata, &collection_metadata(&state.db, collection, token_id)?);
+    let Some(attributes) = metadata["attributes"].as_array_mut() else { return Ok(()) };
+    attributes.retain(|attribute| !attribute["trait_type"].as_str().unwrap_or_default().ends_with(PERCENTILE_SUFFIX));
+    for rarity in ranked.iter().filter(|rarity| config.traits.contains(&rarity.trait_type)) {
+        attributes.push(json!({
+            "trait_type": format!("{}{}", rarity.trait_type, PERCENTILE_SUFFIX),
+            "display_type": "boost_percentage",
+            "value": rarity.percentile,
+        }));
+    }
+    Ok(())
+}
+
+pub async fn rarity(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Rarity>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let metadata: Option<String> = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT metadata FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection.name, token_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+    };
+    let metadata = metadata.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} was not minted through this API", token_id))
+    })?;
+    let metadata: Value = serde_json::from_str(&metadata).map_err(|e| format!("Invalid stored metadata: {}", e))?;
+    let others = collection_metadata(&state.db, &collection.name, Some(token_id))?;
+    Ok(Json(Rarity {
+        tokens: others.len() + 1,
+        traits: rank(&metadata, &others),
+        collection: collection.name,
+        token_id,
+    }))
+}
@@ -0,0 +1,77 @@
+use axum::extract::State;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+
+/// What a caller may do. Admins are identified by the `ADMIN_API_KEY` bearer token,
+/// organization API keys carry one of the other roles, and unauthenticated callers get
+/// `ANONYMOUS_ROLE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Appraiser,
+    Agent,
+    Viewer,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Permission {
+    Read,
+    Mint,
+    /// Create listings and make offers.
+    Trade,
+    Revalue,
+    /// See private property fields such as owner contact details and exact coordinates.
+    ReadPrivate,
+}
+
+impl Role {
+    pub fn parse(value: &str) -> Option<Role> {
+        match value {
+            "admin" => Some(Role::Admin),
+            "appraiser" => Some(Role::Appraiser),
+            "agent" => Some(Role::Agent),
+            "viewer" => Some(Role::Viewer),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Appraiser => "appraiser",
+            Role::Agent => "agent",
+            Role::Viewer => "viewer",
+        }
+    }
+
+    pub fn allows(self, permission: Permission) -> bool {
+        matches!(
+            (self, permission),
+            (Role::Admin, _)
+                | (_, Permission::Read)
+                | (Role::Agent, Permission::Mint | Permission::Trade)
+                | (Role::Appraiser, Permission::Revalue | Permission::ReadPrivate)
+        )
+    }
+}
+
+/// Route layer that rejects callers whose role (set by `organizations::identify`) lacks `permission`.
+pub async fn require<B>(
+    State(permission): State<Permission>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, ApiError> {
+    match request.extensions().get::<Role>() {
+        Some(role) if role.allows(permission) => Ok(next.run(request).await),
+        Some(role) => Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            format!("Role {} is not allowed to {:?}", role.as_str(), permission).to_lowercase(),
+        )),
+        None => Err(ApiError::new(StatusCode::UNAUTHORIZED, "An API key is required")),
+    }
+}
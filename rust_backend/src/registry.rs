@@ -0,0 +1,218 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::Address;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+
+use crate::artifacts::BUNDLED_CONTRACT;
+use crate::config::{optional_env, Config};
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+pub const DEFAULT_COLLECTION: &str = "default";
+
+const COLLECTION_COLUMNS: &str =
+    "name, address, description, contract_name, contract_version, allowlist, organization, created_at, network";
+
+#[derive(Clone, Serialize)]
+pub struct Collection {
+    pub name: String,
+    pub address: Address,
+    pub description: Option<String>,
+    pub contract_name: String,
+    pub contract_version: Option<String>,
+    pub allowlist: Option<String>,
+    /// Owning organization; `None` for platform collections.
+    pub organization: Option<String>,
+    pub created_at: String,
+    /// The NETWORKS chain the contract is on; `None` for the primary chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ContractRef<'a> {
+    pub name: &'a str,
+    pub version: Option<&'a str>,
+}
+
+impl Default for ContractRef<'_> {
+    fn default() -> Self {
+        ContractRef { name: BUNDLED_CONTRACT, version: None }
+    }
+}
+
+/// Registers CONTRACT_ADDRESS as the default collection plus any extra deployments
+/// listed in COLLECTIONS (`name=address[@ContractName[/version]]`, comma separated), and
+/// each NETWORKS contract as a collection named after its network.
+pub fn seed_from_env(db: &Db, config: &Config) {
+    let contract_name = optional_env("CONTRACT_NAME");
+    let contract_version = optional_env("CONTRACT_VERSION");
+    let contract = ContractRef {
+        name: contract_name.as_deref().unwrap_or(BUNDLED_CONTRACT),
+        version: contract_version.as_deref(),
+    };
+    register(db, DEFAULT_COLLECTION, config.contract_address, contract, None)
+        .expect("Failed to register default collection");
+    for network in &config.networks {
+        let name = network.chain.network.as_deref().unwrap_or_default();
+        register(db, name, network.contract_address, contract, None).expect("Failed to register network collection");
+        let conn = db.lock().unwrap();
+        conn.execute("UPDATE collections SET network = ?1 WHERE name = ?1", params![name])
+            .expect("Failed to register network collection");
+    }
+
+    if let Some(collections) = optional_env("COLLECTIONS") {
+        for entry in collections.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, address) = entry
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Invalid COLLECTIONS entry: {}", entry));
+            let (address, contract) = match address.split_once('@') {
+                Some((address, contract)) => {
+                    let (name, version) = match contract.split_once('/') {
+                        Some((name, version)) => (name, Some(version)),
+                        None => (contract, None),
+                    };
+                    (address, ContractRef { name, version })
+                }
+                None => (address, ContractRef::default()),
+            };
+            let address: Address = address
+                .trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid address for collection {}", name));
+            register(db, name.trim(), address, contract, None).expect("Failed to register collection");
+            println!("Registered collection {} at {:?}", name.trim(), address);
+        }
+    }
+}
+
+pub fn register(
+    db: &Db,
+    name: &str,
+    address: Address,
+    contract: ContractRef,
+    description: Option<&str>,
+) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO collections (name, address, description, contract_name, contract_version)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(name) DO UPDATE SET address = excluded.address,
+             description = COALESCE(excluded.description, collections.description),
+             contract_name = excluded.contract_name,
+             contract_version = excluded.contract_version",
+        params![name, format!("{:?}", address), description, contract.name, contract.version],
+    )
+    .map_err(|e| format!("Failed to register collection: {}", e))?;
+    Ok(())
+}
+
+pub fn resolve(db: &Db, name: Option<&str>) -> Result<Collection, ApiError> {
+    let name = name.unwrap_or(DEFAULT_COLLECTION);
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        &format!("SELECT {} FROM collections WHERE name = ?1", COLLECTION_COLUMNS),
+        params![name],
+        row_to_collection,
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up collection: {}", e))?
+    .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, format!("Unknown collection: {}", name)))
+}
+
+/// Moves a collection into an organization; returns false if the collection is unknown.
+pub fn assign(db: &Db, collection: &str, organization: &str) -> Result<bool, String> {
+    let conn = db.lock().unwrap();
+    let updated = conn
+        .execute(
+            "UPDATE collections SET organization = ?1 WHERE name = ?2",
+            params![organization, collection],
+        )
+        .map_err(|e| format!("Failed to assign collection: {}", e))?;
+    Ok(updated == 1)
+}
+
+/// Resolves a collection visible to the tenant: organizations only see their own collections
+/// (defaulting to their `default_collection`), everyone else only platform collections.
+pub fn resolve_for(db: &Db, tenant: &Tenant, name: Option<&str>) -> Result<Collection, ApiError> {
+    let default = tenant.0.as_ref().and_then(|org| org.default_collection.as_deref());
+    let name = name.or(default);
+    let collection = resolve(db, name)?;
+    if collection.organization.as_deref() != tenant.organization_id() {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            format!("Unknown collection: {}", name.unwrap_or(DEFAULT_COLLECTION)),
+        ));
+    }
+    Ok(collection)
+}
+
+pub fn list(db: &Db, organization: Option<&str>) -> Result<Vec<Collection>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM collections WHERE organization IS ?1 ORDER BY name",
+            COLLECTION_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to list collections: {}", e))?;
+    let rows = stmt
+        .query_map(params![organization], row_to_collection)
+        .map_err(|e| format!("Failed to list collections: {}", e))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to list collections: {}", e))
+}
+
+/// Every collection across organizations, for background work.
+pub fn list_all(db: &Db) -> Result<Vec<Collection>, String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM collections ORDER BY name", COLLECTION_COLUMNS))
+        .map_err(|e| format!("Failed to list collections: {}", e))?;
+    let rows = stmt
+        .query_map([], row_to_collection)
+        .map_err(|e| format!("Failed to list collections: {}", e))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to list collections: {}", e))
+}
+
+fn row_to_collection(row: &rusqlite::Row) -> rusqlite::Result<Collection> {
+    let address: String = row.get(1)?;
+    Ok(Collection {
+        name: row.get(0)?,
+        address: address.parse().unwrap_or_default(),
+        description: row.get(2)?,
+        contract_name: row.get(3)?,
+        contract_version: row.get(4)?,
+        allowlist: row.get(5)?,
+        organization: row.get(6)?,
+        created_at: row.get(7)?,
+        network: row.get(8)?,
+    })
+}
+
+pub async fn list_collections(
+    State(state): State<AppState>,
+    tenant: Tenant,
+) -> Result<Json<Vec<Collection>>, ApiError> {
+    Ok(Json(list(&state.db, tenant.organization_id())?))
+}
+
+#[derive(Serialize)]
+pub struct ArtifactEntry {
+    contract_name: String,
+    version: String,
+}
+
+pub async fn list_artifacts(State(state): State<AppState>) -> Json<Vec<ArtifactEntry>> {
+    let entries = state
+        .artifacts
+        .list()
+        .into_iter()
+        .map(|(contract_name, version)| ArtifactEntry { contract_name, version })
+        .collect();
+    Json(entries)
+}
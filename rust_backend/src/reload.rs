@@ -0,0 +1,137 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+use std::sync::RwLock;
+
+use crate::audit;
+use crate::backpressure::BackpressureConfig;
+use crate::config::optional_env;
+use crate::error::ApiError;
+use crate::network::{self, TxPolicy};
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct ReloadResponse {
+    /// One line per setting that changed.
+    changes: Vec<String>,
+}
+
+/// Sets the variables in .env again, overriding the ones read at startup. Variables removed from
+/// the file keep their old value until a restart.
+fn reread_dotenv() -> Result<(), String> {
+    // The iterator is the only dotenv API that can override variables that are already set.
+    #[allow(deprecated)]
+    let Ok(entries) = dotenv::dotenv_iter() else {
+        return Ok(());
+    };
+    for entry in entries {
+        let (key, value) = entry.map_err(|e| format!("Failed to read .env: {}", e))?;
+        std::env::set_var(key, value);
+    }
+    Ok(())
+}
+
+fn webhook_secret(name: &str) -> Result<String, String> {
+    optional_env(name).ok_or_else(|| format!("{} is not set in .env", name))
+}
+
+/// Swaps `setting` for `value`, noting the change; secrets are never written to the log.
+fn rotate(setting: &RwLock<String>, value: String, name: &str, changes: &mut Vec<String>) {
+    let previous = std::mem::replace(&mut *setting.write().unwrap(), value.clone());
+    if previous != value {
+        changes.push(format!("{}: rotated", name));
+    }
+}
+
+/// Re-reads the settings that can change without a restart: each chain's fee cap and confirmation
+/// count, the mint and prediction concurrency limits, and the Stripe and KYC webhook secrets.
+/// Everything is checked before anything is applied, so a bad value changes nothing. Mint jobs in
+/// flight keep running and pick the new limits up on their next transaction.
+pub fn reload(state: &AppState) -> Result<Vec<String>, String> {
+    reread_dotenv()?;
+    let chains = state.chains();
+    let mut policies = Vec::new();
+    for chain in &chains {
+        let chain = &chain.chain;
+        let prefix = chain.env_prefix();
+        let policy = TxPolicy::load(&prefix, chain.chain_id)?;
+        if network::is_mainnet(chain.chain_id) && policy.max_fee_per_gas.is_none() {
+            return Err(format!("{}MAX_FEE_GWEI must be set on mainnet", prefix));
+        }
+        policies.push(policy);
+    }
+    let backpressure = BackpressureConfig::load()?;
+    let stripe_secret = state.config.stripe.as_ref().map(|_| webhook_secret("STRIPE_WEBHOOK_SECRET")).transpose()?;
+    let kyc_secret = state.config.kyc.as_ref().map(|_| webhook_secret("KYC_WEBHOOK_SECRET")).transpose()?;
+
+    let mut changes = Vec::new();
+    for (chain, policy) in chains.iter().zip(policies) {
+        let chain = &chain.chain;
+        let previous = std::mem::replace(&mut *chain.tx_policy.write().unwrap(), policy);
+        if previous != policy {
+            changes.push(format!(
+                "chain {}: {} (was {})",
+                chain.network.as_deref().map_or(chain.chain_id.to_string(), str::to_string),
+                policy.describe(),
+                previous.describe()
+            ));
+        }
+    }
+    for (prefix, slots, limit) in [
+        ("MINT", &state.mint_slots, backpressure.mint),
+        ("PREDICT", &state.predict_slots, backpressure.predict),
+    ] {
+        let previous = slots.resize(limit);
+        if previous != limit {
+            changes.push(format!("{}_CONCURRENCY: {} (was {})", prefix, limit.describe(), previous.describe()));
+        }
+    }
+    if let (Some(stripe), Some(secret)) = (&state.config.stripe, stripe_secret) {
+        rotate(&stripe.webhook_secret, secret, "STRIPE_WEBHOOK_SECRET", &mut changes);
+    }
+    if let (Some(kyc), Some(secret)) = (&state.config.kyc, kyc_secret) {
+        rotate(&kyc.webhook_secret, secret, "KYC_WEBHOOK_SECRET", &mut changes);
+    }
+    Ok(changes)
+}
+
+fn log(changes: &Result<Vec<String>, String>) {
+    match changes {
+        Ok(changes) if changes.is_empty() => println!("Config reloaded, nothing changed"),
+        Ok(changes) => changes.iter().for_each(|change| println!("Config reloaded, {}", change)),
+        Err(e) => eprintln!("Config reload failed, keeping the previous settings: {}", e),
+    }
+}
+
+/// Reloads the config on SIGHUP, alongside the contract artifacts.
+pub fn reload_on_sighup(state: AppState) {
+    tokio::spawn(async move {
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())
+            .expect("Failed to install SIGHUP handler");
+        while hangup.recv().await.is_some() {
+            println!("SIGHUP received, reloading config...");
+            let changes = reload(&state);
+            log(&changes);
+            let entry = audit::Entry {
+                actor: "system",
+                ip: None,
+                method: "SIGHUP",
+                path: "config/reload",
+                payload_hash: None,
+                status: None,
+                success: changes.is_ok(),
+            };
+            if let Err(e) = audit::append(&state.db, entry) {
+                eprintln!("{}", e);
+            }
+        }
+    });
+}
+
+pub async fn reload_config(State(state): State<AppState>) -> Result<Json<ReloadResponse>, ApiError> {
+    let changes = reload(&state);
+    log(&changes);
+    let changes = changes.map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    Ok(Json(ReloadResponse { changes }))
+}
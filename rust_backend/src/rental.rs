@@ -0,0 +1,85 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bindings::Rentable;
+use crate::capabilities::{self, Interface};
+use crate::error::ApiError;
+use crate::registry;
+use crate::simulation;
+use crate::state::{AppState, EthClient};
+use crate::tx::{self, TransactionResponse};
+use crate::CollectionQuery;
+
+#[derive(Deserialize)]
+pub struct SetUserRequest {
+    /// Tenant address; the zero address ends the lease.
+    user: Address,
+    /// Lease end as a unix timestamp in seconds.
+    expires: u64,
+}
+
+#[derive(Serialize)]
+pub struct UserResponse {
+    token_id: u64,
+    user: Address,
+    expires: u64,
+    active: bool,
+}
+
+/// The state for the collection's chain, and its rental interface on that chain.
+async fn contract(state: &AppState, collection: Option<&str>) -> Result<(AppState, Rentable<EthClient>), ApiError> {
+    let collection = registry::resolve(&state.db, collection)?;
+    capabilities::require(state, &collection, Interface::Rentable).await?;
+    let state = state.on(&collection)?;
+    let contract = Rentable::new(collection.address, state.client.clone());
+    Ok((state, contract))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<UserResponse>, ApiError> {
+    let (_, contract) = contract(&state, query.collection.as_deref()).await?;
+    let user = contract
+        .user_of(U256::from(token_id))
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read token user", e))?;
+    let expires = contract
+        .user_expires(U256::from(token_id))
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read lease expiry", e))?;
+    let expires = expires.min(U256::from(u64::MAX)).as_u64();
+
+    Ok(Json(UserResponse {
+        token_id,
+        user,
+        expires,
+        active: user != Address::zero() && expires > now(),
+    }))
+}
+
+pub async fn set_user(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<SetUserRequest>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    if request.user != Address::zero() && request.expires <= now() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "expires must be in the future"));
+    }
+    let (state, contract) = contract(&state, query.collection.as_deref()).await?;
+    println!("Setting user of token {} to {:?} until {}...", token_id, request.user, request.expires);
+    let receipt = tx::submit(&state, contract.set_user(U256::from(token_id), request.user, request.expires)).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
@@ -0,0 +1,308 @@
+use axum::extract::{Path, Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use printpdf::{BuiltinFont, Color, IndirectFontRef, Mm, PdfDocument, PdfLayerReference, Rect, Rgb};
+use qrcode::QrCode;
+use rusqlite::{params, OptionalExtension};
+use serde_json::{Map, Value};
+
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::qr;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const MAX_COMPARABLES: u32 = 5;
+const MAX_VALUATIONS: u32 = 8;
+
+struct MintRecord {
+    recipient: String,
+    transaction_hash: String,
+    block_number: u64,
+    price: f64,
+    /// The stored details, without the fields PRIVATE_FIELDS keeps out of them.
+    details: Map<String, Value>,
+    minted_at: String,
+}
+
+struct Comparable {
+    token_id: u64,
+    name: String,
+    sqft_living: u64,
+    price: f64,
+}
+
+struct ValuationRow {
+    price: f64,
+    source: String,
+    created_at: String,
+}
+
+/// Writes the report top to bottom, tracking the current line.
+struct Writer {
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl Writer {
+    fn text(&self, text: &str, size: f32, x: f32, font: &IndirectFontRef) {
+        self.layer.use_text(text, size, Mm(x), Mm(self.y), font);
+    }
+
+    fn title(&mut self, text: &str) {
+        self.text(text, 20.0, MARGIN, &self.bold);
+        self.y -= 10.0;
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.y -= 4.0;
+        self.text(text, 13.0, MARGIN, &self.bold);
+        self.y -= 7.0;
+    }
+
+    fn field(&mut self, label: &str, value: &str) {
+        self.text(label, 10.0, MARGIN, &self.bold);
+        self.text(value, 10.0, MARGIN + 45.0, &self.regular);
+        self.y -= 5.5;
+    }
+
+    fn row(&mut self, columns: &[(f32, &str)]) {
+        for (x, text) in columns {
+            self.text(text, 9.0, MARGIN + x, &self.regular);
+        }
+        self.y -= 5.0;
+    }
+
+    /// Draws `code` as filled squares with its top-right corner at the page's top-right margin.
+    fn qr_code(&self, code: &QrCode, size: f32) {
+        let width = code.width();
+        let module = size / width as f32;
+        let left = PAGE_WIDTH - MARGIN - size;
+        let top = PAGE_HEIGHT - MARGIN;
+        self.layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+        for (i, color) in code.to_colors().into_iter().enumerate() {
+            if color == qrcode::Color::Dark {
+                let x = left + (i % width) as f32 * module;
+                let y = top - (i / width + 1) as f32 * module;
+                self.layer.add_rect(Rect::new(Mm(x), Mm(y), Mm(x + module), Mm(y + module)));
+            }
+        }
+    }
+}
+
+pub fn money(value: f64) -> String {
+    let whole = format!("{:.0}", value.abs());
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("{}${}", if value < 0.0 { "-" } else { "" }, grouped)
+}
+
+fn load_mint(state: &AppState, collection: &Collection, token_id: u64) -> Result<MintRecord, ApiError> {
+    let conn = state.db.lock().unwrap();
+    let row = conn
+        .query_row(
+            "SELECT recipient, transaction_hash, block_number, price, details, created_at
+             FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection.name, token_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u64>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?;
+    let (recipient, transaction_hash, block_number, price, details, minted_at) = row.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} was not minted through this service", token_id))
+    })?;
+    // The report is public, so private fields stay out of it.
+    let details = serde_json::from_str(&details).map_err(|_| {
+        ApiError::new(StatusCode::CONFLICT, format!("Property details for token {} are no longer available", token_id))
+    })?;
+    Ok(MintRecord {
+        recipient,
+        transaction_hash,
+        block_number,
+        price,
+        details,
+        minted_at,
+    })
+}
+
+/// A stored detail for the report, with `unit` after it; private fields are shown as such.
+fn shown(details: &Map<String, Value>, field: &str, unit: &str) -> String {
+    match details.get(field) {
+        Some(value) => format!("{}{}", value, unit),
+        None => "Private".to_string(),
+    }
+}
+
+/// Other properties in the same collection and zipcode, closest in living area first.
+fn comparables(state: &AppState, collection: &Collection, token_id: u64, details: &Map<String, Value>) -> Result<Vec<Comparable>, String> {
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT token_id, json_extract(details, '$.name'), json_extract(details, '$.sqft_living'), price
+             FROM mints
+             WHERE collection = ?1 AND token_id != ?2 AND json_extract(details, '$.zipcode') = ?3
+             ORDER BY abs(json_extract(details, '$.sqft_living') - ?4) LIMIT ?5",
+        )
+        .map_err(|e| format!("Failed to load comparables: {}", e))?;
+    let rows = stmt
+        .query_map(
+            params![
+                collection.name,
+                token_id,
+                details.get("zipcode").and_then(Value::as_u64),
+                details.get("sqft_living").and_then(Value::as_u64),
+                MAX_COMPARABLES
+            ],
+            |row| {
+                Ok(Comparable {
+                    token_id: row.get(0)?,
+                    name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    sqft_living: row.get::<_, Option<u64>>(2)?.unwrap_or_default(),
+                    price: row.get(3)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Failed to load comparables: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load comparables: {}", e))
+}
+
+fn valuations(state: &AppState, collection: &Collection, token_id: u64) -> Result<Vec<ValuationRow>, String> {
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(
+            "SELECT price, source, created_at FROM valuations
+             WHERE collection = ?1 AND token_id = ?2 AND valuation_type = 'market' ORDER BY id DESC LIMIT ?3",
+        )
+        .map_err(|e| format!("Failed to load valuations: {}", e))?;
+    let rows = stmt
+        .query_map(params![collection.name, token_id, MAX_VALUATIONS], |row| {
+            Ok(ValuationRow {
+                price: row.get(0)?,
+                source: row.get(1)?,
+                created_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to load valuations: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load valuations: {}", e))
+}
+
+/// Renders a one-page appraisal report for a token minted through this service.
+pub async fn appraisal_report(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Response, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let mint = load_mint(&state, &collection, token_id)?;
+    let comparables = comparables(&state, &collection, token_id, &mint.details)?;
+    let valuations = valuations(&state, &collection, token_id)?;
+    let verification_url = qr::verification_url(&state, &collection, token_id);
+
+    let name = mint.details.get("name").and_then(Value::as_str).unwrap_or_default();
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Appraisal report: {}", name),
+        Mm(PAGE_WIDTH),
+        Mm(PAGE_HEIGHT),
+        "Report",
+    );
+    let font_error = |e: printpdf::Error| format!("Failed to load report font: {}", e);
+    let mut writer = Writer {
+        layer: doc.get_page(page).get_layer(layer),
+        regular: doc.add_builtin_font(BuiltinFont::Helvetica).map_err(font_error)?,
+        bold: doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(font_error)?,
+        y: PAGE_HEIGHT - MARGIN - 7.0,
+    };
+    let code = QrCode::new(&verification_url).map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    writer.qr_code(&code, 30.0);
+
+    let details = &mint.details;
+    writer.title("Property Appraisal Report");
+    writer.text(name, 12.0, MARGIN, &writer.regular);
+    writer.y -= 8.0;
+
+    writer.heading("Valuation");
+    writer.field("Appraised value", &money(mint.price));
+    writer.field("Model version", state.config.model_version.as_deref().unwrap_or("unspecified"));
+    writer.field("Minted", &mint.minted_at);
+
+    writer.heading("Property");
+    writer.field("Bedrooms", &shown(details, "bedrooms", ""));
+    writer.field("Bathrooms", &shown(details, "bathrooms", ""));
+    writer.field("Living area", &shown(details, "sqft_living", " sqft"));
+    writer.field("Lot size", &shown(details, "sqft_lot", " sqft"));
+    writer.field("Floors", &shown(details, "floors", ""));
+    writer.field(
+        "Condition / grade",
+        &format!("{} / {}", shown(details, "condition", ""), shown(details, "grade", "")),
+    );
+    writer.field("Built", &shown(details, "yr_built", ""));
+    if details.get("yr_renovated").and_then(Value::as_u64).is_some_and(|year| year > 0) {
+        writer.field("Renovated", &shown(details, "yr_renovated", ""));
+    }
+    let waterfront = details.get("waterfront").and_then(Value::as_u64);
+    writer.field("Waterfront", waterfront.map_or("Private", |waterfront| if waterfront > 0 { "Yes" } else { "No" }));
+    writer.field("Zipcode", &shown(details, "zipcode", ""));
+
+    writer.heading("Comparable properties");
+    if comparables.is_empty() {
+        writer.row(&[(0.0, "No comparable properties in this zipcode.")]);
+    }
+    for comp in &comparables {
+        writer.row(&[
+            (0.0, &format!("#{}", comp.token_id)),
+            (15.0, &comp.name),
+            (95.0, &format!("{} sqft", comp.sqft_living)),
+            (130.0, &money(comp.price)),
+        ]);
+    }
+
+    writer.heading("Valuation history");
+    for valuation in &valuations {
+        writer.row(&[(0.0, &valuation.created_at), (45.0, &valuation.source), (95.0, &money(valuation.price))]);
+    }
+
+    writer.heading("On-chain record");
+    writer.field("Chain ID", &state.chain.chain_id.to_string());
+    writer.field("Contract", &format!("{:?}", collection.address));
+    writer.field("Token ID", &token_id.to_string());
+    writer.field("Owner at mint", &mint.recipient);
+    writer.field("Mint transaction", &mint.transaction_hash);
+    writer.field("Block", &mint.block_number.to_string());
+    writer.field("Verify", &verification_url);
+
+    let pdf = doc
+        .save_to_bytes()
+        .map_err(|e| format!("Failed to render report: {}", e))?;
+    Ok((
+        [
+            (CONTENT_TYPE, "application/pdf".to_string()),
+            (CONTENT_DISPOSITION, format!("inline; filename=\"token-{}-appraisal.pdf\"", token_id)),
+        ],
+        pdf,
+    )
+        .into_response())
+}
@@ -0,0 +1,104 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use crate::bindings::Royalties;
+use crate::capabilities::{self, Interface};
+use crate::error::ApiError;
+use crate::registry;
+use crate::simulation;
+use crate::state::{AppState, EthClient};
+use crate::tx::{self, TransactionResponse};
+use crate::CollectionQuery;
+
+// ERC-2981 fees are expressed in basis points of the sale price.
+const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+
+#[derive(Deserialize)]
+pub struct RoyaltyQuery {
+    sale_price: String,
+    collection: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RoyaltyResponse {
+    token_id: u64,
+    sale_price: String,
+    receiver: Address,
+    royalty_amount: String,
+}
+
+#[derive(Deserialize)]
+pub struct SetRoyalty {
+    receiver: Address,
+    fee_basis_points: u16,
+}
+
+/// The state for the collection's chain, and its royalty interface on that chain.
+async fn contract(state: &AppState, collection: Option<&str>) -> Result<(AppState, Royalties<EthClient>), ApiError> {
+    let collection = registry::resolve(&state.db, collection)?;
+    capabilities::require(state, &collection, Interface::Royalties).await?;
+    let state = state.on(&collection)?;
+    let contract = Royalties::new(collection.address, state.client.clone());
+    Ok((state, contract))
+}
+
+fn validate(request: &SetRoyalty) -> Result<(), ApiError> {
+    if request.fee_basis_points > MAX_FEE_BASIS_POINTS {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("fee_basis_points must not exceed {}", MAX_FEE_BASIS_POINTS),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn royalty_info(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<RoyaltyQuery>,
+) -> Result<Json<RoyaltyResponse>, ApiError> {
+    let sale_price = U256::from_dec_str(&query.sale_price)
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "sale_price must be an integer amount in wei"))?;
+    let (_, contract) = contract(&state, query.collection.as_deref()).await?;
+    let (receiver, royalty_amount) = contract
+        .royalty_info(U256::from(token_id), sale_price)
+        .call()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read royalty info", e))?;
+    Ok(Json(RoyaltyResponse {
+        token_id,
+        sale_price: sale_price.to_string(),
+        receiver,
+        royalty_amount: royalty_amount.to_string(),
+    }))
+}
+
+pub async fn set_default_royalty(
+    State(state): State<AppState>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<SetRoyalty>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    validate(&request)?;
+    let (state, contract) = contract(&state, query.collection.as_deref()).await?;
+    println!("Setting default royalty to {} bps for {:?}...", request.fee_basis_points, request.receiver);
+    let call = contract.set_default_royalty(request.receiver, request.fee_basis_points.into());
+    let receipt = tx::submit(&state, call).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
+
+pub async fn set_token_royalty(
+    State(state): State<AppState>,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(request): Json<SetRoyalty>,
+) -> Result<Json<TransactionResponse>, ApiError> {
+    validate(&request)?;
+    let (state, contract) = contract(&state, query.collection.as_deref()).await?;
+    println!("Setting royalty for token {} to {} bps...", token_id, request.fee_basis_points);
+    let call = contract.set_token_royalty(U256::from(token_id), request.receiver, request.fee_basis_points.into());
+    let receipt = tx::submit(&state, call).await?;
+    Ok(Json(tx::response(&state, &receipt)))
+}
@@ -0,0 +1,243 @@
+use axum::async_trait;
+use axum::body::Bytes;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::optional_env;
+use crate::storage::{MetadataStore, PresignedUpload};
+
+#[cfg(test)]
+mod tests;
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// An S3-compatible bucket (AWS, MinIO, R2, ...) addressed path-style and signed with SigV4.
+pub struct S3 {
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+    /// Where stored objects are publicly readable.
+    public_url: String,
+    upload_expiry_secs: u64,
+}
+
+impl S3 {
+    /// Enabled by S3_BUCKET.
+    pub fn from_env() -> Option<Self> {
+        let bucket = optional_env("S3_BUCKET")?;
+        let region = optional_env("S3_REGION").unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = optional_env("S3_ENDPOINT").unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        let endpoint: Url = endpoint.parse().expect("S3_ENDPOINT must be a URL");
+        let public_url = optional_env("S3_PUBLIC_URL")
+            .unwrap_or_else(|| format!("{}/{}", endpoint.as_str().trim_end_matches('/'), bucket));
+        println!("S3_BUCKET: {} at {}", bucket, endpoint);
+        Some(S3 {
+            access_key_id: optional_env("S3_ACCESS_KEY_ID").expect("S3_BUCKET needs S3_ACCESS_KEY_ID"),
+            secret_access_key: optional_env("S3_SECRET_ACCESS_KEY").expect("S3_BUCKET needs S3_SECRET_ACCESS_KEY"),
+            upload_expiry_secs: optional_env("S3_UPLOAD_EXPIRY_SECS")
+                .map(|secs| secs.parse().expect("S3_UPLOAD_EXPIRY_SECS must be a number"))
+                .unwrap_or(900),
+            endpoint,
+            bucket,
+            region,
+            public_url: public_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn object_url(&self, key: &str) -> Url {
+        let mut url = self.endpoint.clone();
+        url.set_path(&format!("/{}/{}", self.bucket, key));
+        url
+    }
+
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        match self.endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        }
+    }
+
+    fn scope(&self, date: &str) -> String {
+        format!("{}/{}/s3/aws4_request", date, self.region)
+    }
+
+    fn signature(&self, timestamp: &str, canonical_request: &str) -> String {
+        let date = &timestamp[..8];
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            self.scope(date),
+            ethers::utils::hex::encode(Sha256::digest(canonical_request))
+        );
+        let key = [date, &self.region, "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.secret_access_key).into_bytes(), |key, part| {
+                hmac(&key, part.as_bytes())
+            });
+        ethers::utils::hex::encode(hmac(&key, string_to_sign.as_bytes()))
+    }
+
+    async fn put_object(&self, http: &Client, key: &str, bytes: Bytes, content_type: &str) -> Result<String, String> {
+        let url = self.object_url(key);
+        let timestamp = amz_timestamp(SystemTime::now());
+        let payload_hash = ethers::utils::hex::encode(Sha256::digest(&bytes));
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            url.path(),
+            self.host(),
+            payload_hash,
+            timestamp,
+            payload_hash
+        );
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.access_key_id,
+            self.scope(&timestamp[..8]),
+            self.signature(&timestamp, &canonical_request)
+        );
+        http.put(url)
+            .header("authorization", authorization)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", &timestamp)
+            .header("content-type", content_type)
+            .body(bytes)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload {} to S3: {}", key, e))?
+            .error_for_status()
+            .map_err(|e| format!("S3 upload of {} failed: {}", key, e))?;
+        Ok(format!("{}/{}", self.public_url, key))
+    }
+
+    /// A query-string signed URL that lets whoever holds it PUT `key` until it expires.
+    fn presign_put(&self, key: &str, now: SystemTime) -> String {
+        let url = self.object_url(key);
+        let query = self.presigned_query("PUT", url.path(), now);
+        format!("{}?{}", url, query)
+    }
+
+    /// The query string, signature included, that authorizes `method` on `path` until it expires.
+    fn presigned_query(&self, method: &str, path: &str, now: SystemTime) -> String {
+        let timestamp = amz_timestamp(now);
+        let credential = format!("{}/{}", self.access_key_id, self.scope(&timestamp[..8]));
+        // Already in the sorted order SigV4 requires.
+        let query = [
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+            ("X-Amz-Credential", credential),
+            ("X-Amz-Date", timestamp.clone()),
+            ("X-Amz-Expires", self.upload_expiry_secs.to_string()),
+            ("X-Amz-SignedHeaders", "host".to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, uri_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+        let canonical_request =
+            format!("{}\n{}\n{}\nhost:{}\n\nhost\n{}", method, path, query, self.host(), UNSIGNED_PAYLOAD);
+        format!("{}&X-Amz-Signature={}", query, self.signature(&timestamp, &canonical_request))
+    }
+}
+
+#[async_trait]
+impl MetadataStore for S3 {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn put(&self, http: &Client, metadata: &Value) -> Result<String, String> {
+        self.put_file(http, Bytes::from(metadata.to_string()), "application/json").await
+    }
+
+    fn stores_files(&self) -> bool {
+        true
+    }
+
+    async fn put_file(&self, http: &Client, bytes: Bytes, content_type: &str) -> Result<String, String> {
+        // Content-addressed, like the filesystem store, so re-uploads are idempotent.
+        let hash = ethers::utils::hex::encode(Sha256::digest(&bytes));
+        let key = match content_type {
+            "application/json" => format!("metadata/{}.json", hash),
+            _ => format!("images/{}.{}", hash, crate::storage::extension(content_type)),
+        };
+        self.put_object(http, &key, bytes, content_type).await
+    }
+
+    fn presign_file(&self, content_type: &str) -> Option<PresignedUpload> {
+        // The content isn't known yet, so the key is random instead.
+        let key = format!(
+            "images/{}.{}",
+            ethers::utils::hex::encode(ethers::core::rand::random::<[u8; 16]>()),
+            crate::storage::extension(content_type)
+        );
+        Some(PresignedUpload {
+            upload_url: self.presign_put(&key, SystemTime::now()),
+            method: "PUT",
+            uri: format!("{}/{}", self.public_url, key),
+            expires_in: self.upload_expiry_secs,
+        })
+    }
+
+    fn owns(&self, token_uri: &str) -> bool {
+        token_uri.starts_with(&format!("{}/", self.public_url))
+    }
+
+    async fn get(&self, http: &Client, token_uri: &str) -> Result<Value, String> {
+        http.get(token_uri)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", token_uri, e))?
+            .error_for_status()
+            .map_err(|e| format!("Fetching {} failed: {}", token_uri, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid metadata at {}: {}", token_uri, e))
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes everything but unreserved characters, as SigV4 canonical queries require.
+fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// `YYYYMMDDTHHMMSSZ` in UTC.
+fn amz_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    // Days since the epoch to a civil date (Howard Hinnant's algorithm).
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time_of_day = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3_600,
+        time_of_day / 60 % 60,
+        time_of_day % 60
+    )
+}
@@ -0,0 +1,88 @@
+use axum::http::StatusCode;
+use ethers::types::Address;
+use rusqlite::params;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::bindings::SanctionsOracle;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::simulation;
+use crate::state::AppState;
+
+/// Sanctions screening against a local address list, the Chainalysis on-chain oracle, or both.
+pub struct SanctionsConfig {
+    oracle: Option<Address>,
+    list: HashSet<Address>,
+}
+
+impl SanctionsConfig {
+    /// Enabled by setting SANCTIONS_LIST_PATH (one address per line, `#` comments allowed)
+    /// and/or SANCTIONS_ORACLE_ADDRESS.
+    pub fn from_env() -> Option<Self> {
+        let oracle = optional_env("SANCTIONS_ORACLE_ADDRESS").map(|address| {
+            println!("SANCTIONS_ORACLE_ADDRESS: {}", address);
+            address.parse().expect("Invalid sanctions oracle address")
+        });
+        let path = optional_env("SANCTIONS_LIST_PATH");
+        if oracle.is_none() && path.is_none() {
+            return None;
+        }
+
+        let mut list = HashSet::new();
+        if let Some(path) = path {
+            let contents = fs::read_to_string(&path)
+                .unwrap_or_else(|e| panic!("Failed to read sanctions list {}: {}", path, e));
+            for line in contents.lines().map(|line| line.split('#').next().unwrap_or_default().trim()) {
+                if !line.is_empty() {
+                    list.insert(line.parse().unwrap_or_else(|_| panic!("Invalid address in sanctions list: {}", line)));
+                }
+            }
+            println!("SANCTIONS_LIST_PATH: {} ({} addresses)", path, list.len());
+        }
+        Some(SanctionsConfig { oracle, list })
+    }
+}
+
+fn record_rejection(db: &Db, address: Address, action: &str, source: &str) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO sanctions_rejections (address, action, source) VALUES (?1, ?2, ?3)",
+        params![format!("{:?}", address), action, source],
+    )
+    .map_err(|e| format!("Failed to record sanctions rejection: {}", e))?;
+    Ok(())
+}
+
+/// Rejects `action` (e.g. "mint", "transfer") to a sanctioned address. Fails closed when
+/// the oracle can't be reached.
+pub async fn screen(state: &AppState, address: Address, action: &str) -> Result<(), ApiError> {
+    let Some(sanctions) = &state.config.sanctions else {
+        return Ok(());
+    };
+
+    let source = if sanctions.list.contains(&address) {
+        Some("local sanctions list")
+    } else if let Some(oracle) = sanctions.oracle {
+        let sanctioned = SanctionsOracle::new(oracle, state.client.clone())
+            .is_sanctioned(address)
+            .call()
+            .await
+            .map_err(|e| simulation::call_error("Sanctions screening is unavailable", e))?;
+        sanctioned.then_some("Chainalysis sanctions oracle")
+    } else {
+        None
+    };
+
+    let Some(source) = source else {
+        return Ok(());
+    };
+    eprintln!("Rejected {} to {:?}: listed by {}", action, address, source);
+    record_rejection(&state.db, address, action, source)?;
+    Err(ApiError::new(
+        StatusCode::FORBIDDEN,
+        format!("Cannot {} to {:?}: address is sanctioned", action, address),
+    )
+    .with_details(serde_json::json!({ "source": source })))
+}
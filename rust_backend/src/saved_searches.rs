@@ -0,0 +1,301 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::email;
+use crate::error::ApiError;
+use crate::organizations::{self, Caller, Tenant};
+use crate::registry::Collection;
+use crate::search::Criteria;
+use crate::state::AppState;
+
+pub const MATCH_EVENT: &str = "search.match";
+
+#[derive(Deserialize)]
+pub struct CreateSavedSearch {
+    name: String,
+    /// Same as `q` and `filters` on GET /search.
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    filters: String,
+    collection: Option<String>,
+    /// Where matches are POSTed, signed like organization webhooks.
+    webhook_url: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    pub q: String,
+    pub filters: String,
+    pub collection: Option<String>,
+    pub webhook_url: Option<String>,
+    pub email: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct CreatedSavedSearch {
+    #[serde(flatten)]
+    search: SavedSearch,
+    /// Signing secret for `webhook_url` deliveries, shown once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    webhook_secret: Option<String>,
+}
+
+fn row_to_search(row: &rusqlite::Row) -> rusqlite::Result<SavedSearch> {
+    Ok(SavedSearch {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        q: row.get(2)?,
+        filters: row.get(3)?,
+        collection: row.get(4)?,
+        webhook_url: row.get(5)?,
+        email: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+const COLUMNS: &str = "id, name, q, filters, collection, webhook_url, email, created_at";
+
+/// Saved searches hold their owner's email and webhook, so they belong to whoever created them
+/// and anonymous callers, who can't be told apart, have none.
+fn owner(caller: Caller) -> Result<String, ApiError> {
+    caller
+        .0
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "Saved searches need an API key or admin token"))
+}
+
+fn load(state: &AppState, tenant: &Tenant, owner: &str, id: Option<i64>) -> Result<Vec<SavedSearch>, String> {
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM saved_searches
+             WHERE organization IS ?1 AND owner = ?2 AND (?3 IS NULL OR id = ?3) ORDER BY id",
+            COLUMNS
+        ))
+        .map_err(|e| format!("Failed to load saved searches: {}", e))?;
+    let rows = stmt
+        .query_map(params![tenant.organization_id(), owner, id], row_to_search)
+        .map_err(|e| format!("Failed to load saved searches: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load saved searches: {}", e))
+}
+
+/// Rejects webhook URLs that aren't http(s) or that resolve to a private, loopback or
+/// link-local address, since anyone with an API key can have the server POST to them.
+/// Deliveries check the address again, so this only catches mistakes early.
+async fn check_webhook_url(url: &str) -> Result<(), ApiError> {
+    organizations::public_address(url)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, format!("webhook_url {}", e)))?;
+    Ok(())
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    caller: Caller,
+    Json(request): Json<CreateSavedSearch>,
+) -> Result<Json<CreatedSavedSearch>, ApiError> {
+    let owner = owner(caller)?;
+    Criteria::parse(&request.q, &request.filters)?;
+    if let Some(url) = &request.webhook_url {
+        check_webhook_url(url).await?;
+    }
+    if let Some(address) = &request.email {
+        if state.config.email.is_none() {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "Email notifications need SMTP_URL to be set"));
+        }
+        if !email::is_valid(address) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, format!("{} is not an email address", address)));
+        }
+    }
+    // Organizations fall back to their own webhooks; the platform has none.
+    if request.webhook_url.is_none() && request.email.is_none() && tenant.organization_id().is_none() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "A saved search needs a webhook_url or email"));
+    }
+    let webhook_secret = request.webhook_url.as_ref().map(|_| organizations::random_secret("whsec"));
+    let id = {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "INSERT INTO saved_searches
+                 (organization, name, q, filters, collection, webhook_url, webhook_secret, email, owner)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                tenant.organization_id(),
+                request.name,
+                request.q,
+                request.filters,
+                request.collection,
+                request.webhook_url,
+                webhook_secret,
+                request.email,
+                owner,
+            ],
+        )
+        .map_err(|e| format!("Failed to save search: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    let search = load(&state, &tenant, &owner, Some(id))?.pop().ok_or("Saved search disappeared")?;
+    Ok(Json(CreatedSavedSearch { search, webhook_secret }))
+}
+
+/// The caller's own saved searches.
+pub async fn list(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    caller: Caller,
+) -> Result<Json<Vec<SavedSearch>>, ApiError> {
+    let owner = owner(caller)?;
+    Ok(Json(load(&state, &tenant, &owner, None)?))
+}
+
+pub async fn delete(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    caller: Caller,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let owner = owner(caller)?;
+    let deleted = {
+        let conn = state.db.lock().unwrap();
+        let deleted = conn
+            .execute(
+                "DELETE FROM saved_searches WHERE id = ?1 AND organization IS ?2 AND owner = ?3",
+                params![id, tenant.organization_id(), owner],
+            )
+            .map_err(|e| format!("Failed to delete saved search: {}", e))?;
+        if deleted > 0 {
+            conn.execute("DELETE FROM saved_search_matches WHERE search_id = ?1", params![id])
+                .map_err(|e| format!("Failed to delete saved search: {}", e))?;
+        }
+        deleted
+    };
+    if deleted == 0 {
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("No saved search {}", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+struct Subscription {
+    search: SavedSearch,
+    organization: Option<String>,
+    webhook_secret: Option<String>,
+}
+
+/// Checks newly minted tokens of `collection` against the saved searches of its owner and
+/// notifies each search once per matching token. Tokens minted outside this service have no
+/// recorded metadata and are skipped.
+pub fn evaluate(state: &AppState, collection: &Collection, token_ids: &[u64]) -> Result<(), String> {
+    if token_ids.is_empty() {
+        return Ok(());
+    }
+    let (subscriptions, notifications) = {
+        let conn = state.db.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {}, organization, webhook_secret FROM saved_searches
+                 WHERE organization IS ?1 AND (collection IS NULL OR collection = ?2)",
+                COLUMNS
+            ))
+            .map_err(|e| format!("Failed to load saved searches: {}", e))?;
+        let subscriptions = stmt
+            .query_map(params![collection.organization, collection.name], |row| {
+                Ok(Subscription {
+                    search: row_to_search(row)?,
+                    organization: row.get(8)?,
+                    webhook_secret: row.get(9)?,
+                })
+            })
+            .map_err(|e| format!("Failed to load saved searches: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to load saved searches: {}", e))?;
+        if subscriptions.is_empty() {
+            return Ok(());
+        }
+        let mut notifications = Vec::new();
+
+        for &token_id in token_ids {
+            let Some((metadata, price)) = conn
+                .query_row(
+                    "SELECT metadata, price FROM mints WHERE collection = ?1 AND token_id = ?2",
+                    params![collection.name, token_id],
+                    |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)),
+                )
+                .ok()
+            else {
+                continue;
+            };
+            let metadata: Value = serde_json::from_str(&metadata).unwrap_or_default();
+            for (index, subscription) in subscriptions.iter().enumerate() {
+                let criteria = Criteria::parse(&subscription.search.q, &subscription.search.filters)
+                    .map_err(|e| e.to_string())?;
+                if !criteria.matches(&conn, &collection.name, token_id, &metadata)? {
+                    continue;
+                }
+                // Re-indexing a block range mustn't notify twice.
+                let new = conn
+                    .execute(
+                        "INSERT OR IGNORE INTO saved_search_matches (search_id, collection, token_id) VALUES (?1, ?2, ?3)",
+                        params![subscription.search.id, collection.name, token_id],
+                    )
+                    .map_err(|e| format!("Failed to record saved search match: {}", e))?;
+                if new > 0 {
+                    notifications.push((index, token_id, metadata.clone(), price));
+                }
+            }
+        }
+        (subscriptions, notifications)
+    };
+    for (index, token_id, metadata, price) in notifications {
+        notify(state, &subscriptions[index], &collection.name, token_id, metadata, price);
+    }
+    Ok(())
+}
+
+fn notify(state: &AppState, subscription: &Subscription, collection: &str, token_id: u64, metadata: Value, price: f64) {
+    let search = &subscription.search;
+    let data = json!({
+        "saved_search": { "id": search.id, "name": search.name },
+        "collection": collection,
+        "token_id": token_id,
+        "price": price,
+        "metadata": metadata,
+    });
+    println!("Token {} of {} matches saved search {}", token_id, collection, search.id);
+    if let (Some(url), Some(secret)) = (&search.webhook_url, &subscription.webhook_secret) {
+        let body = json!({ "type": MATCH_EVENT, "organization": subscription.organization, "data": data });
+        organizations::deliver(url.clone(), secret.clone(), body.to_string());
+    }
+    if let (Some(address), Some(_)) = (&search.email, &state.config.email) {
+        let state = state.clone();
+        let address = address.clone();
+        let subject = format!("New match for \"{}\"", search.name);
+        let body = format!(
+            "{} was just minted in {} at ${} and matches your saved search \"{}\".\n\nToken ID: {}\n",
+            metadata["name"].as_str().unwrap_or("A property"),
+            collection,
+            price,
+            search.name,
+            token_id
+        );
+        tokio::spawn(async move {
+            let config = state.config.email.as_ref().unwrap();
+            if let Err(e) = email::send(config, &address, &subject, body).await {
+                eprintln!("{}", e);
+            }
+        });
+    }
+    if search.webhook_url.is_none() && search.email.is_none() {
+        if let Some(organization) = &subscription.organization {
+            organizations::notify(state, organization, MATCH_EVENT, data);
+        }
+    }
+}
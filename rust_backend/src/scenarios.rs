@@ -0,0 +1,238 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::ApiError;
+use crate::mint::{self, HouseDetails};
+use crate::money::Money;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+use crate::valuations;
+use crate::CollectionQuery;
+
+const MAX_SCENARIOS: usize = 100;
+
+/// Hypothetical changes to a property. Additions may be negative, e.g. to model converting a bedroom.
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub add_bedrooms: i64,
+    pub add_bathrooms: f64,
+    /// Added above ground, e.g. an extension.
+    pub add_sqft_living: i64,
+    pub add_sqft_basement: i64,
+    pub add_floors: i64,
+    /// Year of a renovation.
+    pub renovated_in: Option<u64>,
+    /// Condition (1-5) after the work, e.g. for insulation or new windows.
+    pub condition: Option<u64>,
+    /// Construction grade (1-13) after the work.
+    pub grade: Option<u64>,
+    /// What the work would cost, for the return on investment.
+    pub cost: Option<f64>,
+}
+
+/// A batch of scenarios: a list, the combinations of a matrix of values per change, or both.
+#[derive(Deserialize)]
+pub struct WhatIfBatch {
+    #[serde(default)]
+    scenarios: Vec<Value>,
+    /// Each change mapped to the values to try, e.g. `{"add_bathrooms": [0, 1], "grade": [8, 9]}`.
+    #[serde(default)]
+    matrix: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+pub struct WhatIfResponse {
+    collection: String,
+    token_id: u64,
+    /// The model's price for the property as it is, so every price comes from the same model.
+    current_price: f64,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Serialize)]
+pub struct WhatIfTable {
+    collection: String,
+    token_id: u64,
+    current_price: f64,
+    scenarios: Vec<ScenarioRow>,
+}
+
+#[derive(Serialize)]
+pub struct ScenarioRow {
+    /// The scenario's changes as submitted.
+    changes: Value,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+#[derive(Serialize)]
+pub struct Outcome {
+    predicted_price: f64,
+    delta: f64,
+    delta_percent: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost: Option<f64>,
+    /// Gain in value net of the cost, as a percentage of the cost.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    roi_percent: Option<f64>,
+}
+
+fn add(value: u64, delta: i64, field: &str) -> Result<u64, ApiError> {
+    value.checked_add_signed(delta).ok_or_else(|| {
+        ApiError::new(StatusCode::BAD_REQUEST, format!("The scenario would leave {} below zero", field))
+    })
+}
+
+/// `details` with the scenario's changes made.
+pub fn apply(details: &HouseDetails, scenario: &Scenario) -> Result<HouseDetails, ApiError> {
+    let mut changed = details.clone();
+    changed.bedrooms = add(details.bedrooms, scenario.add_bedrooms, "bedrooms")?;
+    changed.bathrooms = details.bathrooms + scenario.add_bathrooms;
+    if !scenario.add_bathrooms.is_finite() || changed.bathrooms < 0.0 {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "The scenario would leave bathrooms below zero"));
+    }
+    changed.sqft_above = add(details.sqft_above, scenario.add_sqft_living, "sqft_above")?;
+    changed.sqft_basement = add(details.sqft_basement, scenario.add_sqft_basement, "sqft_basement")?;
+    changed.sqft_living = add(
+        details.sqft_living,
+        scenario.add_sqft_living + scenario.add_sqft_basement,
+        "sqft_living",
+    )?;
+    changed.floors = add(details.floors, scenario.add_floors, "floors")?;
+    if let Some(year) = scenario.renovated_in {
+        if year < details.yr_built {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("renovated_in must not be before the house was built in {}", details.yr_built),
+            ));
+        }
+        changed.yr_renovated = year;
+    }
+    if let Some(condition) = scenario.condition {
+        if !(1..=5).contains(&condition) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "condition must be between 1 and 5"));
+        }
+        changed.condition = condition;
+    }
+    if let Some(grade) = scenario.grade {
+        if !(1..=13).contains(&grade) {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "grade must be between 1 and 13"));
+        }
+        changed.grade = grade;
+    }
+    if scenario.cost.is_some_and(|cost| !(cost.is_finite() && cost > 0.0)) {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "cost must be a positive number"));
+    }
+    Ok(changed)
+}
+
+fn outcome(current_price: Money, predicted_price: Money, cost: Option<f64>) -> Outcome {
+    let delta = (predicted_price.cents - current_price.cents) as f64 / 100.0;
+    Outcome {
+        predicted_price: predicted_price.to_f64(),
+        delta,
+        delta_percent: delta / current_price.to_f64() * 100.0,
+        cost,
+        roi_percent: cost.map(|cost| (delta - cost) / cost * 100.0),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, message)
+}
+
+/// The listed scenarios followed by every combination of the matrix values.
+fn expand(batch: WhatIfBatch) -> Result<Vec<Value>, ApiError> {
+    let mut combinations = vec![Map::new()];
+    for (change, values) in batch.matrix {
+        let values = match values {
+            Value::Array(values) if !values.is_empty() => values,
+            _ => return Err(invalid(format!("matrix.{} must be a non-empty list of values", change))),
+        };
+        if combinations.len() * values.len() > MAX_SCENARIOS {
+            return Err(invalid(format!("A batch can have at most {} scenarios", MAX_SCENARIOS)));
+        }
+        let mut expanded = Vec::with_capacity(combinations.len() * values.len());
+        for combination in &combinations {
+            for value in &values {
+                let mut combination = combination.clone();
+                combination.insert(change.clone(), value.clone());
+                expanded.push(combination);
+            }
+        }
+        combinations = expanded;
+    }
+    let mut scenarios = batch.scenarios;
+    if combinations.first().is_some_and(|combination| !combination.is_empty()) {
+        scenarios.extend(combinations.into_iter().map(Value::Object));
+    }
+    if scenarios.is_empty() {
+        return Err(invalid("scenarios or matrix is required"));
+    }
+    if scenarios.len() > MAX_SCENARIOS {
+        return Err(invalid(format!("A batch can have at most {} scenarios", MAX_SCENARIOS)));
+    }
+    Ok(scenarios)
+}
+
+/// Re-runs the model on the token's property with hypothetical changes, for renovation planning.
+/// A body with `scenarios` or `matrix` prices a batch of them side by side in one model call.
+pub async fn what_if(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+    Json(body): Json<Value>,
+) -> Result<Response, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let (details, _, _) = valuations::stored_details(&state, &collection.name, token_id)?;
+    let parse = |value: Value| {
+        serde_json::from_value::<Scenario>(value).map_err(|e| invalid(format!("Invalid scenario: {}", e)))
+    };
+
+    if body.get("scenarios").is_none() && body.get("matrix").is_none() {
+        let scenario = parse(body)?;
+        let changed = apply(&details, &scenario)?;
+        let prices = mint::predict_batch(&state, &[details, changed]).await?;
+        return Ok(Json(WhatIfResponse {
+            collection: collection.name,
+            token_id,
+            current_price: prices[0].to_f64(),
+            outcome: outcome(prices[0], prices[1], scenario.cost),
+        })
+        .into_response());
+    }
+
+    let batch = serde_json::from_value(body).map_err(|e| invalid(format!("Invalid scenarios: {}", e)))?;
+    let changes = expand(batch)?;
+    let scenarios = changes.iter().cloned().map(parse).collect::<Result<Vec<_>, _>>()?;
+    let mut houses = vec![details.clone()];
+    for (index, scenario) in scenarios.iter().enumerate() {
+        houses.push(apply(&details, scenario).map_err(|e| invalid(format!("Scenario {}: {}", index, e.message)))?);
+    }
+    let prices = mint::predict_batch(&state, &houses).await?;
+    let current_price = prices[0];
+    let rows = changes
+        .into_iter()
+        .zip(&scenarios)
+        .zip(&prices[1..])
+        .map(|((changes, scenario), price)| ScenarioRow {
+            changes,
+            outcome: outcome(current_price, *price, scenario.cost),
+        })
+        .collect();
+    Ok(Json(WhatIfTable {
+        collection: collection.name,
+        token_id,
+        current_price: current_price.to_f64(),
+        scenarios: rows,
+    })
+    .into_response())
+}
@@ -0,0 +1,223 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::state::AppState;
+
+/// Traits with more distinct values than this (prices, areas) get no facet counts.
+const MAX_FACET_VALUES: usize = 20;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    /// Words to match in token names and descriptions; prefixes match too.
+    #[serde(default)]
+    q: String,
+    /// Comma-separated `Trait:value`, `Trait:a|b` or `Trait:min..max` (either end optional).
+    #[serde(default)]
+    filters: String,
+    collection: Option<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub collection: String,
+    pub token_id: u64,
+    pub name: String,
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    pub price: f64,
+}
+
+#[derive(Serialize)]
+pub struct SearchResults {
+    pub total: usize,
+    pub results: Vec<SearchHit>,
+    /// Per trait, how many matching tokens have each value.
+    pub facets: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+enum Filter {
+    OneOf(Vec<String>),
+    Range(Option<f64>, Option<f64>),
+}
+
+/// Parsed search words and trait filters.
+pub struct Criteria {
+    words: Option<String>,
+    filters: Vec<(String, Filter)>,
+}
+
+impl Criteria {
+    pub fn parse(q: &str, filters: &str) -> Result<Self, ApiError> {
+        Ok(Criteria {
+            words: match_expression(q),
+            filters: parse_filters(filters)?,
+        })
+    }
+
+    /// Whether a token recorded in `mints`, with this metadata, matches.
+    pub fn matches(&self, conn: &Connection, collection: &str, token_id: u64, metadata: &Value) -> Result<bool, String> {
+        if !matches(&traits(metadata), &self.filters) {
+            return Ok(false);
+        }
+        let Some(words) = &self.words else { return Ok(true) };
+        conn.query_row(
+            "SELECT 1 FROM search_index WHERE search_index MATCH ?1 AND collection = ?2 AND token_id = ?3",
+            params![words, collection, token_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|found| found.is_some())
+        .map_err(|e| format!("Failed to match search: {}", e))
+    }
+}
+
+fn parse_filters(filters: &str) -> Result<Vec<(String, Filter)>, ApiError> {
+    filters
+        .split(',')
+        .filter(|filter| !filter.trim().is_empty())
+        .map(|filter| {
+            let (trait_type, value) = filter.split_once(':').ok_or_else(|| {
+                ApiError::new(StatusCode::BAD_REQUEST, format!("Filter {} must look like Trait:value", filter))
+            })?;
+            let filter = match value.split_once("..") {
+                Some((min, max)) => {
+                    let bound = |bound: &str| {
+                        (!bound.is_empty())
+                            .then(|| bound.parse::<f64>())
+                            .transpose()
+                            .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, format!("{} is not a number", bound)))
+                    };
+                    Filter::Range(bound(min)?, bound(max)?)
+                }
+                None => Filter::OneOf(value.split('|').map(str::to_string).collect()),
+            };
+            Ok((trait_type.trim().to_string(), filter))
+        })
+        .collect()
+}
+
+/// The metadata's attributes by trait type.
+fn traits(metadata: &Value) -> Map<String, Value> {
+    metadata["attributes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|attribute| Some((attribute["trait_type"].as_str()?.to_string(), attribute["value"].clone())))
+        .collect()
+}
+
+/// How a trait value is written in filters and facets.
+fn facet_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn matches(traits: &Map<String, Value>, filters: &[(String, Filter)]) -> bool {
+    filters.iter().all(|(trait_type, filter)| {
+        let Some(value) = traits.get(trait_type) else { return false };
+        match filter {
+            Filter::OneOf(values) => values.contains(&facet_value(value)),
+            Filter::Range(min, max) => value
+                .as_f64()
+                .is_some_and(|value| min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)),
+        }
+    })
+}
+
+/// Quotes each word as an FTS5 prefix query, so user input can't use (or break) the query syntax.
+fn match_expression(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Full-text search over token names and descriptions, narrowed by trait filters, with facet
+/// counts over the matches.
+pub async fn search(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<SearchResults>, ApiError> {
+    let criteria = Criteria::parse(&query.q, &query.filters)?;
+    let rows = {
+        let conn = state.db.lock().unwrap();
+        // Best matches first; without words, newest first.
+        let sql = match criteria.words {
+            Some(_) => {
+                "SELECT m.collection, m.token_id, m.metadata, m.price
+                 FROM search_index s
+                 JOIN mints m ON m.collection = s.collection AND m.token_id = s.token_id
+                 JOIN collections c ON c.name = m.collection
+                 WHERE search_index MATCH ?1 AND (?2 IS NULL OR m.collection = ?2) AND c.organization IS ?3
+                 ORDER BY s.rank"
+            }
+            None => {
+                "SELECT m.collection, m.token_id, m.metadata, m.price
+                 FROM mints m JOIN collections c ON c.name = m.collection
+                 WHERE ?1 IS NULL AND (?2 IS NULL OR m.collection = ?2) AND c.organization IS ?3
+                 ORDER BY m.id DESC"
+            }
+        };
+        let mut stmt = conn.prepare(sql).map_err(|e| format!("Failed to search tokens: {}", e))?;
+        let rows = stmt
+            .query_map(
+                params![criteria.words, query.collection, tenant.organization_id()],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, u64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, f64>(3)?,
+                    ))
+                },
+            )
+            .map_err(|e| format!("Failed to search tokens: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to search tokens: {}", e))?
+    };
+
+    let mut hits = Vec::new();
+    let mut facets: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for (collection, token_id, metadata, price) in rows {
+        let metadata: Value = serde_json::from_str(&metadata).unwrap_or_default();
+        let traits = traits(&metadata);
+        if !matches(&traits, &criteria.filters) {
+            continue;
+        }
+        for (trait_type, value) in &traits {
+            *facets.entry(trait_type.clone()).or_default().entry(facet_value(value)).or_default() += 1;
+        }
+        hits.push(SearchHit {
+            collection,
+            token_id,
+            name: metadata["name"].as_str().unwrap_or_default().to_string(),
+            description: metadata["description"].as_str().unwrap_or_default().to_string(),
+            image: metadata["image"].as_str().map(str::to_string),
+            price,
+        });
+    }
+    facets.retain(|_, values| values.len() <= MAX_FACET_VALUES);
+
+    let total = hits.len();
+    let results = hits
+        .into_iter()
+        .skip(query.offset.unwrap_or(0))
+        .take(query.limit.unwrap_or(50).min(500))
+        .collect();
+    Ok(Json(SearchResults { total, results, facets }))
+}
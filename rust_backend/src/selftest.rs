@@ -0,0 +1,118 @@
+use ethers::abi::Abi;
+use ethers::providers::Middleware;
+use std::time::Duration;
+
+use crate::abi_check;
+use crate::mint::PRICE_MODEL_URL;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+
+/// Functions every collection contract must expose for minting and metadata reads.
+const REQUIRED_FUNCTIONS: &[&str] = &["mintNFT", "tokenURI"];
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Check {
+    name: String,
+    result: Result<String, String>,
+}
+
+impl Check {
+    fn new(name: impl Into<String>, result: Result<String, String>) -> Self {
+        Check { name: name.into(), result }
+    }
+}
+
+async fn chain_id(state: &AppState) -> Result<String, String> {
+    let chain_id = state
+        .client
+        .get_chainid()
+        .await
+        .map_err(|e| format!("RPC endpoint unreachable: {}", e))?;
+    if chain_id.as_u64() != state.chain.chain_id {
+        return Err(format!(
+            "RPC endpoint is on chain {} but CHAIN_ID is {}",
+            chain_id, state.chain.chain_id
+        ));
+    }
+    Ok(format!("{}", chain_id))
+}
+
+async fn bytecode(state: &AppState, collection: &Collection) -> Result<String, String> {
+    let code = state
+        .client
+        .get_code(collection.address, None)
+        .await
+        .map_err(|e| format!("Failed to read contract code: {}", e))?;
+    if code.is_empty() {
+        return Err(format!("No contract is deployed at {:?}", collection.address));
+    }
+    Ok(format!("{} bytes at {:?}", code.len(), collection.address))
+}
+
+fn abi(abi: &Abi) -> Result<String, String> {
+    let missing: Vec<&str> = REQUIRED_FUNCTIONS
+        .iter()
+        .copied()
+        .filter(|name| abi.function(name).is_err())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("ABI is missing {}", missing.join(", ")));
+    }
+    Ok(format!("has {}", REQUIRED_FUNCTIONS.join(", ")))
+}
+
+async fn selectors(state: &AppState, collection: &Collection, abi: &Abi) -> Result<String, String> {
+    let chain = state.on(collection).map_err(|e| e.message)?;
+    match abi_check::missing_functions(&chain, collection, abi).await? {
+        None => Err("No bytecode to probe".to_string()),
+        Some(missing) if missing.is_empty() => Ok("deployed bytecode matches the ABI".to_string()),
+        Some(missing) => Err(format!("deployed bytecode doesn't expose {}", missing.join(", "))),
+    }
+}
+
+/// Any HTTP response means the service is up; it only accepts POSTs with property details.
+async fn price_model(state: &AppState) -> Result<String, String> {
+    let response = state
+        .http
+        .get(PRICE_MODEL_URL)
+        .timeout(PING_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Prediction service unreachable: {}", e))?;
+    Ok(format!("{} responded with {}", PRICE_MODEL_URL, response.status()))
+}
+
+/// Checks the chain, each registered contract and the prediction service.
+pub async fn run(state: &AppState) -> Vec<Check> {
+    let mut checks = vec![Check::new("chain id", chain_id(state).await)];
+    match registry::list_all(&state.db) {
+        Ok(collections) => {
+            for collection in collections {
+                let name = format!("collection {}", collection.name);
+                checks.push(Check::new(format!("{} bytecode", name), bytecode(state, &collection).await));
+                let loaded = state
+                    .artifacts
+                    .abi(&collection.contract_name, collection.contract_version.as_deref())
+                    .map_err(|e| e.message);
+                checks.push(Check::new(format!("{} ABI", name), loaded.clone().and_then(|loaded| abi(&loaded))));
+                if let Ok(loaded) = loaded {
+                    checks.push(Check::new(format!("{} selectors", name), selectors(state, &collection, &loaded).await));
+                }
+            }
+        }
+        Err(e) => checks.push(Check::new("collections", Err(e))),
+    }
+    checks.push(Check::new("prediction service", price_model(state).await));
+    checks
+}
+
+/// Prints one line per check and returns whether all of them passed.
+pub fn report(checks: &[Check]) -> bool {
+    for check in checks {
+        match &check.result {
+            Ok(detail) => println!("[ok]   {}: {}", check.name, detail),
+            Err(problem) => println!("[FAIL] {}: {}", check.name, problem),
+        }
+    }
+    checks.iter().all(|check| check.result.is_ok())
+}
@@ -0,0 +1,187 @@
+use ethers::abi::ethabi::AbiError;
+use ethers::abi::{Abi, Detokenize, ParamType, Token};
+use ethers::contract::{ContractCall, ContractError, EthError};
+use ethers::providers::{Http, Middleware, Provider, RpcError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes};
+use ethers::utils::hex;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+
+use crate::bindings::REALESTATENFT_ABI;
+
+use crate::error::ApiError;
+use crate::tenderly::TenderlyConfig;
+
+/// Solidity's `Panic(uint256)`, raised by failed asserts and arithmetic errors.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Dry-runs a contract call with `eth_call` so that calls which would revert
+/// are rejected before any gas is spent on broadcasting them. When Tenderly is
+/// configured the transaction is also simulated there and the decoded trace is
+/// attached to the error response on failure.
+pub async fn simulate<M, D>(
+    call: &ContractCall<M, D>,
+    tenderly: Option<&TenderlyConfig>,
+    chain_id: u64,
+    from: Address,
+) -> Result<(), ApiError>
+where
+    M: Middleware + 'static,
+    D: Detokenize,
+{
+    let mut error = match call.call().await {
+        Ok(_) => None,
+        Err(err) if err.is_revert() => {
+            let revert = revert(&err);
+            println!("Simulation reverted: {}", revert.reason);
+            Some(revert.into_error("Transaction would revert"))
+        }
+        Err(err) => return Err(format!("Failed to simulate transaction: {}", err).into()),
+    };
+
+    if let Some(tenderly) = tenderly {
+        let to = call.tx.to_addr().copied().unwrap_or_default();
+        let input = call.tx.data().cloned().unwrap_or_default();
+        match tenderly.simulate(chain_id, from, to, &input).await {
+            Ok(None) => {}
+            Ok(Some(failure)) => {
+                println!("Tenderly simulation failed: {}", failure.error_message);
+                let err = error.take().unwrap_or_else(|| {
+                    ApiError::unprocessable(format!(
+                        "Transaction would revert: {}",
+                        failure.error_message
+                    ))
+                });
+                error = Some(err.with_details(failure.trace));
+            }
+            // Tenderly is a debugging aid; its outages must not block mints.
+            Err(e) => eprintln!("Skipping Tenderly simulation: {}", e),
+        }
+    }
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Maps a failed read call to a 422 carrying the decoded revert, or a 500 for transport errors.
+pub fn call_error<M: Middleware>(context: &str, err: ContractError<M>) -> ApiError {
+    if err.is_revert() {
+        revert(&err).into_error(context)
+    } else {
+        format!("{}: {}", context, err).into()
+    }
+}
+
+fn revert<M: Middleware>(err: &ContractError<M>) -> Revert {
+    decode_revert(err.as_revert().unwrap_or(&Bytes::new()))
+}
+
+/// Custom errors by selector, from the bundled ABI and every loaded artifact.
+static CUSTOM_ERRORS: LazyLock<RwLock<HashMap<[u8; 4], AbiError>>> = LazyLock::new(|| {
+    let mut errors = HashMap::new();
+    insert_errors(&mut errors, &REALESTATENFT_ABI);
+    RwLock::new(errors)
+});
+
+fn insert_errors(errors: &mut HashMap<[u8; 4], AbiError>, abi: &Abi) {
+    for error in abi.errors() {
+        let mut selector = [0; 4];
+        selector.copy_from_slice(&error.signature()[..4]);
+        errors.insert(selector, error.clone());
+    }
+}
+
+/// Makes an ABI's custom errors decodable in revert reasons.
+pub fn register_errors(abi: &Abi) {
+    insert_errors(&mut CUSTOM_ERRORS.write().unwrap(), abi);
+}
+
+/// A decoded revert: `Error(string)`, `Panic(uint256)`, or a custom error from a known ABI.
+pub struct Revert {
+    pub reason: String,
+    /// The error's name and arguments, when the data could be decoded.
+    pub details: Option<Value>,
+}
+
+impl Revert {
+    pub fn into_error(self, context: &str) -> ApiError {
+        let error = ApiError::unprocessable(format!("{}: {}", context, self.reason));
+        match self.details {
+            Some(details) => error.with_details(json!({ "revert": details })),
+            None => error,
+        }
+    }
+}
+
+/// Decodes raw revert data, e.g. from a failed call or a replayed transaction.
+pub fn decode_revert(data: &Bytes) -> Revert {
+    if data.is_empty() {
+        return Revert { reason: "execution reverted without a reason".to_string(), details: None };
+    }
+    if let Some(reason) = String::decode_with_selector(data) {
+        let details = json!({ "error": "Error", "args": { "reason": reason } });
+        return Revert { reason, details: Some(details) };
+    }
+    if data.len() >= 4 && data[..4] == PANIC_SELECTOR {
+        if let Ok([Token::Uint(code)]) = ethers::abi::decode(&[ParamType::Uint(256)], &data[4..]).as_deref() {
+            let details = json!({ "error": "Panic", "args": { "code": format!("{:#x}", code) } });
+            return Revert { reason: format!("panic {:#x}", code), details: Some(details) };
+        }
+    }
+    let errors = CUSTOM_ERRORS.read().unwrap();
+    let decoded = (data.len() >= 4)
+        .then(|| errors.get(&data[..4]))
+        .flatten()
+        .and_then(|error| Some((error, error.decode(&data[4..]).ok()?)));
+    let Some((error, tokens)) = decoded else {
+        return Revert { reason: format!("unknown revert data {}", data), details: None };
+    };
+    let args: Map<String, Value> = error
+        .inputs
+        .iter()
+        .zip(&tokens)
+        .map(|(param, token)| (param.name.clone(), token_json(token)))
+        .collect();
+    let reason = format!(
+        "{}({})",
+        error.name,
+        args.iter()
+            .map(|(name, value)| match value {
+                Value::String(value) => format!("{}: {}", name, value),
+                value => format!("{}: {}", name, value),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Revert { reason, details: Some(json!({ "error": error.name, "args": args })) }
+}
+
+/// An ABI value as JSON; integers become decimal strings so they survive JavaScript clients.
+pub fn token_json(token: &Token) -> Value {
+    match token {
+        Token::Address(address) => json!(format!("{:?}", address)),
+        Token::Uint(value) | Token::Int(value) => json!(value.to_string()),
+        Token::Bool(value) => json!(value),
+        Token::String(value) => json!(value),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => json!(format!("0x{}", hex::encode(bytes))),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => {
+            Value::Array(tokens.iter().map(token_json).collect())
+        }
+    }
+}
+
+/// Re-runs a mined transaction on the state before its block to recover why it reverted.
+/// Reverts that don't replay, like running out of gas, give `None`.
+pub async fn replay(provider: &Provider<Http>, tx: &TypedTransaction, block_number: u64) -> Option<Revert> {
+    match provider.call(tx, Some(block_number.saturating_sub(1).into())).await {
+        Ok(_) => None,
+        Err(e) => Some(match e.as_error_response().and_then(|response| response.as_revert_data()) {
+            Some(data) => decode_revert(&data),
+            None => Revert { reason: e.to_string(), details: None },
+        }),
+    }
+}
@@ -0,0 +1,205 @@
+use ethers::contract::Contract;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::Middleware;
+use ethers::providers::{Http, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use reqwest::Client;
+use axum::http::StatusCode;
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::artifacts::ArtifactStore;
+use crate::backpressure::Limiter;
+use crate::bindings::{RealEstateNFT, REALESTATENFT_ABI};
+use crate::config::Config;
+use crate::coordination::{NonceAllocator, Redis};
+use crate::db::{self, Db};
+use crate::error::ApiError;
+use crate::network::Chain;
+use crate::organizations::Tenant;
+use crate::queue::JobQueue;
+use crate::registry::Collection;
+use crate::signing::ReplayGuard;
+
+pub type EthClient = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub config: Arc<Config>,
+    pub db: Db,
+    /// The chain `client` is connected to.
+    pub chain: Arc<Chain>,
+    pub client: Arc<EthClient>,
+    pub artifacts: Arc<ArtifactStore>,
+    pub http: Client,
+    pub replay: Arc<ReplayGuard>,
+    pub queue: Option<Arc<JobQueue>>,
+    pub nonces: Option<Arc<NonceAllocator>>,
+    /// MINT_CONCURRENCY's slots, shared by the mint routes and the job workers.
+    pub mint_slots: Limiter,
+    /// PREDICT_CONCURRENCY's slots.
+    pub predict_slots: Limiter,
+    /// Every chain's connection, keyed by NETWORKS name; `None` is the primary chain.
+    connections: Arc<HashMap<Option<String>, Connection>>,
+}
+
+#[derive(Clone)]
+struct Connection {
+    chain: Arc<Chain>,
+    client: Arc<EthClient>,
+    nonces: Option<Arc<NonceAllocator>>,
+}
+
+fn connect(rpc_url: &str, private_key: &str, chain_id: u64) -> Arc<EthClient> {
+    let provider = Provider::<Http>::try_from(rpc_url).expect("Failed to connect to Ethereum provider");
+    let wallet = LocalWallet::from_str(private_key)
+        .expect("Invalid private key")
+        .with_chain_id(chain_id);
+    Arc::new(SignerMiddleware::new(provider, wallet))
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Self {
+        let artifacts = Arc::new(ArtifactStore::new(config.artifacts_dir.clone(), REALESTATENFT_ABI.clone()));
+        // Replicas sharing Redis share the job queue and coordinate nonces for their wallets.
+        let redis = config.queue.as_ref().map(|queue| Arc::new(Redis::open(&queue.url)));
+        let queue = config
+            .queue
+            .as_ref()
+            .zip(redis.as_ref())
+            .map(|(queue, redis)| Arc::new(JobQueue::new(redis.clone(), queue)));
+        let connection = |chain: &Arc<Chain>, rpc_url: &str, private_key: &str| Connection {
+            chain: chain.clone(),
+            client: connect(rpc_url, private_key, chain.chain_id),
+            nonces: redis.clone().map(|redis| Arc::new(NonceAllocator::new(redis, chain.chain_id))),
+        };
+        let primary = connection(&config.chain, &config.alchemy_url, &config.private_key);
+        let mut connections: HashMap<_, _> = config
+            .networks
+            .iter()
+            .map(|network| {
+                let connection = connection(&network.chain, &network.rpc_url, &network.private_key);
+                (network.chain.network.clone(), connection)
+            })
+            .collect();
+        connections.insert(None, primary.clone());
+
+        AppState {
+            mint_slots: Limiter::new(config.backpressure.mint),
+            predict_slots: Limiter::new(config.backpressure.predict),
+            db: db::open(&config.database_path),
+            config: Arc::new(config),
+            chain: primary.chain,
+            client: primary.client,
+            artifacts,
+            http: Client::new(),
+            replay: Arc::new(ReplayGuard::default()),
+            queue,
+            nonces: primary.nonces,
+            connections: Arc::new(connections),
+        }
+    }
+
+    /// The state for each configured chain, the primary one first.
+    pub fn chains(&self) -> Vec<AppState> {
+        let mut networks: Vec<&str> = self.connections.keys().flatten().map(String::as_str).collect();
+        networks.sort();
+        std::iter::once(None)
+            .chain(networks.into_iter().map(Some))
+            .filter_map(|network| self.on_network(network).ok())
+            .collect()
+    }
+
+    /// The state for a NETWORKS chain, or for the primary chain when `network` is `None`.
+    pub fn on_network(&self, network: Option<&str>) -> Result<AppState, ApiError> {
+        let Some(connection) = self.connections.get(&network.map(str::to_string)) else {
+            let mut configured: Vec<&str> = self.connections.keys().flatten().map(String::as_str).collect();
+            configured.sort();
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown network: {}", network.unwrap_or_default()),
+            )
+            .with_details(serde_json::json!({ "networks": configured })));
+        };
+        Ok(AppState {
+            chain: connection.chain.clone(),
+            client: connection.client.clone(),
+            nonces: connection.nonces.clone(),
+            ..self.clone()
+        })
+    }
+
+    /// A copy of this state whose `network` chain is reached through `rpc_url`, e.g. a local
+    /// fork, with the same wallet. Every other chain is left out, so nothing done with the copy
+    /// can reach a real chain, and nonces aren't shared with other replicas.
+    pub fn forked(&self, network: Option<&str>, rpc_url: &str, db: Db) -> Result<AppState, ApiError> {
+        let target = self.on_network(network)?;
+        let provider = Provider::<Http>::try_from(rpc_url).map_err(|e| format!("Invalid fork URL: {}", e))?;
+        let connection = Connection {
+            chain: target.chain.clone(),
+            client: Arc::new(SignerMiddleware::new(provider, target.client.signer().clone())),
+            nonces: None,
+        };
+        let mut connections = HashMap::from([(None, connection.clone())]);
+        connections.insert(network.map(str::to_string), connection.clone());
+        Ok(AppState {
+            db,
+            chain: connection.chain,
+            client: connection.client,
+            nonces: None,
+            queue: None,
+            connections: Arc::new(connections),
+            ..self.clone()
+        })
+    }
+
+    /// The chain the collection is deployed on.
+    pub fn chain_of(&self, collection: &Collection) -> Arc<Chain> {
+        self.connections
+            .get(&collection.network)
+            .map_or_else(|| self.chain.clone(), |connection| connection.chain.clone())
+    }
+
+    /// The state for the chain the collection is deployed on.
+    pub fn on(&self, collection: &Collection) -> Result<AppState, ApiError> {
+        self.on_network(collection.network.as_deref())
+    }
+
+    pub fn contract(&self, collection: &Collection) -> Result<Contract<EthClient>, ApiError> {
+        self.contract_as(collection, self.on(collection)?.client)
+    }
+
+    fn contract_as(&self, collection: &Collection, client: Arc<EthClient>) -> Result<Contract<EthClient>, ApiError> {
+        let abi = self
+            .artifacts
+            .abi(&collection.contract_name, collection.contract_version.as_deref())?;
+        Ok(Contract::new(collection.address, (*abi).clone(), client))
+    }
+
+    /// Typed bindings over the collection's runtime ABI.
+    pub fn nft(&self, collection: &Collection) -> Result<RealEstateNFT<EthClient>, ApiError> {
+        Ok(self.contract(collection)?.into())
+    }
+
+    /// Typed bindings that sign with `client` rather than the platform wallet.
+    pub fn nft_as(&self, collection: &Collection, client: Arc<EthClient>) -> Result<RealEstateNFT<EthClient>, ApiError> {
+        Ok(self.contract_as(collection, client)?.into())
+    }
+
+    /// The signer for a tenant on this state's chain: the organization's own wallet if it has one,
+    /// else the platform's. Call it on `state.on(&collection)?` for a collection's chain.
+    pub fn client_for(&self, tenant: &Tenant) -> Result<Arc<EthClient>, ApiError> {
+        let Some(var) = tenant.0.as_ref().and_then(|org| org.wallet_env.as_deref()) else {
+            return Ok(self.client.clone());
+        };
+        let key = env::var(var).map_err(|_| {
+            ApiError::new(StatusCode::SERVICE_UNAVAILABLE, format!("Organization wallet {} is not configured", var))
+        })?;
+        let wallet = LocalWallet::from_str(&key)
+            .map_err(|_| format!("Invalid private key in {}", var))?
+            .with_chain_id(self.chain.chain_id);
+        Ok(Arc::new(SignerMiddleware::new(self.client.inner().clone(), wallet)))
+    }
+}
@@ -0,0 +1,221 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::providers::Middleware;
+use ethers::types::{Address, U256};
+use ethers::utils::format_ether;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::jobs;
+use crate::organizations::{self, Tenant};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct StatsQuery {
+    /// `1h`, `24h`, `7d` or `30d`.
+    window: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Stats {
+    window: String,
+    mints: MintStats,
+    predictions: PredictionStats,
+    gas: GasStats,
+    queue: QueueStats,
+    wallets: Vec<WalletBalance>,
+}
+
+#[derive(Serialize)]
+struct MintStats {
+    minted: u64,
+    failed: u64,
+    /// Failed jobs as a fraction of finished jobs; null when none finished.
+    failure_rate: Option<f64>,
+    per_hour: f64,
+}
+
+#[derive(Serialize)]
+struct PredictionStats {
+    count: u64,
+    failed: u64,
+    average_latency_ms: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct GasStats {
+    transactions: u64,
+    gas_used: u64,
+    spent_wei: String,
+    spent_eth: String,
+}
+
+#[derive(Serialize)]
+struct QueueStats {
+    awaiting_payment: u64,
+    queued: u64,
+    minting: u64,
+}
+
+#[derive(Serialize)]
+struct WalletBalance {
+    /// `platform` or the organization ID.
+    owner: String,
+    address: String,
+    balance_eth: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The window's length in hours, as accepted by `?window=`.
+fn window_hours(window: &str) -> Option<u32> {
+    match window {
+        "1h" => Some(1),
+        "24h" => Some(24),
+        "7d" => Some(24 * 7),
+        "30d" => Some(24 * 30),
+        _ => None,
+    }
+}
+
+pub fn record_prediction(db: &Db, latency: Duration, success: bool) {
+    let conn = db.lock().unwrap();
+    if let Err(e) = conn.execute(
+        "INSERT INTO predictions (latency_ms, success) VALUES (?1, ?2)",
+        params![latency.as_millis() as i64, success],
+    ) {
+        eprintln!("Failed to record prediction latency: {}", e);
+    }
+}
+
+fn collect(db: &Db, hours: u32) -> Result<(MintStats, PredictionStats, GasStats, QueueStats), String> {
+    let since = format!("-{} hours", hours);
+    let conn = db.lock().unwrap();
+    let error = |e: rusqlite::Error| format!("Failed to compute stats: {}", e);
+
+    let (minted, failed): (u64, u64) = conn
+        .query_row(
+            "SELECT count(*) FILTER (WHERE status = ?2), count(*) FILTER (WHERE status = ?3)
+             FROM mint_jobs WHERE updated_at >= datetime('now', ?1)",
+            params![since, jobs::MINTED, jobs::FAILED],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(error)?;
+    // Direct (synchronous) mints never create a job, so throughput counts the mints themselves.
+    let mints: u64 = conn
+        .query_row("SELECT count(*) FROM mints WHERE created_at >= datetime('now', ?1)", params![since], |row| {
+            row.get(0)
+        })
+        .map_err(error)?;
+
+    let predictions = conn
+        .query_row(
+            "SELECT count(*), count(*) FILTER (WHERE NOT success), avg(latency_ms)
+             FROM predictions WHERE created_at >= datetime('now', ?1)",
+            params![since],
+            |row| {
+                Ok(PredictionStats {
+                    count: row.get(0)?,
+                    failed: row.get(1)?,
+                    average_latency_ms: row.get(2)?,
+                })
+            },
+        )
+        .map_err(error)?;
+
+    let mut stmt = conn
+        .prepare("SELECT gas_used, gas_cost_wei FROM transactions WHERE created_at >= datetime('now', ?1)")
+        .map_err(error)?;
+    let rows = stmt
+        .query_map(params![since], |row| Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(error)?;
+    let (mut transactions, mut gas_used, mut spent) = (0, 0, U256::zero());
+    for row in rows {
+        let (gas, cost) = row.map_err(error)?;
+        transactions += 1;
+        gas_used += gas;
+        spent += U256::from_dec_str(&cost).unwrap_or_default();
+    }
+
+    let queue = conn
+        .query_row(
+            "SELECT count(*) FILTER (WHERE status = ?1), count(*) FILTER (WHERE status = ?2),
+                    count(*) FILTER (WHERE status = ?3)
+             FROM mint_jobs",
+            params![jobs::AWAITING_PAYMENT, jobs::QUEUED, jobs::MINTING],
+            |row| {
+                Ok(QueueStats {
+                    awaiting_payment: row.get(0)?,
+                    queued: row.get(1)?,
+                    minting: row.get(2)?,
+                })
+            },
+        )
+        .map_err(error)?;
+
+    let mint_stats = MintStats {
+        minted: mints,
+        failed,
+        failure_rate: (minted + failed > 0).then(|| failed as f64 / (minted + failed) as f64),
+        per_hour: mints as f64 / hours as f64,
+    };
+    let gas = GasStats {
+        transactions,
+        gas_used,
+        spent_wei: spent.to_string(),
+        spent_eth: format_ether(spent),
+    };
+    Ok((mint_stats, predictions, gas, queue))
+}
+
+async fn balance(state: &AppState, owner: String, address: Address) -> WalletBalance {
+    let (balance_eth, error) = match state.client.get_balance(address, None).await {
+        Ok(balance) => (Some(format_ether(balance)), None),
+        Err(e) => (None, Some(format!("Failed to read balance: {}", e))),
+    };
+    WalletBalance {
+        owner,
+        address: format!("{:?}", address),
+        balance_eth,
+        error,
+    }
+}
+
+/// Operational metrics over a time window for the ops dashboard.
+pub async fn dashboard(State(state): State<AppState>, Query(query): Query<StatsQuery>) -> Result<Json<Stats>, ApiError> {
+    let window = query.window.unwrap_or_else(|| "24h".to_string());
+    let hours = window_hours(&window).ok_or_else(|| {
+        ApiError::new(StatusCode::BAD_REQUEST, format!("window must be 1h, 24h, 7d or 30d. Found: {}", window))
+    })?;
+    let (mints, predictions, gas, queue) = collect(&state.db, hours)?;
+
+    let mut wallets = vec![balance(&state, "platform".to_string(), state.client.address()).await];
+    for org in organizations::list(&state.db)? {
+        if org.wallet_env.is_none() {
+            continue;
+        }
+        let id = org.id.clone();
+        match state.client_for(&Tenant(Some(org))) {
+            Ok(client) => wallets.push(balance(&state, id, client.address()).await),
+            Err(err) => wallets.push(WalletBalance {
+                owner: id,
+                address: String::new(),
+                balance_eth: None,
+                error: Some(err.message),
+            }),
+        }
+    }
+
+    Ok(Json(Stats {
+        window,
+        mints,
+        predictions,
+        gas,
+        queue,
+        wallets,
+    }))
+}
@@ -0,0 +1,610 @@
+use axum::async_trait;
+use axum::body::Bytes;
+use axum::extract::{Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::Json;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ethers::signers::{LocalWallet, Signer};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha384};
+use std::path::PathBuf;
+
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::features;
+use crate::organizations::Tenant;
+use crate::s3::S3;
+use crate::state::AppState;
+
+const DATA_URI_PREFIX: &str = "data:application/json;base64,";
+const PINATA_PIN_JSON_URL: &str = "https://api.pinata.cloud/pinning/pinJSONToIPFS";
+const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+const ARWEAVE_GATEWAY: &str = "https://arweave.net/";
+/// ANS-104 signature type for secp256k1 keys signing with `personal_sign`.
+const ETHEREUM_SIGNATURE_TYPE: u16 = 3;
+
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+/// Accepted property photo types and the extension files are saved with.
+const IMAGE_TYPES: &[(&str, &str)] = &[("image/jpeg", "jpg"), ("image/png", "png"), ("image/webp", "webp")];
+
+// Rough costs of writing a string to contract storage: calldata per (non-zero) byte, plus a
+// fresh storage slot per 32 bytes and one for the length.
+const CALLDATA_GAS_PER_BYTE: u64 = 16;
+const SSTORE_GAS_PER_SLOT: u64 = 22_100;
+
+/// Somewhere token metadata is kept; the token URI written on-chain points at it.
+#[async_trait]
+pub trait MetadataStore: Send + Sync {
+    /// Recorded with each mint, so later metadata updates go to the same store.
+    fn name(&self) -> &'static str;
+
+    /// Stores `metadata` and returns the token URI for it.
+    async fn put(&self, http: &Client, metadata: &Value) -> Result<String, String>;
+
+    /// Whether `put_file` is supported; inline stores only hold metadata.
+    fn stores_files(&self) -> bool {
+        false
+    }
+
+    /// Stores a file such as a property photo and returns a URI for it.
+    async fn put_file(&self, _http: &Client, _bytes: Bytes, _content_type: &str) -> Result<String, String> {
+        Err(format!("{} storage doesn't hold files", self.name()))
+    }
+
+    /// A signed URL clients upload a file to directly, for stores that support it.
+    fn presign_file(&self, _content_type: &str) -> Option<PresignedUpload> {
+        None
+    }
+
+    /// Whether `token_uri` is one this store hands out.
+    fn owns(&self, token_uri: &str) -> bool;
+
+    async fn get(&self, http: &Client, token_uri: &str) -> Result<Value, String>;
+}
+
+/// The raw JSON document as the token URI.
+struct Inline;
+
+#[async_trait]
+impl MetadataStore for Inline {
+    fn name(&self) -> &'static str {
+        "inline"
+    }
+
+    async fn put(&self, _http: &Client, metadata: &Value) -> Result<String, String> {
+        Ok(metadata.to_string())
+    }
+
+    fn owns(&self, token_uri: &str) -> bool {
+        token_uri.starts_with('{')
+    }
+
+    async fn get(&self, _http: &Client, token_uri: &str) -> Result<Value, String> {
+        serde_json::from_str(token_uri).map_err(|e| format!("Invalid inline metadata: {}", e))
+    }
+}
+
+/// A `data:application/json;base64,` URI, which wallets and marketplaces resolve without any
+/// off-chain service.
+struct DataUri;
+
+#[async_trait]
+impl MetadataStore for DataUri {
+    fn name(&self) -> &'static str {
+        "onchain_datauri"
+    }
+
+    async fn put(&self, _http: &Client, metadata: &Value) -> Result<String, String> {
+        Ok(format!("{}{}", DATA_URI_PREFIX, STANDARD.encode(metadata.to_string())))
+    }
+
+    fn owns(&self, token_uri: &str) -> bool {
+        token_uri.starts_with(DATA_URI_PREFIX)
+    }
+
+    async fn get(&self, _http: &Client, token_uri: &str) -> Result<Value, String> {
+        let encoded = &token_uri[DATA_URI_PREFIX.len()..];
+        let json = STANDARD.decode(encoded).map_err(|e| format!("Invalid data URI: {}", e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid data URI metadata: {}", e))
+    }
+}
+
+/// Files in a local directory, served by this backend under /metadata.
+struct Filesystem {
+    dir: PathBuf,
+    base_url: String,
+}
+
+#[async_trait]
+impl MetadataStore for Filesystem {
+    fn name(&self) -> &'static str {
+        "filesystem"
+    }
+
+    async fn put(&self, http: &Client, metadata: &Value) -> Result<String, String> {
+        self.put_file(http, Bytes::from(metadata.to_string()), "application/json").await
+    }
+
+    fn stores_files(&self) -> bool {
+        true
+    }
+
+    async fn put_file(&self, _http: &Client, bytes: Bytes, content_type: &str) -> Result<String, String> {
+        // The token id isn't known until the mint lands, so files are named by their content.
+        let file = format!("{}.{}", ethers::utils::hex::encode(Sha256::digest(&bytes)), extension(content_type));
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        tokio::fs::write(self.dir.join(&file), bytes)
+            .await
+            .map_err(|e| format!("Failed to write file {}: {}", file, e))?;
+        Ok(format!("{}/{}", self.base_url, file))
+    }
+
+    fn owns(&self, token_uri: &str) -> bool {
+        token_uri.starts_with(&format!("{}/", self.base_url))
+    }
+
+    async fn get(&self, _http: &Client, token_uri: &str) -> Result<Value, String> {
+        let file = &token_uri[self.base_url.len() + 1..];
+        if file.contains('/') {
+            return Err(format!("Invalid metadata file {}", file));
+        }
+        let json = tokio::fs::read(self.dir.join(file))
+            .await
+            .map_err(|e| format!("Failed to read metadata file {}: {}", file, e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid metadata file {}: {}", file, e))
+    }
+}
+
+/// Pinned to IPFS through a Pinata-compatible pinning service, as `ipfs://` URIs.
+struct Ipfs {
+    pin_url: String,
+    jwt: String,
+    gateway: String,
+}
+
+#[async_trait]
+impl MetadataStore for Ipfs {
+    fn name(&self) -> &'static str {
+        "ipfs"
+    }
+
+    async fn put(&self, http: &Client, metadata: &Value) -> Result<String, String> {
+        let response: Value = http
+            .post(&self.pin_url)
+            .bearer_auth(&self.jwt)
+            .json(&json!({ "pinataContent": metadata }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to pin metadata: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Pinning metadata failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid pinning response: {}", e))?;
+        let cid = response["IpfsHash"].as_str().ok_or("Pinning response has no IpfsHash")?;
+        Ok(format!("ipfs://{}", cid))
+    }
+
+    fn owns(&self, token_uri: &str) -> bool {
+        token_uri.starts_with("ipfs://")
+    }
+
+    async fn get(&self, http: &Client, token_uri: &str) -> Result<Value, String> {
+        let url = format!("{}{}", self.gateway, &token_uri["ipfs://".len()..]);
+        http.get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+            .error_for_status()
+            .map_err(|e| format!("Fetching {} failed: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid metadata at {}: {}", url, e))
+    }
+}
+
+/// Stored permanently on Arweave for a one-time fee, uploaded through an Irys (formerly Bundlr)
+/// node and paid from the wallet's Irys balance.
+struct Arweave {
+    node_url: String,
+    currency: String,
+    wallet: LocalWallet,
+    gateway: String,
+}
+
+impl Arweave {
+    async fn upload(&self, http: &Client, data: &[u8], content_type: &str) -> Result<String, String> {
+        let item = data_item(&self.wallet, data, &[("Content-Type", content_type)]).await?;
+        let url = format!("{}/tx/{}", self.node_url, self.currency);
+        let response = http
+            .post(&url)
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .body(item)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to upload to {}: {}", url, e))?;
+        if response.status() == StatusCode::PAYMENT_REQUIRED {
+            return Err(format!(
+                "Irys balance of {:?} can't pay for {} bytes; fund it on {}",
+                self.wallet.address(),
+                data.len(),
+                self.node_url
+            ));
+        }
+        let receipt: Value = response
+            .error_for_status()
+            .map_err(|e| format!("Upload to {} failed: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid Irys response: {}", e))?;
+        let id = receipt["id"].as_str().ok_or("Irys response has no id")?;
+        Ok(format!("ar://{}", id))
+    }
+}
+
+#[async_trait]
+impl MetadataStore for Arweave {
+    fn name(&self) -> &'static str {
+        "arweave"
+    }
+
+    async fn put(&self, http: &Client, metadata: &Value) -> Result<String, String> {
+        self.upload(http, metadata.to_string().as_bytes(), "application/json").await
+    }
+
+    fn stores_files(&self) -> bool {
+        true
+    }
+
+    async fn put_file(&self, http: &Client, bytes: Bytes, content_type: &str) -> Result<String, String> {
+        self.upload(http, &bytes, content_type).await
+    }
+
+    fn owns(&self, token_uri: &str) -> bool {
+        token_uri.starts_with("ar://")
+    }
+
+    async fn get(&self, http: &Client, token_uri: &str) -> Result<Value, String> {
+        let url = format!("{}{}", self.gateway, &token_uri["ar://".len()..]);
+        http.get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+            .error_for_status()
+            .map_err(|e| format!("Fetching {} failed: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Invalid metadata at {}: {}", url, e))
+    }
+}
+
+enum DeepHashChunk<'a> {
+    Blob(&'a [u8]),
+    List(Vec<DeepHashChunk<'a>>),
+}
+
+/// Arweave's deep hash, which is what data items are signed over.
+fn deep_hash(chunk: &DeepHashChunk) -> [u8; 48] {
+    match chunk {
+        DeepHashChunk::Blob(data) => {
+            let tag = Sha384::digest(format!("blob{}", data.len()));
+            Sha384::new().chain_update(tag).chain_update(Sha384::digest(data)).finalize().into()
+        }
+        DeepHashChunk::List(chunks) => {
+            let tag: [u8; 48] = Sha384::digest(format!("list{}", chunks.len())).into();
+            chunks.iter().fold(tag, |acc, chunk| {
+                Sha384::new().chain_update(acc).chain_update(deep_hash(chunk)).finalize().into()
+            })
+        }
+    }
+}
+
+/// Appends an Avro long (zig-zag, then variable-length).
+fn avro_long(out: &mut Vec<u8>, value: i64) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Tags as the Avro array of `{ name: bytes, value: bytes }` records data items carry.
+fn avro_tags(tags: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if !tags.is_empty() {
+        avro_long(&mut out, tags.len() as i64);
+        for (name, value) in tags {
+            for field in [name, value] {
+                avro_long(&mut out, field.len() as i64);
+                out.extend_from_slice(field.as_bytes());
+            }
+        }
+    }
+    out.push(0);
+    out
+}
+
+/// A signed ANS-104 data item, the unit Irys bundles onto Arweave.
+async fn data_item(wallet: &LocalWallet, data: &[u8], tags: &[(&str, &str)]) -> Result<Vec<u8>, String> {
+    let owner = wallet.signer().verifying_key().to_encoded_point(false);
+    let owner = owner.as_bytes();
+    let tag_count = tags.len() as u64;
+    let tags = avro_tags(tags);
+    let signature_type = ETHEREUM_SIGNATURE_TYPE.to_string();
+    let message = deep_hash(&DeepHashChunk::List(vec![
+        DeepHashChunk::Blob(b"dataitem"),
+        DeepHashChunk::Blob(b"1"),
+        DeepHashChunk::Blob(signature_type.as_bytes()),
+        DeepHashChunk::Blob(owner),
+        // No target or anchor.
+        DeepHashChunk::Blob(&[]),
+        DeepHashChunk::Blob(&[]),
+        DeepHashChunk::Blob(&tags),
+        DeepHashChunk::Blob(data),
+    ]));
+    let signature = wallet
+        .sign_message(message)
+        .await
+        .map_err(|e| format!("Failed to sign data item: {}", e))?
+        .to_vec();
+
+    let mut item = ETHEREUM_SIGNATURE_TYPE.to_le_bytes().to_vec();
+    item.extend_from_slice(&signature);
+    item.extend_from_slice(owner);
+    item.extend_from_slice(&[0, 0]);
+    item.extend_from_slice(&tag_count.to_le_bytes());
+    item.extend_from_slice(&(tags.len() as u64).to_le_bytes());
+    item.extend_from_slice(&tags);
+    item.extend_from_slice(data);
+    Ok(item)
+}
+
+pub fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "application/json" => return "json",
+        crate::cards::SVG_CONTENT_TYPE => return "svg",
+        _ => {}
+    }
+    IMAGE_TYPES
+        .iter()
+        .find(|(image_type, _)| *image_type == content_type)
+        .map(|(_, extension)| *extension)
+        .unwrap_or("bin")
+}
+
+pub struct StorageConfig {
+    stores: Vec<Box<dyn MetadataStore>>,
+    /// The store for mints that don't choose.
+    pub default: &'static str,
+    /// Served under /metadata when the filesystem store is configured.
+    pub metadata_dir: Option<PathBuf>,
+    /// Estimated gas above which storing metadata is reported as expensive.
+    pub gas_warning: u64,
+}
+
+impl StorageConfig {
+    /// Inline and data URI storage are always available; the others once they're configured.
+    pub fn from_env() -> Self {
+        let mut stores: Vec<Box<dyn MetadataStore>> = vec![Box::new(Inline), Box::new(DataUri)];
+        let metadata_dir = optional_env("METADATA_DIR").map(PathBuf::from);
+        if let Some(dir) = &metadata_dir {
+            let base_url = optional_env("METADATA_BASE_URL")
+                .or_else(|| optional_env("PUBLIC_URL").map(|url| format!("{}/metadata", url.trim_end_matches('/'))))
+                .expect("METADATA_DIR needs METADATA_BASE_URL or PUBLIC_URL");
+            stores.push(Box::new(Filesystem {
+                dir: dir.clone(),
+                base_url: base_url.trim_end_matches('/').to_string(),
+            }));
+        }
+        if let Some(jwt) = optional_env("IPFS_PINNING_JWT") {
+            stores.push(Box::new(Ipfs {
+                pin_url: optional_env("IPFS_PINNING_URL").unwrap_or_else(|| PINATA_PIN_JSON_URL.to_string()),
+                jwt,
+                gateway: optional_env("IPFS_GATEWAY").unwrap_or_else(|| IPFS_GATEWAY.to_string()),
+            }));
+        }
+        if let Some(s3) = S3::from_env() {
+            stores.push(Box::new(s3));
+        }
+        if let Some(node_url) = optional_env("IRYS_NODE_URL") {
+            // Uploads are paid from the backend wallet's Irys balance unless another key is given.
+            let key = optional_env("IRYS_PRIVATE_KEY")
+                .or_else(|| optional_env("PRIVATE_KEY"))
+                .expect("IRYS_NODE_URL needs IRYS_PRIVATE_KEY or PRIVATE_KEY");
+            stores.push(Box::new(Arweave {
+                node_url: node_url.trim_end_matches('/').to_string(),
+                currency: optional_env("IRYS_CURRENCY").unwrap_or_else(|| "ethereum".to_string()),
+                wallet: key.parse().expect("IRYS_PRIVATE_KEY must be a private key"),
+                gateway: optional_env("ARWEAVE_GATEWAY").unwrap_or_else(|| ARWEAVE_GATEWAY.to_string()),
+            }));
+        }
+
+        let names: Vec<_> = stores.iter().map(|store| store.name()).collect();
+        let default = match optional_env("METADATA_STORAGE") {
+            Some(name) => *names.iter().find(|configured| **configured == name).unwrap_or_else(|| {
+                panic!("METADATA_STORAGE must be one of {} (as configured). Found: {}", names.join(", "), name)
+            }),
+            None => "inline",
+        };
+        let gas_warning = optional_env("METADATA_GAS_WARNING")
+            .map(|gas| gas.parse().expect("METADATA_GAS_WARNING must be a number"))
+            .unwrap_or(1_000_000);
+        println!("METADATA_STORAGE: {} (available: {})", default, names.join(", "));
+        StorageConfig {
+            stores,
+            default,
+            metadata_dir,
+            gas_warning,
+        }
+    }
+
+    /// The store a tenant's mint uses. Without the ipfs_storage feature, mints that didn't choose a
+    /// store get inline storage instead of IPFS, and mints that asked for IPFS are refused.
+    pub fn store_for(
+        &self,
+        db: &Db,
+        tenant: &Tenant,
+        name: Option<&str>,
+    ) -> Result<&dyn MetadataStore, ApiError> {
+        let store = self.store(name)?;
+        if store.name() != "ipfs" || features::enabled(db, features::IPFS_STORAGE, tenant.organization_id()) {
+            return Ok(store);
+        }
+        match name {
+            Some(_) => Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                format!("{} is not enabled for this organization", features::IPFS_STORAGE),
+            )
+            .with_details(json!({ "feature": features::IPFS_STORAGE }))),
+            None => self.store(Some("inline")),
+        }
+    }
+
+    /// The named store, or the default.
+    pub fn store(&self, name: Option<&str>) -> Result<&dyn MetadataStore, ApiError> {
+        let name = name.unwrap_or(self.default);
+        self.stores
+            .iter()
+            .find(|store| store.name() == name)
+            .map(|store| store.as_ref())
+            .ok_or_else(|| {
+                ApiError::new(StatusCode::BAD_REQUEST, format!("Metadata storage {} is not configured", name))
+                    .with_details(json!({ "available": self.stores.iter().map(|store| store.name()).collect::<Vec<_>>() }))
+            })
+    }
+}
+
+#[derive(Serialize)]
+pub struct PresignedUpload {
+    pub upload_url: String,
+    pub method: &'static str,
+    /// Where the file can be read once uploaded; pass it as a mint's `image`.
+    pub uri: String,
+    pub expires_in: u64,
+}
+
+#[derive(Serialize)]
+pub struct StorageEstimate {
+    pub storage: &'static str,
+    pub bytes: usize,
+    pub estimated_gas: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Whether the store's token URIs hold the metadata itself rather than pointing at it.
+pub fn is_inline(store: &dyn MetadataStore) -> bool {
+    matches!(store.name(), "inline" | "onchain_datauri")
+}
+
+/// Stores `metadata` in `store` and returns its token URI.
+pub async fn put(state: &AppState, store: &dyn MetadataStore, metadata: &Value) -> Result<String, ApiError> {
+    store.put(&state.http, metadata).await.map_err(|e| {
+        eprintln!("{}", e);
+        ApiError::new(StatusCode::BAD_GATEWAY, format!("Failed to store metadata in {}", store.name()))
+    })
+}
+
+/// The metadata behind a token URI, or `None` when no configured store recognises it.
+pub async fn get(state: &AppState, token_uri: &str) -> Option<Result<Value, String>> {
+    let store = state.config.storage.stores.iter().find(|store| store.owns(token_uri))?;
+    Some(store.get(&state.http, token_uri).await)
+}
+
+/// Approximates the gas spent storing `token_uri`, warning when it passes `config.gas_warning`.
+pub fn estimate(config: &StorageConfig, store: &dyn MetadataStore, token_uri: &str) -> StorageEstimate {
+    let bytes = token_uri.len();
+    let slots = bytes.div_ceil(32) as u64 + 1;
+    let estimated_gas = bytes as u64 * CALLDATA_GAS_PER_BYTE + slots * SSTORE_GAS_PER_SLOT;
+    let warning = (estimated_gas > config.gas_warning).then(|| {
+        format!(
+            "Storing {} bytes of metadata on-chain costs about {} gas; consider trimming attributes",
+            bytes, estimated_gas
+        )
+    });
+    StorageEstimate {
+        storage: store.name(),
+        bytes,
+        estimated_gas,
+        warning,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    /// Defaults to METADATA_STORAGE.
+    storage: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct Upload {
+    storage: &'static str,
+    uri: String,
+    bytes: usize,
+}
+
+/// Stores a property photo; the returned URI is then passed as a mint's `image`.
+pub async fn upload_image(
+    State(state): State<AppState>,
+    Query(query): Query<UploadQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Upload>, ApiError> {
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()).unwrap_or_default();
+    if !IMAGE_TYPES.iter().any(|(image_type, _)| *image_type == content_type) {
+        return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Images must be JPEG, PNG or WebP")
+            .with_details(json!({ "content_type": content_type })));
+    }
+    let store = state.config.storage.store(query.storage.as_deref())?;
+    if !store.stores_files() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("{} storage doesn't hold images; choose another with ?storage=", store.name()),
+        ));
+    }
+    let bytes = body.len();
+    let uri = store.put_file(&state.http, body, content_type).await.map_err(|e| {
+        eprintln!("{}", e);
+        ApiError::new(StatusCode::BAD_GATEWAY, format!("Failed to store image in {}", store.name()))
+    })?;
+    println!("Stored {} byte image in {} at {}", bytes, store.name(), uri);
+    Ok(Json(Upload {
+        storage: store.name(),
+        uri,
+        bytes,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct UploadUrlRequest {
+    content_type: String,
+    /// Defaults to METADATA_STORAGE.
+    storage: Option<String>,
+}
+
+/// Hands out a signed URL so large photos go straight to object storage instead of through
+/// this server.
+pub async fn upload_url(
+    State(state): State<AppState>,
+    Json(request): Json<UploadUrlRequest>,
+) -> Result<Json<PresignedUpload>, ApiError> {
+    if !IMAGE_TYPES.iter().any(|(image_type, _)| *image_type == request.content_type) {
+        return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Images must be JPEG, PNG or WebP")
+            .with_details(json!({ "content_type": request.content_type })));
+    }
+    let store = state.config.storage.store(request.storage.as_deref())?;
+    let upload = store.presign_file(&request.content_type).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("{} storage doesn't support direct uploads; use POST /images", store.name()),
+        )
+    })?;
+    Ok(Json(upload))
+}
@@ -0,0 +1,138 @@
+use ethers::types::{Address, U256};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::config::optional_env;
+use crate::ownership::{OwnedToken, Transfer};
+use crate::state::AppState;
+
+/// The Graph returns at most this many entities per query.
+const PAGE_SIZE: usize = 1000;
+
+/// A subgraph indexing the platform's ERC-721 collections. It must expose `transfers` and
+/// `tokens` entities shaped like the queries below (the layout of the common ERC-721
+/// subgraph templates); contract addresses and owners are lowercase hex strings.
+pub struct SubgraphConfig {
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransferEntity {
+    from: String,
+    to: String,
+    transaction_hash: String,
+    block_number: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenEntity {
+    contract: String,
+    token_id: String,
+}
+
+impl SubgraphConfig {
+    /// Enabled by SUBGRAPH_URL.
+    pub fn from_env() -> Option<Self> {
+        let url = optional_env("SUBGRAPH_URL")?;
+        println!("SUBGRAPH_URL: {}", url);
+        Some(SubgraphConfig { url })
+    }
+
+    async fn query(&self, state: &AppState, query: &str, variables: Value) -> Result<Value, String> {
+        let response: Value = state
+            .http
+            .post(&self.url)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to query the subgraph: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Subgraph query failed: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse subgraph response: {}", e))?;
+        if let Some(errors) = response.get("errors") {
+            return Err(format!("Subgraph query failed: {}", errors));
+        }
+        Ok(response["data"].clone())
+    }
+
+    /// Every transfer of the token, oldest first.
+    pub async fn transfers(&self, state: &AppState, contract: Address, token_id: u64) -> Result<Vec<Transfer>, String> {
+        const QUERY: &str = "query($contract: String!, $tokenId: BigInt!, $first: Int!, $skip: Int!) {
+            transfers(where: { contract: $contract, tokenId: $tokenId }, orderBy: blockNumber,
+                      orderDirection: asc, first: $first, skip: $skip) {
+                from to transactionHash blockNumber
+            }
+        }";
+        let mut transfers = Vec::new();
+        loop {
+            let variables = json!({
+                "contract": format!("{:?}", contract),
+                "tokenId": token_id.to_string(),
+                "first": PAGE_SIZE,
+                "skip": transfers.len(),
+            });
+            let data = self.query(state, QUERY, variables).await?;
+            let page: Vec<TransferEntity> = serde_json::from_value(data["transfers"].clone())
+                .map_err(|e| format!("Unexpected subgraph transfers: {}", e))?;
+            let done = page.len() < PAGE_SIZE;
+            transfers.extend(page.into_iter().map(|transfer| Transfer {
+                from: transfer.from,
+                to: transfer.to,
+                transaction_hash: transfer.transaction_hash,
+                block_number: transfer.block_number.parse().unwrap_or_default(),
+            }));
+            if done {
+                return Ok(transfers);
+            }
+        }
+    }
+
+    /// Tokens currently held by `owner` in any of `contracts`.
+    pub async fn tokens_of_owner(
+        &self,
+        state: &AppState,
+        owner: Address,
+        contracts: &[Address],
+    ) -> Result<Vec<(Address, U256)>, String> {
+        const QUERY: &str = "query($owner: String!, $contracts: [String!]!, $first: Int!, $skip: Int!) {
+            tokens(where: { owner: $owner, contract_in: $contracts }, first: $first, skip: $skip) {
+                contract tokenId
+            }
+        }";
+        let contracts: Vec<String> = contracts.iter().map(|contract| format!("{:?}", contract)).collect();
+        let mut tokens = Vec::new();
+        loop {
+            let variables = json!({
+                "owner": format!("{:?}", owner),
+                "contracts": contracts,
+                "first": PAGE_SIZE,
+                "skip": tokens.len(),
+            });
+            let data = self.query(state, QUERY, variables).await?;
+            let page: Vec<TokenEntity> = serde_json::from_value(data["tokens"].clone())
+                .map_err(|e| format!("Unexpected subgraph tokens: {}", e))?;
+            let done = page.len() < PAGE_SIZE;
+            for token in page {
+                let contract = token.contract.parse().map_err(|_| "Subgraph returned an invalid contract")?;
+                let token_id = U256::from_dec_str(&token.token_id).map_err(|_| "Subgraph returned an invalid token ID")?;
+                tokens.push((contract, token_id));
+            }
+            if done {
+                return Ok(tokens);
+            }
+        }
+    }
+}
+
+impl From<(String, U256)> for OwnedToken {
+    fn from((collection, token_id): (String, U256)) -> Self {
+        OwnedToken {
+            collection,
+            token_id: token_id.to_string(),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{Address, U256};
+use serde::Serialize;
+
+use crate::bindings::SupplyCounters;
+use crate::capabilities::{self, Interface};
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::ownership;
+use crate::registry;
+use crate::simulation;
+use crate::state::AppState;
+use crate::CollectionQuery;
+
+#[derive(Serialize)]
+pub struct TokenExistence {
+    token_id: u64,
+    exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<Address>,
+}
+
+#[derive(Serialize)]
+pub struct Supply {
+    collection: String,
+    total_supply: u64,
+    /// The ID the next mint gets, on contracts that count them (like `tokenCounter`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_token_id: Option<u64>,
+    burned: u64,
+    /// `totalSupply` on ERC-721 Enumerable contracts, otherwise `tokenCounter` less burns.
+    source: &'static str,
+}
+
+/// Whether the token has been minted and not burned, answered by `ownerOf` alone.
+pub async fn token_exists(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<TokenExistence>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let owner = match state.nft(&collection)?.owner_of(U256::from(token_id)).call().await {
+        Ok(owner) => Some(owner),
+        // ERC-721 requires ownerOf to revert for tokens that don't exist.
+        Err(e) if e.is_revert() => None,
+        Err(e) => return Err(simulation::call_error("Failed to read token owner", e)),
+    };
+    Ok(Json(TokenExistence { token_id, exists: owner.is_some(), owner }))
+}
+
+pub async fn supply(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Query(query): Query<CollectionQuery>,
+) -> Result<Json<Supply>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let counters = SupplyCounters::new(collection.address, state.on(&collection)?.client);
+    let next_token_id = match counters.token_counter().call().await {
+        Ok(counter) => Some(counter.min(U256::from(u64::MAX)).as_u64()),
+        Err(e) if e.is_revert() => None,
+        Err(e) => return Err(simulation::call_error("Failed to read token counter", e)),
+    };
+    let burns = state
+        .nft(&collection)?
+        .transfer_filter()
+        .topic2(Address::zero())
+        .from_block(ownership::first_block(&state, &collection)?)
+        .query()
+        .await
+        .map_err(|e| simulation::call_error("Failed to read burn events", e))?;
+    let burned = burns.len() as u64;
+
+    let (total_supply, source) = if capabilities::supports(&state, &collection, Interface::Enumerable).await? {
+        let total_supply = counters
+            .total_supply()
+            .call()
+            .await
+            .map_err(|e| simulation::call_error("Failed to read total supply", e))?;
+        (total_supply.min(U256::from(u64::MAX)).as_u64(), "totalSupply")
+    } else if let Some(next_token_id) = next_token_id {
+        (next_token_id.saturating_sub(burned), "tokenCounter")
+    } else {
+        return Err(ApiError::new(
+            StatusCode::NOT_IMPLEMENTED,
+            format!("Collection {} has neither totalSupply nor tokenCounter", collection.name),
+        ));
+    };
+    Ok(Json(Supply {
+        collection: collection.name,
+        total_supply,
+        next_token_id,
+        burned,
+        source,
+    }))
+}
@@ -0,0 +1,102 @@
+use ethers::types::{Address, Bytes};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::config::optional_env;
+
+pub struct TenderlyConfig {
+    account: String,
+    project: String,
+    access_key: String,
+}
+
+pub struct TenderlyFailure {
+    pub error_message: String,
+    pub trace: Value,
+}
+
+impl TenderlyConfig {
+    /// Tenderly is optional; it is only enabled when all three settings are present.
+    pub fn from_env() -> Option<Self> {
+        Some(TenderlyConfig {
+            account: optional_env("TENDERLY_ACCOUNT")?,
+            project: optional_env("TENDERLY_PROJECT")?,
+            access_key: optional_env("TENDERLY_ACCESS_KEY")?,
+        })
+    }
+
+    /// Simulates the transaction against the latest state. Returns `Ok(None)` when the
+    /// simulation succeeds and `Ok(Some(failure))` with the decoded call trace when it reverts.
+    pub async fn simulate(
+        &self,
+        chain_id: u64,
+        from: Address,
+        to: Address,
+        input: &Bytes,
+    ) -> Result<Option<TenderlyFailure>, String> {
+        let url = format!(
+            "https://api.tenderly.co/api/v1/account/{}/project/{}/simulate",
+            self.account, self.project
+        );
+        let body = json!({
+            "network_id": chain_id.to_string(),
+            "from": format!("{:?}", from),
+            "to": format!("{:?}", to),
+            "input": input.to_string(),
+            "value": "0",
+            "save": false,
+            "save_if_fails": true,
+            "simulation_type": "full",
+        });
+
+        let response: Value = Client::new()
+            .post(url)
+            .header("X-Access-Key", &self.access_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to call Tenderly API: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Tenderly API returned an error: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Tenderly API response: {}", e))?;
+
+        let transaction = &response["transaction"];
+        if transaction["status"].as_bool().unwrap_or(false) {
+            return Ok(None);
+        }
+
+        let mut trace = json!({
+            "call_trace": summarize_call(&transaction["transaction_info"]["call_trace"]),
+        });
+        if let Some(id) = response["simulation"]["id"].as_str() {
+            trace["simulation_url"] = json!(format!(
+                "https://dashboard.tenderly.co/{}/{}/simulator/{}",
+                self.account, self.project, id
+            ));
+        }
+
+        Ok(Some(TenderlyFailure {
+            error_message: transaction["error_message"]
+                .as_str()
+                .unwrap_or("simulation failed")
+                .to_string(),
+            trace,
+        }))
+    }
+}
+
+// Tenderly's raw call trace includes full state diffs and bytecode; keep only the decoded parts.
+fn summarize_call(call: &Value) -> Value {
+    let mut summary = json!({});
+    for key in ["from", "to", "contract_name", "function_name", "error", "error_reason", "decoded_input", "decoded_output"] {
+        if let Some(value) = call.get(key).filter(|v| !v.is_null()) {
+            summary[key] = value.clone();
+        }
+    }
+    if let Some(calls) = call["calls"].as_array() {
+        summary["calls"] = calls.iter().map(summarize_call).collect();
+    }
+    summary
+}
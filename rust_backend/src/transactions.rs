@@ -0,0 +1,225 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::providers::Middleware;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Transaction, TransactionRequest, H256};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::explorer::ExplorerLinks;
+use crate::simulation;
+use crate::state::AppState;
+use crate::tx;
+
+const POLL: Duration = Duration::from_secs(15);
+/// Transactions the node hasn't seen for this long were dropped from the mempool.
+const DROPPED_AFTER_MINUTES: u32 = 30;
+
+#[derive(Serialize)]
+pub struct TransactionStatus {
+    transaction_hash: String,
+    /// `pending`, `mined` (fewer than CONFIRMATIONS blocks deep), `confirmed` or `failed`.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<u64>,
+    confirmations: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gas_used: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revert_reason: Option<String>,
+    /// The decoded error's name and arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revert: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<ExplorerLinks>,
+}
+
+/// Remembers a broadcast transaction until its receipt is recorded, so it can be reported as
+/// pending and picked up by the monitor if the request waiting for it goes away.
+pub fn track(db: &Db, hash: H256, sender: Address) {
+    let conn = db.lock().unwrap();
+    if let Err(e) = conn.execute(
+        "INSERT OR IGNORE INTO pending_transactions (transaction_hash, sender) VALUES (?1, ?2)",
+        params![format!("{:?}", hash), format!("{:?}", sender)],
+    ) {
+        eprintln!("Failed to track transaction {:?}: {}", hash, e);
+    }
+}
+
+fn is_tracked(db: &Db, hash: H256) -> Result<bool, String> {
+    let conn = db.lock().unwrap();
+    conn.query_row(
+        "SELECT 1 FROM pending_transactions WHERE transaction_hash = ?1",
+        params![format!("{:?}", hash)],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|found| found.is_some())
+    .map_err(|e| format!("Failed to load pending transactions: {}", e))
+}
+
+fn to_request(transaction: &Transaction) -> TypedTransaction {
+    let mut request = TransactionRequest::new()
+        .from(transaction.from)
+        .data(transaction.input.clone())
+        .value(transaction.value)
+        .gas(transaction.gas);
+    if let Some(to) = transaction.to {
+        request = request.to(to);
+    }
+    request.into()
+}
+
+pub async fn transaction_status(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+) -> Result<Json<TransactionStatus>, ApiError> {
+    let hash: H256 = hash
+        .parse()
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "hash must be a 32-byte hex transaction hash"))?;
+    // Mints may target any configured chain; the hash says nothing about which.
+    let mut transaction = None;
+    let mut state = state;
+    for chain in state.chains() {
+        transaction = chain
+            .client
+            .get_transaction(hash)
+            .await
+            .map_err(|e| format!("Failed to read transaction: {}", e))?;
+        if transaction.is_some() {
+            state = chain;
+            break;
+        }
+    }
+    let provider = state.client.inner();
+    let links = state
+        .chain
+        .explorer
+        .as_ref()
+        .map(|explorer| explorer.links(transaction.as_ref().and_then(|t| t.to).unwrap_or_default(), Some(hash), None));
+    let mut status = TransactionStatus {
+        transaction_hash: format!("{:?}", hash),
+        status: "pending",
+        block_number: None,
+        confirmations: 0,
+        gas_used: None,
+        revert_reason: None,
+        revert: None,
+        links,
+    };
+
+    let Some(transaction) = transaction else {
+        // Just broadcast, or dropped but not yet noticed by the monitor.
+        if is_tracked(&state.db, hash)? {
+            return Ok(Json(status));
+        }
+        return Err(ApiError::new(StatusCode::NOT_FOUND, format!("Unknown transaction {:?}", hash)));
+    };
+    let Some(receipt) = provider
+        .get_transaction_receipt(hash)
+        .await
+        .map_err(|e| format!("Failed to read transaction receipt: {}", e))?
+    else {
+        return Ok(Json(status));
+    };
+    let Some(block_number) = receipt.block_number.map(|block| block.as_u64()) else {
+        return Ok(Json(status));
+    };
+    let head = provider
+        .get_block_number()
+        .await
+        .map_err(|e| format!("Failed to read the latest block: {}", e))?
+        .as_u64();
+
+    status.block_number = Some(block_number);
+    status.confirmations = (head + 1).saturating_sub(block_number);
+    status.gas_used = receipt.gas_used.map(|gas| gas.as_u64());
+    if receipt.status.is_some_and(|status| status.is_zero()) {
+        status.status = "failed";
+        if let Some(revert) = simulation::replay(provider, &to_request(&transaction), block_number).await {
+            status.revert_reason = Some(revert.reason);
+            status.revert = revert.details;
+        }
+    } else if status.confirmations >= state.chain.tx_policy().confirmations as u64 {
+        status.status = "confirmed";
+    } else {
+        status.status = "mined";
+    }
+    Ok(Json(status))
+}
+
+/// Settles tracked transactions whose submitters stopped waiting for them: mined ones are
+/// recorded like any other, and ones the node no longer knows are given up on.
+async fn sweep(state: &AppState) -> Result<(), String> {
+    let pending: Vec<(String, bool)> = {
+        let conn = state.db.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT transaction_hash, created_at < datetime('now', '-{} minutes') FROM pending_transactions",
+                DROPPED_AFTER_MINUTES
+            ))
+            .map_err(|e| format!("Failed to load pending transactions: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to load pending transactions: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to load pending transactions: {}", e))?
+    };
+    let chains = state.chains();
+    for (hash, stale) in pending {
+        let Ok(parsed) = hash.parse::<H256>() else { continue };
+        let mut receipt = None;
+        for chain in &chains {
+            receipt = chain
+                .client
+                .get_transaction_receipt(parsed)
+                .await
+                .map_err(|e| format!("Failed to read transaction receipt: {}", e))?;
+            if receipt.is_some() {
+                break;
+            }
+        }
+        match receipt {
+            Some(receipt) => tx::record(&state.db, &receipt)?,
+            None if stale => {
+                let mut known = false;
+                for chain in &chains {
+                    known |= chain
+                        .client
+                        .get_transaction(parsed)
+                        .await
+                        .map_err(|e| format!("Failed to read transaction: {}", e))?
+                        .is_some();
+                }
+                if known {
+                    continue;
+                }
+                eprintln!("Transaction {} was dropped before being mined", hash);
+                let conn = state.db.lock().unwrap();
+                conn.execute("DELETE FROM pending_transactions WHERE transaction_hash = ?1", params![hash])
+                    .map_err(|e| format!("Failed to drop pending transaction: {}", e))?;
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Watches broadcast transactions until they are mined or dropped.
+pub fn start(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = sweep(&state).await {
+                eprintln!("Pending transaction monitor failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,127 @@
+use ethers::abi::Detokenize;
+use ethers::contract::ContractCall;
+use ethers::providers::Middleware;
+use ethers::signers::Signer;
+use ethers::types::{TransactionReceipt, U256};
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::explorer::ExplorerLinks;
+use crate::network;
+use crate::simulation;
+use crate::state::{AppState, EthClient};
+use crate::tenderly::TenderlyConfig;
+use crate::transactions;
+
+#[derive(Serialize)]
+pub struct TransactionResponse {
+    pub transaction_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub links: Option<ExplorerLinks>,
+}
+
+/// Simulates the call, broadcasts it and waits for the receipt.
+pub async fn submit<D: Detokenize>(
+    state: &AppState,
+    mut call: ContractCall<EthClient, D>,
+) -> Result<TransactionReceipt, ApiError> {
+    println!("Simulating transaction...");
+    let tenderly = TenderlyConfig::from_env();
+    let from = call.tx.from().copied().unwrap_or(state.client.address());
+    simulation::simulate(&call, tenderly.as_ref(), state.client.signer().chain_id(), from)
+    .await?;
+    network::cap_fees(state, &mut call.tx).await?;
+
+    let nonce = match &state.nonces {
+        Some(nonces) => {
+            let nonce = nonces.acquire(&state.client, from).await?;
+            call.tx.set_nonce(nonce);
+            Some((nonces, nonce))
+        }
+        None => None,
+    };
+    let sent = call.send().await;
+    if let Some((nonces, nonce)) = nonce {
+        let settled = match &sent {
+            Ok(_) => nonces.confirm(from, nonce).await,
+            Err(_) => nonces.release(from, nonce).await,
+        };
+        if let Err(e) = settled {
+            eprintln!("{}", e);
+        }
+    }
+    let pending_tx = sent.map_err(|e| format!("Failed to send transaction: {}", e))?;
+    let hash = pending_tx.tx_hash();
+    transactions::track(&state.db, hash, from);
+    // From here on the transaction may still be mined, so errors say which one it was.
+    let broadcast = |message: String| {
+        ApiError::from(message).with_details(json!({ "transaction_hash": format!("{:?}", hash) }))
+    };
+    let receipt = pending_tx
+        .confirmations(state.chain.tx_policy().confirmations)
+        .await
+        .map_err(|e| broadcast(format!("Transaction failed: {}", e)))?
+        .ok_or_else(|| broadcast("Transaction receipt is None".to_string()))?;
+    if let Err(e) = record(&state.db, &receipt) {
+        eprintln!("Gas spend for {:?} was not recorded: {}", receipt.transaction_hash, e);
+    }
+    // Simulation passed, so the state changed between it and the block.
+    if receipt.status.is_some_and(|status| status.is_zero()) {
+        let block_number = receipt.block_number.unwrap_or_default().as_u64();
+        let mut replayed = call.tx.clone();
+        replayed.set_from(from);
+        let mut error = match simulation::replay(state.client.inner(), &replayed, block_number).await {
+            Some(revert) => revert.into_error("Transaction reverted"),
+            None => ApiError::unprocessable("Transaction reverted"),
+        };
+        let mut details = error.details.take().unwrap_or_else(|| json!({}));
+        details["transaction_hash"] = json!(format!("{:?}", receipt.transaction_hash));
+        return Err(error.with_details(details));
+    }
+    Ok(receipt)
+}
+
+/// What the transaction cost in wei, including the L1 data fee on rollups.
+pub fn gas_cost(receipt: &TransactionReceipt) -> U256 {
+    // OP Stack receipts carry the L1 data fee separately; Arbitrum's is already in gas_used.
+    let l1_fee = receipt.other.get_deserialized::<U256>("l1Fee").and_then(Result::ok).unwrap_or_default();
+    receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default() + l1_fee
+}
+
+/// Keeps the gas paid per transaction for the stats dashboard, and stops tracking it as pending.
+pub fn record(db: &Db, receipt: &TransactionReceipt) -> Result<(), String> {
+    let gas_used = receipt.gas_used.unwrap_or_default();
+    let gas_cost = gas_cost(receipt);
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT OR IGNORE INTO transactions (transaction_hash, sender, gas_used, gas_cost_wei, success)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            format!("{:?}", receipt.transaction_hash),
+            format!("{:?}", receipt.from),
+            gas_used.as_u64(),
+            gas_cost.to_string(),
+            receipt.status.is_none_or(|status| status.as_u64() == 1),
+        ],
+    )
+    .map_err(|e| format!("Failed to record transaction: {}", e))?;
+    conn.execute(
+        "DELETE FROM pending_transactions WHERE transaction_hash = ?1",
+        params![format!("{:?}", receipt.transaction_hash)],
+    )
+    .map_err(|e| format!("Failed to record transaction: {}", e))?;
+    Ok(())
+}
+
+pub fn response(state: &AppState, receipt: &TransactionReceipt) -> TransactionResponse {
+    TransactionResponse {
+        transaction_hash: format!("{:?}", receipt.transaction_hash),
+        links: state.chain.explorer.as_ref().map(|explorer| {
+            let contract = receipt.to.unwrap_or_default();
+            explorer.links(contract, Some(receipt.transaction_hash), None)
+        }),
+    }
+}
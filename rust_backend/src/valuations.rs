@@ -0,0 +1,381 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use ethers::types::{TransactionReceipt, U256};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::appraisals;
+use crate::cards;
+use crate::config::optional_env;
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::explorer::ExplorerLinks;
+use crate::liens;
+use crate::mint::{self, HouseDetails};
+use crate::money::{Currency, Money};
+use crate::nfts;
+use crate::organizations::Tenant;
+use crate::parcels;
+use crate::private;
+use crate::rarity;
+use crate::registry::{self, Collection};
+use crate::state::AppState;
+use crate::storage::{self, MetadataStore};
+use crate::tx;
+use crate::versions;
+
+/// What a valuation prices. Only market valuations set a token's price; the others are kept
+/// alongside them in the history.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ValuationType {
+    #[default]
+    Market,
+    /// Cost to rebuild, as insurers need.
+    InsuranceReplacement,
+    TaxAssessed,
+}
+
+impl ValuationType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ValuationType::Market => "market",
+            ValuationType::InsuranceReplacement => "insurance-replacement",
+            ValuationType::TaxAssessed => "tax-assessed",
+        }
+    }
+}
+
+/// How a non-market valuation is priced.
+pub enum Pricing {
+    /// A dedicated model service.
+    Model(String),
+    /// A multiple of the market price.
+    Multiplier(f64),
+}
+
+impl Pricing {
+    fn from_env(prefix: &str) -> Option<Self> {
+        let pricing = match optional_env(&format!("{}_MODEL_URL", prefix)) {
+            Some(url) => Pricing::Model(url),
+            None => Pricing::Multiplier(
+                optional_env(&format!("{}_MULTIPLIER", prefix))?
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{}_MULTIPLIER must be a number", prefix)),
+            ),
+        };
+        match &pricing {
+            Pricing::Model(url) => println!("{}_MODEL_URL: {}", prefix, url),
+            Pricing::Multiplier(multiplier) => println!("{}_MULTIPLIER: {}", prefix, multiplier),
+        }
+        Some(pricing)
+    }
+}
+
+pub struct ValuationModels {
+    pub insurance_replacement: Option<Pricing>,
+    pub tax_assessed: Option<Pricing>,
+}
+
+impl ValuationModels {
+    pub fn from_env() -> Self {
+        ValuationModels {
+            insurance_replacement: Pricing::from_env("INSURANCE"),
+            tax_assessed: Pricing::from_env("TAX_ASSESSED"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ValuationQuery {
+    #[serde(default)]
+    pub valuation_type: ValuationType,
+}
+
+#[derive(Deserialize)]
+pub struct RevalueQuery {
+    collection: Option<String>,
+    #[serde(default)]
+    valuation_type: ValuationType,
+}
+
+pub struct NewValuation<'a> {
+    pub collection: &'a str,
+    pub token_id: u64,
+    pub price: Money,
+    /// `model` for predictions; `consensus` or `approval` for a price agreed before the mint.
+    pub source: &'a str,
+    pub valuation_type: ValuationType,
+    pub actor: &'a str,
+    /// Unset when the valuation changed nothing on-chain.
+    pub transaction_hash: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+pub struct RevalueResponse {
+    collection: String,
+    token_id: u64,
+    valuation_type: ValuationType,
+    /// The last valuation of this type, if there was one.
+    previous_price: Option<f64>,
+    price: f64,
+    /// `price` in whole cents.
+    value: Money,
+    /// The licensed appraisal that still sets the token's price, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    appraised_price: Option<f64>,
+    /// Unset for valuations that don't change the token's price.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    links: Option<ExplorerLinks>,
+}
+
+/// Appends to the token's valuation history.
+pub fn record(db: &Db, valuation: NewValuation) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO valuations (collection, token_id, price, price_cents, source, actor, transaction_hash, valuation_type)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            valuation.collection,
+            valuation.token_id,
+            valuation.price.to_f64(),
+            valuation.price.cents,
+            valuation.source,
+            valuation.actor,
+            valuation.transaction_hash,
+            valuation.valuation_type.as_str(),
+        ],
+    )
+    .map_err(|e| format!("Failed to record valuation: {}", e))?;
+    Ok(())
+}
+
+/// Prices a property for `valuation_type`.
+pub async fn predict(state: &AppState, details: &HouseDetails, valuation_type: ValuationType) -> Result<Money, ApiError> {
+    let models = &state.config.valuation_models;
+    let pricing = match valuation_type {
+        ValuationType::Market => return mint::predict_price(state, details).await,
+        ValuationType::InsuranceReplacement => models.insurance_replacement.as_ref(),
+        ValuationType::TaxAssessed => models.tax_assessed.as_ref(),
+    };
+    match pricing {
+        Some(Pricing::Model(url)) => mint::predict_with(state, url, details).await,
+        Some(Pricing::Multiplier(multiplier)) => Ok(mint::predict_price(state, details).await?.scale(*multiplier)),
+        None => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            format!("{} valuations are not configured", valuation_type.as_str()),
+        )),
+    }
+}
+
+/// Writes `metadata` on-chain as the token's URI and stores it with the token's new listed `price`.
+/// The store the token was minted with; tokens keep it across metadata updates.
+pub fn token_store<'a>(
+    state: &'a AppState,
+    collection: &Collection,
+    token_id: u64,
+) -> Result<&'a dyn MetadataStore, ApiError> {
+    let storage = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT metadata_storage FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection.name, token_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+    };
+    state.config.storage.store(storage.as_deref())
+}
+
+pub async fn write_metadata(
+    state: &AppState,
+    tenant: &Tenant,
+    collection: &Collection,
+    token_id: u64,
+    price: Money,
+    metadata: &Value,
+) -> Result<TransactionReceipt, ApiError> {
+    let store = token_store(state, collection, token_id)?;
+    let token_uri = storage::put(state, store, metadata).await?;
+    let chain = state.on(collection)?;
+    let contract = chain.nft_as(collection, chain.client_for(tenant)?)?;
+    let call = contract.update_metadata(U256::from(token_id), token_uri.clone());
+    let receipt = tx::submit(&chain, call).await?;
+    let token_uri = (!storage::is_inline(store)).then_some(token_uri);
+    {
+        let conn = state.db.lock().unwrap();
+        conn.execute(
+            "UPDATE mints SET price = ?3, price_cents = ?4, metadata = ?5, token_uri = ?6
+             WHERE collection = ?1 AND token_id = ?2",
+            params![
+                collection.name,
+                token_id,
+                price.to_f64(),
+                price.cents,
+                metadata.to_string(),
+                token_uri
+            ],
+        )
+        .map_err(|e| format!("Failed to update token {}: {}", token_id, e))?;
+    }
+    let transaction_hash = format!("{:?}", receipt.transaction_hash);
+    versions::record(&state.db, &collection.name, token_id, metadata, &transaction_hash)?;
+    Ok(receipt)
+}
+
+/// Re-runs the price model on the stored property details and rewrites the token metadata.
+/// Other valuation types are only added to the history.
+pub async fn revalue(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<RevalueQuery>,
+) -> Result<Json<RevalueResponse>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let response = match query.valuation_type {
+        ValuationType::Market => revalue_token(&state, &tenant, collection, token_id).await?,
+        valuation_type => assess_token(&state, &tenant, collection, token_id, valuation_type).await?,
+    };
+    Ok(Json(response))
+}
+
+pub fn stored_details(state: &AppState, collection: &str, token_id: u64) -> Result<(HouseDetails, Money, bool), ApiError> {
+    let stored = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT details, COALESCE(price_cents, CAST(ROUND(price * 100) AS INTEGER)), soulbound
+             FROM mints WHERE collection = ?1 AND token_id = ?2",
+            params![collection, token_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, bool>(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load token {}: {}", token_id, e))?
+        .map(|(details, price, soulbound)| {
+            private::restore(&conn, state.config.private.as_ref(), collection, token_id, details)
+                .map(|details| (details, price, soulbound))
+        })
+        .transpose()?
+    };
+    let (details, price, soulbound) = stored.ok_or_else(|| {
+        ApiError::new(StatusCode::NOT_FOUND, format!("Token {} was not minted through this service", token_id))
+    })?;
+    let details = serde_json::from_str(&details).map_err(|_| {
+        ApiError::new(StatusCode::CONFLICT, format!("Property details for token {} are no longer available", token_id))
+    })?;
+    Ok((details, Money::new(price, Currency::USD), soulbound))
+}
+
+/// Records an insurance or tax valuation of a minted token without touching its price.
+pub async fn assess_token(
+    state: &AppState,
+    tenant: &Tenant,
+    collection: Collection,
+    token_id: u64,
+    valuation_type: ValuationType,
+) -> Result<RevalueResponse, ApiError> {
+    let (details, _, _) = stored_details(state, &collection.name, token_id)?;
+    let price = predict(state, &details, valuation_type).await?;
+    let previous_price = {
+        let conn = state.db.lock().unwrap();
+        conn.query_row(
+            "SELECT price FROM valuations WHERE collection = ?1 AND token_id = ?2 AND valuation_type = ?3
+             ORDER BY id DESC LIMIT 1",
+            params![collection.name, token_id, valuation_type.as_str()],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load valuations: {}", e))?
+    };
+    record(
+        &state.db,
+        NewValuation {
+            collection: &collection.name,
+            token_id,
+            price,
+            source: "model",
+            valuation_type,
+            actor: tenant.organization_id().unwrap_or("platform"),
+            transaction_hash: None,
+        },
+    )?;
+    Ok(RevalueResponse {
+        collection: collection.name,
+        token_id,
+        valuation_type,
+        previous_price,
+        price: price.to_f64(),
+        value: price,
+        appraised_price: None,
+        transaction_hash: None,
+        links: None,
+    })
+}
+
+/// Re-prices a minted token with the current model and writes the new metadata on-chain.
+pub async fn revalue_token(
+    state: &AppState,
+    tenant: &Tenant,
+    collection: Collection,
+    token_id: u64,
+) -> Result<RevalueResponse, ApiError> {
+    let (details, previous_price, soulbound) = stored_details(state, &collection.name, token_id)?;
+
+    let price = mint::predict_price(state, &details).await?;
+    let verification = parcels::verify(state, &details).await;
+    let mut metadata = mint::public_metadata(state, &details, verification.as_ref(), price, soulbound).await;
+    liens::annotate(&state.db, &collection.name, token_id, &mut metadata)?;
+    rarity::annotate(state, &collection.name, Some(token_id), &mut metadata)?;
+    // A licensed appraisal keeps superseding the model; the new estimate is shown beside it.
+    let appraisal = appraisals::latest(&state.db, &collection.name, token_id)?;
+    let listed_price = match &appraisal {
+        Some(appraisal) => {
+            let appraised = Money::from_f64(appraisal.price, Currency::USD);
+            appraisals::apply(&mut metadata, &details, appraised, &appraisal.license_number, Some(price));
+            appraised
+        }
+        None => price,
+    };
+    cards::attach(state, Some(token_store(state, &collection, token_id)?), &details, listed_price, &mut metadata).await?;
+    println!("Revaluing token {} of {} at {}...", token_id, collection.name, price);
+    let receipt = write_metadata(state, tenant, &collection, token_id, listed_price, &metadata).await?;
+    let transaction_hash = format!("{:?}", receipt.transaction_hash);
+
+    record(
+        &state.db,
+        NewValuation {
+            collection: &collection.name,
+            token_id,
+            price,
+            source: "model",
+            valuation_type: ValuationType::Market,
+            actor: tenant.organization_id().unwrap_or("platform"),
+            transaction_hash: Some(&transaction_hash),
+        },
+    )?;
+    if let Some(verification) = &verification {
+        parcels::record(&state.db, &collection.name, token_id, verification)?;
+    }
+
+    nfts::spawn_marketplace_refresh(state, &collection, token_id);
+
+    Ok(RevalueResponse {
+        token_id,
+        valuation_type: ValuationType::Market,
+        previous_price: Some(previous_price.to_f64()),
+        price: price.to_f64(),
+        value: price,
+        appraised_price: appraisal.map(|appraisal| appraisal.price),
+        transaction_hash: Some(transaction_hash),
+        links: state
+            .chain_of(&collection)
+            .explorer
+            .as_ref()
+            .map(|explorer| explorer.links(collection.address, Some(receipt.transaction_hash), Some(U256::from(token_id)))),
+        collection: collection.name,
+    })
+}
@@ -0,0 +1,186 @@
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::db::Db;
+use crate::error::ApiError;
+use crate::organizations::Tenant;
+use crate::registry;
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    collection: Option<String>,
+    /// Include each version's full metadata, not just what changed.
+    #[serde(default)]
+    full: bool,
+}
+
+#[derive(Serialize)]
+pub struct Change {
+    /// e.g. `description` or `attributes[Price].value`.
+    pub path: String,
+    /// `added`, `removed` or `changed`.
+    pub change: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+}
+
+#[derive(Serialize)]
+pub struct MetadataVersion {
+    pub version: u32,
+    pub transaction_hash: String,
+    pub created_at: String,
+    /// Against the previous version; empty for the first.
+    pub changes: Vec<Change>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+/// Appends a copy of the token's metadata as its next version.
+pub fn record(db: &Db, collection: &str, token_id: u64, metadata: &Value, transaction_hash: &str) -> Result<(), String> {
+    let conn = db.lock().unwrap();
+    conn.execute(
+        "INSERT INTO metadata_versions (collection, token_id, version, metadata, transaction_hash)
+         SELECT ?1, ?2, COALESCE(MAX(version), 0) + 1, ?3, ?4
+         FROM metadata_versions WHERE collection = ?1 AND token_id = ?2",
+        params![collection, token_id, metadata.to_string(), transaction_hash],
+    )
+    .map_err(|e| format!("Failed to record metadata version: {}", e))?;
+    Ok(())
+}
+
+/// What changed between two metadata documents. Attributes are matched by `trait_type`, so a
+/// new trait reads as one addition rather than every later attribute shifting.
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(String::new(), Some(old), Some(new), &mut changes);
+    changes
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Attributes keyed by trait type, or `None` if the array isn't a list of traits. A trait type
+/// that repeats is keyed by its occurrence after the first, e.g. `Feature#2`, so repeats don't
+/// hide each other.
+fn traits(values: &[Value]) -> Option<Map<String, Value>> {
+    let mut keyed = Map::new();
+    for attribute in values {
+        let trait_type = attribute["trait_type"].as_str()?;
+        let mut key = trait_type.to_string();
+        let mut occurrence = 1;
+        while keyed.contains_key(&key) {
+            occurrence += 1;
+            key = format!("{}#{}", trait_type, occurrence);
+        }
+        keyed.insert(key, attribute.clone());
+    }
+    Some(keyed)
+}
+
+fn diff_at(path: String, old: Option<&Value>, new: Option<&Value>, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => diff_objects(&path, old, new, changes),
+        (Some(Value::Array(old)), Some(Value::Array(new))) if old != new => match (traits(old), traits(new)) {
+            (Some(old), Some(new)) => {
+                let keyed = |trait_type: &str| format!("{}[{}]", path, trait_type);
+                let mut trait_types: Vec<_> = old.keys().chain(new.keys()).collect();
+                trait_types.sort();
+                trait_types.dedup();
+                for trait_type in trait_types {
+                    diff_at(keyed(trait_type), old.get(trait_type), new.get(trait_type), changes);
+                }
+            }
+            _ => changes.push(Change {
+                path,
+                change: "changed",
+                from: Some(Value::Array(old.clone())),
+                to: Some(Value::Array(new.clone())),
+            }),
+        },
+        (Some(old), Some(new)) if old != new => changes.push(Change {
+            path,
+            change: "changed",
+            from: Some(old.clone()),
+            to: Some(new.clone()),
+        }),
+        (Some(old), None) => changes.push(Change {
+            path,
+            change: "removed",
+            from: Some(old.clone()),
+            to: None,
+        }),
+        (None, Some(new)) => changes.push(Change {
+            path,
+            change: "added",
+            from: None,
+            to: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_objects(path: &str, old: &Map<String, Value>, new: &Map<String, Value>, changes: &mut Vec<Change>) {
+    // Traits are matched on `trait_type`, so only their other fields can change.
+    let mut keys: Vec<_> = old.keys().chain(new.keys()).filter(|key| *key != "trait_type").collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        diff_at(join(path, key), old.get(key), new.get(key), changes);
+    }
+}
+
+pub async fn history(
+    State(state): State<AppState>,
+    tenant: Tenant,
+    Path(token_id): Path<u64>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<MetadataVersion>>, ApiError> {
+    let collection = registry::resolve_for(&state.db, &tenant, query.collection.as_deref())?;
+    let rows = {
+        let conn = state.db.lock().unwrap();
+        let mut stmt = conn
+            .prepare(
+                "SELECT version, metadata, transaction_hash, created_at FROM metadata_versions
+                 WHERE collection = ?1 AND token_id = ?2 ORDER BY version",
+            )
+            .map_err(|e| format!("Failed to load metadata history: {}", e))?;
+        let rows = stmt
+            .query_map(params![collection.name, token_id], |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(|e| format!("Failed to load metadata history: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to load metadata history: {}", e))?
+    };
+
+    let mut previous: Option<Value> = None;
+    let mut versions = Vec::new();
+    for (version, metadata, transaction_hash, created_at) in rows {
+        let metadata: Value = serde_json::from_str(&metadata).unwrap_or_default();
+        versions.push(MetadataVersion {
+            version,
+            transaction_hash,
+            created_at,
+            changes: previous.as_ref().map(|previous| diff(previous, &metadata)).unwrap_or_default(),
+            metadata: query.full.then(|| metadata.clone()),
+        });
+        previous = Some(metadata);
+    }
+    Ok(Json(versions))
+}